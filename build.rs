@@ -0,0 +1,108 @@
+// Writes the attack tables out as statics, so they are compiled into read-only memory rather than
+// generated every time the engine starts
+#[path = "src/engine/attack_tables/generation.rs"]
+mod generation;
+
+use generation::{LeaperPiece, SliderPiece};
+use std::{env, fs, path::Path};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/engine/attack_tables/generation.rs");
+
+    let mut source = String::new();
+
+    for (name, piece) in [
+        ("WHITE_PAWN_ATTACK_TABLES", LeaperPiece::WhitePawn),
+        ("BLACK_PAWN_ATTACK_TABLES", LeaperPiece::BlackPawn),
+        ("KNIGHT_ATTACK_TABLES", LeaperPiece::Knight),
+        ("KING_ATTACK_TABLES", LeaperPiece::King),
+    ] {
+        source += &static_source(
+            name,
+            "[u64; 64]",
+            array_source(generation::leaper_attack_tables(piece)),
+        );
+    }
+
+    source += &static_source(
+        "BETWEEN",
+        "[[u64; 64]; 64]",
+        array_source((0..64).map(|square| {
+            array_source(
+                (0..64).map(|other_square| generation::between_and_line(square, other_square).0),
+            )
+        })),
+    );
+    source += &static_source(
+        "LINE",
+        "[[u64; 64]; 64]",
+        array_source((0..64).map(|square| {
+            array_source(
+                (0..64).map(|other_square| generation::between_and_line(square, other_square).1),
+            )
+        })),
+    );
+
+    // Only the tables of the slider attack lookup being built are written, as the magic ones come
+    // to over 800 kilobytes
+    match env::var_os("CARGO_FEATURE_LOW_MEMORY") {
+        Some(_) => {
+            for (name, directions) in [
+                ("BISHOP_RAYS", generation::BISHOP_DIRECTIONS),
+                ("ROOK_RAYS", generation::ROOK_DIRECTIONS),
+            ] {
+                source += &static_source(
+                    name,
+                    "[[u64; 64]; 4]",
+                    array_source(directions.map(|direction| {
+                        array_source((0..64).map(|square| generation::ray(direction, square)))
+                    })),
+                );
+            }
+        }
+        None => {
+            for (name, piece) in [
+                ("BISHOP_ATTACK_MASKS", SliderPiece::Bishop),
+                ("ROOK_ATTACK_MASKS", SliderPiece::Rook),
+            ] {
+                source += &static_source(
+                    name,
+                    "[u64; 64]",
+                    array_source((0..64).map(|square| generation::attack_mask(piece, square))),
+                );
+            }
+
+            for (name, piece) in [
+                ("BISHOP_ATTACK_TABLE_OFFSETS", SliderPiece::Bishop),
+                ("ROOK_ATTACK_TABLE_OFFSETS", SliderPiece::Rook),
+            ] {
+                source += &static_source(
+                    name,
+                    "[usize; 64]",
+                    array_source(generation::magic_attack_table_offsets(piece)),
+                );
+            }
+
+            let attack_tables = generation::magic_attack_tables();
+            source += &static_source(
+                "MAGIC_ATTACK_TABLES",
+                &format!("[u64; {}]", attack_tables.len()),
+                array_source(attack_tables),
+            );
+        }
+    }
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("attack_tables.rs");
+    fs::write(path, source).unwrap();
+}
+
+fn static_source(name: &str, table_type: &str, values: String) -> String {
+    format!("pub static {}: {} = {};\n", name, table_type, values)
+}
+
+fn array_source<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+    let values: Vec<String> = values.into_iter().map(|value| value.to_string()).collect();
+
+    format!("[{}]", values.join(","))
+}
@@ -0,0 +1,138 @@
+#[cfg(feature = "telemetry")]
+use crate::telemetry;
+use crate::{
+    crash_report,
+    engine::{self, Engine},
+    random, uci,
+};
+use std::process::ExitCode;
+
+// Runs the UCI loop, or the maintenance subcommand named by the first argument
+pub fn run(arguments: &[String]) -> ExitCode {
+    match arguments.first().map(String::as_str) {
+        Some("regen") => regenerate_tables(arguments.get(1)),
+        Some("magics") => regenerate_magic_numbers(arguments.get(1)),
+        Some("book") => build_book(&arguments[1..]),
+        Some("bench" | "testsuite") => run_command(&arguments.join(" ")),
+        _ => {
+            crash_report::install();
+            uci::engine(output());
+
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+#[cfg(feature = "telemetry")]
+fn output() -> uci::Output {
+    telemetry::output()
+}
+
+#[cfg(not(feature = "telemetry"))]
+fn output() -> uci::Output {
+    uci::Output::stdout()
+}
+
+// Maintenance subcommand printing the magic numbers and zobrist hashes generated from a seed
+fn regenerate_tables(seed: Option<&String>) -> ExitCode {
+    match parse_seed(seed).and_then(engine::generated_tables_source) {
+        Some(source) => {
+            print!("{}", source);
+
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!("Usage: pineapple-chess-engine regen [seed], with a non-zero 32 bit seed");
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Maintenance subcommand printing just the magic numbers generated from a seed, verified against
+// the ray scanning attack generator, for trying out seeds which may give better ones
+fn regenerate_magic_numbers(seed: Option<&String>) -> ExitCode {
+    match parse_seed(seed).and_then(engine::magic_numbers_source) {
+        Some(Ok(source)) => {
+            print!("{}", source);
+
+            ExitCode::SUCCESS
+        }
+        Some(Err(error)) => {
+            eprintln!("{}", error);
+
+            ExitCode::FAILURE
+        }
+        None => {
+            eprintln!("Usage: pineapple-chess-engine magics [seed], with a non-zero 32 bit seed");
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Maintenance subcommand building an opening book from a PGN collection, with the number of plies
+// counted from each game and the number of games a move needs to be kept
+fn build_book(arguments: &[String]) -> ExitCode {
+    let max_ply = arguments
+        .get(2)
+        .map_or(Some(engine::book::BOOK_MAX_PLY_DEFAULT), |max_ply| {
+            max_ply.parse().ok()
+        });
+    let min_games = arguments
+        .get(3)
+        .map_or(Some(engine::book::BOOK_MIN_GAMES_DEFAULT), |min_games| {
+            min_games.parse().ok()
+        });
+
+    let (Some(pgn_path), Some(book_path), Some(max_ply), Some(min_games)) =
+        (arguments.first(), arguments.get(1), max_ply, min_games)
+    else {
+        eprintln!(
+            "Usage: pineapple-chess-engine book <pgn file> <book file> [max ply] [min games]"
+        );
+
+        return ExitCode::FAILURE;
+    };
+
+    match engine::book::build_book(pgn_path, book_path, max_ply, min_games) {
+        Ok((games_read, entry_count)) => {
+            println!(
+                "Read {} games into {} book entries",
+                games_read, entry_count
+            );
+
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Seeds can be grouped with underscores as in the source, and default to the one the constants
+// were generated with
+fn parse_seed(seed: Option<&String>) -> Option<u32> {
+    match seed {
+        Some(seed) => seed.replace('_', "").parse().ok(),
+        None => Some(random::DEFAULT_SEED),
+    }
+}
+
+// Runs the bench or a test suite from the command line, so builds can be compared without a GUI
+fn run_command(input: &str) -> ExitCode {
+    let result = input
+        .parse()
+        .and_then(|command| uci::execute(&mut Engine::initialise(), command));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+
+            ExitCode::FAILURE
+        }
+    }
+}
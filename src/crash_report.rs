@@ -0,0 +1,135 @@
+use std::{
+    collections::VecDeque,
+    panic,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+const RECENT_COMMANDS_MAX: usize = 16;
+
+static CRASH_REPORT: Mutex<CrashReport> = Mutex::new(CrashReport::new());
+
+// What the engine was last asked to do, kept up to date as it runs so a panic can be reported
+// with enough context to reproduce it
+struct CrashReport {
+    recent_commands: VecDeque<String>,
+    position: Option<String>,
+    // The position reached, for reproducing a crash without replaying the moves
+    fen: Option<String>,
+    search: Option<String>,
+}
+
+impl CrashReport {
+    const fn new() -> Self {
+        Self {
+            recent_commands: VecDeque::new(),
+            position: None,
+            fen: None,
+            search: None,
+        }
+    }
+
+    fn record_command(&mut self, command: &str) {
+        if self.recent_commands.len() == RECENT_COMMANDS_MAX {
+            self.recent_commands.pop_front();
+        }
+
+        self.recent_commands.push_back(command.trim().to_string());
+    }
+
+    fn report(&self) -> String {
+        let mut report =
+            "Pineapple crashed, please include this report with the bug report\n".to_string();
+        report += &format!(
+            "Position: {}\n",
+            self.position.as_deref().unwrap_or("no position set")
+        );
+        report += &format!(
+            "FEN: {}\n",
+            self.fen.as_deref().unwrap_or("no position set")
+        );
+        report += &format!(
+            "Last search: {}\n",
+            self.search
+                .as_deref()
+                .unwrap_or("no search completed an iteration")
+        );
+        report += "Last commands received:\n";
+
+        for command in &self.recent_commands {
+            report += &format!("    {}\n", command);
+        }
+
+        report
+    }
+}
+
+// The report is printed before the usual panic message, which gives the location of the panic
+pub fn install() {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |panic_info| {
+        // The panic may have happened while the report was being updated on this same thread
+        if let Ok(crash_report) = CRASH_REPORT.try_lock() {
+            eprint!("{}", crash_report.report());
+        }
+
+        default_hook(panic_info);
+    }));
+}
+
+pub fn record_command(command: &str) {
+    lock().record_command(command);
+}
+
+pub fn record_position(position: Option<String>, fen: Option<String>) {
+    let mut crash_report = lock();
+    crash_report.position = position;
+    crash_report.fen = fen;
+}
+
+pub fn record_search(search: String) {
+    lock().search = Some(search);
+}
+
+fn lock() -> MutexGuard<'static, CrashReport> {
+    CRASH_REPORT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_commands_report() {
+        let mut crash_report = CrashReport::new();
+
+        assert!(crash_report
+            .report()
+            .contains("Position: no position set\n"));
+        assert!(crash_report.report().contains("FEN: no position set\n"));
+
+        for index in 0..20 {
+            crash_report.record_command(&format!("go depth {}\n", index));
+        }
+
+        crash_report.position = Some("position startpos moves e2e4".to_string());
+        crash_report.fen =
+            Some("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".to_string());
+        crash_report.search = Some("depth 3 score cp 20 nodes 500 pv e7e5".to_string());
+        let report = crash_report.report();
+        let commands: Vec<&str> = report
+            .lines()
+            .skip_while(|line| *line != "Last commands received:")
+            .skip(1)
+            .collect();
+
+        assert!(report.contains("Position: position startpos moves e2e4\n"));
+        assert!(
+            report.contains("FEN: rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1\n")
+        );
+        assert!(report.contains("Last search: depth 3 score cp 20 nodes 500 pv e7e5\n"));
+        assert_eq!(commands.len(), RECENT_COMMANDS_MAX);
+        assert_eq!(commands.first(), Some(&"    go depth 4"));
+        assert_eq!(commands.last(), Some(&"    go depth 19"));
+    }
+}
@@ -1,48 +1,348 @@
 mod attack_tables;
+pub mod book;
+pub mod epd;
 mod game;
 mod moves;
+#[cfg(feature = "nnue")]
+mod nnue;
+mod options;
+mod perft;
+pub mod pgn;
 mod search;
 mod zobrist_hashes;
 
-use self::{attack_tables::AttackTables, game::Game, moves::MoveList, search::SearchParameters};
-use crate::uci::InputError;
+pub use self::{
+    game::{Bitboard, Game, Piece, Side, Square},
+    moves::{Move, MoveType},
+    search::{
+        BestMoveReply, Score, SearchProgress, SearchResult, TimeControl, BENCH_DEPTH_DEFAULT,
+        TEST_SUITE_MOVE_TIME_DEFAULT,
+    },
+};
+
+use self::{
+    attack_tables::MagicNumbers,
+    moves::{MoveFlag, MoveList},
+    options::Options,
+    search::{SearchParameters, TuneParameters, Value, TUNABLES},
+    zobrist_hashes::{ZobristHashes, ZobristKey},
+};
+use crate::{
+    crash_report,
+    random::Random,
+    uci::{InputError, Output},
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 pub const MAX_PLY: usize = 64;
 
 pub struct Engine {
     game: Game,
-    attack_tables: AttackTables,
     search_parameters: SearchParameters,
+    options: Options,
+    initialisation_times: Vec<(&'static str, Duration)>,
+    // Shared so `debug` can be switched while a search is running, from the next iteration on
+    debug: Arc<AtomicBool>,
+    start_position: Option<String>,
+    move_history: Vec<String>,
+    // Keys of the positions played before the current one, for detecting repetitions
+    position_history: Vec<ZobristKey>,
+    output: Output,
 }
 
 impl Engine {
     pub fn initialise() -> Self {
+        let mut initialisation_times = Vec::new();
+
+        let options = Options::initialise();
+
+        let start_time = Instant::now();
+        let search_parameters = SearchParameters::initialise(options.spin(options::HASH) as usize);
+        initialisation_times.push(("search parameters", start_time.elapsed()));
+
         Self {
             game: Game::initialise(),
-            attack_tables: AttackTables::initialise(),
-            search_parameters: SearchParameters::initialise(),
+            search_parameters,
+            options,
+            initialisation_times,
+            debug: Arc::new(AtomicBool::new(false)),
+            start_position: None,
+            move_history: Vec::new(),
+            position_history: Vec::new(),
+            output: Output::stdout(),
+        }
+    }
+
+    // Everything the engine reports is written here, so it can be redirected away from stdout
+    pub fn set_output(&mut self, output: Output) {
+        self.output = output;
+    }
+
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    // Initialisation happens eagerly on startup, so by the time the handshake completes the
+    // engine is ready to search - the times are only reported once, on the first `isready` with
+    // debug on
+    pub fn print_initialisation_times(&mut self) {
+        if !self.debug() {
+            return;
+        }
+
+        for (phase, time) in self.initialisation_times.drain(..) {
+            self.output.line(format!(
+                "info string initialised {} in {}ms",
+                phase,
+                time.as_millis()
+            ));
+        }
+    }
+
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug.store(debug, Ordering::Relaxed);
+    }
+
+    pub fn debug_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.debug)
+    }
+
+    fn debug(&self) -> bool {
+        self.debug.load(Ordering::Relaxed)
+    }
+
+    pub fn print_options(&self) {
+        self.options.print(&self.output);
+    }
+
+    // The tune parameters in the form SPSA tuning tools take as input
+    pub fn print_tune_parameters(&self) {
+        for tunable in &TUNABLES {
+            self.output.line(tunable.spsa_spec());
+        }
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), InputError> {
+        let (name, clamped) = self.options.set(name, value)?;
+
+        if let Some(clamped) = clamped {
+            self.output.line(format!("info string {}", clamped));
+        }
+
+        let hash_file = self.options.string(options::HASH_FILE).to_string();
+
+        match name {
+            options::SAVE_HASH | options::LOAD_HASH if hash_file.is_empty() => {
+                Err(InputError::InvalidHashFileArguments)
+            }
+            options::HASH => {
+                self.resize_transposition_table(self.options.spin(options::HASH) as usize);
+
+                Ok(())
+            }
+            options::CLEAR_HASH => {
+                self.clear_transposition_table();
+
+                Ok(())
+            }
+            options::CHESS960 => {
+                self.game
+                    .set_chess960(self.options.check(options::CHESS960));
+
+                Ok(())
+            }
+            #[cfg(feature = "nnue")]
+            options::EVAL_FILE => self.load_network(),
+            #[cfg(feature = "syzygy")]
+            options::SYZYGY_PATH => self.load_tablebases(),
+            options::SAVE_HASH => self.save_transposition_table(&hash_file),
+            options::LOAD_HASH => self.load_transposition_table(&hash_file),
+            name if TuneParameters::is_tunable(name) => {
+                self.set_tune_parameter(name, self.options.spin(name) as Value);
+
+                Ok(())
+            }
+            _ => Ok(()),
         }
     }
 
+    // An empty path goes back to the hand-crafted evaluation, and every cached evaluation is
+    // dropped as positions now evaluate differently
+    #[cfg(feature = "nnue")]
+    fn load_network(&mut self) -> Result<(), InputError> {
+        let eval_file = self.options.string(options::EVAL_FILE);
+        let network = match eval_file.is_empty() {
+            true => None,
+            false => Some(Arc::new(nnue::Network::load(eval_file)?)),
+        };
+        self.game.set_network(network);
+        self.clear_evaluation_cache();
+
+        Ok(())
+    }
+
+    // The GUI only expects a ponder move after the best move if it lets the engine ponder
+    pub fn ponder_enabled(&self) -> bool {
+        self.options.check(options::PONDER)
+    }
+
     pub fn load_fen(&mut self, fen: &[&str]) -> Result<(), InputError> {
-        self.game.load_fen(fen)?;
+        match self.options.check(options::STRICT_FEN) {
+            true => self.game.load_fen_strict(fen)?,
+            false => self.game.load_fen(fen)?,
+        }
+
+        self.start_position = match fen.first() {
+            Some(&"startpos") => Some("startpos".to_string()),
+            _ => Some(format!("fen {}", fen.join(" "))),
+        };
+        self.move_history.clear();
+        self.position_history.clear();
+        crash_report::record_position(self.history(), Some(self.game.to_fen()));
 
         Ok(())
     }
 
     pub fn make_move(&mut self, move_string: &str) -> Result<(), InputError> {
-        let move_list = MoveList::generate_moves(&self.game, &self.attack_tables);
+        let move_list = MoveList::generate_moves(&self.game, MoveFlag::All);
         let mv = move_list.find_move_from_string(move_string)?;
-        self.game.make_move(&mv, &self.attack_tables)?;
+        let zobrist_key = self.game.zobrist_key();
+        self.game.make_move(&mv)?;
+        self.move_history.push(mv.as_string());
+        self.position_history.push(zobrist_key);
+        crash_report::record_position(self.history(), Some(self.game.to_fen()));
 
         Ok(())
     }
 
+    // The position command which would reproduce the current game, as far as it was set up
+    // successfully
+    pub fn history(&self) -> Option<String> {
+        let start_position = self.start_position.as_ref()?;
+
+        match self.move_history.is_empty() {
+            true => Some(format!("position {}", start_position)),
+            false => Some(format!(
+                "position {} moves {}",
+                start_position,
+                self.move_history.join(" ")
+            )),
+        }
+    }
+
+    // Shows the position the engine believes it has, to check it matches what the GUI sent
+    pub fn display(&self) {
+        let checkers = self.game.checkers();
+        let mut checker_squares = Vec::new();
+
+        for square in checkers.squares() {
+            checker_squares.push(square.to_string().to_lowercase());
+        }
+
+        self.output.line(&self.game);
+        self.output
+            .line(format!("Checkers: {}", checker_squares.join(" ")));
+
+        if let Some(history) = self.history() {
+            self.output.line(history);
+        }
+    }
+
+    pub fn verify(&self) {
+        let divergences = self.game.verify();
+
+        if divergences.is_empty() {
+            self.output.line("info string verify ok");
+        }
+
+        for divergence in divergences {
+            self.output
+                .line(format!("info string verify {}", divergence));
+        }
+    }
+
     pub fn reset_game(&mut self) {
+        #[cfg(feature = "nnue")]
+        let network = self.game.network();
         self.game = Game::initialise();
+        self.game
+            .set_chess960(self.options.check(options::CHESS960));
+        #[cfg(feature = "nnue")]
+        self.game.set_network(network);
+        self.start_position = None;
+        self.move_history.clear();
+        self.position_history.clear();
+        crash_report::record_position(None, None);
+        self.clear_transposition_table();
+        self.clear_move_history_scores();
     }
 }
 
+// Regenerates the magic numbers and zobrist hashes from the given seed as Rust source, so the
+// constants can be refreshed reproducibly - both tables are generated from a fresh generator
+pub fn generated_tables_source(seed: u32) -> Option<String> {
+    let magic_numbers = MagicNumbers::generate(&mut Random::new(seed)?);
+    let zobrist_hashes = ZobristHashes::generate(&mut Random::new(seed)?);
+
+    Some(magic_numbers.source(seed) + "\n" + &zobrist_hashes.source(seed))
+}
+
+// Only the magic numbers, once they have been checked against the ray scanning attack generator
+pub fn magic_numbers_source(seed: u32) -> Option<Result<String, String>> {
+    let magic_numbers = MagicNumbers::generate(&mut Random::new(seed)?);
+
+    Some(match magic_numbers.verify() {
+        Ok(()) => Ok(magic_numbers.source(seed)),
+        Err(square) => Err(format!(
+            "The magic number of {} maps different attacks to the same index",
+            square
+        )),
+    })
+}
+
+fn array_source(name: &str, values: &[u64]) -> String {
+    let mut source = format!("    {}: [\n", name);
+
+    for value in values {
+        source += &format!("        {},\n", hex_literal(*value));
+    }
+
+    source + "    ],\n"
+}
+
+// Digits are grouped with underscores as rustfmt and clippy expect of long literals
+fn hex_literal(value: u64) -> String {
+    let digits = format!("{:016X}", value);
+    let groups: Vec<&str> = (0..digits.len())
+        .step_by(4)
+        .map(|index| &digits[index..index + 4])
+        .collect();
+
+    format!("0x{}", groups.join("_"))
+}
+
+fn number_literal(value: u64) -> String {
+    let digits = value.to_string();
+    let first_group_length = match digits.len() % 3 {
+        0 => 3,
+        length => length,
+    };
+    let mut groups = vec![&digits[..first_group_length]];
+    groups.extend(
+        (first_group_length..digits.len())
+            .step_by(3)
+            .map(|index| &digits[index..index + 3]),
+    );
+
+    groups.join("_")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,7 +353,7 @@ mod tests {
         let fen = vec!["startpos"];
         engine.load_fen(&fen).unwrap();
 
-        let move_list = MoveList::generate_moves(&engine.game, &engine.attack_tables);
+        let move_list = MoveList::generate_moves(&engine.game, MoveFlag::All);
 
         assert_eq!(move_list._length(), 20);
     }
@@ -71,7 +371,7 @@ mod tests {
         ];
         engine.load_fen(&fen).unwrap();
 
-        let move_list = MoveList::generate_moves(&engine.game, &engine.attack_tables);
+        let move_list = MoveList::generate_moves(&engine.game, MoveFlag::All);
 
         assert_eq!(move_list._length(), 48);
     }
@@ -102,4 +402,55 @@ mod tests {
         engine.make_move("a6e2").unwrap();
         engine.make_move("c3e2").unwrap();
     }
+
+    #[test]
+    fn position_history() {
+        let mut engine = Engine::initialise();
+
+        assert_eq!(engine.history(), None);
+
+        let fen = vec!["startpos", "moves", "e2e4"];
+        engine.load_fen(&fen).unwrap();
+        engine.make_move("e2e4").unwrap();
+        engine.make_move("e7e5").unwrap();
+
+        assert_eq!(
+            engine.history(),
+            Some("position startpos moves e2e4 e7e5".to_string())
+        );
+
+        let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        engine.make_move("b4b5").unwrap();
+
+        assert!(engine.make_move("b5b1q").is_err());
+        assert_eq!(
+            engine.history(),
+            Some("position fen 4k3/8/5K2/8/1Q6/8/8/8 w - - 0 1 moves b4b5".to_string())
+        );
+
+        engine.reset_game();
+
+        assert_eq!(engine.history(), None);
+    }
+
+    #[test]
+    fn source_literals() {
+        assert_eq!(hex_literal(0x40_0408_4440_4084), "0x0040_0408_4440_4084");
+        assert_eq!(number_literal(1_804_289_383), "1_804_289_383");
+        assert_eq!(number_literal(42), "42");
+        assert_eq!(number_literal(123_456), "123_456");
+        assert!(generated_tables_source(0).is_none());
+    }
+
+    #[test]
+    fn hash_option() {
+        let mut engine = Engine::initialise();
+        engine.set_option("Hash", "1").unwrap();
+        engine.set_option("Clear Hash", "").unwrap();
+
+        assert!(engine.set_option("Hash", "sixteen").is_err());
+        assert!(engine.set_option("Hash", "0").is_ok());
+        assert_eq!(engine.options.spin(options::HASH), 1);
+    }
 }
@@ -0,0 +1,392 @@
+// Generates the attack tables from scratch. The build script includes this file to write the tables
+// into the binary, and the engine uses it to check them and to regenerate magic numbers, so it
+// depends on nothing else in the crate
+#![allow(dead_code)]
+
+pub type MagicNumber = u64;
+
+#[derive(Debug, PartialEq)]
+pub struct MagicNumbers {
+    pub(super) bishop_magic_numbers: [MagicNumber; 64],
+    pub(super) rook_magic_numbers: [MagicNumber; 64],
+}
+
+// Magic numbers generated with random_state = 1_804_289_383
+pub const MAGIC_NUMBERS: MagicNumbers = MagicNumbers {
+    bishop_magic_numbers: [
+        0x0040_0408_4440_4084,
+        0x0020_0420_8A00_4208,
+        0x0010_1900_4108_0202,
+        0x0108_0608_4504_2010,
+        0x0581_1041_8080_0210,
+        0x2112_0804_4620_0010,
+        0x1080_8208_2006_0210,
+        0x03C0_8084_1022_0200,
+        0x0004_0504_0444_0404,
+        0x0000_0210_0142_0088,
+        0x24D0_0808_0108_2102,
+        0x0001_020A_0A02_0400,
+        0x0000_0403_0820_0402,
+        0x0004_0110_0210_0800,
+        0x0401_4841_0410_4005,
+        0x0801_0104_0202_0200,
+        0x0040_0210_C388_0100,
+        0x0404_0220_2410_8200,
+        0x0810_0182_0020_4102,
+        0x0004_0028_01A0_2003,
+        0x0085_0408_2008_0400,
+        0x8101_02C8_0888_0400,
+        0x000E_9004_1088_4800,
+        0x8002_0204_8084_0102,
+        0x0220_2008_6509_0201,
+        0x2010_100A_0202_1202,
+        0x0152_0484_0802_2401,
+        0x0020_0800_0208_1110,
+        0x4001_0010_2100_4000,
+        0x8000_4040_0A01_1002,
+        0x00E4_0040_8101_1002,
+        0x001C_0040_0101_2080,
+        0x8004_2009_62A0_0220,
+        0x8422_1002_0850_0202,
+        0x2000_4022_0030_0C08,
+        0x8646_0200_8008_0080,
+        0x8002_0A02_0010_0808,
+        0x2010_0048_8011_1000,
+        0x6230_00A0_8001_1400,
+        0x4200_8C03_4020_9202,
+        0x0209_1882_4000_1000,
+        0x4004_08A8_8400_1800,
+        0x0011_0400_A608_0400,
+        0x1840_060A_4402_0800,
+        0x0090_0801_0400_0041,
+        0x0201_0110_0080_8101,
+        0x1A22_0808_0504_F080,
+        0x8012_0206_0021_1212,
+        0x0500_8610_1124_0000,
+        0x0180_8061_0820_0800,
+        0x4000_020E_0104_0044,
+        0x3000_0026_1044_000A,
+        0x0802_2411_0202_0002,
+        0x0020_9060_6121_0001,
+        0x5A84_8410_0401_0310,
+        0x0004_0108_0101_1C04,
+        0x000A_0101_0950_2200,
+        0x0000_004A_0201_2000,
+        0x5002_0101_0098_B028,
+        0x8040_0028_1104_0900,
+        0x0028_0000_1002_0204,
+        0x0600_0020_202D_0240,
+        0x8918_8448_4208_2200,
+        0x4010_0110_2902_0020,
+    ],
+    rook_magic_numbers: [
+        0x8A80_1040_0080_0020,
+        0x0140_0020_0010_0040,
+        0x0280_1880_A001_7001,
+        0x0100_0810_0100_0420,
+        0x0200_0200_1008_0420,
+        0x0300_1C00_0201_0008,
+        0x8480_0080_0200_0100,
+        0x2080_0880_0440_2900,
+        0x0000_8000_9820_4000,
+        0x2024_4010_0020_0040,
+        0x0100_8020_0080_1000,
+        0x0120_8008_0080_1000,
+        0x0208_8080_8800_0400,
+        0x0002_8022_0080_0400,
+        0x2200_8001_0002_0080,
+        0x0801_0000_6082_1100,
+        0x0080_0440_0642_2000,
+        0x0100_8080_2000_4000,
+        0x1210_8A00_1020_4200,
+        0x0140_8480_1000_0802,
+        0x0481_8280_1400_2800,
+        0x8094_0040_0200_4100,
+        0x4010_0400_1001_0802,
+        0x0000_0200_0880_6104,
+        0x0100_4000_8020_8000,
+        0x2040_0021_2008_1000,
+        0x0021_2006_8010_0081,
+        0x0020_1000_8008_0080,
+        0x0002_000A_0020_0410,
+        0x0000_0200_8080_0400,
+        0x0080_0884_0010_0102,
+        0x0080_0046_0004_2881,
+        0x4040_0080_4080_0020,
+        0x0440_0030_0020_0801,
+        0x0004_2000_1100_4500,
+        0x0188_0200_1010_0100,
+        0x0014_8004_0180_2800,
+        0x2080_0400_8080_0200,
+        0x0124_0802_0400_1001,
+        0x0200_0465_0200_0484,
+        0x0480_4000_8008_8020,
+        0x1000_4220_1003_4000,
+        0x0030_2001_0011_0040,
+        0x0000_1000_2101_0009,
+        0x2002_0801_0011_0004,
+        0x0202_0080_0400_8002,
+        0x0020_0200_0401_0100,
+        0x2048_4400_4082_0001,
+        0x0101_0022_0040_8200,
+        0x0040_8020_0040_1080,
+        0x4008_1420_0441_0100,
+        0x0206_0820_C012_0200,
+        0x0001_0010_0408_0100,
+        0x020C_0200_8004_0080,
+        0x2935_6108_3002_2400,
+        0x0044_4400_4100_9200,
+        0x0280_0010_4080_2101,
+        0x2100_1900_4000_2085,
+        0x80C0_0841_0010_2001,
+        0x4024_0810_0100_0421,
+        0x0002_0030_A024_4872,
+        0x0012_0010_0841_4402,
+        0x0200_6104_900A_0804,
+        0x0001_0040_8100_2402,
+    ],
+};
+
+pub const BISHOP_DIRECTIONS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+pub const ROOK_DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+pub const ROOK_OCCUPANCY_INDEX_MAX: usize = 4096;
+
+#[rustfmt::skip]
+const BISHOP_ATTACK_MASK_BIT_COUNT: [u8; 64] = [
+    6, 5, 5, 5, 5, 5, 5, 6,
+    5, 5, 5, 5, 5, 5, 5, 5,
+    5, 5, 7, 7, 7, 7, 5, 5,
+    5, 5, 7, 9, 9, 7, 5, 5,
+    5, 5, 7, 9, 9, 7, 5, 5, 
+    5, 5, 7, 7, 7, 7, 5, 5,
+    5, 5, 5, 5, 5, 5, 5, 5,
+    6, 5, 5, 5, 5, 5, 5, 6,
+];
+#[rustfmt::skip]
+const ROOK_ATTACK_MASK_BIT_COUNT: [u8; 64] = [
+    12, 11, 11, 11, 11, 11, 11, 12,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    12, 11, 11, 11, 11, 11, 11, 12,
+];
+
+#[derive(Clone, Copy)]
+pub enum LeaperPiece {
+    WhitePawn,
+    BlackPawn,
+    Knight,
+    King,
+}
+
+#[derive(Clone, Copy)]
+pub enum SliderPiece {
+    Bishop,
+    Rook,
+}
+
+impl SliderPiece {
+    pub fn directions(self) -> [(isize, isize); 4] {
+        match self {
+            SliderPiece::Bishop => BISHOP_DIRECTIONS,
+            SliderPiece::Rook => ROOK_DIRECTIONS,
+        }
+    }
+
+    pub fn attack_mask_bit_count(self, square: usize) -> u8 {
+        match self {
+            SliderPiece::Bishop => BISHOP_ATTACK_MASK_BIT_COUNT[square],
+            SliderPiece::Rook => ROOK_ATTACK_MASK_BIT_COUNT[square],
+        }
+    }
+}
+
+impl MagicNumbers {
+    pub fn magic_index(
+        &self,
+        attack_mask: u64,
+        board: u64,
+        piece: SliderPiece,
+        square: usize,
+    ) -> usize {
+        let magic_number = match piece {
+            SliderPiece::Bishop => self.bishop_magic_numbers[square],
+            SliderPiece::Rook => self.rook_magic_numbers[square],
+        };
+        let magic_index = (board & attack_mask).overflowing_mul(magic_number).0
+            >> (64 - piece.attack_mask_bit_count(square));
+
+        magic_index as usize
+    }
+}
+
+// Squares are numbered from a8, so shifting right moves a piece up the board and shifting left
+// moves it down
+pub fn leaper_attack_tables(piece: LeaperPiece) -> [u64; 64] {
+    let not_a_file = 0xFEFE_FEFE_FEFE_FEFE;
+    let not_h_file = 0x7F7F_7F7F_7F7F_7F7F;
+    let not_ab_file = 0xFCFC_FCFC_FCFC_FCFC;
+    let not_gh_file = 0x3F3F_3F3F_3F3F_3F3F;
+
+    let mut attack_tables = [0; 64];
+
+    for (square, attack_table) in attack_tables.iter_mut().enumerate() {
+        let bitboard = 1u64 << square;
+
+        *attack_table = match piece {
+            LeaperPiece::WhitePawn => {
+                ((bitboard >> 7) & not_a_file) | ((bitboard >> 9) & not_h_file)
+            }
+            LeaperPiece::BlackPawn => {
+                ((bitboard << 7) & not_h_file) | ((bitboard << 9) & not_a_file)
+            }
+            LeaperPiece::Knight => {
+                ((bitboard >> 6) & not_ab_file)
+                    | ((bitboard >> 10) & not_gh_file)
+                    | ((bitboard >> 15) & not_a_file)
+                    | ((bitboard >> 17) & not_h_file)
+                    | ((bitboard << 6) & not_gh_file)
+                    | ((bitboard << 10) & not_ab_file)
+                    | ((bitboard << 15) & not_h_file)
+                    | ((bitboard << 17) & not_a_file)
+            }
+            LeaperPiece::King => {
+                ((bitboard >> 1) & not_h_file)
+                    | ((bitboard >> 7) & not_a_file)
+                    | (bitboard >> 8)
+                    | ((bitboard >> 9) & not_h_file)
+                    | ((bitboard << 1) & not_a_file)
+                    | ((bitboard << 7) & not_h_file)
+                    | (bitboard << 8)
+                    | ((bitboard << 9) & not_a_file)
+            }
+        };
+    }
+
+    attack_tables
+}
+
+// The attack masks leave out the edge of the board, as a piece there never blocks anything further
+pub fn attack_mask(piece: SliderPiece, square: usize) -> u64 {
+    scan_rays(0, &piece.directions(), square, false)
+}
+
+pub fn attack_table(board: u64, piece: SliderPiece, square: usize) -> u64 {
+    scan_rays(board, &piece.directions(), square, true)
+}
+
+// The ray from the square in the direction to the edge of the board
+pub fn ray(direction: (isize, isize), square: usize) -> u64 {
+    scan_rays(0, &[direction], square, true)
+}
+
+// Walks out from the square in each direction, up to and including the first occupied square
+fn scan_rays(board: u64, directions: &[(isize, isize)], square: usize, include_edge: bool) -> u64 {
+    let on_board = |rank: isize, file: isize| (0..8).contains(&rank) && (0..8).contains(&file);
+    let mut attacks = 0;
+
+    for &(rank_step, file_step) in directions {
+        let mut rank = (square / 8) as isize + rank_step;
+        let mut file = (square % 8) as isize + file_step;
+
+        while on_board(rank, file) && (include_edge || on_board(rank + rank_step, file + file_step))
+        {
+            let bit = 1 << (rank * 8 + file);
+            attacks |= bit;
+
+            if board & bit != 0 {
+                break;
+            }
+
+            rank += rank_step;
+            file += file_step;
+        }
+    }
+
+    attacks
+}
+
+// The subset of the attack mask picked out by the bits of the index
+pub fn set_occupancy(index: usize, attack_mask: u64) -> u64 {
+    let mut occupancy = 0;
+    let mut remaining = attack_mask;
+    let mut count = 0;
+
+    while remaining != 0 {
+        if index & (1 << count) != 0 {
+            occupancy |= remaining & remaining.wrapping_neg();
+        }
+
+        remaining &= remaining - 1;
+        count += 1;
+    }
+
+    occupancy
+}
+
+// The attack tables of every square only take as many entries as their attack mask has
+// occupancies, so they are packed one after another into a single table, bishops first, with each
+// square looked up from where its own table starts - well under half the memory of giving every
+// square a table as long as the longest
+pub fn magic_attack_tables() -> Vec<u64> {
+    let mut attack_tables = Vec::new();
+
+    for piece in [SliderPiece::Bishop, SliderPiece::Rook] {
+        for square in 0..64 {
+            let attack_mask = attack_mask(piece, square);
+            let offset = attack_tables.len();
+            attack_tables.resize(offset + (1 << piece.attack_mask_bit_count(square)), 0);
+
+            for index in 0..1 << piece.attack_mask_bit_count(square) {
+                let occupancy = set_occupancy(index, attack_mask);
+                let magic_index = MAGIC_NUMBERS.magic_index(attack_mask, occupancy, piece, square);
+                attack_tables[offset + magic_index] = attack_table(occupancy, piece, square);
+            }
+        }
+    }
+
+    attack_tables
+}
+
+// Where the table of each square starts in the packed magic attack tables
+pub fn magic_attack_table_offsets(piece: SliderPiece) -> [usize; 64] {
+    let mut offset = match piece {
+        SliderPiece::Bishop => 0,
+        SliderPiece::Rook => (0..64)
+            .map(|square| 1 << SliderPiece::Bishop.attack_mask_bit_count(square))
+            .sum(),
+    };
+    let mut offsets = [0; 64];
+
+    for (square, square_offset) in offsets.iter_mut().enumerate() {
+        *square_offset = offset;
+        offset += 1 << piece.attack_mask_bit_count(square);
+    }
+
+    offsets
+}
+
+// Two squares share a line when a slider on one, on an empty board, attacks the other - the
+// squares between them are then the ones both attack with only the other square occupied
+pub fn between_and_line(square: usize, other_square: usize) -> (u64, u64) {
+    for piece in [SliderPiece::Bishop, SliderPiece::Rook] {
+        let attacks = attack_table(0, piece, square);
+
+        if square == other_square || attacks & (1 << other_square) == 0 {
+            continue;
+        }
+
+        let between = attack_table(1 << other_square, piece, square)
+            & attack_table(1 << square, piece, other_square);
+        let line =
+            (attacks & attack_table(0, piece, other_square)) | (1 << square) | (1 << other_square);
+
+        return (between, line);
+    }
+
+    (0, 0)
+}
@@ -0,0 +1,355 @@
+mod polyglot_random;
+
+use super::{
+    attack_tables::ATTACK_TABLES,
+    game::{CastlingType, Game, Piece, Side, Square},
+    moves::{Move, MoveType},
+    pgn::{self, PgnGame, PgnResult},
+    zobrist_hashes::ZobristKey,
+};
+use crate::uci::InputError;
+use polyglot_random::RANDOM64;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+};
+
+pub const BOOK_MAX_PLY_DEFAULT: usize = 20;
+pub const BOOK_MIN_GAMES_DEFAULT: u32 = 3;
+
+// Points a move scores for the side playing it, as in Polyglot's book maker
+const WIN_POINTS: u32 = 2;
+const DRAW_POINTS: u32 = 1;
+
+#[derive(Default)]
+struct MoveStatistics {
+    games: u32,
+    points: u32,
+}
+
+// Counts the moves played in each position of a collection of games, for writing out as a book in
+// the Polyglot layout - 16 big-endian bytes of key, move, weight and learn data per entry, sorted
+// by key. Positions are keyed with Polyglot's own random numbers, so other Polyglot tools can read
+// the book
+pub struct BookBuilder {
+    max_ply: usize,
+    min_games: u32,
+    moves: HashMap<(ZobristKey, u16), MoveStatistics>,
+}
+
+impl BookBuilder {
+    pub fn new(max_ply: usize, min_games: u32) -> Self {
+        Self {
+            max_ply,
+            min_games,
+            moves: HashMap::new(),
+        }
+    }
+
+    // Only the first moves of the game are counted, and a move scores by the result of the game
+    // for the side which played it
+    pub fn add_game(&mut self, pgn_game: &PgnGame) -> Result<(), InputError> {
+        let mut game = Game::initialise();
+        game.load_fen(&pgn_game.fen().split_whitespace().collect::<Vec<&str>>())?;
+
+        for mv in pgn_game.moves().iter().take(self.max_ply) {
+            let side = game.side_to_move();
+            let points = match (pgn_game.result(), side) {
+                (PgnResult::WhiteWin, Side::White) | (PgnResult::BlackWin, Side::Black) => {
+                    WIN_POINTS
+                }
+                (PgnResult::Draw, _) => DRAW_POINTS,
+                _ => 0,
+            };
+            let statistics = self
+                .moves
+                .entry((polyglot_key(&game), polyglot_move(&game, mv)))
+                .or_default();
+            statistics.games += 1;
+            statistics.points += points;
+
+            game.make_move(mv)?;
+        }
+
+        Ok(())
+    }
+
+    // Moves played in fewer games than the threshold are left out, and the rest are ordered by
+    // weight within each position, as Polyglot readers expect
+    pub fn entries(&self) -> Vec<[u8; 16]> {
+        let mut entries: Vec<(ZobristKey, u16, u16)> = self
+            .moves
+            .iter()
+            .filter(|(_, statistics)| statistics.games >= self.min_games)
+            .map(|((key, mv), statistics)| {
+                (*key, *mv, statistics.points.min(u16::MAX as u32) as u16)
+            })
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0).then(b.2.cmp(&a.2)).then(a.1.cmp(&b.1)));
+
+        entries
+            .into_iter()
+            .map(|(key, mv, weight)| {
+                let mut entry = [0; 16];
+                entry[..8].copy_from_slice(&key.to_be_bytes());
+                entry[8..10].copy_from_slice(&mv.to_be_bytes());
+                entry[10..12].copy_from_slice(&weight.to_be_bytes());
+
+                entry
+            })
+            .collect()
+    }
+}
+
+// Builds a book from every readable game of a PGN file, returning the number of games read and
+// the number of entries written - unreadable games are skipped, as large collections usually
+// have a few
+pub fn build_book(
+    pgn_path: &str,
+    book_path: &str,
+    max_ply: usize,
+    min_games: u32,
+) -> Result<(usize, usize), InputError> {
+    let input = fs::read_to_string(pgn_path).map_err(|error| InputError::BookFile(error.kind()))?;
+    let mut book_builder = BookBuilder::new(max_ply, min_games);
+    let mut games_read = 0;
+
+    for pgn_game in pgn::parse_games(&input).iter().flatten() {
+        if book_builder.add_game(pgn_game).is_ok() {
+            games_read += 1;
+        }
+    }
+
+    let entries = book_builder.entries();
+    let file = File::create(book_path).map_err(|error| InputError::BookFile(error.kind()))?;
+    let mut writer = BufWriter::new(file);
+    entries
+        .iter()
+        .try_for_each(|entry| writer.write_all(entry))
+        .and_then(|_| writer.flush())
+        .map_err(|error| InputError::BookFile(error.kind()))?;
+
+    Ok((games_read, entries.len()))
+}
+
+// Random64 offsets of the castling rights, en passant files and side to move numbers
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+// The en passant file only counts when a pawn of the side to move could take en passant, and the
+// turn number is only added with white to move
+fn polyglot_key(game: &Game) -> ZobristKey {
+    let mut key = 0;
+
+    for (bitboard, piece, side) in game.piece_bitboards() {
+        let kind = piece as usize * 2 + (side == Side::White) as usize;
+
+        for square in bitboard.squares() {
+            key ^= RANDOM64[64 * kind + polyglot_square(square)];
+        }
+    }
+
+    for (index, castling_type) in [
+        CastlingType::WhiteShort,
+        CastlingType::WhiteLong,
+        CastlingType::BlackShort,
+        CastlingType::BlackLong,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if game.castling_type_allowed(castling_type) {
+            key ^= RANDOM64[CASTLING_OFFSET + index];
+        }
+    }
+
+    let side = game.side_to_move();
+
+    if let Some(square) = game.en_passant_square() {
+        let capturing_pawns =
+            ATTACK_TABLES.attack_table(game.board(None), Piece::Pawn, side.opponent_side(), square)
+                & game.piece_bitboard(Piece::Pawn, side);
+
+        if capturing_pawns.count_bits() > 0 {
+            key ^= RANDOM64[EN_PASSANT_OFFSET + square.file()];
+        }
+    }
+
+    if side == Side::White {
+        key ^= RANDOM64[TURN_OFFSET];
+    }
+
+    key
+}
+
+fn polyglot_square(square: Square) -> usize {
+    (7 - square.rank()) * 8 + square.file()
+}
+
+// Target file and rank in bits 0-5, source file and rank in bits 6-11 and the promoted piece in
+// bits 12-14, with castling written as the king taking its own rook
+fn polyglot_move(game: &Game, mv: &Move) -> u16 {
+    let target_square = match mv.move_type() {
+        MoveType::Castling => {
+            game.castling_rook_square(CastlingType::from_move(mv, game.side_to_move()))
+        }
+        _ => mv.target_square(),
+    };
+    let promoted_piece = mv.promoted_piece().map_or(0, |piece| piece as usize);
+
+    (polyglot_square(target_square)
+        | polyglot_square(mv.source_square()) << 6
+        | promoted_piece << 12) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::moves::{MoveFlag, MoveList},
+        *,
+    };
+    use std::env;
+
+    #[test]
+    fn encode_polyglot_moves() {
+        let mut game = Game::initialise();
+        game.load_fen(&["r3k2r/8/8/8/8/8/6p1/R3K2R", "b", "KQkq", "-", "0", "1"])
+            .unwrap();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+
+        for (move_string, polyglot_move_value) in [
+            ("e8g8", 0o74_77),
+            ("e8c8", 0o74_70),
+            ("g2h1q", 0o4_16_07),
+            ("g2g1n", 0o1_16_06),
+            ("a8a1", 0o70_00),
+        ] {
+            let mv = move_list.find_move_from_string(move_string).unwrap();
+
+            assert_eq!(
+                polyglot_move(&game, &mv),
+                polyglot_move_value,
+                "{}",
+                move_string
+            );
+        }
+    }
+
+    // The example keys of the Polyglot book format description
+    #[test]
+    fn generate_polyglot_keys() {
+        for (fen, key) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                0x463B_9618_1691_FC9C,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823C_9B50_FD11_4196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756_B944_61C5_0FB0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662F_AFB9_65DB_29D4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22A4_8B5A_8E47_FF78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+                0x652A_607C_A3F2_42C1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00FD_D303_C946_BDD9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3C81_23EA_7B06_7637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5C3F_9B82_9B27_9560,
+            ),
+        ] {
+            let mut game = Game::initialise();
+            game.load_fen(&fen.split_whitespace().collect::<Vec<&str>>())
+                .unwrap();
+
+            assert_eq!(polyglot_key(&game), key, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn build_weighted_book() {
+        let pgn = "1. e4 e5 2. Nf3 1-0\n\
+            1. e4 c5 0-1\n\
+            1. e4 e5 1/2-1/2\n\
+            1. d4 d5 1-0\n\
+            1. e4 Ke7 2. Ke2 {illegal} Kf1 *\n";
+        let pgn_path = env::temp_dir().join("pineapple_build_weighted_book.pgn");
+        let book_path = env::temp_dir().join("pineapple_build_weighted_book.bin");
+        fs::write(&pgn_path, pgn).unwrap();
+
+        let (games_read, entry_count) = build_book(
+            pgn_path.to_str().unwrap(),
+            book_path.to_str().unwrap(),
+            BOOK_MAX_PLY_DEFAULT,
+            2,
+        )
+        .unwrap();
+        let book = fs::read(&book_path).unwrap();
+        fs::remove_file(&pgn_path).unwrap();
+        fs::remove_file(&book_path).unwrap();
+
+        // Only 1. e4 from the start and 1... e5 after it were played in two games or more
+        assert_eq!(games_read, 4);
+        assert_eq!(entry_count, 2);
+        assert_eq!(book.len(), 32);
+
+        let mut game = Game::initialise();
+        game.load_fen(&["startpos"]).unwrap();
+        let start_key = polyglot_key(&game);
+        assert_eq!(start_key, 0x463B_9618_1691_FC9C);
+        let entry = |index: usize| {
+            let entry = &book[index * 16..(index + 1) * 16];
+
+            (
+                u64::from_be_bytes(entry[..8].try_into().unwrap()),
+                u16::from_be_bytes(entry[8..10].try_into().unwrap()),
+                u16::from_be_bytes(entry[10..12].try_into().unwrap()),
+            )
+        };
+        let start_entry = match entry(0).0 == start_key {
+            true => entry(0),
+            false => entry(1),
+        };
+
+        // 1. e4 won one game, lost one and drew one for white
+        assert_eq!(
+            start_entry,
+            (start_key, 0o14_34, (WIN_POINTS + DRAW_POINTS) as u16)
+        );
+        assert!(entry(0).0 <= entry(1).0);
+    }
+
+    #[test]
+    fn book_depth_limit() {
+        let mut book_builder = BookBuilder::new(1, 1);
+
+        for pgn_game in pgn::parse_games("1. e4 e5 2. Nf3 *\n1. d4 *")
+            .iter()
+            .flatten()
+        {
+            book_builder.add_game(pgn_game).unwrap();
+        }
+
+        assert_eq!(book_builder.entries().len(), 2);
+    }
+}
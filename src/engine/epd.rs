@@ -0,0 +1,230 @@
+use super::game::Game;
+use crate::uci::{EpdError, InputError};
+use std::{mem, str::FromStr};
+
+// A position from an EPD line, as used by test suites - the board, side to move, castling rights
+// and en passant square, followed by operations such as `bm Qg6; id "WAC.001";`
+pub struct EpdRecord {
+    fen: String,
+    best_moves: Vec<String>,
+    avoid_moves: Vec<String>,
+    id: Option<String>,
+    centipawn_evaluation: Option<i32>,
+}
+
+impl EpdRecord {
+    // The position as a full FEN, with the clocks from the hmvc and fmvn operations if given
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    // Moves are kept as written, which in test suites is SAN
+    pub fn best_moves(&self) -> &[String] {
+        &self.best_moves
+    }
+
+    pub fn avoid_moves(&self) -> &[String] {
+        &self.avoid_moves
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn centipawn_evaluation(&self) -> Option<i32> {
+        self.centipawn_evaluation
+    }
+}
+
+impl FromStr for EpdRecord {
+    type Err = InputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut position = Vec::new();
+        let mut remaining = s.trim_start();
+
+        for _ in 0..4 {
+            let (field, rest) = remaining
+                .split_once(char::is_whitespace)
+                .unwrap_or((remaining, ""));
+            position.push(field);
+            remaining = rest.trim_start();
+        }
+
+        let mut record = Self {
+            fen: String::new(),
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            id: None,
+            centipawn_evaluation: None,
+        };
+        let mut halfmove_clock = "0".to_string();
+        let mut fullmove_number = "1".to_string();
+
+        for operation in parse_operations(remaining)? {
+            let (opcode, operands) = operation.split_first().expect("operations are never empty");
+            let operand = || match operands {
+                [operand] => Ok(operand.clone()),
+                _ => Err(InputError::InvalidEpd(EpdError::InvalidOperand(
+                    opcode.clone(),
+                ))),
+            };
+
+            match opcode.as_str() {
+                "bm" | "am" if operands.is_empty() => {
+                    return Err(InputError::InvalidEpd(EpdError::InvalidOperand(
+                        opcode.clone(),
+                    )))
+                }
+                "bm" => record.best_moves = operands.to_vec(),
+                "am" => record.avoid_moves = operands.to_vec(),
+                "id" => record.id = Some(operand()?),
+                "ce" => {
+                    record.centipawn_evaluation = Some(operand()?.parse().map_err(|_| {
+                        InputError::InvalidEpd(EpdError::InvalidOperand(opcode.clone()))
+                    })?)
+                }
+                "hmvc" => halfmove_clock = operand()?,
+                "fmvn" => fullmove_number = operand()?,
+                // Suites carry plenty of other operations, such as comments and engine analysis,
+                // which are of no use here
+                _ => {}
+            }
+        }
+
+        // Loaded into a game so the position goes through the same checks as the position command
+        position.extend([halfmove_clock.as_str(), fullmove_number.as_str()]);
+        let mut game = Game::initialise();
+        game.load_fen(&position)?;
+        record.fen = game.to_fen();
+
+        Ok(record)
+    }
+}
+
+// Each operation as its opcode followed by its operands - operations end with a semicolon, which
+// the last one is sometimes written without, and string operands are quoted as they may contain
+// spaces and semicolons
+fn parse_operations(input: &str) -> Result<Vec<Vec<String>>, InputError> {
+    let mut operations = Vec::new();
+    let mut operation = Vec::new();
+    let mut token = String::new();
+    // Set once a quote is seen, so an empty string still counts as an operand
+    let mut quoted_token = false;
+    let mut in_string = false;
+
+    for character in input.chars() {
+        match character {
+            '"' => {
+                in_string = !in_string;
+                quoted_token = true;
+            }
+            _ if in_string => token.push(character),
+            ';' => {
+                push_token(&mut operation, &mut token, &mut quoted_token);
+
+                if !operation.is_empty() {
+                    operations.push(mem::take(&mut operation));
+                }
+            }
+            _ if character.is_whitespace() => {
+                push_token(&mut operation, &mut token, &mut quoted_token)
+            }
+            _ => token.push(character),
+        }
+    }
+
+    if in_string {
+        return Err(InputError::InvalidEpd(EpdError::UnterminatedString));
+    }
+
+    push_token(&mut operation, &mut token, &mut quoted_token);
+
+    if !operation.is_empty() {
+        operations.push(operation);
+    }
+
+    Ok(operations)
+}
+
+fn push_token(operation: &mut Vec<String>, token: &mut String, quoted_token: &mut bool) {
+    if !token.is_empty() || *quoted_token {
+        operation.push(mem::take(token));
+        *quoted_token = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci::FenError;
+
+    #[test]
+    fn parse_record() {
+        let record: EpdRecord =
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";"
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            record.fen(),
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+        );
+        assert_eq!(record.best_moves(), ["Qg6"]);
+        assert!(record.avoid_moves().is_empty());
+        assert_eq!(record.id(), Some("WAC.001"));
+        assert_eq!(record.centipawn_evaluation(), None);
+
+        let record: EpdRecord = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq -  \
+            am Ba6 Qh5 ; ce -15;hmvc 2; fmvn 3; c0 \"quoted; with \\ semicolons\"; id \"\""
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            record.fen(),
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"
+        );
+        assert!(record.best_moves().is_empty());
+        assert_eq!(record.avoid_moves(), ["Ba6", "Qh5"]);
+        assert_eq!(record.centipawn_evaluation(), Some(-15));
+        assert_eq!(record.id(), Some(""));
+    }
+
+    #[test]
+    fn malformed_records() {
+        for (input, expected_error) in [
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - bm;",
+                EpdError::InvalidOperand("bm".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - ce 1.5;",
+                EpdError::InvalidOperand("ce".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - id a b;",
+                EpdError::InvalidOperand("id".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - id \"WAC;",
+                EpdError::UnterminatedString,
+            ),
+        ] {
+            assert!(
+                matches!(input.parse::<EpdRecord>(), Err(InputError::InvalidEpd(ref error)) if *error == expected_error),
+                "{}",
+                input
+            );
+        }
+
+        assert!(matches!(
+            "4k3/8/8/8/8/8/8/4K3 x - - bm Kd2;".parse::<EpdRecord>(),
+            Err(InputError::InvalidFen(FenError::SideToMove))
+        ));
+        assert!(matches!(
+            "4k3/8/8/8/8/8/8/4K3 w - - hmvc x;".parse::<EpdRecord>(),
+            Err(InputError::InvalidFen(FenError::ParseHalfmoveClock))
+        ));
+        assert!("4k3/8/8/8/8/8/8/4K3 w".parse::<EpdRecord>().is_err());
+    }
+}
@@ -1,12 +1,16 @@
+#[cfg(feature = "nnue")]
+use super::nnue::{Network, NnueState};
 use super::{
-    attack_tables::AttackTables,
-    moves::{Move, MoveType},
+    attack_tables,
+    moves::{Move, MoveFlag, MoveList, MoveType},
     search::Value,
     zobrist_hashes::{self, ZobristKey},
 };
 use crate::uci::{FenError, InputError};
 use num_derive::FromPrimitive;
 use num_traits::{AsPrimitive, FromPrimitive, Unsigned};
+#[cfg(feature = "nnue")]
+use std::sync::Arc;
 use std::{
     ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Shl, Shr, ShrAssign},
     str::FromStr,
@@ -15,6 +19,11 @@ use strum::{IntoEnumIterator, ParseError};
 use strum_macros::{Display, EnumIter, EnumString};
 
 const HALFMOVE_CLOCK_MAX: u8 = 99;
+// Index of the squares occupied by either side, after those of each side
+const BOTH_SIDES_OCCUPANCY: usize = 2;
+// In the order of the castling types
+const STANDARD_CASTLING_ROOK_SQUARES: [Square; 4] =
+    [Square::H1, Square::A1, Square::H8, Square::A8];
 
 #[derive(Clone)]
 pub struct Game {
@@ -30,8 +39,31 @@ pub struct Game {
     black_rooks: Bitboard,
     black_queens: Bitboard,
     black_king: Bitboard,
+    // The piece on each square, kept alongside the bitboards so a square can be looked up directly
+    mailbox: [Option<(Piece, Side)>; 64],
+    // Squares occupied by each side, indexed by the Side enum, followed by those of both sides
+    occupancies: [Bitboard; 3],
     side_to_move: Side,
     castling_rights: CastlingRights,
+    castling_rook_squares: [Square; 4],
+    en_passant_square: Option<Square>,
+    halfmove_clock: u8,
+    // Starts at one and goes up after every move of black, as in FEN
+    fullmove_number: u16,
+    zobrist_key: ZobristKey,
+    chess960: bool,
+    undo_stack: Vec<UndoState>,
+    #[cfg(feature = "nnue")]
+    nnue: Option<NnueState>,
+}
+
+// What a move changes that cannot be worked out from the move itself, saved before it is made - a
+// null move has no move
+#[derive(Clone)]
+struct UndoState {
+    mv: Option<Move>,
+    captured_piece: Option<Piece>,
+    castling_rights: CastlingRights,
     en_passant_square: Option<Square>,
     halfmove_clock: u8,
     zobrist_key: ZobristKey,
@@ -52,16 +84,35 @@ impl Game {
             black_rooks: Bitboard(0),
             black_queens: Bitboard(0),
             black_king: Bitboard(0),
+            mailbox: [None; 64],
+            occupancies: [Bitboard(0); 3],
             side_to_move: Side::White,
             castling_rights: CastlingRights(0),
+            castling_rook_squares: STANDARD_CASTLING_ROOK_SQUARES,
             en_passant_square: None,
             halfmove_clock: 0,
+            fullmove_number: 1,
             zobrist_key: 0,
+            chess960: false,
+            undo_stack: Vec::new(),
+            #[cfg(feature = "nnue")]
+            nnue: None,
         }
     }
 
+    // A whole FEN string, or `startpos` for the standard starting position
+    pub fn from_fen(fen: &str) -> Result<Self, InputError> {
+        let mut game = Self::initialise();
+        game.load_fen(&fen.split_whitespace().collect::<Vec<&str>>())?;
+
+        Ok(game)
+    }
+
     pub fn load_fen(&mut self, fen: &[&str]) -> Result<(), InputError> {
-        if fen[0] == "startpos" {
+        // Missing fields are treated as empty, so they fail to parse instead of panicking
+        let fen_field = |index: usize| fen.get(index).copied().unwrap_or_default();
+
+        if fen_field(0) == "startpos" {
             self.white_pawns = Bitboard(0xFF_0000_0000_0000);
             self.white_knights = Bitboard(0x4200_0000_0000_0000);
             self.white_bishops = Bitboard(0x2400_0000_0000_0000);
@@ -77,11 +128,16 @@ impl Game {
             self.black_king = Bitboard(0x10);
 
             self.side_to_move = Side::White;
-            self.castling_rights = CastlingRights::initialise("KQkq")?;
+            self.castling_rights = CastlingRights(0b1111);
+            self.castling_rook_squares = STANDARD_CASTLING_ROOK_SQUARES;
             self.en_passant_square = None;
             self.halfmove_clock = 0;
+            self.fullmove_number = 1;
 
+            self.generate_square_lookups();
             self.zobrist_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(self);
+            self.undo_stack.clear();
+            self.refresh_nnue();
 
             return Ok(());
         }
@@ -101,71 +157,96 @@ impl Game {
         let mut black_king = Bitboard(0);
 
         let mut square_index = 0;
+        let mut rank_start = 0;
 
-        for character in fen[0].chars() {
+        for character in fen_field(0).chars() {
             match character {
                 'P' => {
-                    white_pawns.set_bit(Square::from_usize(square_index).unwrap());
+                    white_pawns.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'N' => {
-                    white_knights.set_bit(Square::from_usize(square_index).unwrap());
+                    white_knights.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'B' => {
-                    white_bishops.set_bit(Square::from_usize(square_index).unwrap());
+                    white_bishops.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'R' => {
-                    white_rooks.set_bit(Square::from_usize(square_index).unwrap());
+                    white_rooks.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'Q' => {
-                    white_queens.set_bit(Square::from_usize(square_index).unwrap());
+                    white_queens.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'K' => {
-                    white_king.set_bit(Square::from_usize(square_index).unwrap());
+                    white_king.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'p' => {
-                    black_pawns.set_bit(Square::from_usize(square_index).unwrap());
+                    black_pawns.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'n' => {
-                    black_knights.set_bit(Square::from_usize(square_index).unwrap());
+                    black_knights.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'b' => {
-                    black_bishops.set_bit(Square::from_usize(square_index).unwrap());
+                    black_bishops.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'r' => {
-                    black_rooks.set_bit(Square::from_usize(square_index).unwrap());
+                    black_rooks.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'q' => {
-                    black_queens.set_bit(Square::from_usize(square_index).unwrap());
+                    black_queens.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
                 'k' => {
-                    black_king.set_bit(Square::from_usize(square_index).unwrap());
+                    black_king.set_bit(Self::fen_square(square_index, rank_start)?);
                     square_index += 1;
                 }
-                '0'..='9' => square_index += character as usize - '0' as usize,
-                '/' => {}
+                '1'..='9' => square_index += character as usize - '0' as usize,
+                '/' => {
+                    if square_index != rank_start + 8 {
+                        return Err(InputError::InvalidFen(FenError::RankLength));
+                    }
+
+                    rank_start = square_index;
+                }
                 _ => return Err(InputError::InvalidFen(FenError::BoardPosition)),
             }
         }
 
-        let side_to_move = match fen[1] {
+        // Every rank must cover exactly eight squares, and there must be eight of them
+        if square_index != rank_start + 8 {
+            return Err(InputError::InvalidFen(FenError::RankLength));
+        }
+
+        if square_index != 64 {
+            return Err(InputError::InvalidFen(FenError::BoardPosition));
+        }
+
+        // Pawns on their promotion rank would generate moves off the board
+        if white_pawns & 0xFFu64 != 0u64 || black_pawns & 0xFF00_0000_0000_0000u64 != 0u64 {
+            return Err(InputError::InvalidFen(FenError::PawnOnBackRank));
+        }
+
+        let side_to_move = match fen_field(1) {
             "w" => Side::White,
             "b" => Side::Black,
             _ => return Err(InputError::InvalidFen(FenError::SideToMove)),
         };
-        let castling_rights = CastlingRights::initialise(fen[2])?;
-        let en_passant_square = Self::parse_en_passant_square(fen[3])?;
-        let halfmove_clock = match fen[4].parse() {
+        let (castling_rights, castling_rook_squares) = Self::parse_castling_rights(
+            fen_field(2),
+            [white_king, black_king],
+            [white_rooks, black_rooks],
+        )?;
+        let en_passant_square = Self::parse_en_passant_square(fen_field(3), side_to_move)?;
+        let halfmove_clock = match fen_field(4).parse() {
             Ok(halfmove_clock) => {
                 if halfmove_clock > HALFMOVE_CLOCK_MAX {
                     return Err(InputError::InvalidFen(FenError::InvalidHalfmoveClock));
@@ -175,6 +256,14 @@ impl Game {
             }
             Err(_) => return Err(InputError::InvalidFen(FenError::ParseHalfmoveClock)),
         };
+        // Often left off, as in EPD, so a missing fullmove number is taken as the first move
+        let fullmove_number = match fen_field(5) {
+            "" => 1,
+            fullmove_number => match fullmove_number.parse() {
+                Ok(fullmove_number) if fullmove_number > 0 => fullmove_number,
+                _ => return Err(InputError::InvalidFen(FenError::FullmoveNumber)),
+            },
+        };
 
         self.white_pawns = white_pawns;
         self.white_knights = white_knights;
@@ -192,195 +281,441 @@ impl Game {
 
         self.side_to_move = side_to_move;
         self.castling_rights = castling_rights;
+        self.castling_rook_squares = castling_rook_squares;
         self.en_passant_square = en_passant_square;
         self.halfmove_clock = halfmove_clock;
+        self.fullmove_number = fullmove_number;
 
+        self.generate_square_lookups();
         self.zobrist_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(self);
+        self.undo_stack.clear();
+        self.refresh_nnue();
+
+        Ok(())
+    }
+
+    // Loads the position only if it is also legal, leaving the game as it was otherwise
+    pub fn load_fen_strict(&mut self, fen: &[&str]) -> Result<(), InputError> {
+        let mut game = self.clone();
+        game.load_fen(fen)?;
+        game.validate().map_err(InputError::InvalidFen)?;
+        *self = game;
 
         Ok(())
     }
 
-    pub fn make_move(&mut self, mv: &Move, attack_tables: &AttackTables) -> Result<(), InputError> {
-        let mut game_clone = self.clone();
-        let side = game_clone.side_to_move;
+    // Checks load_fen leaves to the GUI - each side has one king, the side not to move is not in
+    // check, and every castling right has its king and rook on the back rank
+    pub fn validate(&self) -> Result<(), FenError> {
+        for side in Side::iter() {
+            if self.piece_bitboard(Piece::King, side).count_bits() != 1 {
+                return Err(FenError::KingCount);
+            }
+        }
+
+        if self.king_in_check(self.side_to_move.opponent_side()) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        for castling_type in CastlingType::iter() {
+            if !self.castling_type_allowed(castling_type) {
+                continue;
+            }
+
+            let side = castling_type.side();
+            let rook_square = self.castling_rook_square(castling_type);
+            let king_on_back_rank = self
+                .piece_bitboard(Piece::King, side)
+                .get_lsb_square()
+                .is_some_and(|king_square| king_square.rank() == rook_square.rank());
+
+            if !king_on_back_rank
+                || !self
+                    .piece_bitboard(Piece::Rook, side)
+                    .bit_occupied(rook_square)
+            {
+                return Err(FenError::ImpossibleCastlingRights);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Made in place, with the state needed to undo it saved on the undo stack - an illegal move is
+    // undone straight away, leaving the game as it was
+    pub fn make_move(&mut self, mv: &Move) -> Result<(), InputError> {
+        let side = self.side_to_move;
         let opponent_side = side.opponent_side();
-        game_clone
-            .mut_piece_bitboard(mv.piece(), side)
-            .pop_bit(mv.source_square());
-        game_clone.zobrist_key ^=
+        let captured_piece = match mv.move_type() {
+            MoveType::Capture => self
+                .piece_at_square(mv.target_square())
+                .map(|(piece, _)| piece),
+            _ => None,
+        };
+        self.undo_stack.push(UndoState {
+            mv: Some(*mv),
+            captured_piece,
+            castling_rights: self.castling_rights.clone(),
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            zobrist_key: self.zobrist_key,
+        });
+
+        self.remove_piece(mv.piece(), side, mv.source_square());
+        self.zobrist_key ^=
             zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(mv.piece(), side, mv.source_square());
+        self.remove_feature(mv.piece(), side, mv.source_square());
 
-        if let Some(en_passant_square) = game_clone.en_passant_square {
-            game_clone.en_passant_square = None;
-            game_clone.zobrist_key ^=
+        if let Some(en_passant_square) = self.en_passant_square {
+            self.en_passant_square = None;
+            self.zobrist_key ^=
                 zobrist_hashes::ZOBRIST_HASHES.en_passant_square_hash(en_passant_square);
         }
 
-        let target_square_index = mv.target_square() as usize;
-
         match mv.move_type() {
             MoveType::Quiet => {}
             MoveType::Capture => {
-                if let Some((piece, side)) = game_clone.piece_at_square(mv.target_square()) {
-                    game_clone
-                        .mut_piece_bitboard(piece, side)
-                        .pop_bit(mv.target_square());
-                    game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
+                if let Some(piece) = captured_piece {
+                    self.remove_piece(piece, opponent_side, mv.target_square());
+                    self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
                         piece,
-                        side,
+                        opponent_side,
                         mv.target_square(),
                     );
+                    self.remove_feature(piece, opponent_side, mv.target_square());
                 }
             }
             MoveType::DoublePawnPush => {
-                let en_passant_square = match side {
-                    Side::White => Square::from_usize(target_square_index + 8),
-                    Side::Black => Square::from_usize(target_square_index - 8),
-                }
-                .unwrap();
-                game_clone.en_passant_square = Some(en_passant_square);
-                game_clone.zobrist_key ^=
+                let en_passant_square = Self::pawn_behind_square(mv.target_square(), side);
+                self.en_passant_square = Some(en_passant_square);
+                self.zobrist_key ^=
                     zobrist_hashes::ZOBRIST_HASHES.en_passant_square_hash(en_passant_square);
             }
             MoveType::EnPassant => {
-                let capture_square = match side {
-                    Side::White => Square::from_usize(target_square_index + 8),
-                    Side::Black => Square::from_usize(target_square_index - 8),
-                }
-                .unwrap();
-                game_clone
-                    .mut_piece_bitboard(Piece::Pawn, opponent_side)
-                    .pop_bit(capture_square);
-                game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
+                let capture_square = Self::pawn_behind_square(mv.target_square(), side);
+                self.remove_piece(Piece::Pawn, opponent_side, capture_square);
+                self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
                     Piece::Pawn,
                     opponent_side,
                     capture_square,
                 );
+                self.remove_feature(Piece::Pawn, opponent_side, capture_square);
             }
-            MoveType::Castling => match side {
-                Side::White => {
-                    match mv.target_square() {
-                        Square::C1 => {
-                            game_clone
-                                .mut_piece_bitboard(Piece::Rook, side)
-                                .pop_bit(Square::A1);
-                            game_clone
-                                .mut_piece_bitboard(Piece::Rook, side)
-                                .set_bit(Square::D1);
-                            game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES
-                                .piece_square_hash(Piece::Rook, side, Square::A1);
-                            game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES
-                                .piece_square_hash(Piece::Rook, side, Square::D1);
-                        }
-                        Square::G1 => {
-                            game_clone
-                                .mut_piece_bitboard(Piece::Rook, side)
-                                .pop_bit(Square::H1);
-                            game_clone
-                                .mut_piece_bitboard(Piece::Rook, side)
-                                .set_bit(Square::F1);
-                            game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES
-                                .piece_square_hash(Piece::Rook, side, Square::H1);
-                            game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES
-                                .piece_square_hash(Piece::Rook, side, Square::F1);
-                        }
-                        _ => {}
-                    };
-                }
-                Side::Black => {
-                    match mv.target_square() {
-                        Square::C8 => {
-                            game_clone
-                                .mut_piece_bitboard(Piece::Rook, side)
-                                .pop_bit(Square::A8);
-                            game_clone
-                                .mut_piece_bitboard(Piece::Rook, side)
-                                .set_bit(Square::D8);
-                            game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES
-                                .piece_square_hash(Piece::Rook, side, Square::A8);
-                            game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES
-                                .piece_square_hash(Piece::Rook, side, Square::D8);
-                        }
-                        Square::G8 => {
-                            game_clone
-                                .mut_piece_bitboard(Piece::Rook, side)
-                                .pop_bit(Square::H8);
-                            game_clone
-                                .mut_piece_bitboard(Piece::Rook, side)
-                                .set_bit(Square::F8);
-                            game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES
-                                .piece_square_hash(Piece::Rook, side, Square::H8);
-                            game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES
-                                .piece_square_hash(Piece::Rook, side, Square::F8);
-                        }
-                        _ => {}
-                    };
-                }
-            },
-        }
-
-        match mv.promoted_piece() {
-            Some(promoted_piece) => {
-                game_clone
-                    .mut_piece_bitboard(promoted_piece, side)
-                    .set_bit(mv.target_square());
-                game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
-                    promoted_piece,
+            MoveType::Castling => {
+                let castling_type = CastlingType::from_move(mv, side);
+                let rook_square = self.castling_rook_square(castling_type);
+                let rook_target_square = castling_type.rook_target_square();
+                self.remove_piece(Piece::Rook, side, rook_square);
+                self.put_piece(Piece::Rook, side, rook_target_square);
+                self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
+                    Piece::Rook,
                     side,
-                    mv.target_square(),
+                    rook_square,
                 );
-            }
-            None => {
-                game_clone
-                    .mut_piece_bitboard(mv.piece(), side)
-                    .set_bit(mv.target_square());
-                game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
-                    mv.piece(),
+                self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
+                    Piece::Rook,
                     side,
-                    mv.target_square(),
+                    rook_target_square,
                 );
+                self.remove_feature(Piece::Rook, side, rook_square);
+                self.add_feature(Piece::Rook, side, rook_target_square);
             }
         }
 
-        let king_square = game_clone
-            .piece_bitboard(Piece::King, side)
-            .get_lsb_square();
+        let destination_square = Self::destination_square(mv, side);
+        let placed_piece = mv.promoted_piece().unwrap_or(mv.piece());
+        self.put_piece(placed_piece, side, destination_square);
+        self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.piece_square_hash(
+            placed_piece,
+            side,
+            destination_square,
+        );
+        self.add_feature(placed_piece, side, destination_square);
+
+        // Pawn moves and captures cannot be undone, so no position before them can come back
+        let irreversible_move = mv.piece() == Piece::Pawn
+            || mv.move_type() == MoveType::Capture
+            || mv.move_type() == MoveType::EnPassant;
+        self.halfmove_clock = match irreversible_move {
+            true => 0,
+            false => self.halfmove_clock.saturating_add(1),
+        };
+
+        self.update_castling_rights(mv);
+        self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.side_hash();
+        self.side_to_move = opponent_side;
+        self.advance_fullmove_number(side);
 
-        if let Some(king_square) = king_square {
-            let own_king_in_check =
-                game_clone.is_square_attacked(attack_tables, opponent_side, king_square);
+        if self.king_in_check(side) {
+            self.discard_pending_features();
 
-            if own_king_in_check {
-                return Err(InputError::IllegalMove);
+            if let Some(undo_state) = self.undo_stack.pop() {
+                self.restore(undo_state);
             }
+
+            return Err(InputError::IllegalMove);
         }
 
-        game_clone.update_castling_rights(mv);
-        game_clone.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.side_hash();
-        game_clone.side_to_move = opponent_side;
-        *self = game_clone;
+        self.apply_pending_features();
 
         Ok(())
     }
 
+    pub fn legal_moves(&self) -> Vec<Move> {
+        MoveList::generate_moves(self, MoveFlag::All).vec().to_vec()
+    }
+
+    // Takes back the last move or null move made
+    pub fn undo_move(&mut self) {
+        if let Some(undo_state) = self.undo_stack.pop() {
+            let piece_moved = undo_state.mv.is_some();
+            self.restore(undo_state);
+
+            if piece_moved {
+                self.undo_features();
+            }
+        }
+    }
+
+    // The move is reversed on the board, while everything else is restored as it was saved
+    fn restore(&mut self, undo_state: UndoState) {
+        let side = self.side_to_move.opponent_side();
+        self.side_to_move = side;
+
+        if side == Side::Black {
+            self.fullmove_number -= 1;
+        }
+
+        self.castling_rights = undo_state.castling_rights;
+        self.en_passant_square = undo_state.en_passant_square;
+        self.halfmove_clock = undo_state.halfmove_clock;
+        self.zobrist_key = undo_state.zobrist_key;
+
+        let mv = match undo_state.mv {
+            Some(mv) => mv,
+            None => return,
+        };
+        let placed_piece = mv.promoted_piece().unwrap_or(mv.piece());
+        self.remove_piece(placed_piece, side, Self::destination_square(&mv, side));
+
+        match mv.move_type() {
+            MoveType::Capture => {
+                if let Some(piece) = undo_state.captured_piece {
+                    self.put_piece(piece, side.opponent_side(), mv.target_square());
+                }
+            }
+            MoveType::EnPassant => self.put_piece(
+                Piece::Pawn,
+                side.opponent_side(),
+                Self::pawn_behind_square(mv.target_square(), side),
+            ),
+            MoveType::Castling => {
+                let castling_type = CastlingType::from_move(&mv, side);
+                let rook_square = self.castling_rook_square(castling_type);
+                self.remove_piece(Piece::Rook, side, castling_type.rook_target_square());
+                self.put_piece(Piece::Rook, side, rook_square);
+            }
+            MoveType::Quiet | MoveType::DoublePawnPush => {}
+        }
+
+        self.put_piece(mv.piece(), side, mv.source_square());
+    }
+
+    fn advance_fullmove_number(&mut self, side: Side) {
+        if side == Side::Black {
+            self.fullmove_number = self.fullmove_number.saturating_add(1);
+        }
+    }
+
+    // Castling moves are written as the king taking its own rook in Chess960, so the king's
+    // destination comes from the castling type instead
+    fn destination_square(mv: &Move, side: Side) -> Square {
+        match mv.move_type() {
+            MoveType::Castling => CastlingType::from_move(mv, side).king_target_square(),
+            _ => mv.target_square(),
+        }
+    }
+
+    // The square a pawn of the side passed over with a double push, or the square of the pawn it
+    // takes en passant
+    pub fn pawn_behind_square(square: Square, side: Side) -> Square {
+        match side {
+            Side::White => Square::from_usize(square as usize + 8),
+            Side::Black => Square::from_usize(square as usize - 8),
+        }
+        .unwrap()
+    }
+
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(|network| NnueState::new(network, self));
+    }
+
+    #[cfg(feature = "nnue")]
+    pub fn network(&self) -> Option<Arc<Network>> {
+        self.nnue.as_ref().map(NnueState::network)
+    }
+
+    // From the side to move's point of view, if a network is loaded
+    #[cfg(feature = "nnue")]
+    pub fn nnue_evaluation(&self) -> Option<Value> {
+        self.nnue
+            .as_ref()
+            .map(|nnue| nnue.evaluate(self.side_to_move))
+    }
+
+    #[cfg(feature = "nnue")]
+    fn refresh_nnue(&mut self) {
+        if let Some(mut nnue) = self.nnue.take() {
+            nnue.refresh(self);
+            self.nnue = Some(nnue);
+        }
+    }
+
+    #[cfg(not(feature = "nnue"))]
+    fn refresh_nnue(&mut self) {}
+
+    // A loaded network's accumulators follow every piece added to or removed from the board, with
+    // the changes a move makes applied together once it is known to be legal
+    #[cfg(feature = "nnue")]
+    fn add_feature(&mut self, piece: Piece, side: Side, square: Square) {
+        if let Some(nnue) = &mut self.nnue {
+            nnue.add_feature(piece, side, square);
+        }
+    }
+
+    #[cfg(not(feature = "nnue"))]
+    fn add_feature(&mut self, _piece: Piece, _side: Side, _square: Square) {}
+
+    #[cfg(feature = "nnue")]
+    fn remove_feature(&mut self, piece: Piece, side: Side, square: Square) {
+        if let Some(nnue) = &mut self.nnue {
+            nnue.remove_feature(piece, side, square);
+        }
+    }
+
+    #[cfg(not(feature = "nnue"))]
+    fn remove_feature(&mut self, _piece: Piece, _side: Side, _square: Square) {}
+
+    #[cfg(feature = "nnue")]
+    fn apply_pending_features(&mut self) {
+        if let Some(mut nnue) = self.nnue.take() {
+            nnue.apply_pending(self);
+            self.nnue = Some(nnue);
+        }
+    }
+
+    #[cfg(not(feature = "nnue"))]
+    fn apply_pending_features(&mut self) {}
+
+    #[cfg(feature = "nnue")]
+    fn discard_pending_features(&mut self) {
+        if let Some(nnue) = &mut self.nnue {
+            nnue.discard_pending();
+        }
+    }
+
+    #[cfg(not(feature = "nnue"))]
+    fn discard_pending_features(&mut self) {}
+
+    #[cfg(feature = "nnue")]
+    fn undo_features(&mut self) {
+        if let Some(nnue) = &mut self.nnue {
+            nnue.undo();
+        }
+    }
+
+    #[cfg(not(feature = "nnue"))]
+    fn undo_features(&mut self) {}
+
+    pub fn halfmove_clock(&self) -> u8 {
+        self.halfmove_clock
+    }
+
+    // The position as a full FEN string, which loads back into the same position - castling rights
+    // are written the way they are read, so Chess960 positions keep their castling rooks
+    pub fn to_fen(&self) -> String {
+        let mut board = String::new();
+        let mut empty_squares = 0;
+
+        for square in Square::iter() {
+            match self.piece_at_square(square) {
+                Some((piece, side)) => {
+                    if empty_squares > 0 {
+                        board += &empty_squares.to_string();
+                        empty_squares = 0;
+                    }
+
+                    board.push(piece.to_char(Some(side)));
+                }
+                None => empty_squares += 1,
+            }
+
+            if square.file() == 7 {
+                if empty_squares > 0 {
+                    board += &empty_squares.to_string();
+                    empty_squares = 0;
+                }
+
+                if square.rank() != 7 {
+                    board.push('/');
+                }
+            }
+        }
+
+        let side_to_move = match self.side_to_move {
+            Side::White => "w",
+            Side::Black => "b",
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            board,
+            side_to_move,
+            self.fen_castling_rights(),
+            self.fen_en_passant_square(),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    fn fen_castling_rights(&self) -> String {
+        match self.castling_rights_string() {
+            castling_rights if castling_rights.is_empty() => "-".to_string(),
+            castling_rights => castling_rights,
+        }
+    }
+
+    fn fen_en_passant_square(&self) -> String {
+        match self.en_passant_square {
+            Some(square) => square.to_string().to_lowercase(),
+            None => "-".to_string(),
+        }
+    }
+
     pub fn make_null_move(&mut self) {
+        self.undo_stack.push(UndoState {
+            mv: None,
+            captured_piece: None,
+            castling_rights: self.castling_rights.clone(),
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            zobrist_key: self.zobrist_key,
+        });
+
         if let Some(square) = self.en_passant_square {
             self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.en_passant_square_hash(square);
         }
 
         self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.side_hash();
+        self.advance_fullmove_number(self.side_to_move);
         self.side_to_move = self.side_to_move.opponent_side();
         self.en_passant_square = None;
     }
 
-    pub fn is_square_attacked(
-        &self,
-        attack_tables: &AttackTables,
-        attacking_side: Side,
-        square: Square,
-    ) -> bool {
+    pub fn is_square_attacked(&self, attacking_side: Side, square: Square) -> bool {
         for piece in Piece::iter() {
-            let piece_attacks_square = attack_tables.attack_table(
+            let piece_attacks_square = attack_tables::ATTACK_TABLES.attack_table(
                 self.board(None),
                 piece,
                 attacking_side.opponent_side(),
@@ -396,40 +731,51 @@ impl Game {
         false
     }
 
+    pub fn king_in_check(&self, side: Side) -> bool {
+        match self.piece_bitboard(Piece::King, side).get_lsb_square() {
+            Some(king_square) => self.is_square_attacked(side.opponent_side(), king_square),
+            None => false,
+        }
+    }
+
+    // Pieces of either side attacking a square with only the given squares occupied - pieces off
+    // those squares are left out, so sliders behind a piece taken off the board attack through it
+    pub fn attackers_to(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        let mut attackers = Bitboard(0);
+
+        for side in Side::iter() {
+            for piece in Piece::iter() {
+                // Attacks are symmetric, apart from pawns, whose attacks are looked up as if they
+                // were a pawn of the other side standing on the square
+                attackers |= attack_tables::ATTACK_TABLES.attack_table(
+                    occupancy,
+                    piece,
+                    side.opponent_side(),
+                    square,
+                ) & self.piece_bitboard(piece, side);
+            }
+        }
+
+        attackers & occupancy
+    }
+
+    // Pieces of the opponent attacking the king of the side to move
+    pub fn checkers(&self) -> Bitboard {
+        let side = self.side_to_move;
+
+        match self.piece_bitboard(Piece::King, side).get_lsb_square() {
+            Some(king_square) => {
+                self.attackers_to(king_square, self.board(None))
+                    & self.board(Some(side.opponent_side()))
+            }
+            None => Bitboard(0),
+        }
+    }
+
     pub fn board(&self, side: Option<Side>) -> Bitboard {
         match side {
-            Some(side) => match side {
-                Side::White => {
-                    self.white_pawns
-                        | self.white_knights
-                        | self.white_bishops
-                        | self.white_rooks
-                        | self.white_queens
-                        | self.white_king
-                }
-                Side::Black => {
-                    self.black_pawns
-                        | self.black_knights
-                        | self.black_bishops
-                        | self.black_rooks
-                        | self.black_queens
-                        | self.black_king
-                }
-            },
-            None => {
-                self.white_pawns
-                    | self.white_knights
-                    | self.white_bishops
-                    | self.white_rooks
-                    | self.white_queens
-                    | self.white_king
-                    | self.black_pawns
-                    | self.black_knights
-                    | self.black_bishops
-                    | self.black_rooks
-                    | self.black_queens
-                    | self.black_king
-            }
+            Some(side) => self.occupancies[side as usize],
+            None => self.occupancies[BOTH_SIDES_OCCUPANCY],
         }
     }
 
@@ -445,6 +791,85 @@ impl Game {
         self.en_passant_square
     }
 
+    pub fn zobrist_key(&self) -> ZobristKey {
+        self.zobrist_key
+    }
+
+    // Recomputes state which is otherwise updated incrementally, returning a description of any
+    // divergence found
+    pub fn verify(&self) -> Vec<String> {
+        let mut divergences = Vec::new();
+
+        let zobrist_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(self);
+        if self.zobrist_key != zobrist_key {
+            divergences.push(format!(
+                "zobrist key {:#018x} expected {:#018x}",
+                self.zobrist_key, zobrist_key
+            ));
+        }
+
+        let piece_count: u32 = self
+            .piece_bitboards()
+            .iter()
+            .map(|(bitboard, _, _)| bitboard.count_bits())
+            .sum();
+        let occupied_count = self.board(None).count_bits();
+        if piece_count != occupied_count {
+            divergences.push(format!(
+                "occupancy {} squares expected {}",
+                occupied_count, piece_count
+            ));
+        }
+
+        for side in Side::iter() {
+            let occupancy = self
+                .piece_bitboards()
+                .iter()
+                .filter(|(_, _, bitboard_side)| *bitboard_side == side)
+                .fold(Bitboard(0), |occupancy, (bitboard, ..)| {
+                    occupancy | *bitboard
+                });
+            if self.board(Some(side)) != occupancy {
+                divergences.push(format!(
+                    "{:?} occupancy {:#018x} expected {:#018x}",
+                    side,
+                    self.board(Some(side)).0,
+                    occupancy.0
+                ));
+            }
+        }
+
+        for square in Square::iter() {
+            let bitboard_piece = self
+                .piece_bitboards()
+                .into_iter()
+                .find(|(bitboard, ..)| bitboard.bit_occupied(square))
+                .map(|(_, piece, side)| (piece, side));
+            if self.mailbox[square as usize] != bitboard_piece {
+                divergences.push(format!(
+                    "mailbox {:?} on {:?} expected {:?}",
+                    self.mailbox[square as usize], square, bitboard_piece
+                ));
+            }
+        }
+
+        for side in Side::iter() {
+            let king_count = self.piece_bitboard(Piece::King, side).count_bits();
+            if king_count != 1 {
+                divergences.push(format!("{:?} king count {} expected 1", side, king_count));
+            }
+        }
+
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &self.nnue {
+            if !nnue.matches_refresh(self) {
+                divergences.push("nnue accumulators differ from a refresh".to_string());
+            }
+        }
+
+        divergences
+    }
+
     pub fn castling_rights_value(&self) -> u8 {
         self.castling_rights.0
     }
@@ -453,6 +878,40 @@ impl Game {
         self.castling_rights.0 & castling_type as u8 != 0
     }
 
+    pub fn castling_rook_square(&self, castling_type: CastlingType) -> Square {
+        self.castling_rook_squares[castling_type.index()]
+    }
+
+    // Chess960 castling is written as the king taking its own rook, which is also the only way to
+    // write it when the king and rook did not start on their standard squares
+    pub fn castling_move_target_square(
+        &self,
+        castling_type: CastlingType,
+        king_square: Square,
+    ) -> Square {
+        let rook_square = self.castling_rook_square(castling_type);
+        let standard_squares = king_square.file() == 4
+            && rook_square == STANDARD_CASTLING_ROOK_SQUARES[castling_type.index()];
+
+        match self.chess960 || !standard_squares {
+            true => rook_square,
+            false => castling_type.king_target_square(),
+        }
+    }
+
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    // Positions with only king and pawns are prone to zugzwang, where passing would be the best move
+    pub fn has_non_pawn_material(&self, side: Side) -> bool {
+        self.piece_bitboard(Piece::Knight, side)
+            | self.piece_bitboard(Piece::Bishop, side)
+            | self.piece_bitboard(Piece::Rook, side)
+            | self.piece_bitboard(Piece::Queen, side)
+            != 0u64
+    }
+
     pub fn piece_bitboard(&self, piece: Piece, side: Side) -> Bitboard {
         match side {
             Side::White => match piece {
@@ -495,16 +954,109 @@ impl Game {
         }
     }
 
+    // Pieces past the end of their rank would otherwise be placed on the next one
+    fn fen_square(square_index: usize, rank_start: usize) -> Result<Square, InputError> {
+        if square_index >= rank_start + 8 {
+            return Err(InputError::InvalidFen(FenError::RankLength));
+        }
+
+        Square::from_usize(square_index).ok_or(InputError::InvalidFen(FenError::BoardPosition))
+    }
+
+    // Rights are either KQkq, meaning the outermost rook on that side of the king as in X-FEN, or
+    // the files of the castling rooks as in Shredder-FEN - rights without a king on the back rank
+    // are accepted as if it were on the e-file, as they were before Chess960 was supported
+    fn parse_castling_rights(
+        castling_rights_string: &str,
+        kings: [Bitboard; 2],
+        rooks: [Bitboard; 2],
+    ) -> Result<(CastlingRights, [Square; 4]), InputError> {
+        let mut castling_rights = CastlingRights(0);
+        let mut castling_rook_squares = STANDARD_CASTLING_ROOK_SQUARES;
+
+        if castling_rights_string == "-" {
+            return Ok((castling_rights, castling_rook_squares));
+        }
+
+        for character in castling_rights_string.chars() {
+            let side = match character.is_ascii_uppercase() {
+                true => Side::White,
+                false => Side::Black,
+            };
+            let back_rank = match side {
+                Side::White => 7,
+                Side::Black => 0,
+            };
+            let king_file = match kings[side as usize].get_lsb_square() {
+                Some(king_square) if king_square.rank() == back_rank => king_square.file(),
+                _ => 4,
+            };
+            let mut rook_files = (0..8).filter(|file| {
+                rooks[side as usize].bit_occupied(Square::from_rank_file(back_rank, *file))
+            });
+            let rook_file = match character.to_ascii_lowercase() {
+                'k' => rook_files.rfind(|file| *file > king_file).unwrap_or(7),
+                'q' => rook_files.find(|file| *file < king_file).unwrap_or(0),
+                file @ 'a'..='h' if file as usize - 'a' as usize != king_file => {
+                    file as usize - 'a' as usize
+                }
+                _ => return Err(InputError::InvalidFen(FenError::CastlingRights)),
+            };
+            let castling_type = CastlingType::new(side, rook_file > king_file);
+            castling_rights.0 |= castling_type as u8;
+            castling_rook_squares[castling_type.index()] =
+                Square::from_rank_file(back_rank, rook_file);
+        }
+
+        Ok((castling_rights, castling_rook_squares))
+    }
+
+    // KQkq for rooks on their standard squares, otherwise the file of the rook
+    fn castling_rights_string(&self) -> String {
+        let mut castling_rights_string = String::new();
+
+        for castling_type in CastlingType::iter() {
+            if !self.castling_type_allowed(castling_type) {
+                continue;
+            }
+
+            let rook_square = self.castling_rook_square(castling_type);
+            let character =
+                match rook_square == STANDARD_CASTLING_ROOK_SQUARES[castling_type.index()] {
+                    true => match castling_type {
+                        CastlingType::WhiteShort | CastlingType::BlackShort => 'k',
+                        CastlingType::WhiteLong | CastlingType::BlackLong => 'q',
+                    },
+                    false => (b'a' + rook_square.file() as u8) as char,
+                };
+
+            match castling_type.side() {
+                Side::White => castling_rights_string.push(character.to_ascii_uppercase()),
+                Side::Black => castling_rights_string.push(character),
+            }
+        }
+
+        castling_rights_string
+    }
+
+    // The en passant square is behind a pawn which has just double pushed, so it can only be on
+    // the sixth rank from the side to move's point of view
     fn parse_en_passant_square(
         en_passant_square_string: &str,
+        side_to_move: Side,
     ) -> Result<Option<Square>, InputError> {
         if en_passant_square_string == "-" {
             return Ok(None);
         }
 
+        let en_passant_rank = match side_to_move {
+            Side::White => 2,
+            Side::Black => 5,
+        };
+
         match Square::from_str(en_passant_square_string.to_uppercase().as_str()) {
-            Ok(square) => Ok(Some(square)),
-            Err(_) => Err(InputError::InvalidFen(FenError::EnPassantSquare)),
+            Ok(square) if square.rank() == en_passant_rank => Ok(Some(square)),
+            _ => Err(InputError::InvalidFen(FenError::EnPassantSquare)),
         }
     }
 
@@ -513,102 +1065,25 @@ impl Game {
             return;
         }
 
-        match self.side_to_move {
-            Side::White => {
-                if (mv.source_square() == Square::A1 || mv.source_square() == Square::E1)
-                    && self.castling_type_allowed(CastlingType::WhiteLong)
-                {
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                    self.castling_rights
-                        .remove_castling_type(CastlingType::WhiteLong);
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                }
+        let castling_rights = self.castling_rights.0;
 
-                if (mv.source_square() == Square::E1 || mv.source_square() == Square::H1)
-                    && self.castling_type_allowed(CastlingType::WhiteShort)
-                {
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                    self.castling_rights
-                        .remove_castling_type(CastlingType::WhiteShort);
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                }
-            }
-            Side::Black => {
-                if (mv.source_square() == Square::A8 || mv.source_square() == Square::E8)
-                    && self.castling_type_allowed(CastlingType::BlackLong)
-                {
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                    self.castling_rights
-                        .remove_castling_type(CastlingType::BlackLong);
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                }
+        for castling_type in CastlingType::iter() {
+            let rook_square = self.castling_rook_square(castling_type);
+            // Moving the king or the castling rook gives up the right, as does losing the rook
+            let right_lost = match castling_type.side() == self.side_to_move {
+                true => mv.piece() == Piece::King || mv.source_square() == rook_square,
+                false => mv.target_square() == rook_square,
+            };
 
-                if (mv.source_square() == Square::E8 || mv.source_square() == Square::H8)
-                    && self.castling_type_allowed(CastlingType::BlackShort)
-                {
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                    self.castling_rights
-                        .remove_castling_type(CastlingType::BlackShort);
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                }
+            if right_lost {
+                self.castling_rights.remove_castling_type(castling_type);
             }
         }
 
-        match self.side_to_move {
-            Side::White => {
-                if mv.target_square() == Square::A8
-                    && self.castling_type_allowed(CastlingType::BlackLong)
-                {
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                    self.castling_rights
-                        .remove_castling_type(CastlingType::BlackLong);
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                }
-
-                if mv.target_square() == Square::H8
-                    && self.castling_type_allowed(CastlingType::BlackShort)
-                {
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                    self.castling_rights
-                        .remove_castling_type(CastlingType::BlackShort);
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                }
-            }
-            Side::Black => {
-                if mv.target_square() == Square::A1
-                    && self.castling_type_allowed(CastlingType::WhiteLong)
-                {
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                    self.castling_rights
-                        .remove_castling_type(CastlingType::WhiteLong);
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                }
-
-                if mv.target_square() == Square::H1
-                    && self.castling_type_allowed(CastlingType::WhiteShort)
-                {
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                    self.castling_rights
-                        .remove_castling_type(CastlingType::WhiteShort);
-                    self.zobrist_key ^=
-                        zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
-                }
-            }
+        if self.castling_rights != castling_rights {
+            self.zobrist_key ^= zobrist_hashes::ZOBRIST_HASHES.castling_hash(castling_rights);
+            self.zobrist_key ^=
+                zobrist_hashes::ZOBRIST_HASHES.castling_hash(self.castling_rights.0);
         }
     }
 
@@ -630,39 +1105,104 @@ impl Game {
     }
 
     pub fn piece_at_square(&self, square: Square) -> Option<(Piece, Side)> {
+        self.mailbox[square as usize]
+    }
+
+    fn generate_square_lookups(&mut self) {
+        self.mailbox = [None; 64];
+        self.occupancies = [Bitboard(0); 3];
+
         for (bitboard, piece, side) in self.piece_bitboards() {
-            if bitboard.bit_occupied(square) {
-                return Some((piece, side));
+            self.occupancies[side as usize] |= bitboard;
+            self.occupancies[BOTH_SIDES_OCCUPANCY] |= bitboard;
+
+            for square in bitboard.squares() {
+                self.mailbox[square as usize] = Some((piece, side));
             }
         }
+    }
 
-        None
+    fn put_piece(&mut self, piece: Piece, side: Side, square: Square) {
+        self.mut_piece_bitboard(piece, side).set_bit(square);
+        self.mailbox[square as usize] = Some((piece, side));
+        self.occupancies[side as usize].set_bit(square);
+        self.occupancies[BOTH_SIDES_OCCUPANCY].set_bit(square);
+    }
+
+    fn remove_piece(&mut self, piece: Piece, side: Side, square: Square) {
+        self.mut_piece_bitboard(piece, side).pop_bit(square);
+        self.mailbox[square as usize] = None;
+        self.occupancies[side as usize].pop_bit(square);
+        self.occupancies[BOTH_SIDES_OCCUPANCY].pop_bit(square);
+    }
+
+    // The same position with the board mirrored vertically and the colours of the pieces swapped,
+    // which should evaluate as the exact opposite
+    #[cfg_attr(not(feature = "eval-symmetry-check"), allow(dead_code))]
+    pub fn colour_flipped(&self) -> Self {
+        let castling_rights = self.castling_rights.0;
+        let [white_short, white_long, black_short, black_long] =
+            self.castling_rook_squares.map(Square::horizontal_mirror);
+        let mut game = Self {
+            white_pawns: self.black_pawns.horizontal_mirror(),
+            white_knights: self.black_knights.horizontal_mirror(),
+            white_bishops: self.black_bishops.horizontal_mirror(),
+            white_rooks: self.black_rooks.horizontal_mirror(),
+            white_queens: self.black_queens.horizontal_mirror(),
+            white_king: self.black_king.horizontal_mirror(),
+            black_pawns: self.white_pawns.horizontal_mirror(),
+            black_knights: self.white_knights.horizontal_mirror(),
+            black_bishops: self.white_bishops.horizontal_mirror(),
+            black_rooks: self.white_rooks.horizontal_mirror(),
+            black_queens: self.white_queens.horizontal_mirror(),
+            black_king: self.white_king.horizontal_mirror(),
+            mailbox: [None; 64],
+            occupancies: [Bitboard(0); 3],
+            side_to_move: self.side_to_move.opponent_side(),
+            castling_rights: CastlingRights((castling_rights & 0b0011) << 2 | castling_rights >> 2),
+            castling_rook_squares: [black_short, black_long, white_short, white_long],
+            en_passant_square: self.en_passant_square.map(Square::horizontal_mirror),
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            zobrist_key: 0,
+            chess960: self.chess960,
+            undo_stack: Vec::new(),
+            #[cfg(feature = "nnue")]
+            nnue: self.nnue.clone(),
+        };
+        game.generate_square_lookups();
+        game.zobrist_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
+        game.refresh_nnue();
+
+        game
     }
+}
 
-    pub fn _print(&self) {
+impl std::fmt::Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for square in Square::iter() {
             if square.file() == 0 {
-                print!("{:<4}", (64 - square as usize) / 8);
+                write!(f, "{:<4}", (64 - square as usize) / 8)?;
             }
 
             match self.piece_at_square(square) {
-                Some((piece, side)) => print!("{:<2}", piece._to_char(Some(side))),
-                None => print!(". "),
+                Some((piece, side)) => write!(f, "{:<2}", piece.to_char(Some(side)))?,
+                None => write!(f, ". ")?,
             }
 
             if square.file() == 7 {
-                println!();
+                writeln!(f)?;
             }
         }
 
-        println!();
-        println!("    a b c d e f g h");
-        println!();
-        println!("Side to move: {:?}", self.side_to_move);
-        println!("En passant square: {:?}", self.en_passant_square);
-        println!("Castling rights: {}", self.castling_rights._as_string());
-        println!("Board value: 0x{:X}", self.board(None).0);
-        println!("Zobrist key: 0x{:X}", self.zobrist_key);
+        writeln!(f)?;
+        writeln!(f, "    a b c d e f g h")?;
+        writeln!(f)?;
+        writeln!(f, "Side to move: {:?}", self.side_to_move)?;
+        writeln!(f, "En passant square: {}", self.fen_en_passant_square())?;
+        writeln!(f, "Castling rights: {}", self.fen_castling_rights())?;
+        writeln!(f, "FEN: {}", self.to_fen())?;
+        write!(f, "Zobrist key: 0x{:X}", self.zobrist_key)
     }
 }
 
@@ -706,10 +1246,21 @@ impl Bitboard {
         Square::from_u32(self.0.trailing_zeros())
     }
 
-    pub fn _count_bits(self) -> u32 {
+    // Each byte of the bitboard is a rank, so reversing the bytes flips the ranks
+    #[cfg_attr(not(feature = "eval-symmetry-check"), allow(dead_code))]
+    pub fn horizontal_mirror(self) -> Self {
+        Self(self.0.swap_bytes())
+    }
+
+    pub fn count_bits(self) -> u32 {
         self.0.count_ones()
     }
 
+    // The occupied squares, from the least significant bit up
+    pub fn squares(self) -> Squares {
+        Squares(self.0)
+    }
+
     fn _print(self) {
         for square in Square::iter() {
             if square.file() == 0 {
@@ -730,6 +1281,20 @@ impl Bitboard {
     }
 }
 
+pub struct Squares(u64);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    // Clearing the lowest set bit leaves the next square to hand out as the lowest
+    fn next(&mut self) -> Option<Square> {
+        let square = Bitboard(self.0).get_lsb_square()?;
+        self.0 &= self.0 - 1;
+
+        Some(square)
+    }
+}
+
 impl BitAnd for Bitboard {
     type Output = Self;
 
@@ -831,7 +1396,7 @@ impl Piece {
         }
     }
 
-    pub fn _to_char(self, side: Option<Side>) -> char {
+    pub fn to_char(self, side: Option<Side>) -> char {
         match side {
             Some(side) => match side {
                 Side::White => match self {
@@ -911,6 +1476,14 @@ impl Square {
         self as usize % 8
     }
 
+    // Number of king moves between the squares
+    pub fn distance(self, square: Square) -> usize {
+        let rank_distance = self.rank().abs_diff(square.rank());
+        let file_distance = self.file().abs_diff(square.file());
+
+        rank_distance.max(file_distance)
+    }
+
     pub fn horizontal_mirror(self) -> Square {
         let mirror_rank = 7 - self.rank();
         let square_index = mirror_rank * 8 + self.file();
@@ -918,12 +1491,12 @@ impl Square {
         Square::from_usize(square_index).unwrap()
     }
 
-    pub fn _to_lowercase_string(self) -> String {
+    pub fn to_lowercase_string(self) -> String {
         self.to_string().to_lowercase()
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, EnumIter, PartialEq)]
 pub enum CastlingType {
     WhiteShort = 0b0001,
     WhiteLong = 0b0010,
@@ -932,6 +1505,50 @@ pub enum CastlingType {
 }
 
 impl CastlingType {
+    fn new(side: Side, short: bool) -> Self {
+        match (side, short) {
+            (Side::White, true) => Self::WhiteShort,
+            (Side::White, false) => Self::WhiteLong,
+            (Side::Black, true) => Self::BlackShort,
+            (Side::Black, false) => Self::BlackLong,
+        }
+    }
+
+    // Castling moves go from the king towards the rook, whichever way they are written
+    pub fn from_move(mv: &Move, side: Side) -> Self {
+        Self::new(side, mv.target_square().file() > mv.source_square().file())
+    }
+
+    pub fn side(self) -> Side {
+        match self {
+            Self::WhiteShort | Self::WhiteLong => Side::White,
+            Self::BlackShort | Self::BlackLong => Side::Black,
+        }
+    }
+
+    // The king and rook end up on the same squares as in standard chess, wherever they started
+    pub fn king_target_square(self) -> Square {
+        match self {
+            Self::WhiteShort => Square::G1,
+            Self::WhiteLong => Square::C1,
+            Self::BlackShort => Square::G8,
+            Self::BlackLong => Square::C8,
+        }
+    }
+
+    pub fn rook_target_square(self) -> Square {
+        match self {
+            Self::WhiteShort => Square::F1,
+            Self::WhiteLong => Square::D1,
+            Self::BlackShort => Square::F8,
+            Self::BlackLong => Square::D8,
+        }
+    }
+
+    fn index(self) -> usize {
+        (self as u8).trailing_zeros() as usize
+    }
+
     pub fn _move_string(&self) -> &str {
         match self {
             Self::WhiteShort => "e1g1",
@@ -946,51 +1563,9 @@ impl CastlingType {
 struct CastlingRights(u8);
 
 impl CastlingRights {
-    fn initialise(castling_rights_string: &str) -> Result<Self, InputError> {
-        if castling_rights_string == "-" {
-            return Ok(Self(0));
-        };
-
-        let mut castling_rights = 0;
-
-        for character in castling_rights_string.chars() {
-            match character {
-                'K' => castling_rights |= CastlingType::WhiteShort as u8,
-                'Q' => castling_rights |= CastlingType::WhiteLong as u8,
-                'k' => castling_rights |= CastlingType::BlackShort as u8,
-                'q' => castling_rights |= CastlingType::BlackLong as u8,
-                _ => return Err(InputError::InvalidFen(FenError::CastlingRights)),
-            }
-        }
-
-        Ok(Self(castling_rights))
-    }
-
     fn remove_castling_type(&mut self, castling_type: CastlingType) {
         self.0 &= !(castling_type as u8);
     }
-
-    fn _as_string(&self) -> String {
-        let mut castling_rights_string = String::new();
-
-        if self.0 & CastlingType::WhiteShort as u8 != 0 {
-            castling_rights_string.push('K');
-        }
-
-        if self.0 & CastlingType::WhiteLong as u8 != 0 {
-            castling_rights_string.push('Q');
-        }
-
-        if self.0 & CastlingType::BlackShort as u8 != 0 {
-            castling_rights_string.push('k');
-        }
-
-        if self.0 & CastlingType::BlackLong as u8 != 0 {
-            castling_rights_string.push('q');
-        }
-
-        castling_rights_string
-    }
 }
 
 impl<T: Unsigned + AsPrimitive<u8>> PartialEq<T> for CastlingRights {
@@ -1002,7 +1577,7 @@ impl<T: Unsigned + AsPrimitive<u8>> PartialEq<T> for CastlingRights {
 #[cfg(test)]
 mod tests {
     use super::{
-        super::moves::{MoveList, MoveSearch},
+        super::moves::{MoveFlag, MoveList, MoveSearch},
         *,
     };
 
@@ -1460,11 +2035,10 @@ mod tests {
         assert_eq!(game.en_passant_square, desired_en_passant_square);
         assert_eq!(game.halfmove_clock, desired_halfmove_clock);
 
-        let attack_tables = AttackTables::initialise();
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::E2, Square::E4, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         desired_white_pawns_bitboard.pop_bit(Square::E2);
         desired_white_pawns_bitboard.set_bit(Square::E4);
@@ -1493,10 +2067,10 @@ mod tests {
         assert_eq!(game.en_passant_square, desired_en_passant_square);
         assert_eq!(game.halfmove_clock, desired_halfmove_clock);
 
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::E7, Square::E5, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         desired_black_pawns_bitboard.pop_bit(Square::E7);
         desired_black_pawns_bitboard.set_bit(Square::E5);
@@ -1525,10 +2099,10 @@ mod tests {
         assert_eq!(game.en_passant_square, desired_en_passant_square);
         assert_eq!(game.halfmove_clock, desired_halfmove_clock);
 
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::G1, Square::F3, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         desired_white_knights_bitboard.pop_bit(Square::G1);
         desired_white_knights_bitboard.set_bit(Square::F3);
@@ -1536,7 +2110,7 @@ mod tests {
         let desired_side_to_move = Side::Black;
         let desired_castling_rights = CastlingRights(0b1111);
         let desired_en_passant_square = None;
-        let desired_halfmove_clock = 0;
+        let desired_halfmove_clock = 1;
 
         assert_eq!(game.white_pawns, desired_white_pawns_bitboard);
         assert_eq!(game.white_knights, desired_white_knights_bitboard);
@@ -1651,11 +2225,10 @@ mod tests {
         assert_eq!(game.en_passant_square, desired_en_passant_square);
         assert_eq!(game.halfmove_clock, desired_halfmove_clock);
 
-        let attack_tables = AttackTables::initialise();
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::D5, Square::E6, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         desired_white_pawns_bitboard.pop_bit(Square::D5);
         desired_white_pawns_bitboard.set_bit(Square::E6);
@@ -1685,10 +2258,10 @@ mod tests {
         assert_eq!(game.en_passant_square, desired_en_passant_square);
         assert_eq!(game.halfmove_clock, desired_halfmove_clock);
 
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::A6, Square::E2, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         desired_black_bishops_bitboard.pop_bit(Square::A6);
         desired_black_bishops_bitboard.set_bit(Square::E2);
@@ -1718,10 +2291,10 @@ mod tests {
         assert_eq!(game.en_passant_square, desired_en_passant_square);
         assert_eq!(game.halfmove_clock, desired_halfmove_clock);
 
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::C3, Square::E2, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         desired_white_knights_bitboard.pop_bit(Square::C3);
         desired_white_knights_bitboard.set_bit(Square::E2);
@@ -1822,11 +2395,10 @@ mod tests {
         ];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::F8, Square::E8, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1846,11 +2418,10 @@ mod tests {
         ];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::E2, Square::A6, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1863,20 +2434,19 @@ mod tests {
         let fen = vec!["startpos"];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::E2, Square::E4, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
         assert_eq!(game.zobrist_key, generated_key);
 
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::E7, Square::E5, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1896,11 +2466,10 @@ mod tests {
         ];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::F5, Square::E6, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1921,11 +2490,10 @@ mod tests {
         game.load_fen(&fen).unwrap();
 
         // short castle
-        let attack_tables = AttackTables::initialise();
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::E1, Square::G1, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1936,7 +2504,7 @@ mod tests {
 
         let move_search = MoveSearch::new(Square::E1, Square::C1, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1947,7 +2515,7 @@ mod tests {
 
         let move_search = MoveSearch::new(Square::E1, Square::D1, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1958,7 +2526,7 @@ mod tests {
 
         let move_search = MoveSearch::new(Square::A1, Square::B1, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1969,7 +2537,7 @@ mod tests {
 
         let move_search = MoveSearch::new(Square::H1, Square::G1, None);
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -1986,10 +2554,10 @@ mod tests {
         ];
         game.load_fen(&fen).unwrap();
 
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::G7, Square::H8, Some(Piece::Queen));
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -2006,16 +2574,112 @@ mod tests {
         ];
         game.load_fen(&fen).unwrap();
 
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::B7, Square::A8, Some(Piece::Queen));
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
         assert_eq!(game.zobrist_key, generated_key);
     }
 
+    #[test]
+    fn chess960_castling_rights() {
+        let mut game = Game::initialise();
+        let fen = vec!["1r2k1r1/8/8/8/8/8/8/RR2K2R", "w", "KQkq", "-", "0", "1"];
+        game.load_fen(&fen).unwrap();
+
+        assert_eq!(
+            game.castling_rook_squares,
+            [Square::H1, Square::A1, Square::G8, Square::B8]
+        );
+        assert_eq!(game.castling_rights_string(), "KQgb");
+
+        let fen = vec!["1r2k1r1/8/8/8/8/8/8/RR2K2R", "w", "HBgb", "-", "0", "1"];
+        game.load_fen(&fen).unwrap();
+
+        assert_eq!(game.castling_rights, CastlingRights(0b1111));
+        assert_eq!(
+            game.castling_rook_squares,
+            [Square::H1, Square::B1, Square::G8, Square::B8]
+        );
+        assert_eq!(game.castling_rights_string(), "KBgb");
+
+        for castling_rights in ["E", "Kx", "K-"] {
+            let fen = vec![
+                "1r2k1r1/8/8/8/8/8/8/RR2K2R",
+                "w",
+                castling_rights,
+                "-",
+                "0",
+                "1",
+            ];
+
+            assert!(game.load_fen(&fen).is_err(), "{}", castling_rights);
+        }
+    }
+
+    #[test]
+    fn chess960_castling_moves() {
+        let mut game = Game::initialise();
+        let fen = vec!["1r2k1r1/8/8/8/8/8/8/1R2K2R", "w", "HBgb", "-", "0", "1"];
+        game.load_fen(&fen).unwrap();
+
+        // The rook is not in the corner, so the move can only be written as taking it
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let mv = move_list.find_move_from_string("e1b1").unwrap();
+        game.make_move(&mv).unwrap();
+
+        assert_eq!(
+            game.piece_at_square(Square::C1),
+            Some((Piece::King, Side::White))
+        );
+        assert_eq!(
+            game.piece_at_square(Square::D1),
+            Some((Piece::Rook, Side::White))
+        );
+        assert_eq!(game.piece_at_square(Square::B1), None);
+        assert_eq!(game.castling_rights_string(), "gb");
+        assert_eq!(
+            game.zobrist_key,
+            zobrist_hashes::ZOBRIST_HASHES.generate_key(&game)
+        );
+
+        game.set_chess960(true);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let mv = move_list.find_move_from_string("e8g8").unwrap();
+        game.make_move(&mv).unwrap();
+
+        assert_eq!(
+            game.piece_at_square(Square::G8),
+            Some((Piece::King, Side::Black))
+        );
+        assert_eq!(
+            game.piece_at_square(Square::F8),
+            Some((Piece::Rook, Side::Black))
+        );
+        assert_eq!(game.castling_rights, CastlingRights(0));
+        assert_eq!(
+            game.zobrist_key,
+            zobrist_hashes::ZOBRIST_HASHES.generate_key(&game)
+        );
+
+        // Standard castling is only written as taking the rook in Chess960 mode
+        let fen = vec!["r3k2r/8/8/8/8/8/8/R3K2R", "w", "KQkq", "-", "0", "1"];
+        game.load_fen(&fen).unwrap();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+
+        assert!(move_list.find_move_from_string("e1h1").is_ok());
+        assert!(move_list.find_move_from_string("e1g1").is_err());
+
+        game.set_chess960(false);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+
+        assert!(move_list.find_move_from_string("e1g1").is_ok());
+        assert!(move_list.find_move_from_string("e1h1").is_err());
+    }
+
     #[test]
     fn update_zobrist_key_promotion() {
         let mut game = Game::initialise();
@@ -2029,11 +2693,10 @@ mod tests {
         ];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
-        let move_list = MoveList::generate_moves(&game, &attack_tables);
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
         let move_search = MoveSearch::new(Square::G7, Square::H8, Some(Piece::Queen));
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -2043,7 +2706,7 @@ mod tests {
 
         let move_search = MoveSearch::new(Square::G7, Square::H8, Some(Piece::Rook));
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -2053,7 +2716,7 @@ mod tests {
 
         let move_search = MoveSearch::new(Square::G7, Square::H8, Some(Piece::Bishop));
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -2063,7 +2726,7 @@ mod tests {
 
         let move_search = MoveSearch::new(Square::G7, Square::H8, Some(Piece::Knight));
         let mv = move_list.find_move(move_search).unwrap();
-        game.make_move(&mv, &attack_tables).unwrap();
+        game.make_move(&mv).unwrap();
 
         let generated_key = zobrist_hashes::ZOBRIST_HASHES.generate_key(&game);
 
@@ -2088,6 +2751,59 @@ mod tests {
 
         assert_eq!(game.zobrist_key, generated_key);
     }
+    #[test]
+    fn verify_incremental_state() {
+        let mut game = Game::initialise();
+        let fen = vec![
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R",
+            "w",
+            "KQkq",
+            "-",
+            "0",
+            "1",
+        ];
+        game.load_fen(&fen).unwrap();
+
+        for move_string in ["e1g1", "e8c8", "d5e6", "h3g2"] {
+            let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+            let mv = move_list.find_move_from_string(move_string).unwrap();
+            game.make_move(&mv).unwrap();
+
+            assert!(game.verify().is_empty());
+        }
+
+        game.zobrist_key ^= 1;
+        game.white_queens.set_bit(Square::A1);
+        game.black_king = Bitboard(0);
+
+        // The missing king also leaves the mailbox out of step with the bitboards
+        assert_eq!(game.verify().len(), 4);
+    }
+
+    #[test]
+    fn attackers_to_square() {
+        let mut game = Game::initialise();
+        let fen = vec!["4k3/1b6/8/3r4/4P3/2N5/3Q4/3RK3", "b", "-", "-", "0", "1"];
+        game.load_fen(&fen).unwrap();
+
+        let mut attackers = Bitboard(0);
+        attackers.set_bit(Square::B7);
+        attackers.set_bit(Square::E4);
+        attackers.set_bit(Square::C3);
+        attackers.set_bit(Square::D2);
+
+        assert_eq!(game.attackers_to(Square::D5, game.board(None)), attackers);
+
+        // Taking the queen off the board reveals the rook behind it
+        let mut occupancy = game.board(None);
+        occupancy.pop_bit(Square::D2);
+        attackers.pop_bit(Square::D2);
+        attackers.set_bit(Square::D1);
+
+        assert_eq!(game.attackers_to(Square::D5, occupancy), attackers);
+        assert_eq!(game.checkers(), Bitboard(0));
+    }
+
     #[test]
     fn set_bit() {
         let mut bitboard1 = Bitboard(0);
@@ -2122,6 +2838,232 @@ mod tests {
         assert_eq!(bitboard3.0, u64::pow(2, Square::B8 as u32));
     }
 
+    // Covers captures, promotions, en passant, and castling in standard chess and Chess960, along
+    // with illegal moves, which must leave the game as it was
+    #[test]
+    fn undo_restores_position() {
+        let state = |game: &Game| {
+            (
+                game.piece_bitboards().map(|(bitboard, ..)| bitboard.0),
+                game.mailbox,
+                game.occupancies.map(|occupancy| occupancy.0),
+                game.side_to_move,
+                game.castling_rights.0,
+                game.en_passant_square,
+                game.halfmove_clock,
+                game.fullmove_number,
+                game.zobrist_key,
+            )
+        };
+
+        for fen in [
+            [
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R",
+                "w",
+                "KQkq",
+                "-",
+                "0",
+                "1",
+            ],
+            ["n1n5/PPPk4/8/8/8/8/4Kppp/5N1N", "b", "-", "-", "0", "1"],
+            ["8/8/3p4/KPp4r/1R2Pp1k/8/6P1/8", "w", "-", "c6", "0", "1"],
+            ["1r2k1r1/8/8/8/8/8/8/1R2K1R1", "w", "GBgb", "-", "0", "1"],
+        ] {
+            let mut game = Game::initialise();
+            game.load_fen(&fen).unwrap();
+            let before = state(&game);
+
+            for mv in MoveList::generate_moves(&game, MoveFlag::All).vec() {
+                if game.make_move(mv).is_ok() {
+                    game.make_null_move();
+                    game.undo_move();
+                    game.undo_move();
+                }
+
+                assert!(state(&game) == before, "{}", mv.as_string());
+                assert!(game.verify().is_empty());
+                assert!(game.undo_stack.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/8/3p4/KPp4r/1R2Pp1k/8/6P1/8 w - c6 0 1",
+            "1r2k1r1/8/8/8/8/8/8/1R2K1R1 b GBgb - 17 42",
+            "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 99 120",
+        ] {
+            let mut game = Game::initialise();
+            game.load_fen(&fen.split_whitespace().collect::<Vec<&str>>())
+                .unwrap();
+
+            assert_eq!(game.to_fen(), fen);
+        }
+
+        let mut game = Game::initialise();
+        game.load_fen(&["8/8/3p4/KPp4r/1R2Pp1k/8/6P1/8", "w", "-", "c6", "5"])
+            .unwrap();
+
+        assert_eq!(game.to_fen(), "8/8/3p4/KPp4r/1R2Pp1k/8/6P1/8 w - c6 5 1");
+        assert!(game
+            .load_fen(&["8/8/3p4/KPp4r/1R2Pp1k/8/6P1/8", "w", "-", "c6", "5", "0"])
+            .is_err());
+    }
+
+    #[test]
+    fn malformed_board_position() {
+        let mut game = Game::initialise();
+
+        for (board, rank_length) in [
+            ("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR", true),
+            ("rnbqkbnr/pppppppp/9/7/8/8/PPPPPPPP/RNBQKBNR", true),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN", true),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR", true),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP", false),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8", false),
+            ("rnbqkbnr/pppppppp/8/8/8/08/PPPPPPPP/RNBQKBNR", false),
+        ] {
+            let error = game.load_fen(&[board, "w", "-", "-", "0"]).unwrap_err();
+
+            match rank_length {
+                true => assert!(
+                    matches!(error, InputError::InvalidFen(FenError::RankLength)),
+                    "{}",
+                    board
+                ),
+                false => assert!(
+                    matches!(error, InputError::InvalidFen(FenError::BoardPosition)),
+                    "{}",
+                    board
+                ),
+            }
+        }
+
+        assert!(matches!(
+            game.load_fen(&[
+                "rnbqkbnP/pppppppp/8/8/8/8/PPPPPPP1/RNBQKBNR",
+                "w",
+                "-",
+                "-",
+                "0"
+            ]),
+            Err(InputError::InvalidFen(FenError::PawnOnBackRank))
+        ));
+    }
+
+    #[test]
+    fn strict_fen_validation() {
+        let mut game = Game::initialise();
+        game.load_fen_strict(&["startpos"]).unwrap();
+        game.load_fen_strict(&["1r2k1r1/8/8/8/8/8/8/1R2K1R1", "b", "GBgb", "-", "0", "1"])
+            .unwrap();
+
+        for (fen, expected_error) in [
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", FenError::KingCount),
+            ("4k3/8/8/8/8/8/8/3KK3 w - - 0 1", FenError::KingCount),
+            ("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1", FenError::OpponentInCheck),
+            ("4k3/8/8/8/8/8/8/4K2q b - - 0 1", FenError::OpponentInCheck),
+            (
+                "4k3/8/8/8/8/8/8/R3K3 w K - 0 1",
+                FenError::ImpossibleCastlingRights,
+            ),
+            (
+                "r3k2r/8/8/8/8/8/4K3/R6R w Q - 0 1",
+                FenError::ImpossibleCastlingRights,
+            ),
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkC - 0 1",
+                FenError::ImpossibleCastlingRights,
+            ),
+        ] {
+            let before = game.to_fen();
+            let error = game
+                .load_fen_strict(&fen.split_whitespace().collect::<Vec<&str>>())
+                .unwrap_err();
+
+            assert!(
+                matches!(error, InputError::InvalidFen(ref error) if *error == expected_error),
+                "{}",
+                fen
+            );
+            assert_eq!(game.to_fen(), before);
+        }
+    }
+
+    #[test]
+    fn fullmove_number_follows_moves() {
+        let mut game = Game::initialise();
+        game.load_fen(&["startpos"]).unwrap();
+
+        for (move_string, fen) in [
+            (
+                "e2e4",
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            ),
+            (
+                "e7e5",
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            ),
+            (
+                "g1f3",
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            ),
+        ] {
+            let mv = MoveList::generate_moves(&game, MoveFlag::All)
+                .vec()
+                .iter()
+                .copied()
+                .find(|mv| mv.as_string() == move_string)
+                .unwrap();
+            game.make_move(&mv).unwrap();
+
+            assert_eq!(game.to_fen(), fen);
+        }
+
+        game.undo_move();
+        game.undo_move();
+
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+    }
+
+    #[test]
+    fn colour_flipped_position() {
+        let mut game = Game::initialise();
+        game.load_fen(&["r3k2r/8/8/3pP3/8/8/8/R3K2R", "w", "Kq", "d6", "0", "1"])
+            .unwrap();
+        let mut flipped_game = Game::initialise();
+        flipped_game
+            .load_fen(&["r3k2r/8/8/8/3Pp3/8/8/R3K2R", "b", "Qk", "d3", "0", "1"])
+            .unwrap();
+        let colour_flipped = game.colour_flipped();
+
+        for ((bitboard, ..), (flipped_bitboard, ..)) in colour_flipped
+            .piece_bitboards()
+            .iter()
+            .zip(flipped_game.piece_bitboards())
+        {
+            assert_eq!(bitboard.0, flipped_bitboard.0);
+        }
+
+        assert_eq!(colour_flipped.side_to_move, Side::Black);
+        assert_eq!(
+            colour_flipped.castling_rights.0,
+            flipped_game.castling_rights.0
+        );
+        assert_eq!(colour_flipped.en_passant_square, Some(Square::D3));
+        assert_eq!(colour_flipped.zobrist_key, flipped_game.zobrist_key);
+        assert_eq!(
+            colour_flipped.colour_flipped().zobrist_key,
+            game.zobrist_key
+        );
+    }
+
     #[test]
     fn pop_unset_bit() {
         let mut bitboard1 = Bitboard(0);
@@ -2134,4 +3076,15 @@ mod tests {
         assert_eq!(bitboard1.0, 0);
         assert_eq!(bitboard2.0, 0);
     }
+
+    #[test]
+    fn bitboard_squares() {
+        let mut bitboard = Bitboard(0);
+        bitboard.set_bit(Square::H1);
+        bitboard.set_bit(Square::A8);
+        bitboard.set_bit(Square::E4);
+
+        assert!(bitboard.squares().eq([Square::A8, Square::E4, Square::H1]));
+        assert_eq!(Bitboard(0).squares().next(), None);
+    }
 }
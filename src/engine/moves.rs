@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use super::{
-    attack_tables::AttackTables,
+    attack_tables,
     game::{Bitboard, CastlingType, Game, Piece, Side, Square},
     InputError,
 };
@@ -11,29 +11,61 @@ use strum::{IntoEnumIterator, ParseError};
 
 const PROMOTION_PIECES: [Piece; 4] = [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen];
 
+// Which moves are generated - the quiescence search only looks at captures and promotions, so
+// quiet moves are not generated for it in the first place
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveFlag {
+    All,
+    CapturesOnly,
+}
+
+// No legal position has more moves than this
+pub const MAX_MOVES: usize = 256;
+
+// Held in a fixed size array rather than on the heap, as a list is generated at every node
 #[derive(Clone)]
-pub struct MoveList(Vec<Move>);
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    length: usize,
+}
 
 impl MoveList {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self {
+            moves: [Move(0); MAX_MOVES],
+            length: 0,
+        }
     }
 
-    pub fn generate_moves(game: &Game, attack_tables: &AttackTables) -> Self {
+    // Only legal moves are generated - pieces are kept to their pin rays, and when in check to
+    // capturing or blocking the checker, so no move has to be made to find out whether it is legal
+    pub fn generate_moves(game: &Game, move_flag: MoveFlag) -> Self {
         let mut move_list = Self::new();
         let side = game.side_to_move();
+        let restrictions = MoveRestrictions::new(game);
+        // Only the king can get out of a double check
+        let double_check = restrictions.checkers.count_bits() > 1;
 
         for piece in Piece::iter() {
-            let mut bitboard = game.piece_bitboard(piece, side);
+            if double_check && piece != Piece::King {
+                continue;
+            }
 
-            while let Some(source_square) = bitboard.get_lsb_square() {
+            let bitboard = game.piece_bitboard(piece, side);
+
+            for source_square in bitboard.squares() {
                 match piece {
                     Piece::Pawn => {
-                        move_list.generate_pawn_moves(game, source_square, attack_tables)
+                        move_list.generate_pawn_moves(game, source_square, &restrictions, move_flag)
                     }
-                    _ => move_list.generate_piece_moves(game, piece, source_square, attack_tables),
+                    _ => move_list.generate_piece_moves(
+                        game,
+                        piece,
+                        source_square,
+                        &restrictions,
+                        move_flag,
+                    ),
                 };
-                bitboard.pop_bit(source_square);
             }
         }
 
@@ -51,34 +83,119 @@ impl MoveList {
         }
     }
 
+    // SAN gives the piece and target square, and only as much of the source square as tells apart
+    // moves of the same piece to that square - check, mate and annotation suffixes are ignored
+    pub fn find_move_from_san(&self, san: &str) -> Result<Move, InputError> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let castling_short = match san {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+
+        if let Some(short) = castling_short {
+            return self
+                .vec()
+                .iter()
+                .copied()
+                .find(|mv| {
+                    mv.move_type() == MoveType::Castling
+                        && (mv.target_square().file() > mv.source_square().file()) == short
+                })
+                .ok_or(InputError::IllegalMove);
+        }
+
+        // Slicing is only safe on ASCII strings
+        if !san.is_ascii() || san.len() < 2 {
+            return Err(InputError::InvalidMoveString);
+        }
+
+        let (piece, san) = match san.chars().next() {
+            Some(character @ ('N' | 'B' | 'R' | 'Q' | 'K')) => {
+                (Piece::from_char(character).unwrap(), &san[1..])
+            }
+            _ => (Piece::Pawn, san),
+        };
+        // Promotions are written both with and without the equals sign
+        let (san, promoted_piece) = match san.strip_suffix(['N', 'B', 'R', 'Q']) {
+            Some(rest) if piece == Piece::Pawn => (
+                rest.trim_end_matches('='),
+                Some(Piece::from_char(san.chars().last().unwrap()).unwrap()),
+            ),
+            _ => (san, None),
+        };
+
+        if san.len() < 2 {
+            return Err(InputError::InvalidMoveString);
+        }
+
+        let (source, target_square_string) = san.split_at(san.len() - 2);
+        let target_square = Square::from_str(target_square_string.to_uppercase().as_str())
+            .map_err(|_| InputError::InvalidMoveString)?;
+        let mut source_file = None;
+        let mut source_rank = None;
+
+        for character in source.chars() {
+            match character {
+                'a'..='h' => source_file = Some(character as usize - 'a' as usize),
+                '1'..='8' => source_rank = Some('8' as usize - character as usize),
+                'x' => {}
+                _ => return Err(InputError::InvalidMoveString),
+            }
+        }
+
+        let mut moves = self.vec().iter().copied().filter(|mv| {
+            mv.piece() == piece
+                && mv.move_type() != MoveType::Castling
+                && mv.target_square() == target_square
+                && mv.promoted_piece() == promoted_piece
+                && source_file.is_none_or(|file| mv.source_square().file() == file)
+                && source_rank.is_none_or(|rank| mv.source_square().rank() == rank)
+        });
+
+        // A move matching more than one legal move is as unplayable as one matching none
+        match (moves.next(), moves.next()) {
+            (Some(mv), None) => Ok(mv),
+            _ => Err(InputError::IllegalMove),
+        }
+    }
+
     pub fn find_move(&self, move_search: MoveSearch) -> Result<Move, InputError> {
-        for mv in &self.0 {
+        for mv in self.vec() {
             if mv.source_square() == move_search.source_square
                 && mv.target_square() == move_search.target_square
                 && mv.promoted_piece() == move_search.promoted_piece
             {
-                return Ok(mv.clone());
+                return Ok(*mv);
             }
         }
 
         Err(InputError::IllegalMove)
     }
 
-    pub fn vec(&self) -> &Vec<Move> {
-        &self.0
+    pub fn vec(&self) -> &[Move] {
+        &self.moves[..self.length]
+    }
+
+    pub fn vec_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.length]
     }
 
-    pub fn mut_vec(&mut self) -> &mut Vec<Move> {
-        &mut self.0
+    fn push(&mut self, mv: Move) {
+        self.moves[self.length] = mv;
+        self.length += 1;
     }
 
     fn generate_pawn_moves(
         &mut self,
         game: &Game,
         source_square: Square,
-        attack_tables: &AttackTables,
+        restrictions: &MoveRestrictions,
+        move_flag: MoveFlag,
     ) {
         let side = game.side_to_move();
+        let target_mask = restrictions.target_mask(source_square);
         let source_square_index = source_square as usize;
         let target_square = match side {
             Side::White => Square::from_usize(source_square_index - 8),
@@ -94,24 +211,31 @@ impl MoveList {
         let pawn_ready_to_promote = (side == Side::White && pawn_on_seventh_rank)
             || (side == Side::Black && pawn_on_second_rank);
 
+        let push_allowed = target_mask.bit_occupied(target_square);
+        let quiet_pushes_allowed = move_flag == MoveFlag::All;
+
         if pawn_ready_to_promote && !game.is_square_occupied(target_square) {
-            for promoted_piece in PROMOTION_PIECES {
-                self.0.push(Move::new(
+            if push_allowed {
+                for promoted_piece in PROMOTION_PIECES {
+                    self.push(Move::new(
+                        source_square,
+                        target_square,
+                        Piece::Pawn,
+                        Some(promoted_piece),
+                        MoveType::Quiet,
+                    ));
+                }
+            }
+        } else if !game.is_square_occupied(target_square) && quiet_pushes_allowed {
+            if push_allowed {
+                self.push(Move::new(
                     source_square,
                     target_square,
                     Piece::Pawn,
-                    Some(promoted_piece),
+                    None,
                     MoveType::Quiet,
                 ));
             }
-        } else if !game.is_square_occupied(target_square) {
-            self.0.push(Move::new(
-                source_square,
-                target_square,
-                Piece::Pawn,
-                None,
-                MoveType::Quiet,
-            ));
 
             let double_push_target_square = if side == Side::White && pawn_on_second_rank {
                 Some(Square::from_usize(source_square_index - 16).unwrap())
@@ -122,8 +246,10 @@ impl MoveList {
             };
 
             if let Some(target_square) = double_push_target_square {
-                if !game.is_square_occupied(target_square) {
-                    self.0.push(Move::new(
+                if !game.is_square_occupied(target_square)
+                    && target_mask.bit_occupied(target_square)
+                {
+                    self.push(Move::new(
                         source_square,
                         target_square,
                         Piece::Pawn,
@@ -134,12 +260,12 @@ impl MoveList {
             }
         }
 
-        let mut attacks = Self::generate_attacks(game, Piece::Pawn, source_square, attack_tables);
+        let attacks = Self::generate_attacks(game, Piece::Pawn, source_square) & target_mask;
 
-        while let Some(target_square) = attacks.get_lsb_square() {
+        for target_square in attacks.squares() {
             if pawn_ready_to_promote {
                 for promoted_piece in PROMOTION_PIECES {
-                    self.0.push(Move::new(
+                    self.push(Move::new(
                         source_square,
                         target_square,
                         Piece::Pawn,
@@ -148,7 +274,7 @@ impl MoveList {
                     ));
                 }
             } else {
-                self.0.push(Move::new(
+                self.push(Move::new(
                     source_square,
                     target_square,
                     Piece::Pawn,
@@ -156,18 +282,28 @@ impl MoveList {
                     MoveType::Capture,
                 ));
             }
-            attacks.pop_bit(target_square);
         }
 
-        let attack_table =
-            attack_tables.attack_table(game.board(None), Piece::Pawn, side, source_square);
+        let attack_table = attack_tables::ATTACK_TABLES.attack_table(
+            game.board(None),
+            Piece::Pawn,
+            side,
+            source_square,
+        );
 
         if let Some(target_square) = game.en_passant_square() {
             let en_passant_square_attacked =
                 attack_table & Bitboard::from_square(target_square) != 0u64;
 
-            if en_passant_square_attacked {
-                self.0.push(Move::new(
+            if en_passant_square_attacked
+                && Self::en_passant_leaves_king_safe(
+                    game,
+                    source_square,
+                    target_square,
+                    restrictions,
+                )
+            {
+                self.push(Move::new(
                     source_square,
                     target_square,
                     Piece::Pawn,
@@ -178,117 +314,151 @@ impl MoveList {
         }
     }
 
+    // Taking en passant clears two squares on one rank, which can uncover an attack on the king no
+    // pin ray covers, so the board after the capture is checked directly
+    fn en_passant_leaves_king_safe(
+        game: &Game,
+        source_square: Square,
+        target_square: Square,
+        restrictions: &MoveRestrictions,
+    ) -> bool {
+        let king_square = match restrictions.king_square {
+            Some(king_square) => king_square,
+            None => return true,
+        };
+        let mut board = game.board(None);
+        board.pop_bit(source_square);
+        board.pop_bit(Game::pawn_behind_square(target_square, game.side_to_move()));
+        board.set_bit(target_square);
+
+        !square_attacked_on_board(game, board, king_square)
+    }
+
     fn generate_piece_moves(
         &mut self,
         game: &Game,
         piece: Piece,
         source_square: Square,
-        attack_tables: &AttackTables,
+        restrictions: &MoveRestrictions,
+        move_flag: MoveFlag,
     ) {
-        let mut attacks = Self::generate_attacks(game, piece, source_square, attack_tables);
+        let mut attacks = Self::generate_attacks(game, piece, source_square);
+
+        if move_flag == MoveFlag::CapturesOnly {
+            attacks &= game.board(Some(game.side_to_move().opponent_side()));
+        }
 
-        while let Some(target_square) = attacks.get_lsb_square() {
+        // The king is not held to pins or the check mask, but cannot step onto an attacked square -
+        // including one behind it on the line of a checking slider, so it is taken off the board
+        if piece == Piece::King {
+            let mut board = game.board(None);
+            board.pop_bit(source_square);
+
+            for target_square in attacks.squares() {
+                if square_attacked_on_board(game, board, target_square) {
+                    attacks.pop_bit(target_square);
+                }
+            }
+        } else {
+            attacks &= restrictions.target_mask(source_square);
+        }
+
+        for target_square in attacks.squares() {
             let move_type = if game.is_square_occupied(target_square) {
                 MoveType::Capture
             } else {
                 MoveType::Quiet
             };
 
-            self.0.push(Move::new(
+            self.push(Move::new(
                 source_square,
                 target_square,
                 piece,
                 None,
                 move_type,
             ));
-            attacks.pop_bit(target_square);
         }
 
-        if piece == Piece::King {
-            self.generate_castling_moves(game, attack_tables);
+        if piece == Piece::King && restrictions.checkers == 0u64 && move_flag == MoveFlag::All {
+            self.generate_castling_moves(game);
         }
     }
 
-    fn generate_castling_moves(&mut self, game: &Game, attack_tables: &AttackTables) {
+    // Chess960 positions can have the king and rook start anywhere on the back rank, so the
+    // squares either passes over are worked out from where they start and end up
+    fn generate_castling_moves(&mut self, game: &Game) {
         let side = game.side_to_move();
         let opponent_side = side.opponent_side();
+        let king_square = match game.piece_bitboard(Piece::King, side).get_lsb_square() {
+            Some(king_square) => king_square,
+            None => return,
+        };
 
-        match side {
-            Side::White => {
-                if game.castling_type_allowed(CastlingType::WhiteShort)
-                    && !game.is_square_occupied(Square::F1)
-                    && !game.is_square_occupied(Square::G1)
-                    && !game.is_square_attacked(attack_tables, opponent_side, Square::E1)
-                    && !game.is_square_attacked(attack_tables, opponent_side, Square::F1)
-                {
-                    self.0.push(Move::new(
-                        Square::E1,
-                        Square::G1,
-                        Piece::King,
-                        None,
-                        MoveType::Castling,
-                    ));
-                }
+        for castling_type in CastlingType::iter() {
+            if castling_type.side() != side || !game.castling_type_allowed(castling_type) {
+                continue;
+            }
 
-                if game.castling_type_allowed(CastlingType::WhiteLong)
-                    && !game.is_square_occupied(Square::B1)
-                    && !game.is_square_occupied(Square::C1)
-                    && !game.is_square_occupied(Square::D1)
-                    && !game.is_square_attacked(attack_tables, opponent_side, Square::D1)
-                    && !game.is_square_attacked(attack_tables, opponent_side, Square::E1)
-                {
-                    self.0.push(Move::new(
-                        Square::E1,
-                        Square::C1,
-                        Piece::King,
-                        None,
-                        MoveType::Castling,
-                    ));
-                }
+            let rook_square = game.castling_rook_square(castling_type);
+            let king_target_square = castling_type.king_target_square();
+
+            if king_square.rank() != rook_square.rank()
+                || !game
+                    .piece_bitboard(Piece::Rook, side)
+                    .bit_occupied(rook_square)
+            {
+                continue;
             }
-            Side::Black => {
-                if game.castling_type_allowed(CastlingType::BlackShort)
-                    && !game.is_square_occupied(Square::F8)
-                    && !game.is_square_occupied(Square::G8)
-                    && !game.is_square_attacked(attack_tables, opponent_side, Square::E8)
-                    && !game.is_square_attacked(attack_tables, opponent_side, Square::F8)
-                {
-                    self.0.push(Move::new(
-                        Square::E8,
-                        Square::G8,
-                        Piece::King,
-                        None,
-                        MoveType::Castling,
-                    ));
-                }
 
-                if game.castling_type_allowed(CastlingType::BlackLong)
-                    && !game.is_square_occupied(Square::B8)
-                    && !game.is_square_occupied(Square::C8)
-                    && !game.is_square_occupied(Square::D8)
-                    && !game.is_square_attacked(attack_tables, opponent_side, Square::D8)
-                    && !game.is_square_attacked(attack_tables, opponent_side, Square::E8)
-                {
-                    self.0.push(Move::new(
-                        Square::E8,
-                        Square::C8,
-                        Piece::King,
-                        None,
-                        MoveType::Castling,
-                    ));
-                }
+            // The king and rook may pass over each other, but nothing else
+            let mut occupied = game.board(None);
+            occupied.pop_bit(king_square);
+            occupied.pop_bit(rook_square);
+            let path = squares_spanned(king_square, king_target_square)
+                | squares_spanned(rook_square, castling_type.rook_target_square());
+
+            if path & occupied != 0u64 {
+                continue;
             }
+
+            let mut king_path = squares_spanned(king_square, king_target_square);
+            king_path.pop_bit(king_target_square);
+            king_path.set_bit(king_square);
+            let mut king_path_attacked = false;
+
+            for square in king_path.squares() {
+                king_path_attacked |= game.is_square_attacked(opponent_side, square);
+            }
+
+            // The castling rook can leave the line of an attacker open, so where the king ends up
+            // is checked with the king and rook already moved
+            let mut board_after_castling = occupied;
+            board_after_castling.set_bit(king_target_square);
+            board_after_castling.set_bit(castling_type.rook_target_square());
+            king_path_attacked |=
+                square_attacked_on_board(game, board_after_castling, king_target_square);
+
+            if king_path_attacked {
+                continue;
+            }
+
+            self.push(Move::new(
+                king_square,
+                game.castling_move_target_square(castling_type, king_square),
+                Piece::King,
+                None,
+                MoveType::Castling,
+            ));
         }
     }
 
-    fn generate_attacks(
-        game: &Game,
-        piece: Piece,
-        source_square: Square,
-        attack_tables: &AttackTables,
-    ) -> Bitboard {
-        let attack_table =
-            attack_tables.attack_table(game.board(None), piece, game.side_to_move(), source_square);
+    fn generate_attacks(game: &Game, piece: Piece, source_square: Square) -> Bitboard {
+        let attack_table = attack_tables::ATTACK_TABLES.attack_table(
+            game.board(None),
+            piece,
+            game.side_to_move(),
+            source_square,
+        );
         let valid_attack_squares = match piece {
             Piece::Pawn => game.board(Some(game.side_to_move().opponent_side())),
             _ => !game.board(Some(game.side_to_move())),
@@ -298,6 +468,11 @@ impl MoveList {
     }
 
     fn parse_move_string(move_string: &str) -> Result<MoveSearch, ParseError> {
+        // Slicing is only safe on ASCII strings of a valid move length
+        if !move_string.is_ascii() || !(4..=5).contains(&move_string.len()) {
+            return Err(ParseError::VariantNotFound);
+        }
+
         let (source_square_string, remaining_move_string) = move_string.split_at(2);
         let (target_square_string, promoted_piece_string) = remaining_move_string.split_at(2);
         let source_square = Square::from_str(source_square_string.to_uppercase().as_str())?;
@@ -314,10 +489,105 @@ impl MoveList {
     }
 
     pub fn _length(&self) -> usize {
-        self.0.len()
+        self.length
+    }
+}
+
+// What a move has to keep to so the king of the side to move is not left in check - with no king
+// on the board, as in some tests, nothing is restricted
+struct MoveRestrictions {
+    king_square: Option<Square>,
+    checkers: Bitboard,
+    // The squares a move other than a king move has to land on, capturing or blocking a single
+    // checker
+    check_mask: Bitboard,
+    pinned: Bitboard,
+}
+
+impl MoveRestrictions {
+    fn new(game: &Game) -> Self {
+        let side = game.side_to_move();
+        let opponent_side = side.opponent_side();
+        let king_square = match game.piece_bitboard(Piece::King, side).get_lsb_square() {
+            Some(king_square) => king_square,
+            None => {
+                return Self {
+                    king_square: None,
+                    checkers: Bitboard::new(0),
+                    check_mask: Bitboard::new(u64::MAX),
+                    pinned: Bitboard::new(0),
+                }
+            }
+        };
+        let checkers = game.checkers();
+        let check_mask = match checkers.get_lsb_square() {
+            None => Bitboard::new(u64::MAX),
+            Some(checker) if checkers.count_bits() == 1 => {
+                checkers | attack_tables::ATTACK_TABLES.between(king_square, checker)
+            }
+            Some(_) => Bitboard::new(0),
+        };
+
+        // Sliders seen from the king through only the pieces of the side to move are the ones
+        // which could pin one of them
+        let mut pinned = Bitboard::new(0);
+
+        for piece in [Piece::Bishop, Piece::Rook] {
+            let sliders = game.piece_bitboard(piece, opponent_side)
+                | game.piece_bitboard(Piece::Queen, opponent_side);
+            let candidate_pinners = attack_tables::ATTACK_TABLES.attack_table(
+                game.board(Some(opponent_side)),
+                piece,
+                side,
+                king_square,
+            ) & sliders;
+
+            for pinner in candidate_pinners.squares() {
+                let blockers =
+                    attack_tables::ATTACK_TABLES.between(king_square, pinner) & game.board(None);
+
+                if blockers.count_bits() == 1 && blockers & game.board(Some(side)) != 0u64 {
+                    pinned |= blockers;
+                }
+            }
+        }
+
+        Self {
+            king_square: Some(king_square),
+            checkers,
+            check_mask,
+            pinned,
+        }
+    }
+
+    // The squares a piece other than the king can move to - a pinned piece can only move along
+    // the line through the king and itself, which its pinner lies on
+    fn target_mask(&self, source_square: Square) -> Bitboard {
+        match self.king_square {
+            Some(king_square) if self.pinned.bit_occupied(source_square) => {
+                self.check_mask & attack_tables::ATTACK_TABLES.line(king_square, source_square)
+            }
+            _ => self.check_mask,
+        }
     }
 }
 
+// Whether the opponent of the side to move attacks a square with only the given squares occupied,
+// leaving out any of its pieces not on them
+fn square_attacked_on_board(game: &Game, board: Bitboard, square: Square) -> bool {
+    let opponent_side = game.side_to_move().opponent_side();
+
+    game.attackers_to(square, board) & game.board(Some(opponent_side)) != 0u64
+}
+
+// Every square from one to the other on the same rank, both included
+fn squares_spanned(square: Square, other_square: Square) -> Bitboard {
+    let low = (square as usize).min(other_square as usize);
+    let high = (square as usize).max(other_square as usize);
+
+    Bitboard::new((u64::MAX << low) & (u64::MAX >> (63 - high)))
+}
+
 #[derive(Clone, Copy, Debug, FromPrimitive, PartialEq)]
 pub enum MoveType {
     Quiet,
@@ -327,14 +597,11 @@ pub enum MoveType {
     Castling,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Move {
-    source_square: Square,
-    target_square: Square,
-    piece: Piece,
-    promoted_piece: Option<Piece>,
-    move_type: MoveType,
-}
+// Packed into the bits of a single integer - bits 0-5 hold the source square, 6-11 the target
+// square, 12-14 the piece, 15-17 the promoted piece offset by one (zero meaning no promotion) and
+// 18-20 the move type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Move(u32);
 
 impl Move {
     fn new(
@@ -344,48 +611,167 @@ impl Move {
         promoted_piece: Option<Piece>,
         move_type: MoveType,
     ) -> Self {
-        Self {
-            source_square,
-            target_square,
-            piece,
-            promoted_piece,
-            move_type,
-        }
+        let promoted_piece = match promoted_piece {
+            Some(promoted_piece) => promoted_piece as u32 + 1,
+            None => 0,
+        };
+
+        Self(
+            source_square as u32
+                | (target_square as u32) << 6
+                | (piece as u32) << 12
+                | promoted_piece << 15
+                | (move_type as u32) << 18,
+        )
     }
 
+    // Every move is built from valid fields, so unpacking them cannot fail
     pub fn source_square(&self) -> Square {
-        self.source_square
+        Square::from_u32(self.0 & 0x3F).unwrap()
     }
 
     pub fn target_square(&self) -> Square {
-        self.target_square
+        Square::from_u32((self.0 >> 6) & 0x3F).unwrap()
     }
 
     pub fn piece(&self) -> Piece {
-        self.piece
+        Piece::from_u32((self.0 >> 12) & 0x7).unwrap()
     }
 
     pub fn promoted_piece(&self) -> Option<Piece> {
-        self.promoted_piece
+        match (self.0 >> 15) & 0x7 {
+            0 => None,
+            promoted_piece => Piece::from_u32(promoted_piece - 1),
+        }
     }
 
     pub fn move_type(&self) -> MoveType {
-        self.move_type
+        MoveType::from_u32((self.0 >> 18) & 0x7).unwrap()
     }
 
     pub fn as_string(&self) -> String {
-        let source_square_string = self.source_square._to_lowercase_string();
-        let target_square_string = self.target_square._to_lowercase_string();
+        let source_square_string = self.source_square().to_lowercase_string();
+        let target_square_string = self.target_square().to_lowercase_string();
 
-        match self.promoted_piece {
+        match self.promoted_piece() {
             Some(promoted_piece) => {
-                let promoted_piece_string = promoted_piece._to_char(None).to_string();
+                let promoted_piece_string = promoted_piece.to_char(None).to_string();
 
                 source_square_string + &target_square_string + &promoted_piece_string
             }
             None => source_square_string + &target_square_string,
         }
     }
+
+    // Standard algebraic notation, as played from the given position - the source square is given
+    // only as far as needed to tell the move apart from other moves of the same piece
+    pub fn to_san(self, game: &Game) -> String {
+        let source_square = self.source_square();
+        let target_square = self.target_square();
+        let capture = matches!(self.move_type(), MoveType::Capture | MoveType::EnPassant);
+        let mut san = String::new();
+
+        match (self.move_type(), self.piece()) {
+            (MoveType::Castling, _) => match target_square.file() > source_square.file() {
+                true => san += "O-O",
+                false => san += "O-O-O",
+            },
+            (_, Piece::Pawn) => {
+                if capture {
+                    san.push((b'a' + source_square.file() as u8) as char);
+                    san.push('x');
+                }
+
+                san += &target_square.to_lowercase_string();
+
+                if let Some(promoted_piece) = self.promoted_piece() {
+                    san.push('=');
+                    san.push(promoted_piece.to_char(Some(Side::White)));
+                }
+            }
+            (_, piece) => {
+                san.push(piece.to_char(Some(Side::White)));
+
+                let move_list = MoveList::generate_moves(game, MoveFlag::All);
+                let ambiguous_source_squares: Vec<Square> = move_list
+                    .vec()
+                    .iter()
+                    .filter(|mv| {
+                        mv.piece() == piece
+                            && mv.move_type() != MoveType::Castling
+                            && mv.target_square() == target_square
+                            && mv.source_square() != source_square
+                    })
+                    .map(|mv| mv.source_square())
+                    .collect();
+
+                if !ambiguous_source_squares.is_empty() {
+                    let source_square_string = source_square.to_lowercase_string();
+                    let file_unique = ambiguous_source_squares
+                        .iter()
+                        .all(|square| square.file() != source_square.file());
+                    let rank_unique = ambiguous_source_squares
+                        .iter()
+                        .all(|square| square.rank() != source_square.rank());
+
+                    match (file_unique, rank_unique) {
+                        (true, _) => san += &source_square_string[..1],
+                        (false, true) => san += &source_square_string[1..],
+                        (false, false) => san += &source_square_string,
+                    }
+                }
+
+                if capture {
+                    san.push('x');
+                }
+
+                san += &target_square.to_lowercase_string();
+            }
+        }
+
+        let mut game = game.clone();
+
+        if game.make_move(&self).is_ok() && game.king_in_check(game.side_to_move()) {
+            match MoveList::generate_moves(&game, MoveFlag::All)
+                .vec()
+                .is_empty()
+            {
+                true => san.push('#'),
+                false => san.push('+'),
+            }
+        }
+
+        san
+    }
+
+    pub fn encode(&self) -> u32 {
+        self.0
+    }
+
+    // An encoded move read back from elsewhere, such as the transposition table, is checked field
+    // by field before it is used
+    pub fn decode(encoded_move: u32) -> Option<Self> {
+        let source_square = Square::from_u32(encoded_move & 0x3F)?;
+        let target_square = Square::from_u32((encoded_move >> 6) & 0x3F)?;
+        let piece = Piece::from_u32((encoded_move >> 12) & 0x7)?;
+        let promoted_piece = match (encoded_move >> 15) & 0x7 {
+            0 => None,
+            promoted_piece => Some(Piece::from_u32(promoted_piece - 1)?),
+        };
+        let move_type = MoveType::from_u32((encoded_move >> 18) & 0x7)?;
+
+        if encoded_move >> 21 != 0 || source_square == target_square {
+            return None;
+        }
+
+        Some(Self::new(
+            source_square,
+            target_square,
+            piece,
+            promoted_piece,
+            move_type,
+        ))
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -409,12 +795,12 @@ impl MoveSearch {
     }
 
     pub fn _as_string(&self) -> String {
-        let source_square_string = self.source_square._to_lowercase_string();
-        let target_square_string = self.target_square._to_lowercase_string();
+        let source_square_string = self.source_square.to_lowercase_string();
+        let target_square_string = self.target_square.to_lowercase_string();
 
         match self.promoted_piece {
             Some(promoted_piece) => {
-                let promoted_piece_string = promoted_piece._to_char(None).to_string();
+                let promoted_piece_string = promoted_piece.to_char(None).to_string();
 
                 source_square_string + &target_square_string + &promoted_piece_string
             }
@@ -437,18 +823,27 @@ mod tests {
         white_game.load_fen(&white_fen).unwrap();
         black_game.load_fen(&black_fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut white_move_list = MoveList::new();
         let mut black_move_list = MoveList::new();
-        white_move_list.generate_pawn_moves(&white_game, Square::D3, &attack_tables);
-        black_move_list.generate_pawn_moves(&black_game, Square::D6, &attack_tables);
+        white_move_list.generate_pawn_moves(
+            &white_game,
+            Square::D3,
+            &MoveRestrictions::new(&white_game),
+            MoveFlag::All,
+        );
+        black_move_list.generate_pawn_moves(
+            &black_game,
+            Square::D6,
+            &MoveRestrictions::new(&black_game),
+            MoveFlag::All,
+        );
 
         let white_pawn_push = Move::new(Square::D3, Square::D4, Piece::Pawn, None, MoveType::Quiet);
         let black_pawn_push = Move::new(Square::D6, Square::D5, Piece::Pawn, None, MoveType::Quiet);
         let white_moves_correct =
-            white_move_list.0.contains(&white_pawn_push) && white_move_list.0.len() == 1;
+            white_move_list.vec().contains(&white_pawn_push) && white_move_list.vec().len() == 1;
         let black_moves_correct =
-            black_move_list.0.contains(&black_pawn_push) && black_move_list.0.len() == 1;
+            black_move_list.vec().contains(&black_pawn_push) && black_move_list.vec().len() == 1;
 
         assert!(white_moves_correct);
         assert!(black_moves_correct);
@@ -461,11 +856,21 @@ mod tests {
 
         let mut white_move_list = MoveList::new();
         let mut black_move_list = MoveList::new();
-        white_move_list.generate_pawn_moves(&white_game, Square::D3, &attack_tables);
-        black_move_list.generate_pawn_moves(&black_game, Square::D6, &attack_tables);
+        white_move_list.generate_pawn_moves(
+            &white_game,
+            Square::D3,
+            &MoveRestrictions::new(&white_game),
+            MoveFlag::All,
+        );
+        black_move_list.generate_pawn_moves(
+            &black_game,
+            Square::D6,
+            &MoveRestrictions::new(&black_game),
+            MoveFlag::All,
+        );
 
-        let white_moves_correct = white_move_list.0.is_empty();
-        let black_moves_correct = black_move_list.0.is_empty();
+        let white_moves_correct = white_move_list.vec().is_empty();
+        let black_moves_correct = black_move_list.vec().is_empty();
 
         assert!(white_moves_correct);
         assert!(black_moves_correct);
@@ -480,11 +885,20 @@ mod tests {
         white_game.load_fen(&white_fen).unwrap();
         black_game.load_fen(&black_fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut white_move_list = MoveList::new();
         let mut black_move_list = MoveList::new();
-        white_move_list.generate_pawn_moves(&white_game, Square::D2, &attack_tables);
-        black_move_list.generate_pawn_moves(&black_game, Square::D7, &attack_tables);
+        white_move_list.generate_pawn_moves(
+            &white_game,
+            Square::D2,
+            &MoveRestrictions::new(&white_game),
+            MoveFlag::All,
+        );
+        black_move_list.generate_pawn_moves(
+            &black_game,
+            Square::D7,
+            &MoveRestrictions::new(&black_game),
+            MoveFlag::All,
+        );
 
         let white_single_pawn_push =
             Move::new(Square::D2, Square::D3, Piece::Pawn, None, MoveType::Quiet);
@@ -504,12 +918,12 @@ mod tests {
             None,
             MoveType::DoublePawnPush,
         );
-        let white_moves_correct = white_move_list.0.contains(&white_single_pawn_push)
-            && white_move_list.0.contains(&white_double_pawn_push)
-            && white_move_list.0.len() == 2;
-        let black_moves_correct = black_move_list.0.contains(&black_single_pawn_push)
-            && black_move_list.0.contains(&black_double_pawn_push)
-            && black_move_list.0.len() == 2;
+        let white_moves_correct = white_move_list.vec().contains(&white_single_pawn_push)
+            && white_move_list.vec().contains(&white_double_pawn_push)
+            && white_move_list.vec().len() == 2;
+        let black_moves_correct = black_move_list.vec().contains(&black_single_pawn_push)
+            && black_move_list.vec().contains(&black_double_pawn_push)
+            && black_move_list.vec().len() == 2;
 
         assert!(white_moves_correct);
         assert!(black_moves_correct);
@@ -522,17 +936,27 @@ mod tests {
 
         let mut white_move_list = MoveList::new();
         let mut black_move_list = MoveList::new();
-        white_move_list.generate_pawn_moves(&white_game, Square::D2, &attack_tables);
-        black_move_list.generate_pawn_moves(&black_game, Square::D7, &attack_tables);
+        white_move_list.generate_pawn_moves(
+            &white_game,
+            Square::D2,
+            &MoveRestrictions::new(&white_game),
+            MoveFlag::All,
+        );
+        black_move_list.generate_pawn_moves(
+            &black_game,
+            Square::D7,
+            &MoveRestrictions::new(&black_game),
+            MoveFlag::All,
+        );
 
         let white_single_pawn_push =
             Move::new(Square::D2, Square::D3, Piece::Pawn, None, MoveType::Quiet);
         let black_single_pawn_push =
             Move::new(Square::D7, Square::D6, Piece::Pawn, None, MoveType::Quiet);
-        let white_moves_correct =
-            white_move_list.0.contains(&white_single_pawn_push) && white_move_list.0.len() == 1;
-        let black_moves_correct =
-            black_move_list.0.contains(&black_single_pawn_push) && black_move_list.0.len() == 1;
+        let white_moves_correct = white_move_list.vec().contains(&white_single_pawn_push)
+            && white_move_list.vec().len() == 1;
+        let black_moves_correct = black_move_list.vec().contains(&black_single_pawn_push)
+            && black_move_list.vec().len() == 1;
 
         assert!(white_moves_correct);
         assert!(black_moves_correct);
@@ -545,11 +969,21 @@ mod tests {
 
         let mut white_move_list = MoveList::new();
         let mut black_move_list = MoveList::new();
-        white_move_list.generate_pawn_moves(&white_game, Square::D2, &attack_tables);
-        black_move_list.generate_pawn_moves(&black_game, Square::D7, &attack_tables);
+        white_move_list.generate_pawn_moves(
+            &white_game,
+            Square::D2,
+            &MoveRestrictions::new(&white_game),
+            MoveFlag::All,
+        );
+        black_move_list.generate_pawn_moves(
+            &black_game,
+            Square::D7,
+            &MoveRestrictions::new(&black_game),
+            MoveFlag::All,
+        );
 
-        let white_moves_correct = white_move_list.0.is_empty();
-        let black_moves_correct = black_move_list.0.is_empty();
+        let white_moves_correct = white_move_list.vec().is_empty();
+        let black_moves_correct = black_move_list.vec().is_empty();
 
         assert!(white_moves_correct);
         assert!(black_moves_correct);
@@ -564,17 +998,26 @@ mod tests {
         white_game.load_fen(&white_fen).unwrap();
         black_game.load_fen(&black_fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut white_move_list = MoveList::new();
         let mut black_move_list = MoveList::new();
-        white_move_list.generate_pawn_moves(&white_game, Square::D4, &attack_tables);
-        black_move_list.generate_pawn_moves(&black_game, Square::D5, &attack_tables);
+        white_move_list.generate_pawn_moves(
+            &white_game,
+            Square::D4,
+            &MoveRestrictions::new(&white_game),
+            MoveFlag::All,
+        );
+        black_move_list.generate_pawn_moves(
+            &black_game,
+            Square::D5,
+            &MoveRestrictions::new(&black_game),
+            MoveFlag::All,
+        );
 
         let white_capture = Move::new(Square::D4, Square::E5, Piece::Pawn, None, MoveType::Capture);
         let black_capture = Move::new(Square::D5, Square::E4, Piece::Pawn, None, MoveType::Capture);
 
-        assert!(white_move_list.0.contains(&white_capture));
-        assert!(black_move_list.0.contains(&black_capture));
+        assert!(white_move_list.vec().contains(&white_capture));
+        assert!(black_move_list.vec().contains(&black_capture));
     }
 
     #[test]
@@ -586,11 +1029,20 @@ mod tests {
         white_game.load_fen(&white_fen).unwrap();
         black_game.load_fen(&black_fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut white_move_list = MoveList::new();
         let mut black_move_list = MoveList::new();
-        white_move_list.generate_pawn_moves(&white_game, Square::D7, &attack_tables);
-        black_move_list.generate_pawn_moves(&black_game, Square::D2, &attack_tables);
+        white_move_list.generate_pawn_moves(
+            &white_game,
+            Square::D7,
+            &MoveRestrictions::new(&white_game),
+            MoveFlag::All,
+        );
+        black_move_list.generate_pawn_moves(
+            &black_game,
+            Square::D2,
+            &MoveRestrictions::new(&black_game),
+            MoveFlag::All,
+        );
 
         let white_promotion_queen = Move::new(
             Square::D7,
@@ -648,16 +1100,16 @@ mod tests {
             Some(Piece::Knight),
             MoveType::Quiet,
         );
-        let white_moves_correct = white_move_list.0.contains(&white_promotion_queen)
-            && white_move_list.0.contains(&white_promotion_rook)
-            && white_move_list.0.contains(&white_promotion_bishop)
-            && white_move_list.0.contains(&white_promotion_knight)
-            && white_move_list.0.len() == 4;
-        let black_moves_correct = black_move_list.0.contains(&black_promotion_queen)
-            && black_move_list.0.contains(&black_promotion_rook)
-            && black_move_list.0.contains(&black_promotion_bishop)
-            && black_move_list.0.contains(&black_promotion_knight)
-            && black_move_list.0.len() == 4;
+        let white_moves_correct = white_move_list.vec().contains(&white_promotion_queen)
+            && white_move_list.vec().contains(&white_promotion_rook)
+            && white_move_list.vec().contains(&white_promotion_bishop)
+            && white_move_list.vec().contains(&white_promotion_knight)
+            && white_move_list.vec().len() == 4;
+        let black_moves_correct = black_move_list.vec().contains(&black_promotion_queen)
+            && black_move_list.vec().contains(&black_promotion_rook)
+            && black_move_list.vec().contains(&black_promotion_bishop)
+            && black_move_list.vec().contains(&black_promotion_knight)
+            && black_move_list.vec().len() == 4;
 
         assert!(white_moves_correct);
         assert!(black_moves_correct);
@@ -672,11 +1124,20 @@ mod tests {
         white_game.load_fen(&white_fen).unwrap();
         black_game.load_fen(&black_fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut white_move_list = MoveList::new();
         let mut black_move_list = MoveList::new();
-        white_move_list.generate_pawn_moves(&white_game, Square::D5, &attack_tables);
-        black_move_list.generate_pawn_moves(&black_game, Square::D4, &attack_tables);
+        white_move_list.generate_pawn_moves(
+            &white_game,
+            Square::D5,
+            &MoveRestrictions::new(&white_game),
+            MoveFlag::All,
+        );
+        black_move_list.generate_pawn_moves(
+            &black_game,
+            Square::D4,
+            &MoveRestrictions::new(&black_game),
+            MoveFlag::All,
+        );
 
         let white_en_passant = Move::new(
             Square::D5,
@@ -692,8 +1153,8 @@ mod tests {
             None,
             MoveType::EnPassant,
         );
-        let white_moves_correct = white_move_list.0.contains(&white_en_passant);
-        let black_moves_correct = black_move_list.0.contains(&black_en_passant);
+        let white_moves_correct = white_move_list.vec().contains(&white_en_passant);
+        let black_moves_correct = black_move_list.vec().contains(&black_en_passant);
 
         assert!(white_moves_correct);
         assert!(black_moves_correct);
@@ -705,9 +1166,14 @@ mod tests {
         let fen = vec!["8/8/2p5/5P2/3N4/1p6/2p1P3/8", "w", "-", "-", "0", "1"];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut move_list = MoveList::new();
-        move_list.generate_piece_moves(&game, Piece::Knight, Square::D4, &attack_tables);
+        move_list.generate_piece_moves(
+            &game,
+            Piece::Knight,
+            Square::D4,
+            &MoveRestrictions::new(&game),
+            MoveFlag::All,
+        );
 
         let desired_c6_move = Move::new(
             Square::D4,
@@ -736,13 +1202,13 @@ mod tests {
             None,
             MoveType::Capture,
         );
-        let knight_moves_correct = move_list.0.contains(&desired_c6_move)
-            && move_list.0.contains(&desired_e6_move)
-            && move_list.0.contains(&desired_b5_move)
-            && move_list.0.contains(&desired_b3_move)
-            && move_list.0.contains(&desired_f3_move)
-            && move_list.0.contains(&desired_c2_move)
-            && move_list.0.len() == 6;
+        let knight_moves_correct = move_list.vec().contains(&desired_c6_move)
+            && move_list.vec().contains(&desired_e6_move)
+            && move_list.vec().contains(&desired_b5_move)
+            && move_list.vec().contains(&desired_b3_move)
+            && move_list.vec().contains(&desired_f3_move)
+            && move_list.vec().contains(&desired_c2_move)
+            && move_list.vec().len() == 6;
 
         assert!(knight_moves_correct);
     }
@@ -753,9 +1219,14 @@ mod tests {
         let fen = vec!["8/6p1/8/8/3B4/8/5P2/8", "w", "-", "-", "0", "1"];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut move_list = MoveList::new();
-        move_list.generate_piece_moves(&game, Piece::Bishop, Square::D4, &attack_tables);
+        move_list.generate_piece_moves(
+            &game,
+            Piece::Bishop,
+            Square::D4,
+            &MoveRestrictions::new(&game),
+            MoveFlag::All,
+        );
 
         let desired_a7_move =
             Move::new(Square::D4, Square::A7, Piece::Bishop, None, MoveType::Quiet);
@@ -782,17 +1253,17 @@ mod tests {
             Move::new(Square::D4, Square::A1, Piece::Bishop, None, MoveType::Quiet);
         let desired_e3_move =
             Move::new(Square::D4, Square::E3, Piece::Bishop, None, MoveType::Quiet);
-        let bishop_moves_correct = move_list.0.contains(&desired_a7_move)
-            && move_list.0.contains(&desired_b6_move)
-            && move_list.0.contains(&desired_c5_move)
-            && move_list.0.contains(&desired_g7_move)
-            && move_list.0.contains(&desired_f6_move)
-            && move_list.0.contains(&desired_e5_move)
-            && move_list.0.contains(&desired_c3_move)
-            && move_list.0.contains(&desired_b2_move)
-            && move_list.0.contains(&desired_a1_move)
-            && move_list.0.contains(&desired_e3_move)
-            && move_list.0.len() == 10;
+        let bishop_moves_correct = move_list.vec().contains(&desired_a7_move)
+            && move_list.vec().contains(&desired_b6_move)
+            && move_list.vec().contains(&desired_c5_move)
+            && move_list.vec().contains(&desired_g7_move)
+            && move_list.vec().contains(&desired_f6_move)
+            && move_list.vec().contains(&desired_e5_move)
+            && move_list.vec().contains(&desired_c3_move)
+            && move_list.vec().contains(&desired_b2_move)
+            && move_list.vec().contains(&desired_a1_move)
+            && move_list.vec().contains(&desired_e3_move)
+            && move_list.vec().len() == 10;
 
         assert!(bishop_moves_correct);
     }
@@ -803,9 +1274,14 @@ mod tests {
         let fen = vec!["3p4/8/8/8/3R1P2/8/8/8", "w", "-", "-", "0", "1"];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut move_list = MoveList::new();
-        move_list.generate_piece_moves(&game, Piece::Rook, Square::D4, &attack_tables);
+        move_list.generate_piece_moves(
+            &game,
+            Piece::Rook,
+            Square::D4,
+            &MoveRestrictions::new(&game),
+            MoveFlag::All,
+        );
 
         let desired_d8_move =
             Move::new(Square::D4, Square::D8, Piece::Rook, None, MoveType::Capture);
@@ -819,18 +1295,18 @@ mod tests {
         let desired_d3_move = Move::new(Square::D4, Square::D3, Piece::Rook, None, MoveType::Quiet);
         let desired_d2_move = Move::new(Square::D4, Square::D2, Piece::Rook, None, MoveType::Quiet);
         let desired_d1_move = Move::new(Square::D4, Square::D1, Piece::Rook, None, MoveType::Quiet);
-        let rook_moves_correct = move_list.0.contains(&desired_d8_move)
-            && move_list.0.contains(&desired_d7_move)
-            && move_list.0.contains(&desired_d6_move)
-            && move_list.0.contains(&desired_d5_move)
-            && move_list.0.contains(&desired_a4_move)
-            && move_list.0.contains(&desired_b4_move)
-            && move_list.0.contains(&desired_c4_move)
-            && move_list.0.contains(&desired_e4_move)
-            && move_list.0.contains(&desired_d3_move)
-            && move_list.0.contains(&desired_d2_move)
-            && move_list.0.contains(&desired_d1_move)
-            && move_list.0.len() == 11;
+        let rook_moves_correct = move_list.vec().contains(&desired_d8_move)
+            && move_list.vec().contains(&desired_d7_move)
+            && move_list.vec().contains(&desired_d6_move)
+            && move_list.vec().contains(&desired_d5_move)
+            && move_list.vec().contains(&desired_a4_move)
+            && move_list.vec().contains(&desired_b4_move)
+            && move_list.vec().contains(&desired_c4_move)
+            && move_list.vec().contains(&desired_e4_move)
+            && move_list.vec().contains(&desired_d3_move)
+            && move_list.vec().contains(&desired_d2_move)
+            && move_list.vec().contains(&desired_d1_move)
+            && move_list.vec().len() == 11;
 
         assert!(rook_moves_correct)
     }
@@ -841,9 +1317,14 @@ mod tests {
         let fen = vec!["3p4/6p1/8/8/3Q1P2/8/5P2/8", "w", "-", "-", "0", "1"];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut move_list = MoveList::new();
-        move_list.generate_piece_moves(&game, Piece::Queen, Square::D4, &attack_tables);
+        move_list.generate_piece_moves(
+            &game,
+            Piece::Queen,
+            Square::D4,
+            &MoveRestrictions::new(&game),
+            MoveFlag::All,
+        );
 
         let desired_a7_move =
             Move::new(Square::D4, Square::A7, Piece::Queen, None, MoveType::Quiet);
@@ -897,28 +1378,28 @@ mod tests {
             Move::new(Square::D4, Square::D1, Piece::Queen, None, MoveType::Quiet);
         let desired_e3_move =
             Move::new(Square::D4, Square::E3, Piece::Queen, None, MoveType::Quiet);
-        let queen_moves_correct = move_list.0.contains(&desired_a7_move)
-            && move_list.0.contains(&desired_b6_move)
-            && move_list.0.contains(&desired_c5_move)
-            && move_list.0.contains(&desired_d8_move)
-            && move_list.0.contains(&desired_d7_move)
-            && move_list.0.contains(&desired_d6_move)
-            && move_list.0.contains(&desired_d5_move)
-            && move_list.0.contains(&desired_g7_move)
-            && move_list.0.contains(&desired_f6_move)
-            && move_list.0.contains(&desired_e5_move)
-            && move_list.0.contains(&desired_a4_move)
-            && move_list.0.contains(&desired_b4_move)
-            && move_list.0.contains(&desired_c4_move)
-            && move_list.0.contains(&desired_e4_move)
-            && move_list.0.contains(&desired_c3_move)
-            && move_list.0.contains(&desired_b2_move)
-            && move_list.0.contains(&desired_a1_move)
-            && move_list.0.contains(&desired_d3_move)
-            && move_list.0.contains(&desired_d2_move)
-            && move_list.0.contains(&desired_d1_move)
-            && move_list.0.contains(&desired_e3_move)
-            && move_list.0.len() == 21;
+        let queen_moves_correct = move_list.vec().contains(&desired_a7_move)
+            && move_list.vec().contains(&desired_b6_move)
+            && move_list.vec().contains(&desired_c5_move)
+            && move_list.vec().contains(&desired_d8_move)
+            && move_list.vec().contains(&desired_d7_move)
+            && move_list.vec().contains(&desired_d6_move)
+            && move_list.vec().contains(&desired_d5_move)
+            && move_list.vec().contains(&desired_g7_move)
+            && move_list.vec().contains(&desired_f6_move)
+            && move_list.vec().contains(&desired_e5_move)
+            && move_list.vec().contains(&desired_a4_move)
+            && move_list.vec().contains(&desired_b4_move)
+            && move_list.vec().contains(&desired_c4_move)
+            && move_list.vec().contains(&desired_e4_move)
+            && move_list.vec().contains(&desired_c3_move)
+            && move_list.vec().contains(&desired_b2_move)
+            && move_list.vec().contains(&desired_a1_move)
+            && move_list.vec().contains(&desired_d3_move)
+            && move_list.vec().contains(&desired_d2_move)
+            && move_list.vec().contains(&desired_d1_move)
+            && move_list.vec().contains(&desired_e3_move)
+            && move_list.vec().len() == 21;
 
         assert!(queen_moves_correct);
     }
@@ -929,9 +1410,14 @@ mod tests {
         let fen = vec!["8/8/8/2pP4/2PK4/2p5/8/8", "w", "-", "-", "0", "1"];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut move_list = MoveList::new();
-        move_list.generate_piece_moves(&game, Piece::King, Square::D4, &attack_tables);
+        move_list.generate_piece_moves(
+            &game,
+            Piece::King,
+            Square::D4,
+            &MoveRestrictions::new(&game),
+            MoveFlag::All,
+        );
 
         let desired_c5_move =
             Move::new(Square::D4, Square::C5, Piece::King, None, MoveType::Capture);
@@ -941,13 +1427,13 @@ mod tests {
             Move::new(Square::D4, Square::C3, Piece::King, None, MoveType::Capture);
         let desired_d3_move = Move::new(Square::D4, Square::D3, Piece::King, None, MoveType::Quiet);
         let desired_e3_move = Move::new(Square::D4, Square::E3, Piece::King, None, MoveType::Quiet);
-        let king_moves_correct = move_list.0.contains(&desired_c5_move)
-            && move_list.0.contains(&desired_e5_move)
-            && move_list.0.contains(&desired_e4_move)
-            && move_list.0.contains(&desired_c3_move)
-            && move_list.0.contains(&desired_d3_move)
-            && move_list.0.contains(&desired_e3_move)
-            && move_list.0.len() == 6;
+        let king_moves_correct = move_list.vec().contains(&desired_c5_move)
+            && move_list.vec().contains(&desired_e5_move)
+            && move_list.vec().contains(&desired_e4_move)
+            && move_list.vec().contains(&desired_c3_move)
+            && move_list.vec().contains(&desired_d3_move)
+            && move_list.vec().contains(&desired_e3_move)
+            && move_list.vec().len() == 6;
 
         assert!(king_moves_correct);
     }
@@ -958,9 +1444,8 @@ mod tests {
         let fen = vec!["8/8/8/8/8/8/8/R3K2R", "w", "KQ", "-", "0", "1"];
         game.load_fen(&fen).unwrap();
 
-        let attack_tables = AttackTables::initialise();
         let mut move_list = MoveList::new();
-        move_list.generate_castling_moves(&game, &attack_tables);
+        move_list.generate_castling_moves(&game);
 
         let desired_short_castle = Move::new(
             Square::E1,
@@ -976,9 +1461,9 @@ mod tests {
             None,
             MoveType::Castling,
         );
-        let castling_moves_correct = move_list.0.contains(&desired_short_castle)
-            && move_list.0.contains(&desired_long_castle)
-            && move_list.0.len() == 2;
+        let castling_moves_correct = move_list.vec().contains(&desired_short_castle)
+            && move_list.vec().contains(&desired_long_castle)
+            && move_list.vec().len() == 2;
 
         assert!(castling_moves_correct);
 
@@ -986,7 +1471,7 @@ mod tests {
         game.load_fen(&fen).unwrap();
 
         let mut move_list = MoveList::new();
-        move_list.generate_castling_moves(&game, &attack_tables);
+        move_list.generate_castling_moves(&game);
 
         let desired_long_castle = Move::new(
             Square::E1,
@@ -996,7 +1481,7 @@ mod tests {
             MoveType::Castling,
         );
         let castling_moves_correct =
-            move_list.0.contains(&desired_long_castle) && move_list.0.len() == 1;
+            move_list.vec().contains(&desired_long_castle) && move_list.vec().len() == 1;
 
         assert!(castling_moves_correct);
 
@@ -1004,17 +1489,83 @@ mod tests {
         game.load_fen(&fen).unwrap();
 
         let mut move_list = MoveList::new();
-        move_list.generate_castling_moves(&game, &attack_tables);
+        move_list.generate_castling_moves(&game);
 
-        assert!(move_list.0.is_empty());
+        assert!(move_list.vec().is_empty());
 
         let fen = vec!["8/8/8/8/8/8/8/R3K2R", "w", "-", "-", "0", "1"];
         game.load_fen(&fen).unwrap();
 
         let mut move_list = MoveList::new();
-        move_list.generate_castling_moves(&game, &attack_tables);
+        move_list.generate_castling_moves(&game);
+
+        assert!(move_list.vec().is_empty());
+    }
+
+    #[test]
+    fn only_legal_moves_generated() {
+        let mut game = Game::initialise();
+        let legal_moves = |game: &Game| {
+            let mut moves: Vec<String> = MoveList::generate_moves(game, MoveFlag::All)
+                .vec()
+                .iter()
+                .map(|mv| mv.as_string())
+                .collect();
+            moves.sort();
+
+            moves
+        };
+
+        // pinned pieces keep to the pin ray
+        game.load_fen(&["4r1k1/8/8/8/8/8/4N3/4K3", "w", "-", "-", "0", "1"])
+            .unwrap();
+        assert_eq!(legal_moves(&game), ["e1d1", "e1d2", "e1f1", "e1f2"]);
+
+        game.load_fen(&["4r1k1/8/8/8/8/8/4R3/4K3", "w", "-", "-", "0", "1"])
+            .unwrap();
+        let moves = legal_moves(&game);
+        assert_eq!(moves.len(), 10);
+        assert!(moves.contains(&"e2e8".to_string()) && !moves.contains(&"e2d2".to_string()));
+
+        // a check is blocked or stepped out of, not along the line of the checker
+        game.load_fen(&["k7/8/8/8/8/8/1N6/r3K2R", "w", "-", "-", "0", "1"])
+            .unwrap();
+        assert_eq!(legal_moves(&game), ["b2d1", "e1d2", "e1e2", "e1f2"]);
+
+        // taking en passant would uncover the rook along the rank
+        game.load_fen(&["8/8/8/K2pP2r/8/8/8/7k", "w", "-", "d6", "0", "1"])
+            .unwrap();
+        let moves = legal_moves(&game);
+        assert!(moves.contains(&"e5e6".to_string()) && !moves.contains(&"e5d6".to_string()));
+
+        // only the king can move out of a double check
+        game.load_fen(&["4k3/8/8/8/8/5n2/8/r3K2Q", "w", "-", "-", "0", "1"])
+            .unwrap();
+        assert_eq!(legal_moves(&game), ["e1e2", "e1f2"]);
+    }
 
-        assert!(move_list.0.is_empty());
+    #[test]
+    fn captures_only_generated() {
+        let mut game = Game::initialise();
+        let fen = vec!["r3k3/1P6/8/3pP3/8/2n5/8/R3K2R", "w", "KQq", "d6", "0", "1"];
+        game.load_fen(&fen).unwrap();
+
+        let all_moves = MoveList::generate_moves(&game, MoveFlag::All);
+        let captures = MoveList::generate_moves(&game, MoveFlag::CapturesOnly);
+        let expected_captures: Vec<Move> = all_moves
+            .vec()
+            .iter()
+            .copied()
+            .filter(|mv| {
+                mv.move_type() == MoveType::Capture
+                    || mv.move_type() == MoveType::EnPassant
+                    || mv.promoted_piece().is_some()
+            })
+            .collect();
+
+        // bxa8, b8 and exd6 along with Rxa8, each promotion to all four pieces
+        assert_eq!(captures.vec(), expected_captures);
+        assert_eq!(captures.vec().len(), 10);
     }
 
     #[test]
@@ -1049,4 +1600,158 @@ mod tests {
 
         assert_eq!(move_search, desired_move_search);
     }
+
+    #[test]
+    fn find_san_moves() {
+        let mut game = Game::initialise();
+        let tricky_position =
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+        for (fen, san, move_string) in [
+            (tricky_position, "O-O", Some("e1g1")),
+            (tricky_position, "O-O-O+", Some("e1c1")),
+            (tricky_position, "Nxf7", Some("e5f7")),
+            (tricky_position, "dxe6!?", Some("d5e6")),
+            (tricky_position, "Qxf6", Some("f3f6")),
+            (tricky_position, "a4", Some("a2a4")),
+            (tricky_position, "Kd1", Some("e1d1")),
+            (tricky_position, "Qf8", None),
+            ("k7/8/8/8/8/8/8/R5KR w - - 0 1", "Rd1", Some("a1d1")),
+            ("k7/8/8/8/8/8/8/R5KR w - - 0 1", "Rgd1", None),
+            ("k7/8/8/8/8/8/8/R6R w - - 0 1", "Rd1", None),
+            ("k7/8/8/8/8/8/8/R6R w - - 0 1", "Rad1", Some("a1d1")),
+            ("k7/8/8/8/8/8/8/R6R w - - 0 1", "Rhxd1", Some("h1d1")),
+            ("7R/k7/8/8/8/8/8/K6R w - - 0 1", "R1h4", Some("h1h4")),
+            ("7R/k7/8/8/8/8/8/K6R w - - 0 1", "Rh8h4", Some("h8h4")),
+            ("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a8=Q+", Some("a7a8q")),
+            ("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a8N", Some("a7a8n")),
+            ("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a8", None),
+        ] {
+            game.load_fen(&fen.split_whitespace().collect::<Vec<&str>>())
+                .unwrap();
+            let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+            let mv = move_list.find_move_from_san(san).ok();
+
+            assert_eq!(
+                mv.map(|mv| mv.as_string()).as_deref(),
+                move_string,
+                "{} {}",
+                fen,
+                san
+            );
+        }
+
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+
+        for malformed_san in ["", "Q", "Za8", "a9", "Kéa8"] {
+            assert!(
+                matches!(
+                    move_list.find_move_from_san(malformed_san),
+                    Err(InputError::InvalidMoveString)
+                ),
+                "{}",
+                malformed_san
+            );
+        }
+    }
+
+    #[test]
+    fn san_moves() {
+        let mut game = Game::initialise();
+
+        for (fen, move_string, san) in [
+            ("startpos", "g1f3", "Nf3"),
+            ("startpos", "e2e4", "e4"),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "e1c1",
+                "O-O-O",
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "d5e6",
+                "dxe6",
+            ),
+            ("r3k2r/8/8/8/8/8/8/1R2K1R1 w GB - 0 1", "e1g1", "O-O"),
+            ("k7/8/8/8/8/8/8/R6R w - - 0 1", "a1d1", "Rad1"),
+            ("k7/8/8/8/8/8/8/R5KR w - - 0 1", "a1d1", "Rd1"),
+            ("7R/k7/8/8/8/8/8/K6R w - - 0 1", "h1h4", "R1h4"),
+            ("4k3/8/8/8/8/8/8/Q1Q1K3 w - - 0 1", "c1b2", "Qcb2"),
+            ("7k/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1", "a4b3", "Qa4b3"),
+            ("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1", "e4d3", "exd3"),
+            ("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a7a8q", "a8=Q+"),
+            ("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a7a8n", "a8=N"),
+            ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Ra8#"),
+        ] {
+            game.load_fen(&fen.split_whitespace().collect::<Vec<&str>>())
+                .unwrap();
+            let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+            let mv = move_list
+                .find_move_from_string(move_string)
+                .expect(move_string);
+
+            assert_eq!(mv.to_san(&game), san, "{} {}", fen, move_string);
+        }
+    }
+
+    #[test]
+    fn san_round_trip() {
+        let mut game = Game::initialise();
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "7k/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1",
+        ] {
+            game.load_fen(&fen.split_whitespace().collect::<Vec<&str>>())
+                .unwrap();
+            let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+
+            for mv in move_list.vec() {
+                let san = mv.to_san(&game);
+
+                assert_eq!(
+                    move_list.find_move_from_san(&san).ok(),
+                    Some(*mv),
+                    "{}",
+                    san
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encode_decode_move() {
+        let moves = [
+            Move::new(
+                Square::E2,
+                Square::E4,
+                Piece::Pawn,
+                None,
+                MoveType::DoublePawnPush,
+            ),
+            Move::new(
+                Square::G7,
+                Square::H8,
+                Piece::Pawn,
+                Some(Piece::Queen),
+                MoveType::Capture,
+            ),
+            Move::new(
+                Square::E8,
+                Square::C8,
+                Piece::King,
+                None,
+                MoveType::Castling,
+            ),
+            Move::new(Square::H1, Square::A8, Piece::Bishop, None, MoveType::Quiet),
+        ];
+
+        for mv in moves {
+            assert_eq!(Move::decode(mv.encode()), Some(mv));
+        }
+
+        assert_eq!(Move::decode(0), None);
+        assert_eq!(Move::decode(0x7 << 18), None);
+    }
 }
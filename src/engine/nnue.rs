@@ -0,0 +1,392 @@
+use super::{
+    game::{Game, Piece, Side, Square},
+    search::Value,
+};
+use crate::uci::{EvalFileError, InputError};
+use std::{fs, sync::Arc};
+
+// A 768 -> 256x2 -> 1 network, with one input for each piece of each side on each square, seen
+// from the point of view of both sides - the hidden layer is stored as the accumulators, which are
+// updated as pieces move instead of being recomputed
+
+// Each side sees the board mirrored left to right while its king is on the e to h files, so the
+// network only learns king positions on one half of the board
+const HIDDEN_SIZE: usize = 256;
+const INPUT_SIZE: usize = 768;
+// The network file holds these as little-endian i16 values, in this order
+const FEATURE_WEIGHT_COUNT: usize = INPUT_SIZE * HIDDEN_SIZE;
+const FEATURE_BIAS_COUNT: usize = HIDDEN_SIZE;
+const OUTPUT_WEIGHT_COUNT: usize = 2 * HIDDEN_SIZE;
+const NETWORK_FILE_BYTES: usize =
+    2 * (FEATURE_WEIGHT_COUNT + FEATURE_BIAS_COUNT + OUTPUT_WEIGHT_COUNT + 1);
+// Quantisation of the hidden layer and the output weights, and the scale of the output in
+// centipawns, matching the usual trainers
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i32 = 400;
+
+pub struct Network {
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+impl Network {
+    pub fn load(file_path: &str) -> Result<Self, InputError> {
+        let contents = fs::read(file_path).map_err(EvalFileError::from_io_error)?;
+
+        Self::from_bytes(&contents)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, InputError> {
+        if bytes.len() != NETWORK_FILE_BYTES {
+            return Err(InputError::EvalFile(EvalFileError::SizeMismatch));
+        }
+
+        let mut values = bytes
+            .chunks_exact(2)
+            .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]));
+        let feature_weights = values.by_ref().take(FEATURE_WEIGHT_COUNT).collect();
+        let feature_biases = values.by_ref().take(FEATURE_BIAS_COUNT).collect();
+        let output_weights = values.by_ref().take(OUTPUT_WEIGHT_COUNT).collect();
+        let output_bias = values.next().unwrap_or_default();
+
+        Ok(Self {
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    // From the side to move's point of view, with its own accumulator feeding the first half of
+    // the output weights
+    fn evaluate(&self, accumulator: &Accumulator, side_to_move: Side) -> Value {
+        let perspectives = [side_to_move, side_to_move.opponent_side()];
+        let mut output = 0;
+
+        for (perspective_index, perspective) in perspectives.into_iter().enumerate() {
+            let output_weights = &self.output_weights
+                [perspective_index * HIDDEN_SIZE..(perspective_index + 1) * HIDDEN_SIZE];
+
+            for (&value, &weight) in accumulator.values[perspective as usize]
+                .iter()
+                .zip(output_weights)
+            {
+                output += (value as i32).clamp(0, QA) * weight as i32;
+            }
+        }
+
+        (output + self.output_bias as i32) * SCALE / (QA * QB)
+    }
+
+    fn feature_weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * HIDDEN_SIZE..(feature + 1) * HIDDEN_SIZE]
+    }
+}
+
+type Feature = (Piece, Side, Square);
+
+// The pieces a move adds to and removes from the board, applied to the accumulators together once
+// the move is known to be legal - no move adds or removes more than two pieces
+#[derive(Clone, Default, PartialEq)]
+struct FeatureDelta {
+    added: [Option<Feature>; 2],
+    removed: [Option<Feature>; 2],
+}
+
+impl FeatureDelta {
+    fn push(features: &mut [Option<Feature>; 2], feature: Feature) {
+        match features.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(feature),
+            None => panic!("a move changed more than two pieces"),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+// The hidden layer before activation for each side's point of view, indexed by the Side enum,
+// along with whether that side's features were mirrored when it was computed
+#[derive(Clone, PartialEq)]
+struct Accumulator {
+    values: [[i16; HIDDEN_SIZE]; 2],
+    mirrored: [bool; 2],
+}
+
+impl Accumulator {
+    fn refresh(network: &Network, game: &Game) -> Self {
+        let mut accumulator = Self {
+            values: [[0; HIDDEN_SIZE]; 2],
+            mirrored: [false; 2],
+        };
+
+        for perspective in [Side::White, Side::Black] {
+            accumulator.refresh_perspective(network, game, perspective);
+        }
+
+        accumulator
+    }
+
+    fn refresh_perspective(&mut self, network: &Network, game: &Game, perspective: Side) {
+        let mirrored = king_mirrored(game, perspective);
+        let values = &mut self.values[perspective as usize];
+        values.copy_from_slice(&network.feature_biases);
+        self.mirrored[perspective as usize] = mirrored;
+
+        for (bitboard, piece, side) in game.piece_bitboards() {
+            for square in bitboard.squares() {
+                let feature = feature_index(perspective, mirrored, (piece, side, square));
+
+                for (value, &weight) in values.iter_mut().zip(network.feature_weights(feature)) {
+                    *value += weight;
+                }
+            }
+        }
+    }
+
+    // Every added and removed feature is applied in a single pass over the hidden layer
+    fn apply_delta(&mut self, network: &Network, perspective: Side, delta: &FeatureDelta) {
+        let mirrored = self.mirrored[perspective as usize];
+        let weights = |feature: Option<Feature>| {
+            feature.map(|feature| {
+                network.feature_weights(feature_index(perspective, mirrored, feature))
+            })
+        };
+        let added = delta.added.map(weights);
+        let removed = delta.removed.map(weights);
+
+        for (index, value) in self.values[perspective as usize].iter_mut().enumerate() {
+            for weights in added.iter().flatten() {
+                *value += weights[index];
+            }
+
+            for weights in removed.iter().flatten() {
+                *value -= weights[index];
+            }
+        }
+    }
+}
+
+// The network and the accumulators of the position a game holds, kept up to date as moves are
+// made - the accumulators before each move are kept on a stack, so undoing a move only pops them
+#[derive(Clone)]
+pub struct NnueState {
+    network: Arc<Network>,
+    accumulators: Vec<Accumulator>,
+    pending: FeatureDelta,
+}
+
+impl NnueState {
+    pub fn new(network: Arc<Network>, game: &Game) -> Self {
+        let accumulator = Accumulator::refresh(&network, game);
+
+        Self {
+            network,
+            accumulators: vec![accumulator],
+            pending: FeatureDelta::default(),
+        }
+    }
+
+    pub fn network(&self) -> Arc<Network> {
+        Arc::clone(&self.network)
+    }
+
+    pub fn refresh(&mut self, game: &Game) {
+        self.accumulators = vec![Accumulator::refresh(&self.network, game)];
+        self.pending = FeatureDelta::default();
+    }
+
+    pub fn add_feature(&mut self, piece: Piece, side: Side, square: Square) {
+        FeatureDelta::push(&mut self.pending.added, (piece, side, square));
+    }
+
+    pub fn remove_feature(&mut self, piece: Piece, side: Side, square: Square) {
+        FeatureDelta::push(&mut self.pending.removed, (piece, side, square));
+    }
+
+    // Takes the game the pending features were made in - a side whose king crossed between the d
+    // and e files sees every piece mirrored, so its accumulator is recomputed instead
+    pub fn apply_pending(&mut self, game: &Game) {
+        let pending = std::mem::take(&mut self.pending);
+        let mut accumulator = self.accumulator().clone();
+
+        for perspective in [Side::White, Side::Black] {
+            match accumulator.mirrored[perspective as usize] == king_mirrored(game, perspective) {
+                true => accumulator.apply_delta(&self.network, perspective, &pending),
+                false => accumulator.refresh_perspective(&self.network, game, perspective),
+            }
+        }
+
+        self.accumulators.push(accumulator);
+    }
+
+    // For a move found to be illegal after its features were added
+    pub fn discard_pending(&mut self) {
+        self.pending = FeatureDelta::default();
+    }
+
+    pub fn undo(&mut self) {
+        if self.accumulators.len() > 1 {
+            self.accumulators.pop();
+        }
+    }
+
+    // Whether the accumulators agree with ones computed from scratch for the game
+    pub fn matches_refresh(&self, game: &Game) -> bool {
+        self.pending.is_empty() && *self.accumulator() == Accumulator::refresh(&self.network, game)
+    }
+
+    pub fn evaluate(&self, side_to_move: Side) -> Value {
+        debug_assert!(self.pending.is_empty());
+
+        self.network.evaluate(self.accumulator(), side_to_move)
+    }
+
+    // The stack is never left empty
+    fn accumulator(&self) -> &Accumulator {
+        &self.accumulators[self.accumulators.len() - 1]
+    }
+}
+
+fn king_mirrored(game: &Game, perspective: Side) -> bool {
+    game.piece_bitboard(Piece::King, perspective)
+        .get_lsb_square()
+        .is_some_and(|king_square| king_square.file() >= 4)
+}
+
+// Each point of view sees its own pieces first, on squares counted from its own first rank
+// starting at a1, so both sides share the same weights
+fn feature_index(perspective: Side, mirrored: bool, (piece, side, square): Feature) -> usize {
+    let (side_index, mut square_index) = match perspective {
+        Side::White => (side as usize, square as usize ^ 56),
+        Side::Black => (side.opponent_side() as usize, square as usize),
+    };
+
+    if mirrored {
+        square_index ^= 7;
+    }
+
+    side_index * 384 + piece as usize * 64 + square_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::moves::{MoveFlag, MoveList},
+        *,
+    };
+    use crate::random::{Random, DEFAULT_SEED};
+
+    // Small random weights, so the accumulators cannot overflow
+    fn random_network() -> Network {
+        let mut random = Random::new(DEFAULT_SEED).unwrap();
+        let bytes: Vec<u8> = (0..NETWORK_FILE_BYTES / 2)
+            .flat_map(|_| ((random.generate_u32() % 33) as i16 - 16).to_le_bytes())
+            .collect();
+
+        Network::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn network_file_size() {
+        assert!(matches!(
+            Network::from_bytes(&[0; 64]),
+            Err(InputError::EvalFile(EvalFileError::SizeMismatch))
+        ));
+        assert!(Network::from_bytes(&vec![0; NETWORK_FILE_BYTES]).is_ok());
+        assert!(matches!(
+            Network::load("missing.nnue"),
+            Err(InputError::EvalFile(EvalFileError::Io(_)))
+        ));
+    }
+
+    #[test]
+    fn incremental_updates_match_refresh() {
+        let network = Arc::new(random_network());
+        let mut game = Game::initialise();
+        game.load_fen(&[
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R",
+            "w",
+            "KQkq",
+            "-",
+            "0",
+            "1",
+        ])
+        .unwrap();
+        game.set_network(Some(Arc::clone(&network)));
+
+        for mv in MoveList::generate_moves(&game, MoveFlag::All).vec() {
+            if game.make_move(mv).is_err() {
+                assert!(game.verify().is_empty());
+                continue;
+            }
+
+            let refreshed = NnueState::new(Arc::clone(&network), &game);
+
+            assert_eq!(
+                game.nnue_evaluation(),
+                Some(refreshed.evaluate(game.side_to_move())),
+                "{}",
+                mv.as_string()
+            );
+
+            game.undo_move();
+
+            assert!(game.verify().is_empty(), "{}", mv.as_string());
+        }
+    }
+
+    // Random games take the kings across the d and e files and promote pawns, which the single
+    // move test above may not reach
+    #[test]
+    fn random_games_match_refresh() {
+        let network = Arc::new(random_network());
+        let mut random = Random::new(DEFAULT_SEED).unwrap();
+
+        for fen in [
+            ["r3k2r/8/8/8/8/8/8/R3K2R", "w", "KQkq", "-", "0", "1"],
+            ["n1n5/PPPk4/8/8/8/8/4Kppp/5N1N", "b", "-", "-", "0", "1"],
+        ] {
+            let mut game = Game::initialise();
+            game.load_fen(&fen).unwrap();
+            game.set_network(Some(Arc::clone(&network)));
+
+            for _ in 0..60 {
+                let legal_games: Vec<Game> = MoveList::generate_moves(&game, MoveFlag::All)
+                    .vec()
+                    .iter()
+                    .filter_map(|mv| {
+                        let mut game = game.clone();
+                        game.make_move(mv).ok().map(|_| game)
+                    })
+                    .collect();
+
+                if legal_games.is_empty() {
+                    break;
+                }
+
+                game = legal_games[random.generate_u32() as usize % legal_games.len()].clone();
+
+                assert!(game.verify().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        let network = Arc::new(random_network());
+        let mut game = Game::initialise();
+        game.load_fen(&["startpos"]).unwrap();
+        let state = NnueState::new(Arc::clone(&network), &game);
+        let flipped_state = NnueState::new(network, &game.colour_flipped());
+
+        assert_eq!(
+            state.evaluate(Side::White),
+            flipped_state.evaluate(Side::Black)
+        );
+    }
+}
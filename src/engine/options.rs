@@ -0,0 +1,405 @@
+use super::search::{ELO_MAX, ELO_MIN, SKILL_LEVEL_MAX, TUNABLES};
+use crate::uci::{InputError, Output};
+use std::fmt::Display;
+
+pub const ANALYSE_WHILE_IDLE: &str = "AnalyseWhileIdle";
+pub const CHESS960: &str = "UCI_Chess960";
+pub const CLEAR_HASH: &str = "Clear Hash";
+pub const CONTEMPT: &str = "Contempt";
+pub const ELO: &str = "UCI_Elo";
+#[cfg(feature = "nnue")]
+pub const EVAL_FILE: &str = "EvalFile";
+pub const HASH: &str = "Hash";
+pub const HASH_FILE: &str = "HashFile";
+pub const INSTANT_RECAPTURES: &str = "InstantRecaptures";
+pub const LIMIT_STRENGTH: &str = "UCI_LimitStrength";
+pub const LOAD_HASH: &str = "LoadHash";
+pub const MULTI_PV: &str = "MultiPV";
+pub const PONDER: &str = "Ponder";
+pub const SAVE_HASH: &str = "SaveHash";
+pub const SEED: &str = "Seed";
+pub const SHOW_REFUTATIONS: &str = "UCI_ShowRefutations";
+pub const SHOW_WDL: &str = "UCI_ShowWDL";
+pub const SKILL_LEVEL: &str = "Skill Level";
+// Rejects illegal positions sent by the GUI instead of searching them
+pub const STRICT_FEN: &str = "StrictFen";
+#[cfg(feature = "syzygy")]
+pub const SYZYGY_PATH: &str = "SyzygyPath";
+pub const THREADS: &str = "Threads";
+
+const HASH_DEFAULT_MB: i64 = 16;
+// Centipawns a draw is worth less than an equal position to the engine
+const CONTEMPT_MAX: i64 = 100;
+const ELO_DEFAULT: i64 = 1500;
+const HASH_MAX_MB: i64 = 4096;
+const MULTI_PV_MAX: i64 = 256;
+// Seeds the randomness of weakened play, so games against the same settings can be replayed
+const SEED_MAX: i64 = u32::MAX as i64;
+const THREADS_MAX: i64 = 256;
+// UCI has no way of sending an empty string, so GUIs send this instead
+const EMPTY_STRING_VALUE: &str = "<empty>";
+
+#[derive(Clone)]
+enum OptionType {
+    Check,
+    Spin { min: i64, max: i64 },
+    String,
+    Button,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum OptionValue {
+    Check(bool),
+    Spin(i64),
+    String(String),
+    Button,
+}
+
+#[derive(Clone)]
+struct EngineOption {
+    name: &'static str,
+    option_type: OptionType,
+    default: OptionValue,
+    value: OptionValue,
+    // Left out of the options sent to the GUI, but still set like any other option
+    hidden: bool,
+}
+
+impl EngineOption {
+    fn new(name: &'static str, option_type: OptionType, default: OptionValue) -> Self {
+        Self {
+            name,
+            option_type,
+            default: default.clone(),
+            value: default,
+            hidden: false,
+        }
+    }
+
+    fn hidden(name: &'static str, option_type: OptionType, default: OptionValue) -> Self {
+        Self {
+            hidden: true,
+            ..Self::new(name, option_type, default)
+        }
+    }
+
+    fn parse_value(&self, value: &str) -> Option<OptionValue> {
+        match self.option_type {
+            OptionType::Check => match value {
+                "true" => Some(OptionValue::Check(true)),
+                "false" => Some(OptionValue::Check(false)),
+                _ => None,
+            },
+            OptionType::Spin { min, max } => match value.parse::<i64>() {
+                Ok(value) => Some(OptionValue::Spin(value.clamp(min, max))),
+                _ => None,
+            },
+            OptionType::String => match value {
+                EMPTY_STRING_VALUE => Some(OptionValue::String(String::new())),
+                _ => Some(OptionValue::String(value.to_string())),
+            },
+            OptionType::Button => Some(OptionValue::Button),
+        }
+    }
+}
+
+impl Display for EngineOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+
+        match (&self.option_type, &self.default) {
+            (OptionType::Check, OptionValue::Check(default)) => {
+                write!(f, "check default {}", default)
+            }
+            (OptionType::Spin { min, max }, OptionValue::Spin(default)) => {
+                write!(f, "spin default {} min {} max {}", default, min, max)
+            }
+            (OptionType::String, OptionValue::String(default)) if default.is_empty() => {
+                write!(f, "string default {}", EMPTY_STRING_VALUE)
+            }
+            (OptionType::String, OptionValue::String(default)) => {
+                write!(f, "string default {}", default)
+            }
+            _ => write!(f, "button"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Options(Vec<EngineOption>);
+
+impl Options {
+    // The tune parameters are hidden, as they are only meant for tuning tools
+    pub fn initialise() -> Self {
+        let tune_options = TUNABLES.iter().map(|tunable| {
+            EngineOption::hidden(
+                tunable.name,
+                OptionType::Spin {
+                    min: tunable.min as i64,
+                    max: tunable.max as i64,
+                },
+                OptionValue::Spin(tunable.default as i64),
+            )
+        });
+        let mut options = vec![
+            EngineOption::new(
+                HASH,
+                OptionType::Spin {
+                    min: 1,
+                    max: HASH_MAX_MB,
+                },
+                OptionValue::Spin(HASH_DEFAULT_MB),
+            ),
+            EngineOption::new(CLEAR_HASH, OptionType::Button, OptionValue::Button),
+            EngineOption::new(
+                THREADS,
+                OptionType::Spin {
+                    min: 1,
+                    max: THREADS_MAX,
+                },
+                OptionValue::Spin(1),
+            ),
+            EngineOption::new(PONDER, OptionType::Check, OptionValue::Check(false)),
+            EngineOption::new(
+                SHOW_REFUTATIONS,
+                OptionType::Check,
+                OptionValue::Check(false),
+            ),
+            EngineOption::new(CHESS960, OptionType::Check, OptionValue::Check(false)),
+            EngineOption::new(SHOW_WDL, OptionType::Check, OptionValue::Check(false)),
+            EngineOption::new(STRICT_FEN, OptionType::Check, OptionValue::Check(false)),
+            EngineOption::new(
+                ANALYSE_WHILE_IDLE,
+                OptionType::Check,
+                OptionValue::Check(false),
+            ),
+            EngineOption::new(
+                INSTANT_RECAPTURES,
+                OptionType::Check,
+                OptionValue::Check(false),
+            ),
+            EngineOption::new(
+                MULTI_PV,
+                OptionType::Spin {
+                    min: 1,
+                    max: MULTI_PV_MAX,
+                },
+                OptionValue::Spin(1),
+            ),
+            EngineOption::new(
+                CONTEMPT,
+                OptionType::Spin {
+                    min: -CONTEMPT_MAX,
+                    max: CONTEMPT_MAX,
+                },
+                OptionValue::Spin(0),
+            ),
+            EngineOption::new(
+                SKILL_LEVEL,
+                OptionType::Spin {
+                    min: 0,
+                    max: SKILL_LEVEL_MAX,
+                },
+                OptionValue::Spin(SKILL_LEVEL_MAX),
+            ),
+            EngineOption::new(LIMIT_STRENGTH, OptionType::Check, OptionValue::Check(false)),
+            EngineOption::new(
+                ELO,
+                OptionType::Spin {
+                    min: ELO_MIN,
+                    max: ELO_MAX,
+                },
+                OptionValue::Spin(ELO_DEFAULT),
+            ),
+            EngineOption::new(
+                SEED,
+                OptionType::Spin {
+                    min: 1,
+                    max: SEED_MAX,
+                },
+                OptionValue::Spin(1),
+            ),
+            EngineOption::new(
+                HASH_FILE,
+                OptionType::String,
+                OptionValue::String(String::new()),
+            ),
+            EngineOption::new(SAVE_HASH, OptionType::Button, OptionValue::Button),
+            EngineOption::new(LOAD_HASH, OptionType::Button, OptionValue::Button),
+            #[cfg(feature = "nnue")]
+            EngineOption::new(
+                EVAL_FILE,
+                OptionType::String,
+                OptionValue::String(String::new()),
+            ),
+            #[cfg(feature = "syzygy")]
+            EngineOption::new(
+                SYZYGY_PATH,
+                OptionType::String,
+                OptionValue::String(String::new()),
+            ),
+        ];
+        options.extend(tune_options);
+
+        Self(options)
+    }
+
+    pub fn print(&self, output: &Output) {
+        for option in self.0.iter().filter(|option| !option.hidden) {
+            output.line(option);
+        }
+    }
+
+    // Option names are case insensitive, so the registered name is returned for the caller to
+    // apply any side effects of the change - spin values outside the advertised range are clamped
+    // rather than rejected, along with a note of the value used instead
+    pub fn set(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) -> Result<(&'static str, Option<String>), InputError> {
+        let option = self
+            .0
+            .iter_mut()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+            .ok_or(InputError::UnknownOption(name.to_string()))?;
+        option.value = option
+            .parse_value(value)
+            .ok_or(InputError::InvalidOptionValue(name.to_string()))?;
+
+        let clamped = match (&option.option_type, &option.value) {
+            (OptionType::Spin { min, max }, OptionValue::Spin(clamped_value))
+                if value.parse() != Ok(*clamped_value) =>
+            {
+                Some(format!(
+                    "{} {} is outside the range {} to {}, using {}",
+                    option.name, value, min, max, clamped_value
+                ))
+            }
+            _ => None,
+        };
+
+        Ok((option.name, clamped))
+    }
+
+    pub fn check(&self, name: &str) -> bool {
+        matches!(self.value(name), Some(OptionValue::Check(true)))
+    }
+
+    pub fn spin(&self, name: &str) -> i64 {
+        match self.value(name) {
+            Some(OptionValue::Spin(value)) => *value,
+            _ => 0,
+        }
+    }
+
+    pub fn string(&self, name: &str) -> &str {
+        match self.value(name) {
+            Some(OptionValue::String(value)) => value,
+            _ => "",
+        }
+    }
+
+    fn value(&self, name: &str) -> Option<&OptionValue> {
+        self.0
+            .iter()
+            .find(|option| option.name == name)
+            .map(|option| &option.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_typed_options() {
+        let mut options = Options::initialise();
+
+        assert!(!options.check(SHOW_REFUTATIONS));
+        assert_eq!(options.spin(MULTI_PV), 1);
+
+        options.set("uci_showrefutations", "true").unwrap();
+        options.set(MULTI_PV, "4").unwrap();
+        options.set(HASH_FILE, "analysis.hash").unwrap();
+
+        assert!(options.check(SHOW_REFUTATIONS));
+        assert_eq!(options.spin(MULTI_PV), 4);
+        assert_eq!(options.string(HASH_FILE), "analysis.hash");
+
+        options.set(HASH_FILE, EMPTY_STRING_VALUE).unwrap();
+
+        assert_eq!(options.string(HASH_FILE), "");
+    }
+
+    #[test]
+    fn reject_invalid_options() {
+        let mut options = Options::initialise();
+
+        assert!(matches!(
+            options.set(SHOW_REFUTATIONS, "maybe"),
+            Err(InputError::InvalidOptionValue(_))
+        ));
+        assert!(matches!(
+            options.set(MULTI_PV, "many"),
+            Err(InputError::InvalidOptionValue(_))
+        ));
+        assert!(matches!(
+            options.set("Nonexistent Option", "true"),
+            Err(InputError::UnknownOption(_))
+        ));
+        assert_eq!(options.spin(MULTI_PV), 1);
+    }
+
+    #[test]
+    fn clamp_spin_options() {
+        let mut options = Options::initialise();
+
+        assert_eq!(options.set(THREADS, "4").unwrap(), (THREADS, None));
+        assert_eq!(
+            options.set("threads", "0").unwrap(),
+            (
+                THREADS,
+                Some("Threads 0 is outside the range 1 to 256, using 1".to_string())
+            )
+        );
+        assert_eq!(options.spin(THREADS), 1);
+
+        options.set(MULTI_PV, "1000").unwrap();
+
+        assert_eq!(options.spin(MULTI_PV), MULTI_PV_MAX);
+    }
+
+    #[test]
+    fn advertise_options() {
+        let options = Options::initialise();
+        let option_lines: Vec<String> = options.0.iter().map(|option| option.to_string()).collect();
+
+        assert!(option_lines
+            .contains(&"option name MultiPV type spin default 1 min 1 max 256".to_string()));
+        assert!(
+            option_lines.contains(&"option name HashFile type string default <empty>".to_string())
+        );
+        assert!(option_lines.contains(&"option name SaveHash type button".to_string()));
+        assert!(option_lines.contains(&"option name Ponder type check default false".to_string()));
+        assert!(
+            option_lines.contains(&"option name UCI_Chess960 type check default false".to_string())
+        );
+    }
+
+    #[test]
+    fn hidden_tune_options() {
+        let mut options = Options::initialise();
+
+        assert!(options
+            .0
+            .iter()
+            .any(|option| option.name == "LmrDepthReduction" && option.hidden));
+        assert_eq!(options.spin("LmrDepthReduction"), 2);
+        assert_eq!(
+            options.set("lmrdepthreduction", "3").unwrap(),
+            ("LmrDepthReduction", None)
+        );
+        assert_eq!(options.spin("LmrDepthReduction"), 3);
+    }
+}
@@ -0,0 +1,181 @@
+use super::{
+    game::Game,
+    moves::{MoveFlag, MoveList},
+    Engine,
+};
+use std::time::Instant;
+
+impl Engine {
+    // Number of leaf nodes of the legal move tree from the current position, for checking move
+    // generation against known counts
+    pub fn perft(&self, depth: u8) -> u64 {
+        perft(&mut self.game.clone(), depth)
+    }
+
+    // Dividing also prints the count after each root move, to narrow down which move a move
+    // generation bug is behind when comparing against another engine
+    pub fn print_perft(&self, depth: u8, divide: bool) -> u64 {
+        let start_time = Instant::now();
+        let nodes = match divide {
+            true => self.perft_divide(depth),
+            false => self.perft(depth),
+        };
+        let elapsed_ms = start_time.elapsed().as_millis();
+
+        self.output.line(format!("Nodes searched: {}", nodes));
+        self.output.line(format!("Time (ms): {}", elapsed_ms));
+
+        nodes
+    }
+
+    fn perft_divide(&self, depth: u8) -> u64 {
+        let mut game = self.game.clone();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let mut total_nodes = 0;
+
+        for mv in move_list.vec() {
+            if game.make_move(mv).is_err() {
+                continue;
+            }
+
+            let nodes = perft(&mut game, depth.saturating_sub(1));
+            game.undo_move();
+            self.output.line(format!("{}: {}", mv.as_string(), nodes));
+            total_nodes += nodes;
+        }
+
+        self.output.line("");
+
+        total_nodes
+    }
+}
+
+fn perft(game: &mut Game, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let move_list = MoveList::generate_moves(game, MoveFlag::All);
+
+    // Every generated move is legal, so the moves of the last ply are counted without being made
+    if depth == 1 {
+        return move_list.vec().len() as u64;
+    }
+
+    let mut nodes = 0;
+
+    for mv in move_list.vec() {
+        if game.make_move(mv).is_err() {
+            continue;
+        }
+
+        nodes += perft(game, depth - 1);
+        game.undo_move();
+    }
+
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perft_position(fen: &str, depth: u8) -> u64 {
+        let mut engine = Engine::initialise();
+        let fen: Vec<&str> = fen.split_whitespace().collect();
+        engine.load_fen(&fen).unwrap();
+
+        engine.perft(depth)
+    }
+
+    #[test]
+    fn perft_verification_positions() {
+        let positions = [
+            ("startpos", 4, 197_281),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                3,
+                97_862,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43_238),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                3,
+                9_467,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                3,
+                62_379,
+            ),
+            (
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                3,
+                89_890,
+            ),
+        ];
+
+        for (fen, depth, nodes) in positions {
+            assert_eq!(perft_position(fen, depth), nodes, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn perft_chess960_positions() {
+        let positions = [
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                4,
+                326_672,
+            ),
+            (
+                "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+                3,
+                18_002,
+            ),
+            (
+                "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+                4,
+                273_318,
+            ),
+            (
+                "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+                4,
+                382_958,
+            ),
+            (
+                "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+                3,
+                31_058,
+            ),
+        ];
+
+        for (fen, depth, nodes) in positions {
+            assert_eq!(perft_position(fen, depth), nodes, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn perft_divide() {
+        let mut engine = Engine::initialise();
+        engine.load_fen(&["startpos"]).unwrap();
+
+        assert_eq!(engine.perft(0), 1);
+        assert_eq!(engine.perft_divide(2), 400);
+        assert_eq!(engine.print_perft(3, true), 8_902);
+    }
+
+    #[test]
+    #[ignore]
+    fn perft_start_position() {
+        assert_eq!(perft_position("startpos", 6), 119_060_324);
+    }
+
+    #[test]
+    #[ignore]
+    fn perft_tricky_position() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+        assert_eq!(perft_position(fen, 5), 193_690_690);
+    }
+}
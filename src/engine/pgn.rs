@@ -0,0 +1,420 @@
+use super::{
+    game::Game,
+    moves::{Move, MoveFlag, MoveList},
+};
+use crate::uci::{InputError, PgnError};
+use std::{iter::Peekable, str::Chars};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PgnResult {
+    WhiteWin,
+    BlackWin,
+    Draw,
+    Unknown,
+}
+
+impl PgnResult {
+    fn parse(result: &str) -> Option<Self> {
+        match result {
+            "1-0" => Some(Self::WhiteWin),
+            "0-1" => Some(Self::BlackWin),
+            "1/2-1/2" => Some(Self::Draw),
+            "*" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+}
+
+// The mainline of a game, with the moves checked to be legal from its starting position
+pub struct PgnGame {
+    tags: Vec<(String, String)>,
+    fen: String,
+    moves: Vec<Move>,
+    result: PgnResult,
+}
+
+impl PgnGame {
+    #[allow(dead_code)]
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // The standard starting position unless the game has a FEN tag
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn result(&self) -> PgnResult {
+        self.result
+    }
+}
+
+// Every game of a PGN file in order - a game which cannot be read is returned as an error in its
+// place, and reading carries on from the next game
+pub fn parse_games(input: &str) -> Vec<Result<PgnGame, InputError>> {
+    let mut games = Vec::new();
+    let mut game_reader: Option<GameReader> = None;
+
+    for token in tokenise(input) {
+        // Tags after the movetext belong to the next game, which happens when a game is missing
+        // its result
+        if let (Token::Tag(..), Some(reader)) = (&token, &game_reader) {
+            if reader.in_movetext {
+                games.push(game_reader.take().unwrap().finish());
+            }
+        }
+
+        let reader = game_reader.get_or_insert_with(GameReader::new);
+
+        match token {
+            Token::Tag(name, value) => reader.add_tag(name, value),
+            Token::Move(san) => reader.play_move(&san),
+            Token::Result(result) => {
+                reader.result = Some(result);
+                games.push(game_reader.take().unwrap().finish());
+            }
+            Token::Error(error) => reader.set_error(InputError::InvalidPgn(error)),
+        }
+    }
+
+    if let Some(reader) = game_reader {
+        games.push(reader.finish());
+    }
+
+    games
+}
+
+struct GameReader {
+    tags: Vec<(String, String)>,
+    fen: String,
+    game: Game,
+    moves: Vec<Move>,
+    result: Option<PgnResult>,
+    in_movetext: bool,
+    // Only the first error is kept, as every later move is played from the wrong position
+    error: Option<InputError>,
+}
+
+impl GameReader {
+    fn new() -> Self {
+        let mut game = Game::initialise();
+        game.load_fen(&["startpos"]).unwrap();
+
+        Self {
+            tags: Vec::new(),
+            fen: game.to_fen(),
+            game,
+            moves: Vec::new(),
+            result: None,
+            in_movetext: false,
+            error: None,
+        }
+    }
+
+    fn add_tag(&mut self, name: String, value: String) {
+        if name == "FEN" {
+            let fen: Vec<&str> = value.split_whitespace().collect();
+
+            match self.game.load_fen(&fen) {
+                Ok(()) => self.fen = self.game.to_fen(),
+                Err(error) => self.set_error(error),
+            }
+        }
+
+        self.tags.push((name, value));
+    }
+
+    fn play_move(&mut self, san: &str) {
+        self.in_movetext = true;
+
+        if self.error.is_some() {
+            return;
+        }
+
+        let move_list = MoveList::generate_moves(&self.game, MoveFlag::All);
+
+        match move_list.find_move_from_san(san) {
+            Ok(mv) if self.game.make_move(&mv).is_ok() => self.moves.push(mv),
+            _ => self.set_error(InputError::InvalidPgn(PgnError::Move(san.to_string()))),
+        }
+    }
+
+    fn set_error(&mut self, error: InputError) {
+        self.error.get_or_insert(error);
+    }
+
+    // The result tag stands in for a missing result at the end of the movetext
+    fn finish(self) -> Result<PgnGame, InputError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let result = self.result.unwrap_or_else(|| {
+            self.tags
+                .iter()
+                .find(|(name, _)| name == "Result")
+                .and_then(|(_, value)| PgnResult::parse(value))
+                .unwrap_or(PgnResult::Unknown)
+        });
+
+        Ok(PgnGame {
+            tags: self.tags,
+            fen: self.fen,
+            moves: self.moves,
+            result,
+        })
+    }
+}
+
+enum Token {
+    Tag(String, String),
+    Move(String),
+    Result(PgnResult),
+    Error(PgnError),
+}
+
+// Comments, variations, NAGs and move numbers are dropped here, so only the mainline is left
+fn tokenise(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut previous_character = '\n';
+
+    while let Some(character) = chars.next() {
+        match character {
+            // Skipping a line consumes its newline, which still starts the next line
+            ';' => {
+                skip_line(&mut chars);
+                previous_character = '\n';
+
+                continue;
+            }
+            // Escaped lines, which are only recognised at the start of a line
+            '%' if previous_character == '\n' => {
+                skip_line(&mut chars);
+
+                continue;
+            }
+            '[' => tokens.push(read_tag(&mut chars)),
+            '{' => {
+                if !skip_comment(&mut chars) {
+                    tokens.push(Token::Error(PgnError::UnterminatedComment));
+                }
+            }
+            '(' => {
+                if !skip_variation(&mut chars) {
+                    tokens.push(Token::Error(PgnError::UnterminatedComment));
+                }
+            }
+            '$' => while chars.next_if(char::is_ascii_digit).is_some() {},
+            _ if character.is_whitespace() => {}
+            _ => {
+                let mut symbol = character.to_string();
+
+                while let Some(character) = chars.next_if(|character| !is_symbol_end(*character)) {
+                    symbol.push(character);
+                }
+
+                if let Some(token) = symbol_token(&symbol) {
+                    tokens.push(token);
+                }
+            }
+        }
+
+        previous_character = character;
+    }
+
+    tokens
+}
+
+fn is_symbol_end(character: char) -> bool {
+    character.is_whitespace() || "[]{}();$".contains(character)
+}
+
+// Move numbers such as `12.` and `12...` are dropped, including when written against the move
+fn symbol_token(symbol: &str) -> Option<Token> {
+    if let Some(result) = PgnResult::parse(symbol) {
+        return Some(Token::Result(result));
+    }
+
+    let symbol = match symbol.find(|character: char| !character.is_ascii_digit()) {
+        Some(index) if index > 0 && symbol[index..].starts_with('.') => &symbol[index..],
+        _ => symbol,
+    };
+    let symbol = symbol.trim_start_matches('.');
+
+    match symbol.is_empty() {
+        true => None,
+        false => Some(Token::Move(symbol.to_string())),
+    }
+}
+
+fn skip_line(chars: &mut Peekable<Chars>) {
+    for character in chars.by_ref() {
+        if character == '\n' {
+            break;
+        }
+    }
+}
+
+// Whether the comment was closed before the end of the input
+fn skip_comment(chars: &mut Peekable<Chars>) -> bool {
+    chars.by_ref().any(|character| character == '}')
+}
+
+// Variations may be nested and contain comments, which may themselves contain parentheses
+fn skip_variation(chars: &mut Peekable<Chars>) -> bool {
+    let mut depth = 1;
+
+    while let Some(character) = chars.next() {
+        match character {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            // The comment is skipped by checking it was closed
+            '{' if !skip_comment(chars) => return false,
+            ';' => skip_line(chars),
+            _ => {}
+        }
+
+        if depth == 0 {
+            return true;
+        }
+    }
+
+    false
+}
+
+// Tag values are quoted, with quotes and backslashes inside them escaped by a backslash
+fn read_tag(chars: &mut Peekable<Chars>) -> Token {
+    let mut name = String::new();
+    let mut value = String::new();
+
+    while let Some(character) = chars.next_if(|character| *character != '"' && *character != ']') {
+        name.push(character);
+    }
+
+    if chars.next() != Some('"') {
+        return Token::Error(PgnError::Tag);
+    }
+
+    // A tag never spans lines, so a missing closing quote does not swallow the games after it
+    loop {
+        match chars.next() {
+            Some('"') => break,
+            Some('\n') | None => return Token::Error(PgnError::Tag),
+            Some('\\') => match chars.next() {
+                Some(character) => value.push(character),
+                None => return Token::Error(PgnError::Tag),
+            },
+            Some(character) => value.push(character),
+        }
+    }
+
+    while chars
+        .next_if(|character| character.is_whitespace())
+        .is_some()
+    {}
+
+    match (chars.next(), name.trim()) {
+        (Some(']'), name) if !name.is_empty() => Token::Tag(name.to_string(), value),
+        _ => Token::Error(PgnError::Tag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn move_strings(game: &PgnGame) -> Vec<String> {
+        game.moves().iter().map(|mv| mv.as_string()).collect()
+    }
+
+    #[test]
+    fn parse_mainline() {
+        let input = "[Event \"Casual \\\"blitz\\\"\"]\n\
+            [White \"A\"]\n\
+            [Result \"1-0\"]\n\
+            \n\
+            1. e4 e5 2.Nf3 {best by test (allegedly} Nc6 (2... d6 3. d4 (3. Bc4) exd4) 3. Bb5 $1\n\
+            3... a6?! ; the Morphy defence\n\
+            % an escaped line 4. Qh5\n\
+            4. Ba4 Nf6 5. O-O 1-0\n\
+            \n\
+            [Event \"Second\"]\n\
+            [SetUp \"1\"]\n\
+            [FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n\
+            \n\
+            1. Ra8# 1-0\n";
+        let games = parse_games(input);
+
+        assert_eq!(games.len(), 2);
+
+        let game = games[0].as_ref().unwrap();
+
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(game.tag("White"), Some("A"));
+        assert_eq!(game.tag("Black"), None);
+        assert_eq!(
+            game.fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            move_strings(game),
+            ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "b5a4", "g8f6", "e1g1"]
+        );
+        assert_eq!(game.result(), PgnResult::WhiteWin);
+
+        let game = games[1].as_ref().unwrap();
+
+        assert_eq!(game.fen(), "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(move_strings(game), ["a1a8"]);
+    }
+
+    #[test]
+    fn recover_from_broken_games() {
+        let input = "[Event \"Illegal move\"]\n\
+            1. e4 e5 2. Ke3 Nc6 1-0\n\
+            [Event \"Missing result\"]\n\
+            [Result \"1/2-1/2\"]\n\
+            1. d4 d5\n\
+            [Event \"Bad FEN\"]\n\
+            [FEN \"8/8/8 w - - 0 1\"]\n\
+            1. e4 *\n\
+            [Event \"Broken tag]\n\
+            1. c4 *\n\
+            1. Nf3 d5 0-1\n\
+            1. e4 {never closed";
+        let games = parse_games(input);
+
+        assert_eq!(games.len(), 6);
+        assert!(matches!(
+            &games[0],
+            Err(InputError::InvalidPgn(PgnError::Move(san))) if san == "Ke3"
+        ));
+
+        let game = games[1].as_ref().unwrap();
+
+        assert_eq!(move_strings(game), ["d2d4", "d7d5"]);
+        assert_eq!(game.result(), PgnResult::Draw);
+        assert!(matches!(&games[2], Err(InputError::InvalidFen(_))));
+        assert!(matches!(
+            &games[3],
+            Err(InputError::InvalidPgn(PgnError::Tag))
+        ));
+
+        let game = games[4].as_ref().unwrap();
+
+        assert_eq!(move_strings(game), ["g1f3", "d7d5"]);
+        assert_eq!(game.result(), PgnResult::BlackWin);
+        assert!(matches!(
+            &games[5],
+            Err(InputError::InvalidPgn(PgnError::UnterminatedComment))
+        ));
+    }
+}
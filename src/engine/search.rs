@@ -1,138 +1,1040 @@
+mod bench;
+mod best_move_reply;
+mod cutoff_distribution;
+mod endgames;
 mod evaluation;
+mod evaluation_cache;
+mod expected_recapture;
 mod move_scoring;
+mod multi_pv;
+mod pawn_masks;
+mod piece_square_tables;
+mod re_searches;
+mod search_limits;
+mod search_result;
+mod skill_level;
+mod static_exchange;
+mod strength_limit;
+#[cfg(feature = "syzygy")]
+mod tablebases;
+mod tapered_value;
+mod test_suite;
+mod time_manager;
+mod transposition_table;
+mod tune;
+mod win_draw_loss;
+
+pub use self::{
+    bench::BENCH_DEPTH_DEFAULT,
+    best_move_reply::BestMoveReply,
+    evaluation::Value,
+    search_result::{Score, SearchProgress, SearchResult},
+    skill_level::SKILL_LEVEL_MAX,
+    strength_limit::{ELO_MAX, ELO_MIN},
+    test_suite::TEST_SUITE_MOVE_TIME_DEFAULT,
+    time_manager::TimeControl,
+    tune::{TuneParameters, TUNABLES},
+};
 
-pub use self::evaluation::Value;
-
+#[cfg(feature = "syzygy")]
+use self::tablebases::Tablebases;
 use self::{
+    cutoff_distribution::CutoffDistribution,
     evaluation::Evaluation,
-    move_scoring::{HistoricMoveScore, KillerMoves},
+    evaluation_cache::EvaluationCache,
+    expected_recapture::ExpectedRecapture,
+    move_scoring::{CaptureHistory, HistoricMoveScore, KillerMoves, ScoredMoveList},
+    multi_pv::{MultiPrincipalVariation, RootLine},
+    re_searches::ReSearches,
+    search_limits::SearchLimits,
+    strength_limit::StrengthLimit,
+    time_manager::TimeManager,
+    transposition_table::{Bound, TranspositionTable},
+    win_draw_loss::WinDrawLoss,
 };
 use super::{
-    attack_tables::AttackTables,
     game::{Game, Piece},
-    moves::{Move, MoveList, MoveType},
+    moves::{Move, MoveFlag, MoveList, MoveType},
+    options,
+    zobrist_hashes::ZobristKey,
     Engine,
 };
-use crate::{engine, uci::InputError};
+use crate::{
+    crash_report, engine,
+    random::Random,
+    uci::{InputError, Output},
+};
 use std::{
     ops::Neg,
-    sync::mpsc::Receiver,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+    thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
 
-const ASPIRATION_WINDOW_ADJUSTMENT: Value = 50;
+// The starting window and its maximum are tune parameters - past the maximum the score is most
+// likely decisive or a mate, so the window is dropped entirely rather than widened again and again
+const ASPIRATION_WINDOW_GROWTH: Value = 2;
 
 const NULL_MOVE_DEPTH_MIN: u8 = 3;
-const NULL_MOVE_DEPTH_REDUCTION: u8 = 3;
+// Null move cutoffs this deep are confirmed by a reduced search without the null move, as a
+// wrong cutoff in a zugzwang position would otherwise prune a whole subtree
+const NULL_MOVE_VERIFICATION_DEPTH_MIN: u8 = 6;
+
+// Check extensions stop once a line is this many times the iteration depth, so long sequences
+// of checks cannot keep extending the search
+const CHECK_EXTENSION_PLY_MULTIPLIER: Value = 2;
+
+// LMP = late move pruning
+const LMP_DEPTH_MAX: u8 = 3;
 
-// LMR = late move reduction
-const LMR_MOVES_SEARCHED_MIN: i32 = 4;
-const LMR_DEPTH_MIN: u8 = 3;
-const LMR_DEPTH_REDUCTION: u8 = 2;
+// A best move this far ahead of every other move after a search this deep is played in a fraction
+// of the usual time, as with a recapture - the other moves are only searched shallower, to prove
+// they fall short of the margin
+const EASY_MOVE_DEPTH_MIN: u8 = 6;
+const EASY_MOVE_DEPTH_REDUCTION: u8 = 2;
 
-const SEARCH_TIME_OFFSET_MS: u64 = 50;
-const SEARCH_STOP_CHECK_NODES: u64 = 2047;
+// Nodes searched between checks of the stop flag and search limits - small enough that at fast
+// time controls the search reacts to the clock within a fraction of a millisecond
+const SEARCH_CHECK_INTERVAL_NODES: u64 = 1024;
+const STOP_SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Short searches finish before a GUI could show each root move, so the move being searched is
+// only reported once the search has run this long
+const CURRMOVE_REPORT_TIME: Duration = Duration::from_secs(3);
 
 impl Engine {
-    pub fn search_best_move(&mut self, depth: u8) -> Result<Move, InputError> {
+    pub fn search_best_move(&mut self, mut depth: u8) -> Result<Move, InputError> {
         let mut evaluation_limits = EvaluationLimits::initialise();
+        let mate_moves = self.search_parameters.mate_moves;
+        let mut mate_found = false;
+
+        // Only scores above the lower bound are a mate within the given number of moves, and the
+        // mated side's final position has to be searched at full depth for the mate to be seen
+        if let Some(mate_moves) = mate_moves {
+            evaluation_limits.min = evaluation::CHECKMATE_EVALUATION - 2 * mate_moves as Value;
+            depth = depth.min(mate_moves.saturating_mul(2));
+        }
+
+        if let Some(strength_limit) = &self.search_parameters.strength_limit {
+            depth = depth.min(strength_limit.depth());
+        }
+
+        self.search_parameters.search_start_time = Instant::now();
+        self.search_parameters.ponder_move = None;
+
+        // Searches made while idle prepare for the next search, so share its generation
+        if !self.search_parameters.silent {
+            self.search_parameters.transposition_table.new_search();
+        }
+
         let mut current_depth = 1;
         let ply = 0;
-        let game_clone = self.game.clone();
+        let mut game_clone = self.game.clone();
+        let mut legal_moves = self.legal_moves(&game_clone);
+
+        if !self.search_parameters.search_moves.is_empty() {
+            legal_moves.retain(|mv| self.search_parameters.search_moves.contains(mv));
+        }
+
+        // Checkmated or stalemated positions are only reported, as there is nothing to search
+        if legal_moves.is_empty() {
+            let evaluation =
+                self.negamax_search(&mut game_clone, EvaluationLimits::initialise(), ply, 1);
+            self.output
+                .line(format!("info depth 0 score {}", evaluation.uci_score()));
+            self.search_parameters.clear();
+
+            return Err(InputError::InvalidPosition);
+        }
+
+        if let (false, Some(mv)) = (self.search_parameters.silent, legal_moves.first()) {
+            self.search_parameters.best_move_reply.update(mv);
+        }
+
+        let multi_pv = self.options.spin(options::MULTI_PV) as usize;
+
+        if !self.search_parameters.silent && multi_pv > legal_moves.len() {
+            self.output.line(format!(
+                "info string MultiPV {} is more than the {} moves to search, showing {} lines",
+                multi_pv,
+                legal_moves.len(),
+                legal_moves.len()
+            ));
+        }
+
+        // Weakened play chooses between several lines, searched as if for MultiPV
+        let skill_level = self.options.spin(options::SKILL_LEVEL);
+        let skill_limited =
+            skill_level < SKILL_LEVEL_MAX && mate_moves.is_none() && !self.search_parameters.silent;
+        let searched_lines = match skill_limited {
+            true => multi_pv.max(skill_level::SKILL_LEVEL_LINES),
+            false => multi_pv,
+        };
+
+        // The tablebases already hold the best move of an ending, so it is played without a search,
+        // unless the search is after a mate, several lines or weakened play instead
+        #[cfg(feature = "syzygy")]
+        if !self.search_parameters.silent
+            && mate_moves.is_none()
+            && searched_lines == 1
+            && self.search_parameters.search_moves.is_empty()
+            && self.search_parameters.strength_limit.is_none()
+        {
+            if let Some(mv) = self.tablebase_root_move() {
+                return Ok(mv);
+            }
+        }
+
+        let single_legal_move = legal_moves.len() == 1;
+        let mut previous_best_move = None;
+        let mut previous_evaluation: Option<Evaluation> = None;
+        let mut best_move_changes = 0;
+        let mut completed_principal_variation = None;
+        let mut completed_evaluation = None;
+        let mut completed_depth = 0;
+        let mut previous_iteration_nodes = 0;
+        let mut easy_move_checked = false;
+        let mut iteration_start_nodes = self.search_parameters.nodes_searched;
+        let tune = self.search_parameters.tune;
+        let mut aspiration_window = tune.aspiration_window;
+        let helper_threads = self.spawn_helper_threads(depth);
 
         while current_depth <= depth {
             self.search_parameters.is_principal_variation = true;
 
             let evaluation =
-                self.negamax_search(&game_clone, evaluation_limits, ply, current_depth);
+                self.negamax_search(&mut game_clone, evaluation_limits, ply, current_depth);
+
+            if self.search_parameters.stop_search {
+                break;
+            }
+
+            if mate_moves.is_some() {
+                mate_found = evaluation > evaluation_limits.min;
+
+                if mate_found {
+                    self.output.line(format!(
+                        "info depth {} score {}{} {} pv {}",
+                        current_depth,
+                        evaluation.uci_score(),
+                        self.win_draw_loss(&game_clone, evaluation),
+                        self.search_parameters.search_statistics(),
+                        self.search_parameters.principal_variation.as_string()
+                    ));
+                    break;
+                }
+
+                self.output.line(format!(
+                    "info depth {} {}",
+                    current_depth,
+                    self.search_parameters.search_statistics()
+                ));
+                current_depth += 1;
+                continue;
+            }
+
             let missed_aspiration_window_low = evaluation <= evaluation_limits.min;
             let missed_aspiration_window_high = evaluation >= evaluation_limits.max;
 
-            if missed_aspiration_window_low {
-                evaluation_limits.min = -evaluation::MAX_EVALUATION;
-                continue;
-            } else if missed_aspiration_window_high {
-                evaluation_limits.max = evaluation::MAX_EVALUATION;
+            // The window is widened on the side the score fell outside of, by more after each
+            // miss, so a score far away is reached in a few re-searches that still cut off more
+            // than one with no window at all
+            if missed_aspiration_window_low || missed_aspiration_window_high {
+                let bound = match missed_aspiration_window_low {
+                    true => {
+                        evaluation_limits.min = Evaluation::new(
+                            (evaluation.value() - aspiration_window)
+                                .max(-evaluation::MAX_EVALUATION.value()),
+                        );
+                        self.search_parameters.re_searches.aspiration_fail_lows += 1;
+                        "upperbound"
+                    }
+                    false => {
+                        evaluation_limits.max = Evaluation::new(
+                            (evaluation.value() + aspiration_window)
+                                .min(evaluation::MAX_EVALUATION.value()),
+                        );
+                        self.search_parameters.re_searches.aspiration_fail_highs += 1;
+                        "lowerbound"
+                    }
+                };
+                aspiration_window = match aspiration_window < tune.aspiration_window_max {
+                    true => aspiration_window * ASPIRATION_WINDOW_GROWTH,
+                    false => evaluation::MAX_EVALUATION.value(),
+                };
+
+                // The principal variation of a search outside its window is incomplete
+                if !self.search_parameters.silent {
+                    self.output.line(format!(
+                        "info depth {} score {} {} {}",
+                        current_depth,
+                        evaluation.uci_score(),
+                        bound,
+                        self.search_parameters.search_statistics()
+                    ));
+                }
+
                 continue;
             }
 
-            evaluation_limits.min = evaluation - ASPIRATION_WINDOW_ADJUSTMENT;
-            evaluation_limits.max = evaluation + ASPIRATION_WINDOW_ADJUSTMENT;
+            aspiration_window = tune.aspiration_window;
+            evaluation_limits.min = evaluation - aspiration_window;
+            evaluation_limits.max = evaluation + aspiration_window;
 
-            println!(
-                "info score cp {} depth {} nodes {} pv {}",
-                evaluation.value(),
-                current_depth,
-                self.search_parameters.nodes_searched,
-                self.search_parameters.principal_variation.as_string()
-            );
+            let mut lines = vec![RootLine {
+                evaluation,
+                principal_variation: self.search_parameters.principal_variation.clone(),
+            }];
+            let line_count = searched_lines.min(legal_moves.len());
+
+            if line_count > 1 {
+                let secondary_lines =
+                    self.search_secondary_lines(&mut game_clone, current_depth, line_count);
+
+                if self.search_parameters.stop_search {
+                    break;
+                }
+
+                lines.extend(secondary_lines);
+                lines = self
+                    .search_parameters
+                    .multi_principal_variation
+                    .order_lines(lines);
+
+                if let Some(best_line) = lines.first() {
+                    self.search_parameters.principal_variation =
+                        best_line.principal_variation.clone();
+                }
+            }
+
+            completed_principal_variation =
+                Some(self.search_parameters.principal_variation.clone());
+            completed_evaluation = lines.first().map(|line| line.evaluation);
+            completed_depth = current_depth;
+
+            if let (false, Some(mv)) = (
+                self.search_parameters.silent,
+                self.search_parameters.principal_variation.principal_move(0),
+            ) {
+                self.search_parameters.best_move_reply.update(mv);
+            }
+
+            if !self.search_parameters.silent {
+                for (line_index, line) in lines.iter().enumerate() {
+                    let multi_pv = match line_count > 1 {
+                        true => format!("multipv {} ", line_index + 1),
+                        false => String::new(),
+                    };
+
+                    self.output.line(format!(
+                        "info depth {} {}score {}{} {} pv {}",
+                        current_depth,
+                        multi_pv,
+                        line.evaluation.uci_score(),
+                        self.win_draw_loss(&game_clone, line.evaluation),
+                        self.search_parameters.search_statistics(),
+                        line.principal_variation.as_string()
+                    ));
+                }
+
+                crash_report::record_search(format!(
+                    "depth {} score {} {} pv {}",
+                    current_depth,
+                    evaluation.uci_score(),
+                    self.search_parameters.search_statistics(),
+                    self.search_parameters.principal_variation.as_string()
+                ));
+
+                // Only the best line is sent, and a receiver which has been dropped is simply no
+                // longer listening
+                if let (Some(search_progress), Some(line)) =
+                    (&self.search_parameters.search_progress, lines.first())
+                {
+                    _ = search_progress.send(SearchProgress {
+                        depth: current_depth,
+                        score: line.evaluation.into(),
+                        pv: line.principal_variation.moves(),
+                        nodes: self.search_parameters.nodes(),
+                    });
+                }
+            }
+
+            let best_move = self.search_parameters.principal_variation.table[0][0];
+            let best_move_changed = best_move != previous_best_move;
+            let score_change = match previous_evaluation {
+                Some(previous_evaluation) => evaluation.value() - previous_evaluation.value(),
+                None => 0,
+            };
+
+            if best_move_changed && previous_best_move.is_some() {
+                best_move_changes += 1;
+            }
+
+            let iteration_nodes = self.search_parameters.nodes_searched - iteration_start_nodes;
+
+            if self.debug() && !self.search_parameters.silent {
+                self.output.line(format!(
+                    "info string depth {} score trend {:+} bestmove changes {}",
+                    current_depth, score_change, best_move_changes
+                ));
+                self.output.line(format!(
+                    "info string depth {} {} branching factor {}",
+                    current_depth,
+                    self.search_parameters.re_searches,
+                    ReSearches::branching_factor(previous_iteration_nodes, iteration_nodes)
+                ));
+
+                self.output.line(format!(
+                    "info string depth {} {}",
+                    current_depth,
+                    self.search_parameters.transposition_table_statistics()
+                ));
+                self.output.line(format!(
+                    "info string depth {} {}",
+                    current_depth, self.search_parameters.cutoff_distribution
+                ));
+                self.output.line(format!(
+                    "info string depth {} {}",
+                    current_depth,
+                    self.search_parameters.evaluation_cache.statistics()
+                ));
+
+                if ReSearches::is_explosion(previous_iteration_nodes, iteration_nodes) {
+                    self.output.line(format!(
+                        "info string depth {} search explosion {} nodes after {}",
+                        current_depth, iteration_nodes, previous_iteration_nodes
+                    ));
+                }
+            }
+
+            self.search_parameters.re_searches = ReSearches::default();
+            self.search_parameters.cutoff_distribution = CutoffDistribution::default();
+            self.search_parameters.transposition_table_probes = 0;
+            self.search_parameters.transposition_table_hits = 0;
+            self.search_parameters.evaluation_cache.reset_statistics();
+            previous_iteration_nodes = iteration_nodes;
+            iteration_start_nodes = self.search_parameters.nodes_searched;
+
+            self.search_parameters
+                .search_limits
+                .update_best_move_stability(best_move_changed);
+            self.search_parameters
+                .search_limits
+                .update_score_trend(score_change);
+            let debug = self.debug() && !self.search_parameters.silent;
+
+            // Checked once, as soon as the search is deep enough to trust that no other move
+            // comes close, so most of the time saved is still to come
+            let easy_move_candidate = !easy_move_checked
+                && current_depth >= EASY_MOVE_DEPTH_MIN
+                && line_count == 1
+                && legal_moves.len() > 1
+                && self.search_parameters.search_limits.is_time_limited();
+
+            if easy_move_candidate {
+                easy_move_checked = true;
+
+                if self.is_easy_move(&mut game_clone, current_depth, evaluation) {
+                    self.search_parameters.search_limits.set_easy_move();
+
+                    if debug {
+                        self.output.line(format!(
+                            "info string depth {} easy move, no other move within {}cp",
+                            current_depth, tune.easy_move_margin
+                        ));
+                    }
+                }
+            }
+
+            let search_limits = &mut self.search_parameters.search_limits;
+
+            if let (true, Some(time_manager)) = (debug, search_limits.time_manager()) {
+                self.output.line(format!(
+                    "info string depth {} {}",
+                    current_depth, time_manager
+                ));
+            }
+
+            if single_legal_move && search_limits.is_time_limited() {
+                if debug {
+                    self.output.line(format!(
+                        "info string depth {} single legal move, stopping",
+                        current_depth
+                    ));
+                }
+
+                break;
+            }
+
+            if !search_limits.iteration_allowed(self.search_parameters.nodes_searched) {
+                if debug {
+                    self.output.line(format!(
+                        "info string depth {} no time or nodes left for another iteration, \
+                        stopping",
+                        current_depth
+                    ));
+                }
 
-            if self.search_parameters.stop_search {
                 break;
             }
 
+            previous_best_move = best_move;
+            previous_evaluation = Some(evaluation);
             current_depth += 1;
         }
 
+        drop(helper_threads);
+
+        // A stopped iteration leaves a partial principal variation behind, which may be missing
+        // the ponder move or even hold a worse best move
+        if self.search_parameters.stop_search {
+            if let Some(principal_variation) = completed_principal_variation {
+                self.search_parameters.principal_variation = principal_variation;
+            }
+        }
+
+        // Chosen from the lines of the last completed iteration, which the principal variation
+        // is one of
+        if skill_limited {
+            let seed = self.options.spin(options::SEED) as u32;
+            let zobrist_key = self.game.zobrist_key();
+            let mut random = Random::new(seed ^ zobrist_key as u32 ^ (zobrist_key >> 32) as u32)
+                .or(Random::new(seed))
+                .unwrap();
+            let chosen_line = skill_level::choose_line(
+                self.search_parameters.multi_principal_variation.lines(),
+                skill_level,
+                &mut random,
+            )
+            .cloned();
+
+            if let Some(chosen_line) = chosen_line {
+                self.search_parameters.principal_variation = chosen_line.principal_variation;
+                completed_evaluation = Some(chosen_line.evaluation);
+            }
+        }
+
+        if mate_moves.is_some() && !mate_found {
+            self.search_parameters.clear();
+
+            return Err(InputError::MateNotFound);
+        }
+
+        // Searches of other positions made while idle leave the expected recapture of the last
+        // reported best move in place
+        if !self.search_parameters.silent {
+            self.search_parameters.expected_recapture =
+                match self.options.check(options::INSTANT_RECAPTURES) {
+                    true => ExpectedRecapture::from_principal_variation(
+                        &self.game,
+                        &self.search_parameters.principal_variation.moves(),
+                        completed_depth,
+                    ),
+                    false => None,
+                };
+        }
+
+        let principal_variation = &self.search_parameters.principal_variation;
+        self.search_parameters.ponder_move = match principal_variation.length[0] > 1 {
+            true => principal_variation.principal_move(1).cloned(),
+            false => None,
+        };
+
+        // The search can be stopped before the first iteration completes when very short on time
         let best_move = match &self.search_parameters.principal_variation.table[0][0] {
-            Some(mv) => Ok(mv.clone()),
-            None => Err(InputError::InvalidPosition),
+            Some(mv) => Ok(*mv),
+            None => match legal_moves.first() {
+                Some(mv) => Ok(*mv),
+                None => Err(InputError::InvalidPosition),
+            },
         };
 
+        self.search_parameters.last_search_nodes = self.search_parameters.nodes();
+        self.search_parameters.last_search_depth = completed_depth;
+        self.search_parameters.last_search_evaluation = completed_evaluation;
+        self.search_parameters.last_search_principal_variation =
+            self.search_parameters.principal_variation.moves();
         self.search_parameters.clear();
 
         best_move
     }
 
-    pub fn set_search_timing(
-        &mut self,
-        increment: Option<Duration>,
-        move_time: Option<Duration>,
-        time_left: Option<Duration>,
-        moves_to_go: u64,
-    ) {
-        let increment = match increment {
-            Some(increment) => increment,
-            None => Duration::from_millis(0),
+    // Played straight away, as the root probe is only safe while no helper thread is probing
+    #[cfg(feature = "syzygy")]
+    fn tablebase_root_move(&mut self) -> Option<Move> {
+        let tablebases = self.search_parameters.tablebases.as_ref()?;
+        let (mv, evaluation) =
+            tablebases.probe_root(&self.game, self.draw_evaluation(&self.game))?;
+        self.search_parameters
+            .tablebase_hits
+            .fetch_add(1, Ordering::Relaxed);
+
+        self.output.line(format!(
+            "info depth 1 score {}{} {} pv {}",
+            evaluation.uci_score(),
+            self.win_draw_loss(&self.game, evaluation),
+            self.search_parameters.search_statistics(),
+            mv.as_string()
+        ));
+        self.search_parameters.best_move_reply.update(&mv);
+        self.search_parameters.expected_recapture = None;
+        self.search_parameters.last_search_nodes = 0;
+        self.search_parameters.last_search_depth = 1;
+        self.search_parameters.last_search_evaluation = Some(evaluation);
+        self.search_parameters.last_search_principal_variation = vec![mv];
+        self.search_parameters.clear();
+
+        Some(mv)
+    }
+
+    // Searches the position expected after the best move without any output until the next
+    // command stops it, so the transposition table is primed when the opponent replies
+    pub fn analyse_while_idle(&mut self, best_move: &Move) {
+        let stop_search_received = self
+            .search_parameters
+            .stop_search_flag
+            .load(Ordering::Relaxed);
+
+        if !self.options.check(options::ANALYSE_WHILE_IDLE) || stop_search_received {
+            return;
+        }
+
+        let game = self.game.clone();
+
+        if self.game.make_move(best_move).is_ok() {
+            self.position_history.push(game.zobrist_key());
+            self.search_parameters.silent = true;
+            _ = self.search_best_move(engine::MAX_PLY as u8);
+            self.search_parameters.silent = false;
+            self.position_history.pop();
+        }
+
+        self.game = game;
+    }
+
+    // Searches the position expected after a forced recapture of the best move without any output
+    // until the next command stops it, so the reply is ready if the opponent recaptures
+    pub fn prepare_recapture_reply(&mut self) {
+        let stop_search_received = self
+            .search_parameters
+            .stop_search_flag
+            .load(Ordering::Relaxed);
+        let expected_game = match &self.search_parameters.expected_recapture {
+            Some(expected_recapture) if !stop_search_received => expected_recapture.game().clone(),
+            _ => return,
         };
-        let max_search_time = match move_time {
-            Some(move_time) => move_time,
-            None => match time_left {
-                Some(time_left) => {
-                    let mut max_search_time = time_left;
 
-                    max_search_time /= moves_to_go as u32;
-                    max_search_time += increment;
-                    max_search_time -= Duration::from_millis(SEARCH_TIME_OFFSET_MS);
+        let game = std::mem::replace(&mut self.game, expected_game);
+        self.search_parameters.silent = true;
+        let reply = self.search_best_move(engine::MAX_PLY as u8);
+        self.search_parameters.silent = false;
+        self.game = game;
 
-                    max_search_time
-                }
-                None => return,
-            },
+        if let (Ok(reply), Some(expected_recapture)) =
+            (reply, &mut self.search_parameters.expected_recapture)
+        {
+            expected_recapture.update(reply, self.search_parameters.last_search_depth);
+        }
+    }
+
+    // Played without searching when the opponent made the expected recapture, which only saves
+    // time in games played on the clock
+    pub fn instant_recapture_reply(&mut self) -> Option<Move> {
+        let expected_recapture = self.search_parameters.expected_recapture.take()?;
+        let search_parameters = &mut self.search_parameters;
+
+        if !self.options.check(options::INSTANT_RECAPTURES)
+            || !search_parameters.search_limits.is_time_limited()
+            || search_parameters.mate_moves.is_some()
+            || !search_parameters.search_moves.is_empty()
+        {
+            return None;
+        }
+
+        let hash_move = search_parameters
+            .transposition_table
+            .best_move(self.game.zobrist_key());
+        let reply = *expected_recapture.instant_reply(&self.game, hash_move.as_ref())?;
+
+        self.output.line(format!(
+            "info depth {} pv {}",
+            expected_recapture.depth(),
+            reply.as_string()
+        ));
+        search_parameters.ponder_move = None;
+        search_parameters.clear();
+
+        Some(reply)
+    }
+
+    // Limiting strength caps the nodes searched on top of any limit the GUI sets
+    pub fn set_search_limits(&mut self, node_limit: Option<u64>, time_control: &TimeControl) {
+        let strength_limit = match self.options.check(options::LIMIT_STRENGTH) {
+            true => Some(StrengthLimit::from_elo(
+                self.options.spin(options::ELO),
+                self.options.spin(options::SEED) as u32,
+            )),
+            false => None,
+        };
+        let node_limit = match &strength_limit {
+            Some(strength_limit) => Some(
+                node_limit.map_or(strength_limit.node_limit(), |node_limit| {
+                    node_limit.min(strength_limit.node_limit())
+                }),
+            ),
+            None => node_limit,
+        };
+
+        let contempt = self.options.spin(options::CONTEMPT) as Value;
+        let mut draw_values = [contempt; 2];
+        draw_values[self.game.side_to_move() as usize] = -contempt;
+
+        self.search_parameters.strength_limit = strength_limit;
+        self.search_parameters.draw_values = draw_values;
+        self.search_parameters.search_limits = SearchLimits::initialise(
+            node_limit,
+            time_control,
+            self.game.side_to_move(),
+            &self.search_parameters.ponder_flag,
+            &self.search_parameters.tune,
+        );
+    }
+
+    pub fn set_tune_parameter(&mut self, name: &str, value: Value) {
+        self.search_parameters.tune.set(name, value);
+    }
+
+    pub fn set_mate_search(&mut self, mate_moves: Option<u8>) {
+        self.search_parameters.mate_moves = mate_moves;
+    }
+
+    pub fn set_search_moves(&mut self, move_strings: &[&str]) -> Result<(), InputError> {
+        let move_list = MoveList::generate_moves(&self.game, MoveFlag::All);
+        let mut search_moves = Vec::new();
+
+        for move_string in move_strings {
+            let mv = move_list.find_move_from_string(move_string)?;
+            search_moves.push(mv);
+        }
+
+        self.search_parameters.search_moves = search_moves;
+
+        Ok(())
+    }
+
+    // A fresh reply for every search, so a watchdog left behind by an earlier search can never
+    // reply in place of this one
+    pub fn new_best_move_reply(&mut self) -> Arc<BestMoveReply> {
+        self.search_parameters.best_move_reply = Arc::default();
+
+        Arc::clone(&self.search_parameters.best_move_reply)
+    }
+
+    pub fn best_move_reply(&self) -> &BestMoveReply {
+        &self.search_parameters.best_move_reply
+    }
+
+    // Time after which a search with the given time control has to reply with any move it has
+    pub fn emergency_time(&self, time_control: &TimeControl) -> Option<Duration> {
+        TimeManager::emergency_time(time_control, self.game.side_to_move())
+    }
+
+    // Sent the best line after every completed iteration, except by the silent searches made while
+    // idle
+    pub fn set_search_progress(&mut self, search_progress: Sender<SearchProgress>) {
+        self.search_parameters.search_progress = Some(search_progress);
+    }
+
+    pub fn stop_search_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.search_parameters.stop_search_flag)
+    }
+
+    // Set while the engine is searching the position after the move it expects the opponent to
+    // play, and cleared on `ponderhit`
+    pub fn ponder_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.search_parameters.ponder_flag)
+    }
+
+    // The expected reply to the best move of the last search
+    pub fn ponder_move(&self) -> Option<&Move> {
+        self.search_parameters.ponder_move.as_ref()
+    }
+
+    // An infinite search must not report its best move until told to stop, even if it has
+    // already finished searching
+    pub fn wait_for_stop_signal(&self) {
+        while !self
+            .search_parameters
+            .stop_search_flag
+            .load(Ordering::Relaxed)
+        {
+            thread::sleep(STOP_SIGNAL_POLL_INTERVAL);
+        }
+    }
+
+    // A ponder search which finishes early must not report its best move until the opponent
+    // plays the expected move or the search is stopped
+    pub fn wait_for_ponder_end(&self) {
+        while self.search_parameters.ponder_flag.load(Ordering::Relaxed)
+            && !self
+                .search_parameters
+                .stop_search_flag
+                .load(Ordering::Relaxed)
+        {
+            thread::sleep(STOP_SIGNAL_POLL_INTERVAL);
+        }
+    }
+
+    // Discards any per-search state left behind by a search which did not finish normally
+    pub fn reset_search(&mut self) {
+        self.search_parameters.clear();
+    }
+
+    pub fn resize_transposition_table(&mut self, size_mb: usize) {
+        self.search_parameters.transposition_table =
+            Arc::new(TranspositionTable::initialise(size_mb));
+    }
+
+    // Only one set of tablebases can be open at a time, so the previous set is closed before the
+    // next is opened, and an empty path leaves them closed
+    #[cfg(feature = "syzygy")]
+    pub fn load_tablebases(&mut self) -> Result<(), InputError> {
+        self.search_parameters.tablebases = None;
+        let syzygy_path = self.options.string(options::SYZYGY_PATH);
+
+        if syzygy_path.is_empty() {
+            return Ok(());
+        }
+
+        let tablebases = Tablebases::load(syzygy_path)?;
+        self.output.line(format!(
+            "info string loaded Syzygy tablebases of up to {} pieces",
+            tablebases.max_pieces()
+        ));
+        self.search_parameters.tablebases = Some(Arc::new(tablebases));
+
+        Ok(())
+    }
+
+    pub fn clear_transposition_table(&self) {
+        self.search_parameters.transposition_table.clear();
+    }
+
+    #[cfg(feature = "nnue")]
+    pub fn clear_evaluation_cache(&mut self) {
+        self.search_parameters.evaluation_cache = EvaluationCache::initialise();
+    }
+
+    // History from a previous game says little about the positions of the next one
+    pub fn clear_move_history_scores(&mut self) {
+        self.search_parameters.historic_move_score = HistoricMoveScore::initialise();
+        self.search_parameters.capture_history = CaptureHistory::initialise();
+    }
+
+    pub fn save_transposition_table(&self, file_path: &str) -> Result<(), InputError> {
+        self.search_parameters.transposition_table.save(file_path)
+    }
+
+    pub fn load_transposition_table(&self, file_path: &str) -> Result<(), InputError> {
+        self.search_parameters.transposition_table.load(file_path)
+    }
+
+    // Lazy SMP - helper threads search the same root position with their own move ordering state,
+    // and only help the main thread through the entries they leave in the shared transposition
+    // table
+    // Strength limited searches are searched on one thread, as helpers would make the moves
+    // played depend on thread timing rather than just the seed
+    fn spawn_helper_threads(&self, depth: u8) -> HelperThreads {
+        let helper_count = match self.search_parameters.strength_limit {
+            Some(_) => 0,
+            None => self.options.spin(options::THREADS) as usize - 1,
+        };
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let threads = (0..helper_count)
+            .map(|helper_index| {
+                let mut helper = Engine {
+                    game: self.game.clone(),
+                    search_parameters: self.search_parameters.helper(&stop_flag),
+                    options: self.options.clone(),
+                    initialisation_times: Vec::new(),
+                    debug: Arc::new(AtomicBool::new(false)),
+                    start_position: None,
+                    move_history: Vec::new(),
+                    position_history: self.position_history.clone(),
+                    output: self.output.clone(),
+                };
+
+                thread::spawn(move || helper.helper_search(helper_index, depth))
+            })
+            .collect();
+
+        HelperThreads { stop_flag, threads }
+    }
+
+    // Half of the helpers start one depth ahead so that the threads do not all search the same
+    // tree in step with each other
+    fn helper_search(&mut self, helper_index: usize, depth: u8) {
+        let mut game = self.game.clone();
+        let mut current_depth = 1 + (helper_index % 2) as u8;
+
+        while current_depth <= depth && !self.search_parameters.stop_search {
+            self.search_parameters.is_principal_variation = true;
+            self.negamax_search(&mut game, EvaluationLimits::initialise(), 0, current_depth);
+            current_depth += 1;
+        }
+
+        self.search_parameters.report_nodes_searched();
+    }
+
+    // A null window search around the margin below the best move, with the best move excluded,
+    // fails low only if every other move falls short of it
+    fn is_easy_move(&mut self, game: &mut Game, depth: u8, evaluation: Evaluation) -> bool {
+        let principal_variation = self.search_parameters.principal_variation.clone();
+        let principal_move = match principal_variation.principal_move(0) {
+            Some(principal_move) => *principal_move,
+            None => return false,
         };
-        let search_timing = SearchTiming {
-            start_time: Instant::now(),
-            max_search_time,
+        let margin_evaluation = evaluation - self.search_parameters.tune.easy_move_margin;
+        let evaluation_limits = EvaluationLimits {
+            min: margin_evaluation - 1,
+            max: margin_evaluation,
         };
-        self.search_parameters.search_timing = Some(search_timing);
+
+        self.search_parameters
+            .excluded_root_moves
+            .push(principal_move);
+        self.search_parameters.is_principal_variation = false;
+
+        let alternative_evaluation = self.negamax_search(
+            game,
+            evaluation_limits,
+            0,
+            depth - EASY_MOVE_DEPTH_REDUCTION,
+        );
+
+        self.search_parameters.excluded_root_moves.clear();
+        self.search_parameters.principal_variation = principal_variation;
+
+        !self.search_parameters.stop_search && alternative_evaluation < margin_evaluation
+    }
+
+    // Each further line is searched at full width with the root moves of the previous lines
+    // excluded, following the same line of the previous iteration first if its root move is
+    // still available, after which the principal variation of the first line is restored
+    fn search_secondary_lines(
+        &mut self,
+        game: &mut Game,
+        depth: u8,
+        line_count: usize,
+    ) -> Vec<RootLine> {
+        let principal_variation = self.search_parameters.principal_variation.clone();
+        let mut lines = Vec::new();
+
+        if let Some(principal_move) = principal_variation.principal_move(0) {
+            self.search_parameters
+                .excluded_root_moves
+                .push(*principal_move);
+        }
+
+        for line_index in 1..line_count {
+            let previous_line = self
+                .search_parameters
+                .multi_principal_variation
+                .previous_line(line_index)
+                .filter(|previous_line| match previous_line.principal_move(0) {
+                    Some(root_move) => !self.search_parameters.is_root_move_excluded(root_move),
+                    None => false,
+                })
+                .cloned();
+            self.search_parameters.is_principal_variation = previous_line.is_some();
+            self.search_parameters.principal_variation =
+                previous_line.unwrap_or_else(PrincipalVariation::initialise);
+
+            let evaluation = self.negamax_search(game, EvaluationLimits::initialise(), 0, depth);
+
+            if self.search_parameters.stop_search {
+                break;
+            }
+
+            let root_move = match self.search_parameters.principal_variation.principal_move(0) {
+                Some(root_move) => *root_move,
+                None => break,
+            };
+            self.search_parameters.excluded_root_moves.push(root_move);
+            lines.push(RootLine {
+                evaluation,
+                principal_variation: self.search_parameters.principal_variation.clone(),
+            });
+        }
+
+        self.search_parameters.excluded_root_moves.clear();
+        self.search_parameters.principal_variation = principal_variation;
+
+        lines
     }
 
-    pub fn set_stop_search_receiver(&mut self, stop_search_receiver: Receiver<bool>) {
-        self.search_parameters.stop_search_receiver = Some(stop_search_receiver);
+    fn legal_moves(&self, game: &Game) -> Vec<Move> {
+        let move_list = MoveList::generate_moves(game, MoveFlag::All);
+
+        move_list.vec().to_vec()
     }
 
     fn negamax_search(
         &mut self,
-        game: &Game,
+        game: &mut Game,
         mut evaluation_limits: EvaluationLimits,
         ply: Value,
         mut depth: u8,
     ) -> Evaluation {
         self.search_parameters.stop_search_check();
         self.search_parameters.principal_variation.length[ply as usize] = ply;
+        self.search_parameters.position_keys[ply as usize] = game.zobrist_key();
+
+        if ply == 0 {
+            self.search_parameters.root_depth = depth;
+        }
+
+        // The side to move can always steer back into a repeated position, so either side can
+        // count on the draw from there
+        if ply != 0 && self.is_repetition(game, ply) {
+            return self.draw_evaluation(game);
+        }
+
+        let is_principal_variation_node =
+            evaluation_limits.max.value() - evaluation_limits.min.value() > 1;
+
+        if ply != 0 && !is_principal_variation_node {
+            let transposition_table_evaluation = self.search_parameters.transposition_table.probe(
+                game.zobrist_key(),
+                evaluation_limits,
+                depth,
+                ply,
+            );
+            self.search_parameters.transposition_table_probes += 1;
+
+            if let Some(evaluation) = transposition_table_evaluation {
+                self.search_parameters.transposition_table_hits += 1;
+
+                return evaluation;
+            }
+        }
+
+        #[cfg(feature = "syzygy")]
+        if ply != 0 {
+            if let Some(evaluation) = self.tablebase_evaluation(game, ply) {
+                return evaluation;
+            }
+        }
 
         if ply as usize >= engine::MAX_PLY {
-            return Self::evaluate(game).sided_value(game.side_to_move());
+            return self
+                .static_evaluation(game)
+                .sided_value(game.side_to_move());
         }
 
         if depth == 0 {
@@ -143,145 +1045,331 @@ impl Engine {
 
         self.search_parameters.nodes_searched += 1;
 
-        let king_square = game
-            .piece_bitboard(Piece::King, game.side_to_move())
-            .get_lsb_square();
-        let king_in_check = match king_square {
-            Some(king_square) => {
-                let attacking_side = game.side_to_move().opponent_side();
-
-                game.is_square_attacked(&self.attack_tables, attacking_side, king_square)
-            }
-            None => false,
-        };
+        let king_in_check = game.king_in_check(game.side_to_move());
 
-        if king_in_check {
+        if king_in_check
+            && ply < CHECK_EXTENSION_PLY_MULTIPLIER * self.search_parameters.root_depth as Value
+        {
             depth += 1;
         }
 
-        let apply_null_move_pruning = depth >= NULL_MOVE_DEPTH_MIN && !king_in_check && ply != 0;
-
-        if apply_null_move_pruning {
-            let mut game_clone = game.clone();
-            game_clone.make_null_move();
-
-            let evaluation = -self.negamax_search(
-                &game_clone,
-                evaluation_limits.min_narrowed_bounds(),
-                ply + 1,
-                depth - NULL_MOVE_DEPTH_REDUCTION,
-            );
+        // Two null moves in a row would just search the same position at a lower depth
+        let apply_null_move_pruning = depth >= NULL_MOVE_DEPTH_MIN
+            && !king_in_check
+            && ply != 0
+            && !self.search_parameters.null_moves[ply as usize]
+            && game.has_non_pawn_material(game.side_to_move());
 
-            if evaluation >= evaluation_limits.max {
-                return evaluation_limits.max;
-            }
+        if apply_null_move_pruning && self.null_move_cutoff(game, evaluation_limits, ply, depth) {
+            return evaluation_limits.max;
         }
 
-        let move_list = MoveList::generate_sorted_moves(game, self, ply);
+        let mut move_list = ScoredMoveList::generate(game, self, ply, MoveFlag::All);
         let principal_move_option = self
             .search_parameters
             .principal_variation
             .principal_move(ply);
+        // The principal move is scored above every other move, so is picked first when present
         self.search_parameters.is_principal_variation = match principal_move_option {
             Some(principal_move) => {
-                self.search_parameters.is_principal_variation
-                    && move_list.vec()[0] == *principal_move
+                self.search_parameters.is_principal_variation && move_list.contains(principal_move)
             }
             None => false,
         };
 
+        let tune = self.search_parameters.tune;
         let mut moves_searched = 0;
+        let mut quiet_moves_searched = Vec::new();
+        let mut captures_searched = Vec::new();
+        let mut best_move = None;
+        // Results of a root search with moves excluded do not hold for the position itself
+        let root_moves_excluded = ply == 0 && self.search_parameters.root_moves_restricted();
+
+        while let Some(mv) = move_list.next_move() {
+            if ply == 0 && self.search_parameters.is_root_move_excluded(&mv) {
+                continue;
+            }
+
+            if game.make_move(&mv).is_err() {
+                continue;
+            }
+
+            self.search_parameters.null_moves[ply as usize + 1] = false;
+
+            if ply == 0 {
+                self.search_parameters.refutation = None;
+                self.search_parameters
+                    .print_current_move(&self.output, &mv, moves_searched + 1);
+            }
 
-        for mv in move_list.vec() {
-            let mut game_clone = game.clone();
-            let move_result = game_clone.make_move(mv, &self.attack_tables);
+            let is_quiet_move = mv.move_type() != MoveType::Capture
+                && mv.move_type() != MoveType::EnPassant
+                && mv.promoted_piece().is_none();
+            let gives_check = || game.king_in_check(game.side_to_move());
+
+            // Late move pruning - near the leaves, quiet moves ordered this late are very unlikely
+            // to raise the lower bound, unless a mate is at stake
+            let apply_late_move_pruning = depth <= LMP_DEPTH_MAX
+                && ply != 0
+                && !is_principal_variation_node
+                && !king_in_check
+                && is_quiet_move
+                && moves_searched >= tune.lmp_moves_searched_base + (depth * depth) as i32
+                && evaluation_limits.min.mate_moves().is_none()
+                && !gives_check();
 
-            if move_result.is_err() {
+            if apply_late_move_pruning {
+                game.undo_move();
                 continue;
             }
 
-            let apply_late_move_reduction = moves_searched >= LMR_MOVES_SEARCHED_MIN
-                && depth >= LMR_DEPTH_MIN
+            let apply_late_move_reduction = moves_searched >= tune.lmr_moves_searched_min
+                && depth as Value >= tune.lmr_depth_min
                 && !king_in_check
-                && !(mv.move_type() == MoveType::Capture)
-                && !(mv.move_type() == MoveType::EnPassant)
-                && mv.promoted_piece().is_none();
+                && is_quiet_move
+                && !gives_check();
             let evaluation = if moves_searched == 0 {
-                -self.negamax_search(&game_clone, -evaluation_limits, ply + 1, depth - 1)
+                -self.negamax_search(game, -evaluation_limits, ply + 1, depth - 1)
             } else if apply_late_move_reduction {
-                self.late_move_reduction_search(&game_clone, evaluation_limits, ply, depth)
+                self.late_move_reduction_search(game, evaluation_limits, ply, depth)
             } else {
-                self.candidate_best_move_search(&game_clone, evaluation_limits, ply, depth)
+                self.candidate_best_move_search(game, evaluation_limits, ply, depth)
             };
+            game.undo_move();
 
             moves_searched += 1;
 
-            if self.search_parameters.stop_search {
-                return evaluation::STALEMATE_EVALUATION;
-            }
+            if self.search_parameters.stop_search {
+                return evaluation::STALEMATE_EVALUATION;
+            }
+
+            if ply == 0
+                && self.options.check(options::SHOW_REFUTATIONS)
+                && !self.search_parameters.silent
+            {
+                self.search_parameters.print_root_move_lines(
+                    &self.output,
+                    &mv,
+                    evaluation <= evaluation_limits.min,
+                );
+            }
+
+            if evaluation >= evaluation_limits.max {
+                self.search_parameters
+                    .cutoff_distribution
+                    .record(moves_searched);
+
+                if ply == 1 {
+                    self.search_parameters.refutation = Some(mv);
+                }
+
+                self.search_parameters.killer_moves.push(&mv, ply);
+
+                // Moves searched before the one which cut off should have come after it - captures
+                // are searched before quiet moves, so are behind either kind of cutoff
+                let search_parameters = &mut self.search_parameters;
+
+                match mv.move_type() == MoveType::Capture || mv.move_type() == MoveType::EnPassant {
+                    true => search_parameters.capture_history.reward(&mv, game, depth),
+                    false => {
+                        let historic_move_score = &mut search_parameters.historic_move_score;
+                        historic_move_score.reward(&mv, game.side_to_move(), depth);
+
+                        for quiet_move in &quiet_moves_searched {
+                            historic_move_score.penalise(quiet_move, game.side_to_move(), depth);
+                        }
+                    }
+                }
+
+                for capture in &captures_searched {
+                    search_parameters
+                        .capture_history
+                        .penalise(capture, game, depth);
+                }
+
+                if !root_moves_excluded {
+                    self.search_parameters.transposition_table.store(
+                        game.zobrist_key(),
+                        depth,
+                        ply,
+                        Bound::Lower,
+                        evaluation_limits.max,
+                        Some(&mv),
+                    );
+                }
+
+                return evaluation_limits.max;
+            }
+
+            match mv.move_type() == MoveType::Capture || mv.move_type() == MoveType::EnPassant {
+                true => captures_searched.push(mv),
+                false => quiet_moves_searched.push(mv),
+            }
+
+            if evaluation > evaluation_limits.min {
+                self.search_parameters
+                    .principal_variation
+                    .write_move(&mv, ply);
+                evaluation_limits.min = evaluation;
+                best_move = Some(mv);
+            }
+        }
+
+        if moves_searched == 0 && king_in_check {
+            return -evaluation::CHECKMATE_EVALUATION + ply;
+        } else if moves_searched == 0 {
+            return self.draw_evaluation(game);
+        }
+
+        let bound = match best_move {
+            Some(_) => Bound::Exact,
+            None => Bound::Upper,
+        };
+
+        if !root_moves_excluded {
+            self.search_parameters.transposition_table.store(
+                game.zobrist_key(),
+                depth,
+                ply,
+                bound,
+                evaluation_limits.min,
+                best_move.as_ref(),
+            );
+        }
+
+        evaluation_limits.min
+    }
+
+    // Positions only repeat with the same side to move, and not past the last irreversible move -
+    // earlier positions on the search path come first, then those played before the search
+    fn is_repetition(&self, game: &Game, ply: Value) -> bool {
+        let position_keys = &self.search_parameters.position_keys[..ply as usize];
+        let earlier_keys = self
+            .position_history
+            .iter()
+            .chain(position_keys)
+            .rev()
+            .take(game.halfmove_clock() as usize);
+
+        earlier_keys
+            .skip(1)
+            .step_by(2)
+            .any(|zobrist_key| *zobrist_key == game.zobrist_key())
+    }
+
+    // Reported after the score when the GUI asks for it, empty otherwise
+    fn win_draw_loss(&self, game: &Game, evaluation: Evaluation) -> String {
+        match self.options.check(options::SHOW_WDL) {
+            true => format!(" {}", WinDrawLoss::from_evaluation(evaluation, game)),
+            false => String::new(),
+        }
+    }
+
+    // Contempt makes a draw worth less than zero to the side the engine plays, and so worth more
+    // than zero to its opponent, as the evaluation is from the side to move's point of view
+    fn draw_evaluation(&self, game: &Game) -> Evaluation {
+        evaluation::STALEMATE_EVALUATION
+            + self.search_parameters.draw_values[game.side_to_move() as usize]
+    }
+
+    // Positions in the tablebases are scored by their result instead of being searched any further
+    #[cfg(feature = "syzygy")]
+    fn tablebase_evaluation(&self, game: &Game, ply: Value) -> Option<Evaluation> {
+        let tablebases = self.search_parameters.tablebases.as_ref()?;
+        let evaluation = tablebases.probe_wdl(game, ply, self.draw_evaluation(game))?;
+        self.search_parameters
+            .tablebase_hits
+            .fetch_add(1, Ordering::Relaxed);
 
-            if evaluation >= evaluation_limits.max {
-                self.search_parameters.killer_moves.push(mv, ply);
+        Some(evaluation)
+    }
 
-                return evaluation_limits.max;
+    // Strength limited searches misjudge every position by its own fixed amount, so transpositions
+    // into the same position are still judged alike
+    fn static_evaluation(&mut self, game: &Game) -> Evaluation {
+        let evaluation_cache = &mut self.search_parameters.evaluation_cache;
+        let evaluation = match evaluation_cache.probe(game.zobrist_key()) {
+            Some(evaluation) => evaluation,
+            None => {
+                let evaluation = Self::evaluate(game);
+                evaluation_cache.store(game.zobrist_key(), evaluation);
+
+                evaluation
             }
+        };
 
-            if evaluation > evaluation_limits.min {
-                self.search_parameters
-                    .principal_variation
-                    .write_move(mv, ply);
-                self.search_parameters.historic_move_score.push(
-                    mv,
-                    game_clone.side_to_move(),
-                    depth,
-                );
-                evaluation_limits.min = evaluation;
+        match &self.search_parameters.strength_limit {
+            Some(strength_limit) => {
+                evaluation + strength_limit.evaluation_noise(game.zobrist_key())
             }
-        }
-
-        if moves_searched == 0 && king_in_check {
-            -evaluation::CHECKMATE_EVALUATION + ply
-        } else if moves_searched == 0 {
-            evaluation::STALEMATE_EVALUATION
-        } else {
-            evaluation_limits.min
+            None => evaluation,
         }
     }
 
     fn quiescence_search(
         &mut self,
-        game: &Game,
+        game: &mut Game,
         mut evaluation_limits: EvaluationLimits,
         ply: Value,
     ) -> Evaluation {
         self.search_parameters.stop_search_check();
         self.search_parameters.nodes_searched += 1;
 
-        let evaluation = Self::evaluate(game).sided_value(game.side_to_move());
+        let stand_pat = self
+            .static_evaluation(game)
+            .sided_value(game.side_to_move());
+
+        // Long capture sequences in wild positions would otherwise run past the ply limit
+        if ply as usize >= engine::MAX_PLY {
+            return stand_pat;
+        }
 
-        if evaluation >= evaluation_limits.max {
+        if stand_pat >= evaluation_limits.max {
             return evaluation_limits.max;
         }
 
-        if evaluation > evaluation_limits.min {
-            evaluation_limits.min = evaluation;
+        if stand_pat > evaluation_limits.min {
+            evaluation_limits.min = stand_pat;
         }
 
-        let move_list = MoveList::generate_sorted_moves(game, self, ply);
+        let mut move_list = ScoredMoveList::generate(game, self, ply, MoveFlag::CapturesOnly);
+
+        while let Some(mv) = move_list.next_move() {
+            let is_capture =
+                mv.move_type() == MoveType::Capture || mv.move_type() == MoveType::EnPassant;
+            // Only captures and promotions are generated, but underpromotions are almost never
+            // better than a queen promotion, so are left to the main search
+            let is_queen_promotion = mv.promoted_piece() == Some(Piece::Queen);
+
+            if !is_capture && !is_queen_promotion {
+                continue;
+            }
 
-        for mv in move_list.vec() {
-            if mv.move_type() != MoveType::Capture && mv.move_type() != MoveType::EnPassant {
+            // Delta pruning - a capture which would leave the score short of the lower bound even
+            // if the captured material came with a margin for positional gains cannot raise it
+            if !is_queen_promotion
+                && stand_pat
+                    + mv.material_gain(game)
+                    + self.search_parameters.tune.quiescence_delta_margin
+                    <= evaluation_limits.min
+            {
                 continue;
             }
 
-            let mut game_clone = game.clone();
-            let move_result = game_clone.make_move(mv, &self.attack_tables);
+            let losing_capture = mv.promoted_piece().is_none() && mv.is_losing_capture(game);
+            if game.make_move(&mv).is_err() {
+                continue;
+            }
 
-            if move_result.is_err() {
+            // Captures losing material are pruned unless they give check, as the check may still
+            // win back more than was given up
+            if losing_capture && !game.king_in_check(game.side_to_move()) {
+                game.undo_move();
                 continue;
             }
 
-            let evaluation = -self.quiescence_search(&game_clone, -evaluation_limits, ply + 1);
+            let evaluation = -self.quiescence_search(game, -evaluation_limits, ply + 1);
+            game.undo_move();
 
             if evaluation >= evaluation_limits.max {
                 return evaluation_limits.max;
@@ -295,9 +1383,55 @@ impl Engine {
         evaluation_limits.min
     }
 
+    fn null_move_cutoff(
+        &mut self,
+        game: &mut Game,
+        evaluation_limits: EvaluationLimits,
+        ply: Value,
+        depth: u8,
+    ) -> bool {
+        game.make_null_move();
+        self.search_parameters.null_moves[ply as usize + 1] = true;
+
+        let evaluation = -self.negamax_search(
+            game,
+            evaluation_limits.min_narrowed_bounds(),
+            ply + 1,
+            depth.saturating_sub(self.search_parameters.tune.null_move_depth_reduction as u8),
+        );
+        game.undo_move();
+
+        if evaluation < evaluation_limits.max || self.search_parameters.stop_search {
+            return false;
+        }
+
+        if depth < NULL_MOVE_VERIFICATION_DEPTH_MIN {
+            return true;
+        }
+
+        // Searching this node again as if it followed a null move keeps the verification from
+        // trying another null move
+        self.search_parameters.re_searches.null_move_verifications += 1;
+        self.search_parameters.null_moves[ply as usize] = true;
+
+        let verification_limits = EvaluationLimits {
+            min: evaluation_limits.max - 1,
+            max: evaluation_limits.max,
+        };
+        let evaluation = self.negamax_search(
+            game,
+            verification_limits,
+            ply,
+            depth.saturating_sub(self.search_parameters.tune.null_move_depth_reduction as u8),
+        );
+        self.search_parameters.null_moves[ply as usize] = false;
+
+        evaluation >= evaluation_limits.max
+    }
+
     fn late_move_reduction_search(
         &mut self,
-        game: &Game,
+        game: &mut Game,
         evaluation_limits: EvaluationLimits,
         ply: Value,
         depth: u8,
@@ -306,10 +1440,11 @@ impl Engine {
             game,
             evaluation_limits.max_narrowed_bounds(),
             ply + 1,
-            depth - LMR_DEPTH_REDUCTION,
+            depth.saturating_sub(self.search_parameters.tune.lmr_depth_reduction as u8),
         );
 
         if evaluation > evaluation_limits.min {
+            self.search_parameters.re_searches.late_move_reductions += 1;
             self.candidate_best_move_search(game, evaluation_limits, ply, depth)
         } else {
             evaluation
@@ -318,7 +1453,7 @@ impl Engine {
 
     fn candidate_best_move_search(
         &mut self,
-        game: &Game,
+        game: &mut Game,
         evaluation_limits: EvaluationLimits,
         ply: Value,
         depth: u8,
@@ -331,6 +1466,7 @@ impl Engine {
         );
 
         if evaluation > evaluation_limits.min && evaluation < evaluation_limits.max {
+            self.search_parameters.re_searches.null_windows += 1;
             -self.negamax_search(game, -evaluation_limits, ply + 1, depth - 1)
         } else {
             evaluation
@@ -338,65 +1474,283 @@ impl Engine {
     }
 }
 
+// Helpers are stopped and joined once dropped, which includes the main search unwinding from a
+// panic, so no helper is left searching on its own
+struct HelperThreads {
+    stop_flag: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Drop for HelperThreads {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+
+        for thread in self.threads.drain(..) {
+            _ = thread.join();
+        }
+    }
+}
+
 pub struct SearchParameters {
     principal_variation: PrincipalVariation,
     killer_moves: KillerMoves,
     historic_move_score: HistoricMoveScore,
+    capture_history: CaptureHistory,
     is_principal_variation: bool,
-    stop_search_receiver: Option<Receiver<bool>>,
-    search_timing: Option<SearchTiming>,
+    refutation: Option<Move>,
+    silent: bool,
+    stop_search_flag: Arc<AtomicBool>,
+    ponder_flag: Arc<AtomicBool>,
+    ponder_move: Option<Move>,
+    search_limits: SearchLimits,
+    strength_limit: Option<StrengthLimit>,
+    // Value of a draw for each side to move, set from the side the engine plays when a search
+    // starts and kept for the searches made while idle
+    draw_values: [Value; 2],
+    mate_moves: Option<u8>,
+    multi_principal_variation: MultiPrincipalVariation,
+    excluded_root_moves: Vec<Move>,
+    search_moves: Vec<Move>,
+    transposition_table: Arc<TranspositionTable>,
+    search_start_time: Instant,
     stop_search: bool,
     nodes_searched: u64,
+    nodes_reported: u64,
+    total_nodes_searched: Arc<AtomicU64>,
+    last_search_nodes: u64,
+    last_search_depth: u8,
+    // Of the last completed iteration, which is none if the search was stopped during the first
+    last_search_evaluation: Option<Evaluation>,
+    last_search_principal_variation: Vec<Move>,
+    search_progress: Option<Sender<SearchProgress>>,
+    expected_recapture: Option<ExpectedRecapture>,
+    root_depth: u8,
+    // Whether the move leading to each ply was a null move
+    null_moves: [bool; engine::MAX_PLY + 1],
+    position_keys: [ZobristKey; engine::MAX_PLY + 1],
+    re_searches: ReSearches,
+    cutoff_distribution: CutoffDistribution,
+    // Counted over a single iteration, where a hit is an entry good enough to return straight away
+    transposition_table_probes: u64,
+    transposition_table_hits: u64,
+    evaluation_cache: EvaluationCache,
+    #[cfg(feature = "syzygy")]
+    tablebases: Option<Arc<Tablebases>>,
+    // Shared by every thread, like the total nodes searched
+    #[cfg(feature = "syzygy")]
+    tablebase_hits: Arc<AtomicU64>,
+    best_move_reply: Arc<BestMoveReply>,
+    tune: TuneParameters,
 }
 
 impl SearchParameters {
-    pub fn initialise() -> Self {
+    pub fn initialise(hash_size_mb: usize) -> Self {
+        Self::with_transposition_table(Arc::new(TranspositionTable::initialise(hash_size_mb)))
+    }
+
+    fn with_transposition_table(transposition_table: Arc<TranspositionTable>) -> Self {
         Self {
             principal_variation: PrincipalVariation::initialise(),
             killer_moves: KillerMoves::initialise(),
             historic_move_score: HistoricMoveScore::initialise(),
+            capture_history: CaptureHistory::initialise(),
             is_principal_variation: true,
-            stop_search_receiver: None,
-            search_timing: None,
+            refutation: None,
+            silent: false,
+            stop_search_flag: Arc::new(AtomicBool::new(false)),
+            ponder_flag: Arc::new(AtomicBool::new(false)),
+            ponder_move: None,
+            search_limits: SearchLimits::unlimited(),
+            strength_limit: None,
+            draw_values: [0; 2],
+            mate_moves: None,
+            multi_principal_variation: MultiPrincipalVariation::initialise(),
+            excluded_root_moves: Vec::new(),
+            search_moves: Vec::new(),
+            transposition_table,
+            search_start_time: Instant::now(),
             stop_search: false,
             nodes_searched: 0,
+            nodes_reported: 0,
+            total_nodes_searched: Arc::new(AtomicU64::new(0)),
+            last_search_nodes: 0,
+            last_search_depth: 0,
+            last_search_evaluation: None,
+            last_search_principal_variation: Vec::new(),
+            search_progress: None,
+            expected_recapture: None,
+            root_depth: 0,
+            null_moves: [false; engine::MAX_PLY + 1],
+            position_keys: [0; engine::MAX_PLY + 1],
+            re_searches: ReSearches::default(),
+            cutoff_distribution: CutoffDistribution::default(),
+            transposition_table_probes: 0,
+            transposition_table_hits: 0,
+            evaluation_cache: EvaluationCache::initialise(),
+            #[cfg(feature = "syzygy")]
+            tablebases: None,
+            #[cfg(feature = "syzygy")]
+            tablebase_hits: Arc::new(AtomicU64::new(0)),
+            best_move_reply: Arc::default(),
+            tune: TuneParameters::default(),
+        }
+    }
+
+    // Helpers are stopped by the main thread once it finishes, and follow the same root move
+    // restrictions so their results can be shared
+    fn helper(&self, helper_stop_flag: &Arc<AtomicBool>) -> Self {
+        Self {
+            silent: true,
+            stop_search_flag: Arc::clone(helper_stop_flag),
+            draw_values: self.draw_values,
+            historic_move_score: self.historic_move_score.clone(),
+            capture_history: self.capture_history.clone(),
+            search_moves: self.search_moves.clone(),
+            total_nodes_searched: Arc::clone(&self.total_nodes_searched),
+            #[cfg(feature = "syzygy")]
+            tablebases: self.tablebases.clone(),
+            #[cfg(feature = "syzygy")]
+            tablebase_hits: Arc::clone(&self.tablebase_hits),
+            tune: self.tune,
+            ..Self::with_transposition_table(Arc::clone(&self.transposition_table))
         }
     }
 
     fn stop_search_check(&mut self) {
-        if self.nodes_searched & SEARCH_STOP_CHECK_NODES != 0 || self.stop_search {
+        if !self
+            .nodes_searched
+            .is_multiple_of(SEARCH_CHECK_INTERVAL_NODES)
+            || self.stop_search
+        {
             return;
         }
 
-        let stop_search_received = match &self.stop_search_receiver {
-            Some(stop_search_receiver) => {
-                stop_search_receiver.try_recv().unwrap_or(self.stop_search)
-            }
-            None => false,
-        };
-        let max_evaluation_time_exceeded = match &self.search_timing {
-            Some(evaluation_time) => {
-                evaluation_time.start_time.elapsed() > evaluation_time.max_search_time
-            }
-            None => false,
-        };
-        self.stop_search = stop_search_received || max_evaluation_time_exceeded
+        self.report_nodes_searched();
+
+        let stop_search_received = self.stop_search_flag.load(Ordering::Relaxed);
+        let search_limit_reached = self.search_limits.limit_reached(self.nodes_searched);
+        self.stop_search = stop_search_received || search_limit_reached
+    }
+
+    // Every thread adds its nodes to the shared total, which is what the main thread reports
+    fn report_nodes_searched(&mut self) {
+        self.total_nodes_searched
+            .fetch_add(self.nodes_searched - self.nodes_reported, Ordering::Relaxed);
+        self.nodes_reported = self.nodes_searched;
+    }
+
+    // Nodes searched by every thread, including those this thread has not reported yet
+    fn nodes(&self) -> u64 {
+        self.total_nodes_searched.load(Ordering::Relaxed)
+            + (self.nodes_searched - self.nodes_reported)
+    }
+
+    fn search_statistics(&self) -> String {
+        let search_time = self.search_start_time.elapsed();
+        let nodes_searched = self.nodes();
+        let nodes_per_second = nodes_searched * 1000 / (search_time.as_millis() as u64).max(1);
+        // Only reported by builds which can probe tablebases
+        #[cfg(feature = "syzygy")]
+        let tablebase_hits = format!(" tbhits {}", self.tablebase_hits.load(Ordering::Relaxed));
+        #[cfg(not(feature = "syzygy"))]
+        let tablebase_hits = "";
+
+        format!(
+            "nodes {} nps {} hashfull {}{} time {}",
+            nodes_searched,
+            nodes_per_second,
+            self.transposition_table.hashfull(),
+            tablebase_hits,
+            search_time.as_millis()
+        )
+    }
+
+    // Root moves are excluded when already covered by a previous MultiPV line or, if the search
+    // was restricted to certain moves, when they are not among them
+    fn is_root_move_excluded(&self, mv: &Move) -> bool {
+        let not_search_move = !self.search_moves.is_empty() && !self.search_moves.contains(mv);
+
+        self.excluded_root_moves.contains(mv) || not_search_move
+    }
+
+    fn root_moves_restricted(&self) -> bool {
+        !self.excluded_root_moves.is_empty() || !self.search_moves.is_empty()
+    }
+
+    fn print_current_move(&self, output: &Output, root_move: &Move, move_number: i32) {
+        if self.silent || self.search_start_time.elapsed() < CURRMOVE_REPORT_TIME {
+            return;
+        }
+
+        output.line(format!(
+            "info currmove {} currmovenumber {}",
+            root_move.as_string(),
+            move_number
+        ));
+    }
+
+    // The current line is the root move followed by the best continuation found for it, and the
+    // refutation is the reply which caused a root move to fail low
+    fn print_root_move_lines(&self, output: &Output, root_move: &Move, failed_low: bool) {
+        let mut current_line = root_move.as_string();
+        let continuation = self.principal_variation.line_string(1);
+
+        if !continuation.is_empty() {
+            current_line = current_line + " " + &continuation;
+        }
+
+        output.line(format!("info currline {}", current_line));
+
+        if !failed_low {
+            return;
+        }
+
+        if let Some(refutation) = &self.refutation {
+            output.line(format!(
+                "info refutation {} {}",
+                root_move.as_string(),
+                refutation.as_string()
+            ));
+        }
     }
 
     fn clear(&mut self) {
         self.principal_variation = PrincipalVariation::initialise();
         self.killer_moves = KillerMoves::initialise();
-        self.historic_move_score = HistoricMoveScore::initialise();
+        self.historic_move_score.decay();
+        self.capture_history.decay();
         self.is_principal_variation = true;
-        self.search_timing = None;
+        self.refutation = None;
+        self.search_limits = SearchLimits::unlimited();
+        self.strength_limit = None;
+        self.mate_moves = None;
+        self.multi_principal_variation = MultiPrincipalVariation::initialise();
+        self.excluded_root_moves.clear();
+        self.search_moves.clear();
         self.stop_search = false;
         self.nodes_searched = 0;
+        self.nodes_reported = 0;
+        self.total_nodes_searched.store(0, Ordering::Relaxed);
+        #[cfg(feature = "syzygy")]
+        self.tablebase_hits.store(0, Ordering::Relaxed);
+        self.re_searches = ReSearches::default();
+        self.cutoff_distribution = CutoffDistribution::default();
+        self.transposition_table_probes = 0;
+        self.transposition_table_hits = 0;
+        self.evaluation_cache.reset_statistics();
     }
-}
 
-struct SearchTiming {
-    start_time: Instant,
-    max_search_time: Duration,
+    fn transposition_table_statistics(&self) -> String {
+        let hit_percentage = match self.transposition_table_probes {
+            0 => 0,
+            probes => self.transposition_table_hits * 100 / probes,
+        };
+
+        format!(
+            "hash probes {} hits {} hit rate {}%",
+            self.transposition_table_probes, self.transposition_table_hits, hit_percentage
+        )
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -441,6 +1795,7 @@ impl Neg for EvaluationLimits {
         }
     }
 }
+#[derive(Clone)]
 struct PrincipalVariation {
     table: [[Option<Move>; engine::MAX_PLY]; engine::MAX_PLY],
     length: [Value; engine::MAX_PLY],
@@ -448,8 +1803,7 @@ struct PrincipalVariation {
 
 impl PrincipalVariation {
     fn initialise() -> Self {
-        let table_element = [(); engine::MAX_PLY].map(|_| None);
-        let table = [(); engine::MAX_PLY].map(|_| table_element.clone());
+        let table = [[None; engine::MAX_PLY]; engine::MAX_PLY];
 
         Self {
             table,
@@ -463,99 +1817,225 @@ impl PrincipalVariation {
 
     fn write_move(&mut self, mv: &Move, ply: Value) {
         let ply = ply as usize;
-        self.table[ply][ply] = Some(mv.clone());
+        self.table[ply][ply] = Some(*mv);
 
         for next_ply in (ply + 1)..self.length[ply + 1] as usize {
-            self.table[ply][next_ply] = self.table[ply + 1][next_ply].clone();
+            self.table[ply][next_ply] = self.table[ply + 1][next_ply];
         }
 
         self.length[ply] = self.length[ply + 1];
     }
 
-    fn as_string(&self) -> String {
-        self.table[0]
+    fn line_string(&self, ply: usize) -> String {
+        self.table[ply][ply..self.length[ply] as usize]
             .iter()
             .flatten()
             .map(|mv| mv.as_string())
             .collect::<Vec<String>>()
             .join(" ")
     }
+
+    fn as_string(&self) -> String {
+        self.line_string(0)
+    }
+
+    fn moves(&self) -> Vec<Move> {
+        self.table[0][..self.length[0] as usize]
+            .iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
 }
 
-fn _perft_test(game: &Game, attack_tables: &AttackTables, depth: u8) {
-    let mut total_nodes = 0;
-    let now = Instant::now();
-    let move_list = MoveList::generate_moves(game, attack_tables);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci;
 
-    println!("Move   Nodes   ");
+    #[test]
+    fn one_move_checkmate_white() {
+        let mut engine = Engine::initialise();
+        let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
 
-    for mv in move_list.vec() {
-        let mut game_clone = game.clone();
-        let move_result = game_clone.make_move(mv, attack_tables);
+        let best_move = engine.search_best_move(5).unwrap();
 
-        if move_result.is_err() {
-            continue;
-        }
+        assert_eq!(best_move.as_string(), "b4e7");
 
-        let mut nodes = 0;
-        _perft(&game_clone, attack_tables, &mut nodes, depth - 1);
+        let mut engine = Engine::initialise();
+        let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
 
-        print!("{:<6}", mv.as_string());
-        print!("{:^7}", nodes);
-        println!();
+        let best_move = engine.search_best_move(6).unwrap();
 
-        total_nodes += nodes;
+        assert_eq!(best_move.as_string(), "b4e7");
     }
 
-    println!();
-    println!("Depth: {}", depth);
-    println!("Nodes: {}", total_nodes);
-    println!("Time taken: {:?}", now.elapsed());
-}
+    #[test]
+    fn mate_search() {
+        let mut engine = Engine::initialise();
+        let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        engine.set_mate_search(Some(1));
 
-fn _perft(game: &Game, attack_tables: &AttackTables, nodes: &mut u64, depth: u8) {
-    if depth == 0 {
-        *nodes += 1;
+        let best_move = engine.search_best_move(64).unwrap();
 
-        return;
+        assert_eq!(best_move.as_string(), "b4e7");
+
+        let fen = vec!["startpos"];
+        engine.load_fen(&fen).unwrap();
+        engine.set_mate_search(Some(2));
+
+        assert!(matches!(
+            engine.search_best_move(64),
+            Err(InputError::MateNotFound)
+        ));
     }
 
-    let move_list = MoveList::generate_moves(game, attack_tables);
+    // Run with `cargo test -- --ignored` after changes to the search, as a floor for how well it
+    // still finds short mates
+    #[test]
+    #[ignore]
+    fn mate_puzzles() {
+        const NODE_BUDGET: u64 = 200_000;
+        let puzzles = [
+            (
+                "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1",
+                2,
+                "d5f6",
+            ),
+            (
+                "1rb4r/pkPp3p/1b1P3n/1Q6/N3Pp2/8/P1P3PP/7K w - - 1 1",
+                2,
+                "b5d5",
+            ),
+            (
+                "4kb1r/p2n1ppp/4q3/4p1B1/4P3/1Q6/PPP2PPP/2KR4 w k - 1 1",
+                2,
+                "b3b8",
+            ),
+            (
+                "r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1",
+                2,
+                "d5d8",
+            ),
+            (
+                "5rkr/pp2Rp2/1b1p1Pb1/3P2Q1/2n3P1/2p5/P4P2/4R1K1 w - - 1 1",
+                2,
+                "g5g6",
+            ),
+            (
+                "r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1",
+                3,
+                "f8c5",
+            ),
+            (
+                "r3k2r/ppp2Npp/1b5n/4p2b/2B1P2q/BQP2P2/P5PP/RN5K w kq - 1 1",
+                3,
+                "c4b5",
+            ),
+            (
+                "r1b3kr/ppp1Bp1p/1b6/n2P4/2p3q1/2Q2N2/P4PPP/RN2R1K1 w - - 1 1",
+                3,
+                "c3h8",
+            ),
+        ];
+
+        for (fen, mate_moves, solution) in puzzles {
+            let mut engine = Engine::initialise();
+            let fen_parts: Vec<&str> = fen.split_whitespace().collect();
+            engine.load_fen(&fen_parts).unwrap();
+            engine.set_search_limits(Some(NODE_BUDGET), &TimeControl::initialise());
+            engine.set_mate_search(Some(mate_moves));
 
-    for mv in move_list.vec() {
-        let mut game_clone = game.clone();
-        let move_result = game_clone.make_move(mv, attack_tables);
+            let best_move = engine.search_best_move(64).ok();
 
-        if move_result.is_err() {
-            continue;
+            assert_eq!(
+                best_move.map(|mv| mv.as_string()),
+                Some(solution.to_string()),
+                "{}",
+                fen
+            );
         }
-
-        _perft(&game_clone, attack_tables, nodes, depth - 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn tactical_positions() {
+        let tactics = [
+            ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"),
+            ("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1", "b5c7"),
+            ("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1", "d1d5"),
+            ("4k3/8/8/3q4/8/8/8/3R2K1 b - - 0 1", "d5d1"),
+        ];
+
+        for (fen, tactic) in tactics {
+            let mut engine = Engine::initialise();
+            let fen: Vec<&str> = fen.split_whitespace().collect();
+            engine.load_fen(&fen).unwrap();
+
+            let best_move = engine.search_best_move(5).unwrap();
+
+            assert_eq!(best_move.as_string(), tactic);
+        }
+
+        // Capturing the defended pawn loses the knight, so is pruned from the quiescence search
+        let mut engine = Engine::initialise();
+        let fen = vec!["4k3/p7/3p4/4p3/8/5N2/8/4K3", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+
+        let best_move = engine.search_best_move(5).unwrap();
+
+        assert_ne!(best_move.as_string(), "f3e5");
+    }
 
     #[test]
-    #[ignore]
-    fn perft_start_position() {
-        let mut game = Game::initialise();
-        let fen = vec!["startpos"];
-        game.load_fen(&fen).unwrap();
+    fn quiescence_search_pruning() {
+        let mut engine = Engine::initialise();
+        let fen = vec!["4k3/P7/8/8/8/8/8/4K3", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        let mut game = engine.game.clone();
+        let stand_pat = engine.static_evaluation(&game);
+
+        // A promotion is searched even though it captures nothing
+        let evaluation = engine.quiescence_search(&mut game, EvaluationLimits::initialise(), 1);
+
+        assert!(evaluation > stand_pat, "{:?} {:?}", evaluation, stand_pat);
+        assert_eq!(
+            engine.quiescence_search(
+                &mut game,
+                EvaluationLimits::initialise(),
+                engine::MAX_PLY as Value
+            ),
+            stand_pat
+        );
+
+        // Taking the pawn cannot come close to the lower bound, so is not searched
+        let fen = vec!["4k3/8/8/3p4/4P3/8/8/4K3", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        let mut game = engine.game.clone();
+        let stand_pat = engine.static_evaluation(&game);
+        let evaluation_limits = EvaluationLimits {
+            min: stand_pat + 500,
+            max: stand_pat + 600,
+        };
+        engine.search_parameters.nodes_searched = 0;
 
-        let mut nodes = 0;
-        let attack_tables = AttackTables::initialise();
-        _perft(&game, &attack_tables, &mut nodes, 6);
+        assert_eq!(
+            engine.quiescence_search(&mut game, evaluation_limits, 1),
+            evaluation_limits.min
+        );
+        assert_eq!(engine.search_parameters.nodes_searched, 1);
 
-        assert_eq!(nodes, 119_060_324);
+        engine.search_parameters.nodes_searched = 0;
+        engine.quiescence_search(&mut game, EvaluationLimits::initialise(), 1);
+
+        assert!(engine.search_parameters.nodes_searched > 1);
     }
 
     #[test]
-    #[ignore]
-    fn perft_tricky_position() {
-        let mut game = Game::initialise();
+    fn null_move_safeguards() {
+        let mut engine = Engine::initialise();
         let fen = vec![
             "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R",
             "w",
@@ -564,32 +2044,256 @@ mod tests {
             "0",
             "1",
         ];
-        game.load_fen(&fen).unwrap();
+        engine.load_fen(&fen).unwrap();
+        let mut game = engine.game.clone();
+
+        for depth in 1..=7 {
+            engine.negamax_search(&mut game, EvaluationLimits::initialise(), 0, depth);
+        }
 
-        let mut nodes = 0;
-        let attack_tables = AttackTables::initialise();
-        _perft(&game, &attack_tables, &mut nodes, 5);
+        let re_searches = engine.search_parameters.re_searches;
 
-        assert_eq!(nodes, 193_690_690);
+        assert!(re_searches.null_move_verifications > 0);
+        assert!(re_searches.late_move_reductions > 0);
+        assert_eq!(engine.search_parameters.root_depth, 7);
     }
 
     #[test]
-    fn one_move_checkmate_white() {
+    fn lazy_smp_search() {
         let mut engine = Engine::initialise();
+        engine.set_option("Threads", "4").unwrap();
         let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "w", "-", "-", "0", "1"];
         engine.load_fen(&fen).unwrap();
 
-        let best_move = engine.search_best_move(5).unwrap();
+        let best_move = engine.search_best_move(6).unwrap();
 
         assert_eq!(best_move.as_string(), "b4e7");
 
+        let fen = vec!["startpos"];
+        engine.load_fen(&fen).unwrap();
+        engine.set_search_moves(&["e2e4"]).unwrap();
+
+        let best_move = engine.search_best_move(5).unwrap();
+
+        assert_eq!(best_move.as_string(), "e2e4");
+    }
+
+    #[test]
+    fn easy_move_detection() {
+        for (fen, easy_move) in [
+            ("4k3/ppp5/8/8/3q4/5N2/PPP5/4K3 w - - 0 1", true),
+            ("startpos", false),
+        ] {
+            let mut engine = Engine::initialise();
+            let fen: Vec<&str> = fen.split_whitespace().collect();
+            engine.load_fen(&fen).unwrap();
+            let mut game = engine.game.clone();
+            let mut evaluation = EvaluationLimits::initialise().min;
+
+            for depth in 1..=EASY_MOVE_DEPTH_MIN {
+                evaluation =
+                    engine.negamax_search(&mut game, EvaluationLimits::initialise(), 0, depth);
+            }
+
+            assert_eq!(
+                engine.is_easy_move(&mut game, EASY_MOVE_DEPTH_MIN, evaluation),
+                easy_move,
+                "{:?}",
+                fen
+            );
+            assert!(engine.search_parameters.excluded_root_moves.is_empty());
+        }
+    }
+
+    #[test]
+    fn strength_limited_search() {
+        let limited_search = |elo: &str, seed: &str| {
+            let mut engine = Engine::initialise();
+            engine.set_option("UCI_LimitStrength", "true").unwrap();
+            engine.set_option("UCI_Elo", elo).unwrap();
+            engine.set_option("Seed", seed).unwrap();
+            engine.set_option("Threads", "4").unwrap();
+            let fen: Vec<&str> =
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+                    .split_whitespace()
+                    .collect();
+            engine.load_fen(&fen).unwrap();
+            engine.set_search_limits(None, &TimeControl::initialise());
+            let best_move = engine.search_best_move(engine::MAX_PLY as u8).unwrap();
+
+            (
+                best_move,
+                engine.search_parameters.last_search_nodes,
+                engine.search_parameters.last_search_depth,
+            )
+        };
+
+        assert_eq!(limited_search("1000", "1").2, 1);
+        assert_eq!(limited_search("1750", "7"), limited_search("1750", "7"));
+        assert!(limited_search("1750", "7").1 <= 10_000 + SEARCH_CHECK_INTERVAL_NODES);
+    }
+
+    #[test]
+    fn skill_level_move_choice() {
+        let skill_search = |skill_level: &str, seed: u32| {
+            let mut engine = Engine::initialise();
+            engine.set_option("Skill Level", skill_level).unwrap();
+            engine.set_option("Seed", &seed.to_string()).unwrap();
+            engine.load_fen(&["startpos"]).unwrap();
+
+            engine.search_best_move(4).unwrap().as_string()
+        };
+        let full_strength_move = skill_search("20", 1);
+        let weakened_moves: Vec<String> = (1..=8).map(|seed| skill_search("0", seed)).collect();
+
+        assert!((1..=8).all(|seed| skill_search("20", seed) == full_strength_move));
+        assert_eq!(skill_search("0", 3), weakened_moves[2]);
+        assert!(
+            weakened_moves.iter().any(|mv| *mv != full_strength_move),
+            "{:?}",
+            weakened_moves
+        );
+    }
+
+    #[test]
+    fn contempt_draw_evaluation() {
+        let mut engine = Engine::initialise();
+        engine.set_option("Contempt", "30").unwrap();
+        let stalemate: Vec<&str> = "k7/2K5/1Q6/8/8/8/8/8 b - - 0 1"
+            .split_whitespace()
+            .collect();
+        engine.load_fen(&stalemate).unwrap();
+        engine.set_search_limits(None, &TimeControl::initialise());
+        let mut game = engine.game.clone();
+
+        assert_eq!(
+            engine
+                .negamax_search(&mut game, EvaluationLimits::initialise(), 0, 1)
+                .value(),
+            -30
+        );
+
+        // Reached with the engine's opponent to move, the draw is good for the side to move
+        engine.load_fen(&["startpos"]).unwrap();
+        engine.set_search_limits(None, &TimeControl::initialise());
+
+        assert_eq!(
+            engine
+                .negamax_search(&mut game, EvaluationLimits::initialise(), 0, 1)
+                .value(),
+            30
+        );
+    }
+
+    #[test]
+    fn repetition_detection() {
         let mut engine = Engine::initialise();
+        engine.load_fen(&["startpos"]).unwrap();
+
+        for mv in ["g1f3", "g8f6", "f3g1"] {
+            engine.make_move(mv).unwrap();
+        }
+
+        let game = engine.game.clone();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let mut repeated_game = game.clone();
+        let mv = move_list.find_move_from_string("f6g8").unwrap();
+        repeated_game.make_move(&mv).unwrap();
+        engine.search_parameters.position_keys[0] = game.zobrist_key();
+
+        assert!(engine.is_repetition(&repeated_game, 1));
+
+        // A pawn move since the earlier position rules out a repetition
+        let mut new_game = game.clone();
+        let mv = move_list.find_move_from_string("e7e5").unwrap();
+        new_game.make_move(&mv).unwrap();
+
+        assert!(!engine.is_repetition(&new_game, 1));
+
+        // A draw is the best result there is with enough contempt against the opponent, so the
+        // engine repeats the position
+        engine.set_option("Contempt", "-100").unwrap();
+        engine.set_search_limits(None, &TimeControl::initialise());
+
+        assert_eq!(engine.search_best_move(4).unwrap().as_string(), "f6g8");
+    }
+
+    #[test]
+    fn multi_pv_lines() {
+        let mut engine = Engine::initialise();
+        engine.set_option("MultiPV", "3").unwrap();
         let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "w", "-", "-", "0", "1"];
         engine.load_fen(&fen).unwrap();
+        let mut game = engine.game.clone();
 
-        let best_move = engine.search_best_move(6).unwrap();
+        let mut lines = Vec::new();
 
-        assert_eq!(best_move.as_string(), "b4e7");
+        for depth in 1..=3 {
+            let evaluation =
+                engine.negamax_search(&mut game, EvaluationLimits::initialise(), 0, depth);
+            lines = vec![RootLine {
+                evaluation,
+                principal_variation: engine.search_parameters.principal_variation.clone(),
+            }];
+            lines.extend(engine.search_secondary_lines(&mut game, depth, 3));
+            lines = engine
+                .search_parameters
+                .multi_principal_variation
+                .order_lines(lines);
+        }
+
+        let root_moves: Vec<String> = lines
+            .iter()
+            .map(|line| {
+                line.principal_variation.table[0][0]
+                    .as_ref()
+                    .unwrap()
+                    .as_string()
+            })
+            .collect();
+
+        assert_eq!(root_moves.len(), 3);
+        assert_eq!(root_moves[0], "b4e7");
+        assert!(!root_moves[1..].contains(&root_moves[0]));
+        assert_ne!(root_moves[1], root_moves[2]);
+        assert!(lines[0].evaluation > lines[1].evaluation);
+        assert!(lines[1].evaluation >= lines[2].evaluation);
+        assert!(engine.search_parameters.excluded_root_moves.is_empty());
+    }
+
+    #[test]
+    fn search_moves_restrict_root() {
+        let mut engine = Engine::initialise();
+        let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        engine.set_search_moves(&["b4b5", "f6f5"]).unwrap();
+
+        let best_move = engine.search_best_move(3).unwrap().as_string();
+
+        assert!(best_move == "b4b5" || best_move == "f6f5");
+        assert!(engine.set_search_moves(&["f6e7"]).is_err());
+    }
+
+    #[test]
+    fn mate_scores() {
+        let mut engine = Engine::initialise();
+        let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        let mut game = engine.game.clone();
+
+        let evaluation = engine.negamax_search(&mut game, EvaluationLimits::initialise(), 0, 3);
+
+        assert_eq!(evaluation.uci_score(), "mate 1");
+
+        let fen = vec!["4k3/8/5K2/8/1Q6/8/8/8", "b", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        engine.make_move("e8d8").unwrap();
+        engine.make_move("b4b7").unwrap();
+        let mut game = engine.game.clone();
+
+        let evaluation = engine.negamax_search(&mut game, EvaluationLimits::initialise(), 0, 4);
+
+        assert_eq!(evaluation.uci_score(), "mate -1");
     }
 
     #[test]
@@ -670,6 +2374,32 @@ mod tests {
         assert!(possible_best_moves.contains(&best_move.as_string().as_str()));
     }
 
+    #[test]
+    fn current_move_reporting() {
+        let (output, buffer) = uci::captured_output();
+        let mut engine = Engine::initialise();
+        engine.load_fen(&["startpos"]).unwrap();
+        let mut search_parameters = SearchParameters::initialise(1);
+        let mv = engine.legal_moves(&engine.game).remove(0);
+        search_parameters.print_current_move(&output, &mv, 1);
+
+        assert!(buffer.lines().is_empty());
+
+        search_parameters.search_start_time = Instant::now() - CURRMOVE_REPORT_TIME;
+        search_parameters.silent = true;
+        search_parameters.print_current_move(&output, &mv, 2);
+
+        assert!(buffer.lines().is_empty());
+
+        search_parameters.silent = false;
+        search_parameters.print_current_move(&output, &mv, 3);
+
+        assert_eq!(
+            buffer.lines(),
+            [format!("info currmove {} currmovenumber 3", mv.as_string())]
+        );
+    }
+
     #[test]
     fn zugzwang_black() {
         let mut engine = Engine::initialise();
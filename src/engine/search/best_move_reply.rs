@@ -0,0 +1,77 @@
+use crate::engine::moves::Move;
+use std::{
+    sync::{Condvar, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+// The best move of a search so far, shared with the watchdog which replies in place of a search
+// overrunning the clock - whichever sends a best move first is the only one to send it
+#[derive(Default)]
+pub struct BestMoveReply {
+    best_move: Mutex<Option<Move>>,
+    sent: Mutex<bool>,
+    sent_condvar: Condvar,
+}
+
+impl BestMoveReply {
+    pub fn update(&self, mv: &Move) {
+        *lock(&self.best_move) = Some(*mv);
+    }
+
+    pub fn best_move(&self) -> Option<Move> {
+        *lock(&self.best_move)
+    }
+
+    // Returns whether the caller is the first to send a best move, so should go ahead with it
+    pub fn send(&self) -> bool {
+        let mut sent = lock(&self.sent);
+
+        if *sent {
+            return false;
+        }
+
+        *sent = true;
+        self.sent_condvar.notify_all();
+
+        true
+    }
+
+    // Returns whether a best move was sent before the timeout
+    pub fn wait_for_send(&self, timeout: Duration) -> bool {
+        let sent = lock(&self.sent);
+        let (sent, _) = self
+            .sent_condvar
+            .wait_timeout_while(sent, timeout, |sent| !*sent)
+            .unwrap_or_else(PoisonError::into_inner);
+
+        *sent
+    }
+}
+
+// Neither lock guards anything which could be left half updated by a panic
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::Arc, thread, time::Instant};
+
+    #[test]
+    fn sent_once() {
+        let best_move_reply = Arc::new(BestMoveReply::default());
+
+        assert!(best_move_reply.best_move().is_none());
+        assert!(!best_move_reply.wait_for_send(Duration::from_millis(1)));
+
+        let waiting_reply = Arc::clone(&best_move_reply);
+        let start_time = Instant::now();
+        let waiter = thread::spawn(move || waiting_reply.wait_for_send(Duration::from_secs(10)));
+
+        assert!(best_move_reply.send());
+        assert!(!best_move_reply.send());
+        assert!(waiter.join().unwrap());
+        assert!(start_time.elapsed() < Duration::from_secs(5));
+    }
+}
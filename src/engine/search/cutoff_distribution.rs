@@ -0,0 +1,69 @@
+use std::fmt::Display;
+
+// Cutoffs by the first few moves are counted apart, as good move ordering finds most of them with
+// the first move
+const COUNTED_MOVE_NUMBERS: usize = 3;
+
+// Beta cutoffs of the main search by how many moves were searched before the cutoff, counted over
+// a single iteration
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CutoffDistribution {
+    move_numbers: [u64; COUNTED_MOVE_NUMBERS],
+    later: u64,
+}
+
+impl CutoffDistribution {
+    // The move number of the move which cut off, counting from one
+    pub fn record(&mut self, move_number: i32) {
+        match self.move_numbers.get_mut(move_number as usize - 1) {
+            Some(cutoffs) => *cutoffs += 1,
+            None => self.later += 1,
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.move_numbers.iter().sum::<u64>() + self.later
+    }
+
+    // Share of the cutoffs made by the first move searched
+    pub fn first_move_percentage(&self) -> u64 {
+        self.move_numbers[0] * 100 / self.total().max(1)
+    }
+}
+
+impl Display for CutoffDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cutoffs {} first {} ({}%) second {} third {} later {}",
+            self.total(),
+            self.move_numbers[0],
+            self.first_move_percentage(),
+            self.move_numbers[1],
+            self.move_numbers[2],
+            self.later
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribute_cutoffs() {
+        let mut cutoffs = CutoffDistribution::default();
+
+        assert_eq!(cutoffs.first_move_percentage(), 0);
+
+        for move_number in [1, 1, 1, 2, 3, 4, 9, 1] {
+            cutoffs.record(move_number);
+        }
+
+        assert_eq!(cutoffs.first_move_percentage(), 50);
+        assert_eq!(
+            cutoffs.to_string(),
+            "cutoffs 8 first 4 (50%) second 1 third 1 later 2"
+        );
+    }
+}
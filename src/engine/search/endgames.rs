@@ -0,0 +1,359 @@
+use super::{evaluation::Value, tapered_value::TaperedValue};
+use crate::engine::game::{Game, Piece, Side, Square};
+use strum::IntoEnumIterator;
+
+// Evaluations are scaled in steps of this, so a scale of half this halves the evaluation
+pub const SCALE_MAX: Value = 64;
+
+const KPK_WIN_VALUE: TaperedValue = TaperedValue::new(400, 400);
+const KPK_BLOCKED_SCALE: Value = 16;
+const KBNK_CORNER_DISTANCE_VALUE: Value = 20;
+const KBNK_KING_PROXIMITY_VALUE: Value = 5;
+const OPPOSITE_COLOURED_BISHOPS_SCALE: Value = 32;
+const BOARD_DISTANCE_MAX: usize = 7;
+const LIGHT_CORNERS: [Square; 2] = [Square::A8, Square::H1];
+const DARK_CORNERS: [Square; 2] = [Square::A1, Square::H8];
+
+// Endgames with known outcomes or known drawing tendencies, recognised from the material left on
+// the board, along with the side playing for the win where there is one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endgame {
+    KingPawnKing(Side),
+    KingBishopKnightKing(Side),
+    BishopRookPawns(Side),
+    OppositeColouredBishops,
+}
+
+// A bonus for the side playing for the win, and the scale the whole evaluation is multiplied by
+#[derive(Debug, PartialEq)]
+pub struct EndgameAdjustment {
+    pub side: Side,
+    pub value: TaperedValue,
+    pub scale: Value,
+}
+
+impl Endgame {
+    pub fn recognise(game: &Game) -> Option<Self> {
+        let piece_counts = [Side::White, Side::Black].map(|side| piece_counts(game, side));
+
+        for side in [Side::White, Side::Black] {
+            let counts = piece_counts[side as usize];
+            let opponent_counts = piece_counts[side.opponent_side() as usize];
+
+            if opponent_counts != [0, 0, 0, 0, 0, 1] {
+                continue;
+            }
+
+            match counts {
+                [1, 0, 0, 0, 0, 1] => return Some(Self::KingPawnKing(side)),
+                [0, 1, 1, 0, 0, 1] => return Some(Self::KingBishopKnightKing(side)),
+                [pawns, 0, 1, 0, 0, 1] if pawns > 0 && rook_pawns_only(game, side) => {
+                    return Some(Self::BishopRookPawns(side))
+                }
+                _ => {}
+            }
+        }
+
+        let bishops_only = piece_counts
+            .iter()
+            .all(|counts| counts[1..] == [0, 1, 0, 0, 1]);
+
+        if bishops_only {
+            let [white_bishop, black_bishop] = [Side::White, Side::Black]
+                .map(|side| game.piece_bitboard(Piece::Bishop, side).get_lsb_square());
+
+            if let (Some(white_bishop), Some(black_bishop)) = (white_bishop, black_bishop) {
+                if is_light_square(white_bishop) != is_light_square(black_bishop) {
+                    return Some(Self::OppositeColouredBishops);
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::KingPawnKing(_) => "KPK",
+            Self::KingBishopKnightKing(_) => "KBNK",
+            Self::BishopRookPawns(_) => "KBPK with rook pawns",
+            Self::OppositeColouredBishops => "Opposite coloured bishops",
+        }
+    }
+
+    pub fn adjustment(self, game: &Game) -> EndgameAdjustment {
+        match self {
+            Self::KingPawnKing(side) => king_pawn_king(game, side),
+            Self::KingBishopKnightKing(side) => king_bishop_knight_king(game, side),
+            Self::BishopRookPawns(side) => bishop_rook_pawns(game, side),
+            Self::OppositeColouredBishops => EndgameAdjustment {
+                side: Side::White,
+                value: TaperedValue::default(),
+                scale: OPPOSITE_COLOURED_BISHOPS_SCALE,
+            },
+        }
+    }
+}
+
+// The pawn wins if the king stands on one of its key squares without the pawn being lost, and is
+// held to a draw if the defending king gets in front of it first, or reaches the corner in front
+// of a rook pawn
+fn king_pawn_king(game: &Game, side: Side) -> EndgameAdjustment {
+    let mut adjustment = EndgameAdjustment {
+        side,
+        value: TaperedValue::default(),
+        scale: SCALE_MAX,
+    };
+    let (Some(pawn_square), Some(king_square), Some(opponent_king_square)) = (
+        game.piece_bitboard(Piece::Pawn, side).get_lsb_square(),
+        game.piece_bitboard(Piece::King, side).get_lsb_square(),
+        game.piece_bitboard(Piece::King, side.opponent_side())
+            .get_lsb_square(),
+    ) else {
+        return adjustment;
+    };
+    let promotion_square = promotion_square(side, pawn_square.file());
+
+    if pawn_square.file() == 0 || pawn_square.file() == 7 {
+        if opponent_king_square.distance(promotion_square) <= 1 {
+            adjustment.scale = 0;
+        }
+
+        return adjustment;
+    }
+
+    let pawn_lost = game.side_to_move() != side
+        && opponent_king_square.distance(pawn_square) == 1
+        && king_square.distance(pawn_square) > 1;
+    let on_key_square = key_squares(side, pawn_square).contains(&king_square);
+    let opponent_king_in_front = opponent_king_square.file() == pawn_square.file()
+        && relative_rank(side, opponent_king_square) > relative_rank(side, pawn_square);
+
+    if on_key_square && !pawn_lost {
+        adjustment.value = KPK_WIN_VALUE;
+    } else if opponent_king_in_front {
+        adjustment.scale = KPK_BLOCKED_SCALE;
+    }
+
+    adjustment
+}
+
+// Squares which win for the king standing on them, whoever is to move, two ranks in front of the
+// pawn, or one and two ranks in front once it has crossed into the opponent's half
+fn key_squares(side: Side, pawn_square: Square) -> Vec<Square> {
+    let pawn_rank = relative_rank(side, pawn_square);
+    let rank_offsets = match pawn_rank {
+        0..=3 => 2..=2,
+        _ => 1..=2,
+    };
+    let mut key_squares = Vec::new();
+
+    for rank_offset in rank_offsets {
+        let rank = pawn_rank + rank_offset;
+
+        if rank > 7 {
+            continue;
+        }
+
+        for file in pawn_square.file() - 1..=pawn_square.file() + 1 {
+            key_squares.push(from_relative_rank_file(side, rank, file));
+        }
+    }
+
+    key_squares
+}
+
+// The mate can only be forced in a corner the bishop covers, so the defending king is driven to
+// the nearest of those
+fn king_bishop_knight_king(game: &Game, side: Side) -> EndgameAdjustment {
+    let mut adjustment = EndgameAdjustment {
+        side,
+        value: TaperedValue::default(),
+        scale: SCALE_MAX,
+    };
+    let (Some(bishop_square), Some(king_square), Some(opponent_king_square)) = (
+        game.piece_bitboard(Piece::Bishop, side).get_lsb_square(),
+        game.piece_bitboard(Piece::King, side).get_lsb_square(),
+        game.piece_bitboard(Piece::King, side.opponent_side())
+            .get_lsb_square(),
+    ) else {
+        return adjustment;
+    };
+    let corners = match is_light_square(bishop_square) {
+        true => LIGHT_CORNERS,
+        false => DARK_CORNERS,
+    };
+    let corner_distance = corners
+        .iter()
+        .map(|&corner| opponent_king_square.distance(corner))
+        .min()
+        .unwrap_or(BOARD_DISTANCE_MAX);
+    let king_distance = king_square.distance(opponent_king_square);
+    let value = (BOARD_DISTANCE_MAX - corner_distance) as Value * KBNK_CORNER_DISTANCE_VALUE
+        + (BOARD_DISTANCE_MAX - king_distance) as Value * KBNK_KING_PROXIMITY_VALUE;
+    adjustment.value = TaperedValue::new(value, value);
+
+    adjustment
+}
+
+// Rook pawns with a bishop which does not cover the promotion square cannot win once the
+// defending king reaches the corner
+fn bishop_rook_pawns(game: &Game, side: Side) -> EndgameAdjustment {
+    let mut adjustment = EndgameAdjustment {
+        side,
+        value: TaperedValue::default(),
+        scale: SCALE_MAX,
+    };
+    let (Some(pawn_square), Some(bishop_square), Some(opponent_king_square)) = (
+        game.piece_bitboard(Piece::Pawn, side).get_lsb_square(),
+        game.piece_bitboard(Piece::Bishop, side).get_lsb_square(),
+        game.piece_bitboard(Piece::King, side.opponent_side())
+            .get_lsb_square(),
+    ) else {
+        return adjustment;
+    };
+    let promotion_square = promotion_square(side, pawn_square.file());
+    let wrong_bishop = is_light_square(bishop_square) != is_light_square(promotion_square);
+
+    if wrong_bishop && opponent_king_square.distance(promotion_square) <= 1 {
+        adjustment.scale = 0;
+    }
+
+    adjustment
+}
+
+// Indexed by the Piece enum
+fn piece_counts(game: &Game, side: Side) -> [u32; 6] {
+    let mut piece_counts = [0; 6];
+
+    for piece in Piece::iter() {
+        piece_counts[piece as usize] = game.piece_bitboard(piece, side).count_bits();
+    }
+
+    piece_counts
+}
+
+// All of the side's pawns are on the same rook file
+fn rook_pawns_only(game: &Game, side: Side) -> bool {
+    let pawns = game.piece_bitboard(Piece::Pawn, side);
+    let mut files = Vec::new();
+
+    for pawn_square in pawns.squares() {
+        files.push(pawn_square.file());
+    }
+
+    files.iter().all(|&file| file == 0) || files.iter().all(|&file| file == 7)
+}
+
+fn is_light_square(square: Square) -> bool {
+    (square.rank() + square.file()).is_multiple_of(2)
+}
+
+// Rank counted from the side's own back rank
+fn relative_rank(side: Side, square: Square) -> usize {
+    match side {
+        Side::White => 7 - square.rank(),
+        Side::Black => square.rank(),
+    }
+}
+
+fn from_relative_rank_file(side: Side, relative_rank: usize, file: usize) -> Square {
+    match side {
+        Side::White => Square::from_rank_file(7 - relative_rank, file),
+        Side::Black => Square::from_rank_file(relative_rank, file),
+    }
+}
+
+fn promotion_square(side: Side, file: usize) -> Square {
+    from_relative_rank_file(side, 7, file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(fen: &str, side_to_move: &str) -> Game {
+        let mut game = Game::initialise();
+        game.load_fen(&[fen, side_to_move, "-", "-", "0", "1"])
+            .unwrap();
+
+        game
+    }
+
+    #[test]
+    fn recognised_endgames() {
+        let recognise = |fen| Endgame::recognise(&game(fen, "w"));
+
+        assert_eq!(
+            recognise("4k3/8/8/8/8/8/4P3/4K3"),
+            Some(Endgame::KingPawnKing(Side::White))
+        );
+        assert_eq!(
+            recognise("4k3/8/8/8/8/8/8/1n2K1b1"),
+            Some(Endgame::KingBishopKnightKing(Side::Black))
+        );
+        assert_eq!(
+            recognise("4k3/8/8/P7/P7/8/8/2B1K3"),
+            Some(Endgame::BishopRookPawns(Side::White))
+        );
+        assert_eq!(
+            recognise("4k3/1b3p2/8/8/8/8/4P3/2B1K3"),
+            Some(Endgame::OppositeColouredBishops)
+        );
+        assert_eq!(recognise("4k3/8/8/P7/1P6/8/8/2B1K3"), None);
+        assert_eq!(recognise("4k3/2b2p2/8/8/8/8/4P3/2B1K3"), None);
+        assert_eq!(recognise("r3k3/8/8/8/8/8/8/R3K3"), None);
+    }
+
+    #[test]
+    fn king_pawn_king_rules() {
+        let adjustment = |fen, side_to_move| king_pawn_king(&game(fen, side_to_move), Side::White);
+
+        let key_square = adjustment("8/8/4k3/8/3K4/8/4P3/8", "b");
+
+        assert_eq!(key_square.value, KPK_WIN_VALUE);
+        assert_eq!(key_square.scale, SCALE_MAX);
+
+        let blocked = adjustment("8/4k3/8/8/8/8/4P3/4K3", "w");
+
+        assert_eq!(blocked.value, TaperedValue::default());
+        assert_eq!(blocked.scale, KPK_BLOCKED_SCALE);
+
+        let pawn_lost = adjustment("8/3K4/8/4Pk2/8/8/8/8", "b");
+
+        assert_eq!(pawn_lost.value, TaperedValue::default());
+
+        let rook_pawn_corner = adjustment("k7/8/8/8/P7/8/8/4K3", "w");
+
+        assert_eq!(rook_pawn_corner.scale, 0);
+    }
+
+    #[test]
+    fn wrong_bishop_rook_pawn_draw() {
+        let wrong_bishop = game("k7/8/8/P7/8/8/8/2B1K3", "w");
+        let right_bishop = game("k7/8/8/P7/8/8/8/4KB2", "w");
+        let king_away = game("8/8/4k3/P7/8/8/8/2B1K3", "w");
+
+        assert_eq!(bishop_rook_pawns(&wrong_bishop, Side::White).scale, 0);
+        assert_eq!(
+            bishop_rook_pawns(&right_bishop, Side::White).scale,
+            SCALE_MAX
+        );
+        assert_eq!(bishop_rook_pawns(&king_away, Side::White).scale, SCALE_MAX);
+    }
+
+    #[test]
+    fn bishop_knight_corner() {
+        let right_corner = game("7k/8/5K2/8/8/8/8/3NB3", "w");
+        let wrong_corner = game("k7/8/2K5/8/8/8/8/3NB3", "w");
+
+        assert!(
+            king_bishop_knight_king(&right_corner, Side::White)
+                .value
+                .endgame()
+                > king_bishop_knight_king(&wrong_corner, Side::White)
+                    .value
+                    .endgame()
+        );
+    }
+}
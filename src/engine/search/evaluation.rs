@@ -1,106 +1,617 @@
-use super::Engine;
-use crate::engine::game::{Game, Piece, Side, Square};
-use std::ops::{Add, Neg, Sub};
+use super::{
+    endgames::{self, Endgame},
+    pawn_masks, piece_square_tables,
+    tapered_value::{TaperedValue, PHASE_MAX},
+    Engine,
+};
+use crate::engine::{
+    self,
+    game::{Game, Piece, Side, Square},
+};
+use std::{
+    fmt::Display,
+    ops::{Add, Neg, Sub},
+};
 
 pub type Value = i32;
 
 pub const MAX_EVALUATION: Evaluation = Evaluation(50000);
 pub const CHECKMATE_EVALUATION: Evaluation = Evaluation(49000);
 pub const STALEMATE_EVALUATION: Evaluation = Evaluation(0);
+// Tablebase wins are scored below every mate score but above any evaluation, less the ply they are
+// reached at so the search still heads for the nearest one
+#[cfg(feature = "syzygy")]
+pub const TABLEBASE_WIN_EVALUATION: Evaluation = Evaluation(48000);
 
 // Piece value obtained by indexing into array using Piece enum
-const PIECE_VALUE: [Value; 6] = [100, 300, 350, 500, 900, 0];
-
-#[rustfmt::skip]
-const PAWN_POSITION_VALUE: PositionValue = PositionValue([
-     0,  0,  0,   0,   0,  0,  0,  0,
-    30, 30, 30,  40,  40, 30, 30, 30,
-    20, 20, 20,  30,  30, 30, 20, 20,
-    10, 10, 10,  20,  20, 10, 10, 10,
-     5,  5, 10,  20,  20,  5,  5,  5,
-     0,  0,  0,   5,   5,  0,  0,  0,
-     0,  0,  0, -10, -10,  0,  0,  0,
-     0,  0,  0,   0,   0,  0,  0,  0,
-]);
-#[rustfmt::skip]
-const KNIGHT_POSITION_VALUE: PositionValue = PositionValue([
-    -5,   0,  0,  0,  0,  0,   0, -5,
-    -5,   0,  0, 10, 10,  0,   0, -5,
-    -5,   5, 20, 20, 20, 20,   5, -5,
-    -5,  10, 20, 30, 30, 20,  10, -5,
-    -5,  10, 20, 30, 30, 20,  10, -5,
-    -5,   5, 20, 10, 10, 20,   5, -5,
-    -5,   0,  0,  0,  0,  0,   0, -5,
-    -5, -10,  0,  0,  0,  0, -10, -5,
-]);
-#[rustfmt::skip]
-const BISHOP_POSITION_VALUE: PositionValue = PositionValue([
-    0,  0,   0,  0,  0,   0,  0, 0,
-    0,  0,   0,  0,  0,   0,  0, 0,
-    0,  0,   0, 10, 10,   0,  0, 0,
-    0,  0,  10, 20, 20,  10,  0, 0,
-    0,  0,  10, 20, 20,  10,  0, 0,
-    0, 10,   0,  0,  0,   0, 10, 0,
-    0, 30,   0,  0,  0,   0, 30, 0,
-    0,  0, -10,  0,  0, -10,  0, 0,
-]);
-#[rustfmt::skip]
-const ROOK_POSITION_VALUE: PositionValue = PositionValue([
-    50, 50, 50, 50, 50, 50, 50, 50,
-    50, 50, 50, 50, 50, 50, 50, 50,
-     0,  0, 10, 20, 20, 10,  0,  0,
-     0,  0, 10, 20, 20, 10,  0,  0,
-     0,  0, 10, 20, 20, 10,  0,  0,
-     0,  0, 10, 20, 20, 10,  0,  0,
-     0,  0, 10, 20, 20, 10,  0,  0,
-     0,  0,  0, 20, 20,  0,  0,  0,
-]);
-#[rustfmt::skip]
-const QUEEN_POSITION_VALUE: PositionValue = PositionValue([
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0,
-]);
-#[rustfmt::skip]
-const KING_POSITION_VALUE: PositionValue = PositionValue([
-    0, 0,  0,  0,   0,  0,  0, 0,
-    0, 0,  5,  5,   5,  5,  0, 0,
-    0, 5,  5, 10,  10,  5,  5, 0,
-    0, 5, 10, 20,  20, 10,  5, 0,
-    0, 5, 10, 20,  20, 10,  5, 0,
-    0, 0,  5, 10,  10,  5,  0, 0,
-    0, 5,  5, -5,  -5,  0,  5, 0,
-    0, 0,  5,  0, -15,  0, 10, 0,
-]);
+const PIECE_VALUE: [TaperedValue; 6] = [
+    TaperedValue::new(100, 120),
+    TaperedValue::new(300, 280),
+    TaperedValue::new(350, 330),
+    TaperedValue::new(500, 530),
+    TaperedValue::new(900, 950),
+    TaperedValue::new(0, 0),
+];
+// How much each piece counts towards the game phase, indexed by the Piece enum
+const PHASE_WEIGHT: [Value; 6] = [0, 1, 1, 2, 4, 0];
+
+// King activity is only rewarded once the non-pawn material of both sides combined is at most this
+const ENDGAME_NON_PAWN_MATERIAL_MAX: Value = 1300;
+const KING_CENTRALISATION_VALUE: Value = 10;
+const KING_PASSED_PAWN_PROXIMITY_VALUE: Value = 5;
+const KING_PAWN_MAJORITY_PROXIMITY_VALUE: Value = 5;
+const CENTRE_DISTANCE_MAX: usize = 3;
+const BOARD_DISTANCE_MAX: usize = 7;
+// Files of the queenside and kingside, over which pawn majorities are counted
+const WING_FILES: [(usize, usize); 2] = [(0, 2), (5, 7)];
+const UNSTOPPABLE_PASSER_VALUE: Value = 700;
+// Passed pawn bonus indexed by rank counted from the side's own back rank
+const PASSED_PAWN_VALUE: [TaperedValue; 8] = [
+    TaperedValue::new(0, 0),
+    TaperedValue::new(5, 10),
+    TaperedValue::new(5, 15),
+    TaperedValue::new(10, 25),
+    TaperedValue::new(20, 45),
+    TaperedValue::new(35, 75),
+    TaperedValue::new(55, 110),
+    TaperedValue::new(0, 0),
+];
+const PROTECTED_PASSER_VALUE: TaperedValue = TaperedValue::new(10, 15);
+const CONNECTED_PASSER_VALUE: TaperedValue = TaperedValue::new(10, 20);
+const BLOCKADED_PASSER_PENALTY: TaperedValue = TaperedValue::new(10, 25);
+const BISHOP_PAIR_VALUE: TaperedValue = TaperedValue::new(30, 50);
+const ROOK_OPEN_FILE_VALUE: TaperedValue = TaperedValue::new(25, 10);
+const ROOK_SEMI_OPEN_FILE_VALUE: TaperedValue = TaperedValue::new(10, 5);
+const ROOK_SEVENTH_RANK_VALUE: TaperedValue = TaperedValue::new(10, 20);
+const KNIGHT_OUTPOST_VALUE: TaperedValue = TaperedValue::new(20, 10);
+#[cfg(feature = "nnue")]
+const NNUE_EVALUATION_MAX: Value = 20000;
+// Bonus for the side to move, which is roughly half a move ahead of a position where it is not
+const TEMPO_VALUE: TaperedValue = TaperedValue::new(15, 5);
+const MOP_UP_CENTRE_DISTANCE_VALUE: Value = 20;
+const MOP_UP_KING_PROXIMITY_VALUE: Value = 5;
+const MANHATTAN_DISTANCE_MAX: usize = 14;
 
 impl Engine {
     pub fn evaluate(game: &Game) -> Evaluation {
-        let mut evaluation = Evaluation(0);
-
-        for (mut bitboard, piece, side) in game.piece_bitboards() {
-            let position_value = match piece {
-                Piece::Pawn => PAWN_POSITION_VALUE,
-                Piece::Knight => KNIGHT_POSITION_VALUE,
-                Piece::Bishop => BISHOP_POSITION_VALUE,
-                Piece::Rook => ROOK_POSITION_VALUE,
-                Piece::Queen => QUEEN_POSITION_VALUE,
-                Piece::King => KING_POSITION_VALUE,
+        let evaluation = Self::unchecked_evaluation(game);
+
+        #[cfg(feature = "eval-symmetry-check")]
+        assert_eq!(
+            evaluation,
+            -Self::unchecked_evaluation(&game.colour_flipped()),
+            "Asymmetric evaluation of position:\n{}",
+            game
+        );
+
+        evaluation
+    }
+
+    // A loaded network evaluates instead of the hand-crafted terms, kept short of the mate scores
+    fn unchecked_evaluation(game: &Game) -> Evaluation {
+        #[cfg(feature = "nnue")]
+        if let Some(value) = game.nnue_evaluation() {
+            let value = value.clamp(-NNUE_EVALUATION_MAX, NNUE_EVALUATION_MAX);
+
+            return Evaluation(value).sided_value(game.side_to_move());
+        }
+
+        Self::evaluation_terms(game).total()
+    }
+
+    pub fn print_evaluation(&self) {
+        let evaluation_terms = Self::evaluation_terms(&self.game);
+
+        self.output.line(&evaluation_terms);
+        self.output.line(format!(
+            "Game phase: {}/{}",
+            evaluation_terms.phase, PHASE_MAX
+        ));
+
+        if let Some(endgame) = evaluation_terms.endgame {
+            self.output.line(format!(
+                "Endgame: {} (scale {}/{})",
+                endgame.name(),
+                evaluation_terms.scale,
+                endgames::SCALE_MAX
+            ));
+        }
+
+        #[cfg(feature = "nnue")]
+        if self.game.nnue_evaluation().is_some() {
+            self.output.line(format!(
+                "Hand-crafted evaluation: {} (white side)",
+                evaluation_terms.total().uci_score()
+            ));
+        }
+
+        self.output.line(format!(
+            "Final evaluation: {} (white side)",
+            Self::evaluate(&self.game).uci_score()
+        ));
+    }
+
+    fn evaluation_terms(game: &Game) -> EvaluationTerms {
+        let mut terms = EvaluationTerms {
+            phase: Self::game_phase(game),
+            scale: endgames::SCALE_MAX,
+            ..Default::default()
+        };
+
+        for (bitboard, piece, side) in game.piece_bitboards() {
+            for square in bitboard.squares() {
+                terms.material[side as usize] += PIECE_VALUE[piece as usize];
+                terms.piece_squares[side as usize] +=
+                    piece_square_tables::value(piece, side, square);
+            }
+        }
+
+        for side in [Side::White, Side::Black] {
+            terms.passed_pawns[side as usize] = Self::passed_pawns(game, side);
+            terms.bishop_pair[side as usize] = Self::bishop_pair(game, side);
+            terms.rook_files[side as usize] = Self::rook_files(game, side);
+            terms.rook_seventh_rank[side as usize] = Self::rook_seventh_rank(game, side);
+            terms.knight_outposts[side as usize] = Self::knight_outposts(game, side);
+        }
+
+        let non_pawn_material = Self::non_pawn_material(game);
+
+        if non_pawn_material <= ENDGAME_NON_PAWN_MATERIAL_MAX {
+            for side in [Side::White, Side::Black] {
+                let king_activity = Self::king_activity(game, side);
+                terms.king_activity[side as usize] =
+                    TaperedValue::new(king_activity, king_activity);
+            }
+        }
+
+        if non_pawn_material == 0 {
+            if let Some(side) = Self::pawn_race_winner(game) {
+                terms.pawn_race[side as usize] =
+                    TaperedValue::new(UNSTOPPABLE_PASSER_VALUE, UNSTOPPABLE_PASSER_VALUE);
+            }
+        }
+
+        for side in [Side::White, Side::Black] {
+            let mop_up = Self::mop_up(game, side);
+            terms.mop_up[side as usize] = TaperedValue::new(mop_up, mop_up);
+        }
+
+        terms.tempo[game.side_to_move() as usize] = TEMPO_VALUE;
+        terms.endgame = Endgame::recognise(game);
+
+        if let Some(endgame) = terms.endgame {
+            let adjustment = endgame.adjustment(game);
+            terms.endgame_value[adjustment.side as usize] = adjustment.value;
+            terms.scale = adjustment.scale;
+        }
+
+        terms
+    }
+
+    // Against a bare king, a side with a queen or rook is rewarded for driving the opposing king
+    // towards the edge and bringing its own king closer, so the mate is found
+    fn mop_up(game: &Game, side: Side) -> Value {
+        let opponent_side = side.opponent_side();
+        let opponent_king_bare = game.board(Some(opponent_side)).count_bits() == 1;
+        let has_major_piece = game.piece_bitboard(Piece::Queen, side)
+            | game.piece_bitboard(Piece::Rook, side)
+            != 0u64;
+
+        if !opponent_king_bare || !has_major_piece {
+            return 0;
+        }
+
+        let king_square = game.piece_bitboard(Piece::King, side).get_lsb_square();
+        let opponent_king_square = game
+            .piece_bitboard(Piece::King, opponent_side)
+            .get_lsb_square();
+        let (king_square, opponent_king_square) = match (king_square, opponent_king_square) {
+            (Some(king_square), Some(opponent_king_square)) => (king_square, opponent_king_square),
+            _ => return 0,
+        };
+
+        let opponent_king_centre_distance = Self::centre_distance(opponent_king_square.rank())
+            + Self::centre_distance(opponent_king_square.file());
+        let king_distance = king_square.rank().abs_diff(opponent_king_square.rank())
+            + king_square.file().abs_diff(opponent_king_square.file());
+
+        opponent_king_centre_distance as Value * MOP_UP_CENTRE_DISTANCE_VALUE
+            + (MANHATTAN_DISTANCE_MAX - king_distance) as Value * MOP_UP_KING_PROXIMITY_VALUE
+    }
+
+    fn non_pawn_material(game: &Game) -> Value {
+        let mut non_pawn_material = 0;
+
+        for (bitboard, piece, _) in game.piece_bitboards() {
+            if piece != Piece::Pawn {
+                non_pawn_material +=
+                    PIECE_VALUE[piece as usize].middlegame() * bitboard.count_bits() as Value;
+            }
+        }
+
+        non_pawn_material
+    }
+
+    // Counted down from the starting pieces, with extra pieces from promotions not counted
+    fn game_phase(game: &Game) -> Value {
+        let phase = game
+            .piece_bitboards()
+            .iter()
+            .map(|(bitboard, piece, _)| {
+                PHASE_WEIGHT[*piece as usize] * bitboard.count_bits() as Value
+            })
+            .sum::<Value>();
+
+        phase.min(PHASE_MAX)
+    }
+
+    // With only kings and pawns left, the side able to promote an unstoppable passed pawn first,
+    // using the side to move as the tiebreak, is winning the pawn race
+    fn pawn_race_winner(game: &Game) -> Option<Side> {
+        let white_moves = Self::unstoppable_passer_moves(game, Side::White);
+        let black_moves = Self::unstoppable_passer_moves(game, Side::Black);
+
+        match (white_moves, black_moves) {
+            (Some(_), None) => Some(Side::White),
+            (None, Some(_)) => Some(Side::Black),
+            (Some(white_moves), Some(black_moves)) => {
+                let white_promotes_first = match game.side_to_move() {
+                    Side::White => white_moves <= black_moves,
+                    Side::Black => white_moves < black_moves,
+                };
+
+                match white_promotes_first {
+                    true => Some(Side::White),
+                    false => Some(Side::Black),
+                }
+            }
+            (None, None) => None,
+        }
+    }
+
+    // A passed pawn with a clear path is unstoppable if the opposing king is outside the square
+    // of the pawn, where the king gains a move if it is the opponent to move
+    fn unstoppable_passer_moves(game: &Game, side: Side) -> Option<usize> {
+        let opponent_king_square = game
+            .piece_bitboard(Piece::King, side.opponent_side())
+            .get_lsb_square()?;
+        let opponent_tempo = (game.side_to_move() != side) as usize;
+        let mut unstoppable_passer_moves = None;
+
+        for pawn_square in Self::passed_pawn_squares(game, side) {
+            let (promotion_rank, starting_rank) = match side {
+                Side::White => (0, 6),
+                Side::Black => (7, 1),
             };
+            let promotion_square = Square::from_rank_file(promotion_rank, pawn_square.file());
+            let mut promotion_moves = pawn_square.rank().abs_diff(promotion_rank);
+
+            if pawn_square.rank() == starting_rank {
+                promotion_moves -= 1;
+            }
+
+            let path_clear = (1..=pawn_square.rank().abs_diff(promotion_rank)).all(|rank_offset| {
+                let rank = match side {
+                    Side::White => pawn_square.rank() - rank_offset,
+                    Side::Black => pawn_square.rank() + rank_offset,
+                };
 
-            while let Some(square) = bitboard.get_lsb_square() {
-                evaluation.sided_add(PIECE_VALUE[piece as usize], side);
-                evaluation.sided_add(position_value.value(side, square), side);
+                !game.is_square_occupied(Square::from_rank_file(rank, pawn_square.file()))
+            });
+            let king_distance = opponent_king_square.distance(promotion_square);
 
-                bitboard.pop_bit(square);
+            if path_clear && promotion_moves + opponent_tempo < king_distance {
+                unstoppable_passer_moves = Some(
+                    unstoppable_passer_moves
+                        .map_or(promotion_moves, |moves: usize| moves.min(promotion_moves)),
+                );
             }
         }
 
-        evaluation
+        unstoppable_passer_moves
+    }
+
+    // Rewards a centralised king which is close to the passed pawns of either side, to support or
+    // stop them, and to any wing where one side has a pawn majority
+    fn king_activity(game: &Game, side: Side) -> Value {
+        let king_square = match game.piece_bitboard(Piece::King, side).get_lsb_square() {
+            Some(king_square) => king_square,
+            None => return 0,
+        };
+        let mut king_activity = 0;
+
+        let rank_centre_distance = Self::centre_distance(king_square.rank());
+        let file_centre_distance = Self::centre_distance(king_square.file());
+        let centre_distance = rank_centre_distance.max(file_centre_distance);
+        king_activity +=
+            (CENTRE_DISTANCE_MAX - centre_distance) as Value * KING_CENTRALISATION_VALUE;
+
+        for passed_pawn_side in [Side::White, Side::Black] {
+            for passed_pawn_square in Self::passed_pawn_squares(game, passed_pawn_side) {
+                let distance = king_square.distance(passed_pawn_square);
+                king_activity +=
+                    (BOARD_DISTANCE_MAX - distance) as Value * KING_PASSED_PAWN_PROXIMITY_VALUE;
+            }
+        }
+
+        for (first_file, last_file) in WING_FILES {
+            let white_pawns = Self::wing_pawn_count(game, Side::White, first_file, last_file);
+            let black_pawns = Self::wing_pawn_count(game, Side::Black, first_file, last_file);
+
+            if white_pawns == black_pawns {
+                continue;
+            }
+
+            let king_file = king_square.file();
+            let file_distance = if king_file < first_file {
+                first_file - king_file
+            } else {
+                king_file.saturating_sub(last_file)
+            };
+            king_activity +=
+                (BOARD_DISTANCE_MAX - file_distance) as Value * KING_PAWN_MAJORITY_PROXIMITY_VALUE;
+        }
+
+        king_activity
+    }
+
+    fn centre_distance(rank_or_file: usize) -> usize {
+        match rank_or_file {
+            0..=3 => 3 - rank_or_file,
+            _ => rank_or_file - 4,
+        }
+    }
+
+    // Passed pawns are scored by how far they have advanced, with a bonus when defended by a pawn
+    // or next to another passed pawn, and a penalty when an opposing piece blocks their path
+    fn passed_pawns(game: &Game, side: Side) -> TaperedValue {
+        let opponent_board = game.board(Some(side.opponent_side()));
+        let passed_pawn_squares = Self::passed_pawn_squares(game, side);
+        let mut passed_pawns = TaperedValue::default();
+
+        for &pawn_square in &passed_pawn_squares {
+            let (relative_rank, front_rank) = match side {
+                Side::White => (7 - pawn_square.rank(), pawn_square.rank() - 1),
+                Side::Black => (pawn_square.rank(), pawn_square.rank() + 1),
+            };
+            passed_pawns += PASSED_PAWN_VALUE[relative_rank];
+
+            let is_protected = Self::is_pawn_protected(game, side, pawn_square);
+            let is_connected = passed_pawn_squares.iter().any(|square| {
+                square.file().abs_diff(pawn_square.file()) == 1
+                    && square.rank().abs_diff(pawn_square.rank()) <= 1
+            });
+            let is_blockaded =
+                opponent_board.bit_occupied(Square::from_rank_file(front_rank, pawn_square.file()));
+
+            if is_protected {
+                passed_pawns += PROTECTED_PASSER_VALUE;
+            }
+
+            if is_connected {
+                passed_pawns += CONNECTED_PASSER_VALUE;
+            }
+
+            if is_blockaded {
+                passed_pawns -= BLOCKADED_PASSER_PENALTY;
+            }
+        }
+
+        passed_pawns
+    }
+
+    fn bishop_pair(game: &Game, side: Side) -> TaperedValue {
+        match game.piece_bitboard(Piece::Bishop, side).count_bits() >= 2 {
+            true => BISHOP_PAIR_VALUE,
+            false => TaperedValue::default(),
+        }
+    }
+
+    // A rook is rewarded for a file without pawns of its own side, and more so without any pawns
+    fn rook_files(game: &Game, side: Side) -> TaperedValue {
+        let pawns = game.piece_bitboard(Piece::Pawn, side);
+        let opponent_pawns = game.piece_bitboard(Piece::Pawn, side.opponent_side());
+        let rooks = game.piece_bitboard(Piece::Rook, side);
+        let mut rook_files = TaperedValue::default();
+
+        for rook_square in rooks.squares() {
+            let file_mask = pawn_masks::file_mask(rook_square.file());
+
+            if pawns & file_mask == 0u64 {
+                rook_files += match opponent_pawns & file_mask == 0u64 {
+                    true => ROOK_OPEN_FILE_VALUE,
+                    false => ROOK_SEMI_OPEN_FILE_VALUE,
+                };
+            }
+        }
+
+        rook_files
+    }
+
+    // A rook on the seventh rank only counts if there are opposing pawns there to attack, or the
+    // opposing king is cut off on the back rank
+    fn rook_seventh_rank(game: &Game, side: Side) -> TaperedValue {
+        let (seventh_rank, back_rank) = match side {
+            Side::White => (1, 0),
+            Side::Black => (6, 7),
+        };
+        let opponent_side = side.opponent_side();
+        let seventh_rank_mask = pawn_masks::rank_mask(seventh_rank);
+        let rooks = game.piece_bitboard(Piece::Rook, side) & seventh_rank_mask;
+        let opponent_pawns_on_rank =
+            game.piece_bitboard(Piece::Pawn, opponent_side) & seventh_rank_mask != 0u64;
+        let opponent_king_on_back_rank = game.piece_bitboard(Piece::King, opponent_side)
+            & pawn_masks::rank_mask(back_rank)
+            != 0u64;
+
+        match opponent_pawns_on_rank || opponent_king_on_back_rank {
+            true => ROOK_SEVENTH_RANK_VALUE * rooks.count_bits() as Value,
+            false => TaperedValue::default(),
+        }
+    }
+
+    // An outpost is a square on the fourth to sixth rank defended by a pawn, which no opposing
+    // pawn can ever attack
+    fn knight_outposts(game: &Game, side: Side) -> TaperedValue {
+        let opponent_pawns = game.piece_bitboard(Piece::Pawn, side.opponent_side());
+        let knights = game.piece_bitboard(Piece::Knight, side);
+        let mut knight_outposts = TaperedValue::default();
+
+        for knight_square in knights.squares() {
+            let relative_rank = match side {
+                Side::White => 7 - knight_square.rank(),
+                Side::Black => knight_square.rank(),
+            };
+            let attacking_pawn_mask = pawn_masks::passed_pawn_mask(side, knight_square)
+                & pawn_masks::adjacent_files_mask(knight_square.file());
+
+            if (3..=5).contains(&relative_rank)
+                && Self::is_pawn_protected(game, side, knight_square)
+                && opponent_pawns & attacking_pawn_mask == 0u64
+            {
+                knight_outposts += KNIGHT_OUTPOST_VALUE;
+            }
+        }
+
+        knight_outposts
+    }
+
+    // Whether a pawn of the side defends the square from diagonally behind it
+    fn is_pawn_protected(game: &Game, side: Side, square: Square) -> bool {
+        let behind_rank = match side {
+            Side::White => Some(square.rank() + 1).filter(|&rank| rank < 8),
+            Side::Black => square.rank().checked_sub(1),
+        };
+        let Some(behind_rank) = behind_rank else {
+            return false;
+        };
+        let pawns = game.piece_bitboard(Piece::Pawn, side);
+
+        [square.file().checked_sub(1), Some(square.file() + 1)]
+            .into_iter()
+            .flatten()
+            .filter(|&file| file < 8)
+            .any(|file| pawns.bit_occupied(Square::from_rank_file(behind_rank, file)))
+    }
+
+    // A pawn is passed if no opposing pawn stands in front of it on its own or an adjacent file
+    fn passed_pawn_squares(game: &Game, side: Side) -> Vec<Square> {
+        let mut passed_pawn_squares = Vec::new();
+        let pawns = game.piece_bitboard(Piece::Pawn, side);
+        let opponent_pawns = game.piece_bitboard(Piece::Pawn, side.opponent_side());
+
+        for pawn_square in pawns.squares() {
+            if opponent_pawns & pawn_masks::passed_pawn_mask(side, pawn_square) == 0u64 {
+                passed_pawn_squares.push(pawn_square);
+            }
+        }
+
+        passed_pawn_squares
+    }
+
+    fn wing_pawn_count(game: &Game, side: Side, first_file: usize, last_file: usize) -> usize {
+        let pawns = game.piece_bitboard(Piece::Pawn, side);
+        let mut wing_pawn_count = 0;
+
+        for pawn_square in pawns.squares() {
+            if (first_file..=last_file).contains(&pawn_square.file()) {
+                wing_pawn_count += 1;
+            }
+        }
+
+        wing_pawn_count
+    }
+}
+
+// Contribution of each term of the evaluation for both sides, indexed by side, along with the game
+// phase the terms are blended by and the scale applied to the total for a recognised endgame
+#[derive(Default)]
+struct EvaluationTerms {
+    phase: Value,
+    scale: Value,
+    endgame: Option<Endgame>,
+    material: [TaperedValue; 2],
+    piece_squares: [TaperedValue; 2],
+    passed_pawns: [TaperedValue; 2],
+    bishop_pair: [TaperedValue; 2],
+    rook_files: [TaperedValue; 2],
+    rook_seventh_rank: [TaperedValue; 2],
+    knight_outposts: [TaperedValue; 2],
+    king_activity: [TaperedValue; 2],
+    pawn_race: [TaperedValue; 2],
+    mop_up: [TaperedValue; 2],
+    endgame_value: [TaperedValue; 2],
+    tempo: [TaperedValue; 2],
+}
+
+impl EvaluationTerms {
+    fn terms(&self) -> [(&'static str, [TaperedValue; 2]); 12] {
+        [
+            ("Material", self.material),
+            ("Piece squares", self.piece_squares),
+            ("Passed pawns", self.passed_pawns),
+            ("Bishop pair", self.bishop_pair),
+            ("Rook files", self.rook_files),
+            ("Rook 7th rank", self.rook_seventh_rank),
+            ("Knight outposts", self.knight_outposts),
+            ("King activity", self.king_activity),
+            ("Pawn race", self.pawn_race),
+            ("Mop up", self.mop_up),
+            ("Endgame", self.endgame_value),
+            ("Tempo", self.tempo),
+        ]
+    }
+
+    // Each side's terms are summed before being blended, so the rows of the breakdown may not add
+    // up to the total exactly, and the total is scaled afterwards in a recognised endgame
+    fn side_totals(&self) -> [Value; 2] {
+        let side_totals = self
+            .terms()
+            .iter()
+            .fold([TaperedValue::default(); 2], |totals, (_, values)| {
+                [totals[0] + values[0], totals[1] + values[1]]
+            });
+
+        side_totals.map(|total| total.taper(self.phase))
+    }
+
+    fn total(&self) -> Evaluation {
+        let [white, black] = self.side_totals();
+
+        Evaluation((white - black) * self.scale / endgames::SCALE_MAX)
+    }
+}
+
+impl Display for EvaluationTerms {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<16}{:>8}{:>8}{:>8}",
+            "Term", "White", "Black", "Total"
+        )?;
+
+        for (name, values) in self.terms() {
+            let [white, black] = values.map(|value| value.taper(self.phase));
+            writeln!(
+                f,
+                "{:<16}{:>8}{:>8}{:>8}",
+                name,
+                white,
+                black,
+                white - black
+            )?;
+        }
+
+        let [white, black] = self.side_totals();
+
+        write!(
+            f,
+            "{:<16}{:>8}{:>8}{:>8}",
+            "Total",
+            white,
+            black,
+            self.total().value()
+        )
     }
 }
 
@@ -108,16 +619,57 @@ impl Engine {
 pub struct Evaluation(Value);
 
 impl Evaluation {
+    pub fn new(value: Value) -> Self {
+        Self(value)
+    }
+
     pub fn value(self) -> Value {
         self.0
     }
 
+    // Number of moves until checkmate, negative if the side to move is getting checkmated
+    pub fn mate_moves(self) -> Option<Value> {
+        let mate_plies = CHECKMATE_EVALUATION.0 - self.0.abs();
+
+        if mate_plies < 0 || mate_plies > engine::MAX_PLY as Value {
+            return None;
+        }
+
+        let mate_moves = (mate_plies + 1) / 2;
+
+        match self.0 > 0 {
+            true => Some(mate_moves),
+            false => Some(-mate_moves),
+        }
+    }
+
+    pub fn uci_score(self) -> String {
+        match self.mate_moves() {
+            Some(mate_moves) => format!("mate {}", mate_moves),
+            None => format!("cp {}", self.0),
+        }
+    }
+
     pub fn sided_value(self, side: Side) -> Evaluation {
         Self(self.0 * side.to_value())
     }
 
-    fn sided_add(&mut self, value: Value, side: Side) {
-        self.0 += value * side.to_value()
+    // Mate scores count plies from the root during the search, but from the position itself in
+    // the transposition table, so they stay right when the position is reached at another ply
+    pub fn relative_to_position(self, ply: Value) -> Self {
+        match self.mate_moves() {
+            Some(mate_moves) if mate_moves > 0 => Self(self.0 + ply),
+            Some(_) => Self(self.0 - ply),
+            None => self,
+        }
+    }
+
+    pub fn relative_to_root(self, ply: Value) -> Self {
+        match self.mate_moves() {
+            Some(mate_moves) if mate_moves > 0 => Self(self.0 - ply),
+            Some(_) => Self(self.0 + ply),
+            None => self,
+        }
     }
 }
 
@@ -145,15 +697,294 @@ impl Sub<Value> for Evaluation {
     }
 }
 
-struct PositionValue([Value; 64]);
+#[cfg(test)]
+mod tests {
+    use super::{super::bench::BENCH_POSITIONS, *};
+
+    fn evaluate_fen(fen: &[&str]) -> Value {
+        let mut game = Game::initialise();
+        game.load_fen(fen).unwrap();
+
+        Engine::evaluate(&game).value()
+    }
+
+    #[test]
+    fn centralised_king_in_endgame() {
+        let centralised = evaluate_fen(&["4k3/p7/8/8/3K4/8/P7/8", "w", "-", "-", "0", "1"]);
+        let cornered = evaluate_fen(&["4k3/p7/8/8/8/8/P7/7K", "w", "-", "-", "0", "1"]);
+
+        assert!(centralised > cornered);
+    }
+
+    #[test]
+    fn king_close_to_passed_pawn() {
+        let close = evaluate_fen(&["7k/8/8/1K6/P7/8/8/8", "w", "-", "-", "0", "1"]);
+        let far = evaluate_fen(&["7k/8/8/6K1/P7/8/8/8", "w", "-", "-", "0", "1"]);
+
+        assert!(close > far);
+    }
+
+    #[test]
+    fn unstoppable_passed_pawn() {
+        let unstoppable = evaluate_fen(&["7k/8/8/P7/8/8/8/7K", "w", "-", "-", "0", "1"]);
+        let caught = evaluate_fen(&["8/8/1k6/P7/8/8/8/7K", "w", "-", "-", "0", "1"]);
+
+        assert!(unstoppable - caught > UNSTOPPABLE_PASSER_VALUE / 2);
 
-impl PositionValue {
-    fn value(&self, side: Side, square: Square) -> Value {
-        let sided_square_index = match side {
-            Side::White => square as usize,
-            Side::Black => square.horizontal_mirror() as usize,
+        let unstoppable_with_tempo =
+            evaluate_fen(&["8/4k3/8/P7/8/8/8/7K", "w", "-", "-", "0", "1"]);
+        let caught_without_tempo = evaluate_fen(&["8/4k3/8/P7/8/8/8/7K", "b", "-", "-", "0", "1"]);
+
+        assert!(unstoppable_with_tempo - caught_without_tempo > UNSTOPPABLE_PASSER_VALUE / 2);
+    }
+
+    #[test]
+    fn mop_up_bare_king() {
+        let cornered = evaluate_fen(&["k7/8/2K5/8/8/8/8/7Q", "w", "-", "-", "0", "1"]);
+        let centralised = evaluate_fen(&["8/8/2K5/8/4k3/8/8/7Q", "w", "-", "-", "0", "1"]);
+
+        assert!(cornered > centralised);
+
+        let kings_close = evaluate_fen(&["k7/8/1K6/8/8/8/8/7R", "w", "-", "-", "0", "1"]);
+        let kings_far = evaluate_fen(&["k7/8/8/8/8/8/8/6KR", "w", "-", "-", "0", "1"]);
+
+        assert!(kings_close > kings_far);
+    }
+
+    #[test]
+    fn evaluation_breakdown() {
+        let mut game = Game::initialise();
+        game.load_fen(&["k7/8/2K5/8/8/8/P7/7Q", "w", "-", "-", "0", "1"])
+            .unwrap();
+        let terms = Engine::evaluation_terms(&game);
+
+        assert_eq!(terms.phase, 4);
+        assert_eq!(
+            terms.material,
+            [TaperedValue::new(1000, 1070), TaperedValue::default()]
+        );
+        assert_eq!(terms.pawn_race, [TaperedValue::default(); 2]);
+        assert!(terms.mop_up[0].endgame() > 0);
+        assert_eq!(terms.total(), Engine::evaluate(&game));
+
+        let lines: Vec<String> = terms.to_string().lines().map(String::from).collect();
+
+        assert_eq!(lines.len(), 14);
+        assert_eq!(lines[1], "Material            1058       0    1058");
+        assert!(lines[3].starts_with("Passed pawns"));
+        assert!(lines[13].ends_with(&format!("{:>8}", terms.total().value())));
+    }
+
+    #[test]
+    fn passed_pawn_bonuses() {
+        let passed_pawns = |fen: &[&str], side: Side| {
+            let mut game = Game::initialise();
+            game.load_fen(fen).unwrap();
+
+            Engine::passed_pawns(&game, side)
         };
 
-        self.0[sided_square_index]
+        let advanced = passed_pawns(
+            &["4k3/8/3P4/8/8/8/8/4K3", "w", "-", "-", "0", "1"],
+            Side::White,
+        );
+        let behind = passed_pawns(
+            &["4k3/8/8/8/8/3P4/8/4K3", "w", "-", "-", "0", "1"],
+            Side::White,
+        );
+
+        assert_eq!(advanced, PASSED_PAWN_VALUE[5]);
+        assert_eq!(behind, PASSED_PAWN_VALUE[2]);
+
+        let stopped = passed_pawns(
+            &["4k3/8/2p5/3P4/8/8/8/4K3", "w", "-", "-", "0", "1"],
+            Side::White,
+        );
+
+        assert_eq!(stopped, TaperedValue::default());
+
+        let protected = passed_pawns(
+            &["4k3/8/8/3p4/4p3/8/8/4K3", "b", "-", "-", "0", "1"],
+            Side::Black,
+        );
+
+        assert_eq!(
+            protected,
+            PASSED_PAWN_VALUE[3]
+                + PASSED_PAWN_VALUE[4]
+                + PROTECTED_PASSER_VALUE
+                + CONNECTED_PASSER_VALUE * 2
+        );
+
+        let blockaded = passed_pawns(
+            &["4k3/8/3n4/3P4/8/8/8/4K3", "w", "-", "-", "0", "1"],
+            Side::White,
+        );
+
+        assert_eq!(blockaded, PASSED_PAWN_VALUE[4] - BLOCKADED_PASSER_PENALTY);
+    }
+
+    #[test]
+    fn piece_placement_bonuses() {
+        let terms = |fen: &str| {
+            let mut game = Game::initialise();
+            game.load_fen(&[fen, "w", "-", "-", "0", "1"]).unwrap();
+
+            Engine::evaluation_terms(&game)
+        };
+
+        let bishops = terms("2b1kb2/8/8/8/8/8/8/2B1K3");
+
+        assert_eq!(
+            bishops.bishop_pair,
+            [TaperedValue::default(), BISHOP_PAIR_VALUE]
+        );
+
+        let rooks = terms("r3k3/pp6/8/8/8/8/1P6/R1R1K3");
+
+        assert_eq!(
+            rooks.rook_files,
+            [
+                ROOK_SEMI_OPEN_FILE_VALUE + ROOK_OPEN_FILE_VALUE,
+                TaperedValue::default()
+            ]
+        );
+
+        let seventh_rank = terms("4k3/1R6/8/8/8/8/8/4K3");
+        let seventh_rank_king_escaped = terms("8/1R6/4k3/8/8/8/8/4K3");
+
+        assert_eq!(seventh_rank.rook_seventh_rank[0], ROOK_SEVENTH_RANK_VALUE);
+        assert_eq!(
+            seventh_rank_king_escaped.rook_seventh_rank[0],
+            TaperedValue::default()
+        );
+
+        let outpost = terms("4k3/p3p3/8/4N3/3P4/8/8/4K3");
+        let attackable = terms("4k3/p4p2/8/4N3/3P4/8/8/4K3");
+        let unprotected = terms("4k3/p3p3/8/4N3/8/8/8/4K3");
+
+        assert_eq!(outpost.knight_outposts[0], KNIGHT_OUTPOST_VALUE);
+        assert_eq!(attackable.knight_outposts[0], TaperedValue::default());
+        assert_eq!(unprotected.knight_outposts[0], TaperedValue::default());
+    }
+
+    #[test]
+    fn recognised_endgame_scaling() {
+        let wrong_bishop = evaluate_fen(&["k7/8/8/P7/8/8/8/2B1K3", "w", "-", "-", "0", "1"]);
+        let right_bishop = evaluate_fen(&["k7/8/8/P7/8/8/8/4KB2", "w", "-", "-", "0", "1"]);
+
+        assert_eq!(wrong_bishop, 0);
+        assert!(right_bishop > 0);
+
+        let mut game = Game::initialise();
+        game.load_fen(&["4k3/1b3p2/8/8/8/8/4P3/2B1K3", "w", "-", "-", "0", "1"])
+            .unwrap();
+        let terms = Engine::evaluation_terms(&game);
+
+        assert_eq!(terms.endgame, Some(Endgame::OppositeColouredBishops));
+        assert_eq!(terms.scale, endgames::SCALE_MAX / 2);
+    }
+
+    #[test]
+    fn mate_score_conversion() {
+        assert_eq!(CHECKMATE_EVALUATION.uci_score(), "mate 0");
+        assert_eq!((CHECKMATE_EVALUATION - 1).uci_score(), "mate 1");
+        assert_eq!((CHECKMATE_EVALUATION - 5).uci_score(), "mate 3");
+        assert_eq!((-CHECKMATE_EVALUATION + 2).uci_score(), "mate -1");
+        assert_eq!((-CHECKMATE_EVALUATION + 4).uci_score(), "mate -2");
+        assert_eq!(Evaluation::new(250).uci_score(), "cp 250");
+        assert_eq!(Evaluation::new(-48000).uci_score(), "cp -48000");
+    }
+
+    #[test]
+    fn king_placement_by_phase() {
+        let castled = evaluate_fen(&[
+            "rnbq1rk1/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1",
+            "w",
+            "-",
+            "-",
+            "0",
+            "1",
+        ]);
+        let centralised = evaluate_fen(&[
+            "rnbq1rk1/pppppppp/8/8/8/4K3/PPPPPPPP/RNBQ1R2",
+            "w",
+            "-",
+            "-",
+            "0",
+            "1",
+        ]);
+
+        assert!(castled > centralised);
+
+        let castled = evaluate_fen(&["6k1/8/8/8/8/8/8/6K1", "w", "-", "-", "0", "1"]);
+        let centralised = evaluate_fen(&["6k1/8/8/8/4K3/8/8/8", "w", "-", "-", "0", "1"]);
+
+        assert!(castled < centralised);
+    }
+
+    #[test]
+    fn game_phase_from_material() {
+        let game_phase = |fen: &[&str]| {
+            let mut game = Game::initialise();
+            game.load_fen(fen).unwrap();
+
+            Engine::game_phase(&game)
+        };
+
+        assert_eq!(game_phase(&["startpos"]), PHASE_MAX);
+        assert_eq!(
+            game_phase(&["4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3", "w", "-", "-", "0", "1"]),
+            0
+        );
+        assert_eq!(
+            game_phase(&["3qk3/8/8/8/8/8/8/R3K3", "w", "-", "-", "0", "1"]),
+            6
+        );
+        assert_eq!(
+            game_phase(&["QQQQQQQQ/8/8/8/8/8/8/K6k", "w", "-", "-", "0", "1"]),
+            PHASE_MAX
+        );
+    }
+
+    #[test]
+    fn king_activity_ignored_in_middlegame() {
+        let game_start = evaluate_fen(&["startpos"]);
+
+        assert_eq!(game_start, TEMPO_VALUE.taper(PHASE_MAX));
+    }
+
+    #[test]
+    fn tempo_for_side_to_move() {
+        let white_to_move = evaluate_fen(&["4k3/pp6/8/8/8/8/PP6/4K3", "w", "-", "-", "0", "1"]);
+        let black_to_move = evaluate_fen(&["4k3/pp6/8/8/8/8/PP6/4K3", "b", "-", "-", "0", "1"]);
+
+        assert_eq!(white_to_move, -black_to_move);
+        assert_eq!(white_to_move - black_to_move, 2 * TEMPO_VALUE.endgame());
+    }
+
+    #[test]
+    fn colour_flipped_evaluation_symmetry() {
+        let endgame_positions = [
+            "8/8/4k3/8/3K4/8/4P3/8 b - - 0 1",
+            "7k/8/5K2/8/8/8/8/3NB3 w - - 0 1",
+            "k7/8/8/P7/8/8/8/2B1K3 w - - 0 1",
+            "4k3/1b3p2/8/8/8/8/4P3/2B1K3 w - - 0 1",
+            "4k3/p3p3/8/4N3/3P4/8/8/4K3 b - - 0 1",
+            "r3k3/pp6/8/8/8/8/1P6/R1R1K3 w - - 0 1",
+        ];
+
+        for position in BENCH_POSITIONS.iter().chain(&endgame_positions) {
+            let fen: Vec<&str> = position.split_whitespace().collect();
+            let mut game = Game::initialise();
+            game.load_fen(&fen).unwrap();
+
+            assert_eq!(
+                Engine::evaluate(&game),
+                -Engine::evaluate(&game.colour_flipped()),
+                "{}",
+                position
+            );
+        }
     }
 }
@@ -0,0 +1,98 @@
+use super::evaluation::Evaluation;
+use crate::engine::zobrist_hashes::ZobristKey;
+
+// About one and a half megabytes per search thread, enough for the positions of a few searches
+const EVALUATION_CACHE_ENTRIES: usize = 1 << 16;
+
+// Static evaluations by position, so positions reached again through a transposition are not
+// evaluated over again - each thread keeps its own, as a miss only costs one evaluation, and
+// entries only go stale when a network is loaded, as otherwise a position always evaluates the
+// same
+pub struct EvaluationCache {
+    entries: Vec<Option<(ZobristKey, Evaluation)>>,
+    probes: u64,
+    hits: u64,
+}
+
+impl EvaluationCache {
+    pub fn initialise() -> Self {
+        Self {
+            entries: vec![None; EVALUATION_CACHE_ENTRIES],
+            probes: 0,
+            hits: 0,
+        }
+    }
+
+    pub fn probe(&mut self, zobrist_key: ZobristKey) -> Option<Evaluation> {
+        self.probes += 1;
+
+        match self.entries[Self::index(zobrist_key)] {
+            Some((stored_key, evaluation)) if stored_key == zobrist_key => {
+                self.hits += 1;
+
+                Some(evaluation)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn store(&mut self, zobrist_key: ZobristKey, evaluation: Evaluation) {
+        self.entries[Self::index(zobrist_key)] = Some((zobrist_key, evaluation));
+    }
+
+    pub fn reset_statistics(&mut self) {
+        self.probes = 0;
+        self.hits = 0;
+    }
+
+    pub fn statistics(&self) -> String {
+        let hit_percentage = match self.probes {
+            0 => 0,
+            probes => self.hits * 100 / probes,
+        };
+
+        format!(
+            "evaluation cache probes {} hits {} hit rate {}%",
+            self.probes, self.hits, hit_percentage
+        )
+    }
+
+    fn index(zobrist_key: ZobristKey) -> usize {
+        (zobrist_key % EVALUATION_CACHE_ENTRIES as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_evaluations() {
+        let mut evaluation_cache = EvaluationCache::initialise();
+        let colliding_key = 7 + EVALUATION_CACHE_ENTRIES as u64;
+
+        assert_eq!(evaluation_cache.probe(7), None);
+
+        evaluation_cache.store(7, Evaluation::new(35));
+
+        assert_eq!(evaluation_cache.probe(7), Some(Evaluation::new(35)));
+        assert_eq!(evaluation_cache.probe(colliding_key), None);
+        assert_eq!(
+            evaluation_cache.statistics(),
+            "evaluation cache probes 3 hits 1 hit rate 33%"
+        );
+
+        evaluation_cache.store(colliding_key, Evaluation::new(-10));
+        evaluation_cache.reset_statistics();
+
+        assert_eq!(evaluation_cache.probe(7), None);
+        assert_eq!(
+            evaluation_cache.probe(colliding_key),
+            Some(Evaluation::new(-10))
+        );
+        assert_eq!(
+            evaluation_cache.statistics(),
+            "evaluation cache probes 2 hits 1 hit rate 50%"
+        );
+    }
+}
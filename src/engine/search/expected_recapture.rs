@@ -0,0 +1,157 @@
+use crate::engine::{
+    game::Game,
+    moves::{Move, MoveType},
+};
+
+// Replies prepared at least this deep are trusted enough to be played without another search
+const INSTANT_REPLY_DEPTH_MIN: u8 = 6;
+
+// The position expected once the opponent recaptures on the square of the best move, along with
+// the reply prepared for it and the depth that reply was searched to
+pub struct ExpectedRecapture {
+    game: Game,
+    reply: Move,
+    depth: u8,
+}
+
+impl ExpectedRecapture {
+    // Only a capture answered by a recapture on the same square is forced enough to expect, and
+    // the reply was searched two plies shallower than the line it is taken from
+    pub fn from_principal_variation(
+        game: &Game,
+        principal_variation: &[Move],
+        depth: u8,
+    ) -> Option<Self> {
+        let [best_move, recapture, reply, ..] = principal_variation else {
+            return None;
+        };
+        let best_move_captures = matches!(
+            best_move.move_type(),
+            MoveType::Capture | MoveType::EnPassant
+        );
+        let recaptures = recapture.move_type() == MoveType::Capture
+            && recapture.target_square() == best_move.target_square();
+
+        if !best_move_captures || !recaptures {
+            return None;
+        }
+
+        let mut game = game.clone();
+        game.make_move(best_move).ok()?;
+        game.make_move(recapture).ok()?;
+
+        Some(Self {
+            game,
+            reply: *reply,
+            depth: depth.saturating_sub(2),
+        })
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    // A search of the expected position itself replaces the reply once it gets as deep
+    pub fn update(&mut self, reply: Move, depth: u8) {
+        if depth >= self.depth {
+            self.reply = reply;
+            self.depth = depth;
+        }
+    }
+
+    // The transposition table has to agree with the prepared reply, as it may hold a deeper
+    // result from the search of another line
+    pub fn instant_reply(&self, game: &Game, hash_move: Option<&Move>) -> Option<&Move> {
+        let expected_position = game.zobrist_key() == self.game.zobrist_key();
+        let searched_deep_enough = self.depth >= INSTANT_REPLY_DEPTH_MIN;
+
+        match expected_position && searched_deep_enough && hash_move == Some(&self.reply) {
+            true => Some(&self.reply),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::moves::{MoveFlag, MoveList};
+
+    fn open_centre() -> Game {
+        let mut game = Game::initialise();
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/5N2/PPP2PPP/RNBQKB1R b KQkq - 0 3";
+        let fen: Vec<&str> = fen.split_whitespace().collect();
+        game.load_fen(&fen).unwrap();
+
+        game
+    }
+
+    fn parse_moves(game: &Game, move_strings: &[&str]) -> Vec<Move> {
+        let mut game = game.clone();
+        let mut moves = Vec::new();
+
+        for move_string in move_strings {
+            let mv = MoveList::generate_moves(&game, MoveFlag::All)
+                .find_move_from_string(move_string)
+                .unwrap();
+            game.make_move(&mv).unwrap();
+            moves.push(mv);
+        }
+
+        moves
+    }
+
+    #[test]
+    fn recapture_sequences() {
+        let game = open_centre();
+        let recapture = parse_moves(&game, &["e5d4", "f3d4", "c6d4"]);
+        let other_capture = parse_moves(&game, &["e5d4", "d1d4", "c6d4"]);
+        let quiet = parse_moves(&game, &["g8f6", "d4e5", "c6e5"]);
+
+        let expected_recapture =
+            ExpectedRecapture::from_principal_variation(&game, &recapture, 8).unwrap();
+
+        assert_eq!(expected_recapture.depth(), 6);
+        assert!(ExpectedRecapture::from_principal_variation(&game, &recapture[..2], 8).is_none());
+        assert!(ExpectedRecapture::from_principal_variation(&game, &other_capture, 8).is_some());
+        assert!(ExpectedRecapture::from_principal_variation(&game, &quiet, 8).is_none());
+    }
+
+    #[test]
+    fn instant_reply_conditions() {
+        let game = open_centre();
+        let moves = parse_moves(&game, &["e5d4", "f3d4", "g8f6"]);
+        let other_reply = parse_moves(&game, &["e5d4", "f3d4", "c6d4"]);
+        let mut expected_recapture =
+            ExpectedRecapture::from_principal_variation(&game, &moves, 7).unwrap();
+        let expected_game = expected_recapture.game().clone();
+
+        assert!(expected_recapture
+            .instant_reply(&expected_game, Some(&moves[2]))
+            .is_none());
+
+        expected_recapture.update(other_reply[2], 6);
+
+        assert_eq!(
+            expected_recapture.instant_reply(&expected_game, Some(&other_reply[2])),
+            Some(&other_reply[2])
+        );
+        assert!(expected_recapture
+            .instant_reply(&expected_game, Some(&moves[2]))
+            .is_none());
+        assert!(expected_recapture
+            .instant_reply(&expected_game, None)
+            .is_none());
+        assert!(expected_recapture
+            .instant_reply(&game, Some(&other_reply[2]))
+            .is_none());
+
+        expected_recapture.update(moves[2], 5);
+
+        assert_eq!(expected_recapture.depth(), 6);
+    }
+}
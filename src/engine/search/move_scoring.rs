@@ -1,16 +1,16 @@
-use super::{Engine, SearchParameters, Value};
+use super::{Engine, Value};
 use crate::engine::{
     self,
     game::{Game, Piece, Side},
-    moves::{Move, MoveList, MoveType},
+    moves::{Move, MoveFlag, MoveList, MoveType, MAX_MOVES},
 };
-use std::cmp::Reverse;
 
 type Score = u16;
 
 const KILLER_MOVE_ARRAY_SIZE: usize = 2;
 
 const PRINCIPAL_MOVE_SCORE: Score = 20000;
+const HASH_MOVE_SCORE: Score = 19000;
 // MVV = most valuable victim
 // LVA = least valuable attacker
 // Score obtained by indexing to array as such: [attacker][victim]
@@ -23,36 +23,108 @@ const MVV_LVA_SCORE: [[Score; 6]; 6] = [
     [10100, 10200, 10300, 10400, 10500, 0],
 ];
 const KILLER_MOVE_SCORE: [Score; KILLER_MOVE_ARRAY_SIZE] = [9000, 8000];
+// Captures losing material are moved below the killer moves, keeping their MVV-LVA order
+const LOSING_CAPTURE_PENALTY: Score = 3000;
+// History scores range from minus to plus the maximum, and are shifted to be ordered below the
+// losing captures
+const HISTORY_SCORE_MAX: Value = 3500;
+const HISTORY_BONUS_MAX: Value = 400;
+const HISTORY_DECAY_DIVISOR: Value = 2;
+// Capture history only reorders captures of the same victim, so never moves a capture past
+// one taking a more valuable piece
+const CAPTURE_HISTORY_SCORE_DIVISOR: Value = 75;
 
-impl MoveList {
-    pub fn generate_sorted_moves(game: &Game, engine: &Engine, ply: Value) -> Self {
-        let mut move_list = Self::generate_moves(game, &engine.attack_tables);
-        move_list
-            .mut_vec()
-            .sort_by_key(|mv| Reverse(mv.score(game, &engine.search_parameters, ply)));
+// Moves with their ordering scores, handed out best first - most nodes are cut off after the
+// first few moves, so each next best move is only picked out when it is asked for, rather than
+// sorting the whole list up front
+pub struct ScoredMoveList {
+    move_list: MoveList,
+    scores: [Score; MAX_MOVES],
+    picked: usize,
+}
+
+impl ScoredMoveList {
+    pub fn generate(game: &Game, engine: &Engine, ply: Value, move_flag: MoveFlag) -> Self {
+        let move_list = MoveList::generate_moves(game, move_flag);
+        let hash_move = engine
+            .search_parameters
+            .transposition_table
+            .best_move(game.zobrist_key());
+        let mut scores = [0; MAX_MOVES];
+
+        for (score, mv) in scores.iter_mut().zip(move_list.vec()) {
+            *score = mv.score(game, engine, hash_move.as_ref(), ply);
+        }
+
+        Self {
+            move_list,
+            scores,
+            picked: 0,
+        }
+    }
+
+    pub fn contains(&self, mv: &Move) -> bool {
+        self.move_list.vec().contains(mv)
+    }
+
+    // Equal scores are picked in generation order, with the rotation keeping the order of the
+    // moves not picked yet
+    pub fn next_move(&mut self) -> Option<Move> {
+        let remaining_moves = &mut self.move_list.vec_mut()[self.picked..];
+        let remaining_scores = &mut self.scores[self.picked..][..remaining_moves.len()];
 
-        move_list
+        if remaining_moves.is_empty() {
+            return None;
+        }
+
+        let mut best_index = 0;
+
+        for (index, score) in remaining_scores.iter().enumerate() {
+            if *score > remaining_scores[best_index] {
+                best_index = index;
+            }
+        }
+
+        remaining_moves[..=best_index].rotate_right(1);
+        remaining_scores[..=best_index].rotate_right(1);
+        self.picked += 1;
+
+        Some(remaining_moves[0])
     }
 }
 
 impl Move {
-    fn score(&self, game: &Game, search_parameters: &SearchParameters, ply: Value) -> Score {
+    fn score(&self, game: &Game, engine: &Engine, hash_move: Option<&Move>, ply: Value) -> Score {
+        let search_parameters = &engine.search_parameters;
+
         if let Some(principal_move) = search_parameters.principal_variation.principal_move(ply) {
             if search_parameters.is_principal_variation && principal_move == self {
                 return PRINCIPAL_MOVE_SCORE;
             }
         }
 
+        if hash_move == Some(self) {
+            return HASH_MOVE_SCORE;
+        }
+
         match self.move_type() {
             MoveType::Capture => match game.piece_at_square(self.target_square()) {
                 Some((victim, _)) => {
                     let attacker = self.piece();
+                    let score = MVV_LVA_SCORE[attacker as usize][victim as usize]
+                        .saturating_add_signed(
+                            search_parameters.capture_history.score_move(self, game),
+                        );
 
-                    MVV_LVA_SCORE[attacker as usize][victim as usize]
+                    match self.is_losing_capture(game) {
+                        true => score - LOSING_CAPTURE_PENALTY,
+                        false => score,
+                    }
                 }
                 None => 0,
             },
-            MoveType::EnPassant => MVV_LVA_SCORE[Piece::Pawn as usize][Piece::Pawn as usize],
+            MoveType::EnPassant => MVV_LVA_SCORE[Piece::Pawn as usize][Piece::Pawn as usize]
+                .saturating_add_signed(search_parameters.capture_history.score_move(self, game)),
             _ => match search_parameters.killer_moves.score_move(self, ply) {
                 Some(score) => score,
                 None => search_parameters
@@ -67,10 +139,7 @@ pub struct KillerMoves([[Option<Move>; KILLER_MOVE_ARRAY_SIZE]; engine::MAX_PLY]
 
 impl KillerMoves {
     pub fn initialise() -> Self {
-        let array_element = [(); KILLER_MOVE_ARRAY_SIZE].map(|_| None);
-        let array = [(); engine::MAX_PLY].map(|_| array_element.clone());
-
-        Self(array)
+        Self([[None; KILLER_MOVE_ARRAY_SIZE]; engine::MAX_PLY])
     }
 
     pub fn push(&mut self, mv: &Move, ply: Value) {
@@ -78,8 +147,8 @@ impl KillerMoves {
             return;
         }
 
-        self.0[ply as usize][1] = self.0[ply as usize][0].clone();
-        self.0[ply as usize][0] = Some(mv.clone());
+        self.0[ply as usize][1] = self.0[ply as usize][0];
+        self.0[ply as usize][0] = Some(*mv);
     }
 
     fn score_move(&self, mv: &Move, ply: Value) -> Option<Score> {
@@ -93,27 +162,234 @@ impl KillerMoves {
     }
 }
 
-pub struct HistoricMoveScore([[[Score; 64]; 6]; 2]);
+// Butterfly table of how often each quiet move, by side and source and target square, caused a
+// cutoff - moves are pulled towards the maximum when they cut off and pushed away from it when
+// searched before a move which did, so scores stay bounded and older results lose weight
+#[derive(Clone)]
+pub struct HistoricMoveScore([[[Value; 64]; 64]; 2]);
 
 impl HistoricMoveScore {
     pub fn initialise() -> Self {
-        Self([[[0; 64]; 6]; 2])
+        Self([[[0; 64]; 64]; 2])
+    }
+
+    pub fn reward(&mut self, mv: &Move, side: Side, depth: u8) {
+        self.update(mv, side, history_bonus(depth));
+    }
+
+    pub fn penalise(&mut self, mv: &Move, side: Side, depth: u8) {
+        self.update(mv, side, -history_bonus(depth));
     }
 
-    pub fn push(&mut self, mv: &Move, side: Side, depth: u8) {
+    // Between searches, so scores from earlier positions guide the next search less
+    pub fn decay(&mut self) {
+        self.0
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .for_each(decay_history_score);
+    }
+
+    fn update(&mut self, mv: &Move, side: Side, bonus: Value) {
         if mv.move_type() == MoveType::Capture || mv.move_type() == MoveType::EnPassant {
             return;
         }
 
-        let piece = mv.piece();
-        let target_square = mv.target_square();
-        self.0[side as usize][piece as usize][target_square as usize] += (depth * depth) as Score;
+        update_history_score(
+            &mut self.0[side as usize][mv.source_square() as usize][mv.target_square() as usize],
+            bonus,
+        );
     }
 
     fn score_move(&self, mv: &Move, side: Side) -> Score {
-        let piece = mv.piece();
-        let target_square = mv.target_square();
+        let score = self.0[side as usize][mv.source_square() as usize][mv.target_square() as usize];
+
+        (score + HISTORY_SCORE_MAX) as Score
+    }
+}
+
+// History of captures by side, moving piece, target square and captured piece, kept the same way
+// as the quiet move history - it tells apart captures of the same victim, which MVV-LVA scores
+// alike or nearly so
+#[derive(Clone)]
+pub struct CaptureHistory([[[[Value; 6]; 64]; 6]; 2]);
+
+impl CaptureHistory {
+    pub fn initialise() -> Self {
+        Self([[[[0; 6]; 64]; 6]; 2])
+    }
+
+    pub fn reward(&mut self, mv: &Move, game: &Game, depth: u8) {
+        self.update(mv, game, history_bonus(depth));
+    }
+
+    pub fn penalise(&mut self, mv: &Move, game: &Game, depth: u8) {
+        self.update(mv, game, -history_bonus(depth));
+    }
+
+    pub fn decay(&mut self) {
+        self.0
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .flatten()
+            .for_each(decay_history_score);
+    }
+
+    fn update(&mut self, mv: &Move, game: &Game, bonus: Value) {
+        if let Some(victim) = captured_piece(mv, game) {
+            update_history_score(
+                &mut self.0[game.side_to_move() as usize][mv.piece() as usize]
+                    [mv.target_square() as usize][victim as usize],
+                bonus,
+            );
+        }
+    }
+
+    fn score_move(&self, mv: &Move, game: &Game) -> i16 {
+        let score = match captured_piece(mv, game) {
+            Some(victim) => {
+                self.0[game.side_to_move() as usize][mv.piece() as usize]
+                    [mv.target_square() as usize][victim as usize]
+            }
+            None => 0,
+        };
+
+        (score / CAPTURE_HISTORY_SCORE_DIVISOR) as i16
+    }
+}
+
+fn captured_piece(mv: &Move, game: &Game) -> Option<Piece> {
+    match mv.move_type() {
+        MoveType::Capture => game
+            .piece_at_square(mv.target_square())
+            .map(|(piece, _)| piece),
+        MoveType::EnPassant => Some(Piece::Pawn),
+        _ => None,
+    }
+}
+
+fn history_bonus(depth: u8) -> Value {
+    (depth as Value * depth as Value).min(HISTORY_BONUS_MAX)
+}
+
+// Each update moves the score towards the bound in the direction of the bonus, by less the closer
+// it already is
+fn update_history_score(score: &mut Value, bonus: Value) {
+    *score += bonus - *score * bonus.abs() / HISTORY_SCORE_MAX;
+}
+
+fn decay_history_score(score: &mut Value) {
+    *score /= HISTORY_DECAY_DIVISOR;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Reverse;
+
+    #[test]
+    fn moves_picked_best_first() {
+        let mut engine = Engine::initialise();
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let fen: Vec<&str> = fen.split_whitespace().collect();
+        engine.load_fen(&fen).unwrap();
+        let game = engine.game.clone();
+        let mut move_list = ScoredMoveList::generate(&game, &engine, 0, MoveFlag::All);
+        let mut sorted_moves: Vec<(Move, Score)> = move_list
+            .move_list
+            .vec()
+            .iter()
+            .copied()
+            .zip(move_list.scores)
+            .collect();
+        sorted_moves.sort_by_key(|(_, score)| Reverse(*score));
+
+        let mut picked_moves = Vec::new();
+
+        while let Some(mv) = move_list.next_move() {
+            picked_moves.push(mv);
+        }
+
+        assert_eq!(picked_moves.len(), 48);
+        assert_eq!(move_list.move_list.vec(), picked_moves);
+        assert!(move_list.scores[..48]
+            .iter()
+            .eq(sorted_moves.iter().map(|(_, score)| score)));
+        assert!(sorted_moves
+            .iter()
+            .map(|(mv, _)| mv)
+            .eq(picked_moves.iter()));
+        assert!(move_list.next_move().is_none());
+    }
+
+    #[test]
+    fn history_scores() {
+        let mut engine = Engine::initialise();
+        engine.load_fen(&["startpos"]).unwrap();
+        let move_list = MoveList::generate_moves(&engine.game, MoveFlag::All);
+        let knight_move = move_list.find_move_from_string("g1f3").unwrap();
+        let pawn_move = move_list.find_move_from_string("e2e4").unwrap();
+        let mut historic_move_score = HistoricMoveScore::initialise();
+        let neutral_score = historic_move_score.score_move(&knight_move, Side::White);
+
+        for _ in 0..1000 {
+            historic_move_score.reward(&knight_move, Side::White, 30);
+            historic_move_score.penalise(&pawn_move, Side::White, 30);
+        }
+
+        let knight_score = historic_move_score.score_move(&knight_move, Side::White);
+        let pawn_score = historic_move_score.score_move(&pawn_move, Side::White);
+
+        // Repeated cutoffs approach the maximum without passing it
+        assert!(knight_score <= (2 * HISTORY_SCORE_MAX) as Score);
+        assert!(knight_score > (2 * HISTORY_SCORE_MAX - 100) as Score);
+        assert!(pawn_score < 100);
+        assert!(
+            knight_score
+                < MVV_LVA_SCORE[Piece::King as usize][Piece::Pawn as usize]
+                    - LOSING_CAPTURE_PENALTY
+        );
+        assert_eq!(
+            historic_move_score.score_move(&knight_move, Side::Black),
+            neutral_score
+        );
+
+        historic_move_score.decay();
+
+        assert!(historic_move_score.score_move(&knight_move, Side::White) < knight_score);
+        assert!(historic_move_score.score_move(&knight_move, Side::White) > neutral_score);
+    }
+
+    #[test]
+    fn capture_history_scores() {
+        let mut engine = Engine::initialise();
+        let fen = vec!["4k3/8/8/3p4/4P3/2N5/8/4K3", "w", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        let game = engine.game.clone();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let pawn_takes_pawn = move_list.find_move_from_string("e4d5").unwrap();
+        let knight_takes_pawn = move_list.find_move_from_string("c3d5").unwrap();
+        let quiet_move = move_list.find_move_from_string("c3b5").unwrap();
+        let mut capture_history = CaptureHistory::initialise();
+
+        for _ in 0..1000 {
+            capture_history.reward(&knight_takes_pawn, &game, 30);
+            capture_history.penalise(&pawn_takes_pawn, &game, 30);
+            capture_history.reward(&quiet_move, &game, 30);
+        }
+
+        let knight_score = capture_history.score_move(&knight_takes_pawn, &game);
+        let pawn_score = capture_history.score_move(&pawn_takes_pawn, &game);
+        let victim_step = MVV_LVA_SCORE[5][1] - MVV_LVA_SCORE[0][0];
+
+        // Enough to reorder captures of the same victim, never those of different victims
+        assert!(knight_score > 0 && pawn_score < 0);
+        assert!(((knight_score - pawn_score) as Score) < victim_step);
+        assert_eq!(capture_history.score_move(&quiet_move, &game), 0);
+
+        capture_history.decay();
 
-        self.0[side as usize][piece as usize][target_square as usize]
+        assert!(capture_history.score_move(&knight_takes_pawn, &game) < knight_score);
     }
 }
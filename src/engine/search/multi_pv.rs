@@ -0,0 +1,162 @@
+use super::{evaluation::Evaluation, PrincipalVariation};
+use crate::engine::moves::Move;
+use std::cmp::Reverse;
+
+#[derive(Clone)]
+pub struct RootLine {
+    pub evaluation: Evaluation,
+    pub principal_variation: PrincipalVariation,
+}
+
+impl RootLine {
+    fn root_move(&self) -> Option<&Move> {
+        self.principal_variation.principal_move(0)
+    }
+}
+
+pub struct MultiPrincipalVariation {
+    previous_lines: Vec<RootLine>,
+}
+
+impl MultiPrincipalVariation {
+    pub fn initialise() -> Self {
+        Self {
+            previous_lines: Vec::new(),
+        }
+    }
+
+    // The line found at the same index in the previous iteration, to be followed first when the
+    // line is searched again
+    pub fn previous_line(&self, line_index: usize) -> Option<&PrincipalVariation> {
+        self.previous_lines
+            .get(line_index)
+            .map(|line| &line.principal_variation)
+    }
+
+    // Lines are sorted by evaluation, with ties keeping the order of the previous iteration so a
+    // move does not jump between lines, and lines without a root move or repeating the root move
+    // of an earlier line are dropped
+    pub fn order_lines(&mut self, lines: Vec<RootLine>) -> Vec<RootLine> {
+        let mut ordered_lines: Vec<RootLine> = Vec::new();
+
+        for line in lines {
+            let duplicate_line = ordered_lines
+                .iter()
+                .any(|ordered_line| ordered_line.root_move() == line.root_move());
+
+            if line.root_move().is_some() && !duplicate_line {
+                ordered_lines.push(line);
+            }
+        }
+
+        ordered_lines.sort_by_key(|line| {
+            (
+                Reverse(line.evaluation.value()),
+                self.previous_line_index(line),
+            )
+        });
+        self.previous_lines = ordered_lines.clone();
+
+        ordered_lines
+    }
+
+    pub fn lines(&self) -> &[RootLine] {
+        &self.previous_lines
+    }
+
+    fn previous_line_index(&self, line: &RootLine) -> usize {
+        self.previous_lines
+            .iter()
+            .position(|previous_line| previous_line.root_move() == line.root_move())
+            .unwrap_or(usize::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        game::Game,
+        moves::{Move, MoveFlag, MoveList},
+    };
+
+    fn root_moves(move_strings: &[&str]) -> Vec<Move> {
+        let mut game = Game::initialise();
+        let fen = vec!["startpos"];
+        game.load_fen(&fen).unwrap();
+
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+
+        move_strings
+            .iter()
+            .map(|move_string| move_list.find_move_from_string(move_string).unwrap())
+            .collect()
+    }
+
+    fn root_line(root_move: Option<&Move>, value: i32) -> RootLine {
+        let mut principal_variation = PrincipalVariation::initialise();
+        principal_variation.table[0][0] = root_move.cloned();
+
+        RootLine {
+            evaluation: Evaluation::new(value),
+            principal_variation,
+        }
+    }
+
+    fn line_moves(lines: &[RootLine]) -> Vec<Move> {
+        lines
+            .iter()
+            .filter_map(|line| line.root_move().cloned())
+            .collect()
+    }
+
+    #[test]
+    fn tied_lines_keep_previous_order() {
+        let moves = root_moves(&["e2e4", "d2d4", "g1f3"]);
+        let mut multi_principal_variation = MultiPrincipalVariation::initialise();
+
+        let lines = vec![
+            root_line(Some(&moves[0]), 30),
+            root_line(Some(&moves[1]), 20),
+            root_line(Some(&moves[2]), 20),
+        ];
+        let lines = multi_principal_variation.order_lines(lines);
+
+        assert_eq!(line_moves(&lines), moves);
+
+        let lines = vec![
+            root_line(Some(&moves[2]), 20),
+            root_line(Some(&moves[0]), 20),
+            root_line(Some(&moves[1]), 20),
+        ];
+        let lines = multi_principal_variation.order_lines(lines);
+
+        assert_eq!(line_moves(&lines), moves);
+
+        let lines = vec![
+            root_line(Some(&moves[0]), 10),
+            root_line(Some(&moves[2]), 20),
+            root_line(Some(&moves[1]), 20),
+        ];
+        let lines = multi_principal_variation.order_lines(lines);
+
+        assert_eq!(line_moves(&lines), vec![moves[1], moves[2], moves[0]]);
+    }
+
+    #[test]
+    fn duplicate_lines_are_dropped() {
+        let moves = root_moves(&["e2e4", "d2d4"]);
+        let mut multi_principal_variation = MultiPrincipalVariation::initialise();
+
+        let lines = vec![
+            root_line(Some(&moves[0]), 30),
+            root_line(Some(&moves[0]), 10),
+            root_line(None, 0),
+            root_line(Some(&moves[1]), 20),
+        ];
+        let lines = multi_principal_variation.order_lines(lines);
+
+        assert_eq!(line_moves(&lines), moves);
+        assert_eq!(lines[0].evaluation, Evaluation::new(30));
+    }
+}
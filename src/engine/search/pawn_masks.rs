@@ -0,0 +1,118 @@
+use crate::engine::game::{Bitboard, Side, Square};
+
+// Indexed by the Side enum, then by square
+const PASSED_PAWN_MASKS: [[u64; 64]; 2] = generate_passed_pawn_masks();
+const A_FILE_MASK: u64 = 0x0101010101010101;
+const RANK_8_MASK: u64 = 0xff;
+
+pub fn file_mask(file: usize) -> Bitboard {
+    Bitboard::new(A_FILE_MASK << file)
+}
+
+pub fn adjacent_files_mask(file: usize) -> Bitboard {
+    let mut mask = Bitboard::new(0);
+
+    if file > 0 {
+        mask |= file_mask(file - 1);
+    }
+
+    if file < 7 {
+        mask |= file_mask(file + 1);
+    }
+
+    mask
+}
+
+pub fn rank_mask(rank: usize) -> Bitboard {
+    Bitboard::new(RANK_8_MASK << (rank * 8))
+}
+
+// Squares in front of a pawn on its own and the adjacent files, which must be free of opposing
+// pawns for it to be passed
+pub fn passed_pawn_mask(side: Side, square: Square) -> Bitboard {
+    Bitboard::new(PASSED_PAWN_MASKS[side as usize][square as usize])
+}
+
+// Rank 8 is the first rank of the square index, so white pawns advance towards lower ranks
+const fn generate_passed_pawn_masks() -> [[u64; 64]; 2] {
+    let mut masks = [[0; 64]; 2];
+    let mut square = 0;
+
+    while square < 64 {
+        let rank = square / 8;
+        let file = square % 8;
+        let mut mask_square: usize = 0;
+
+        while mask_square < 64 {
+            let mask_rank = mask_square / 8;
+            let mask_file = mask_square % 8;
+
+            if mask_file.abs_diff(file) <= 1 {
+                if mask_rank < rank {
+                    masks[Side::White as usize][square] |= 1 << mask_square;
+                }
+
+                if mask_rank > rank {
+                    masks[Side::Black as usize][square] |= 1 << mask_square;
+                }
+            }
+
+            mask_square += 1;
+        }
+
+        square += 1;
+    }
+
+    masks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn passed_pawn_mask_squares() {
+        let mask = passed_pawn_mask(Side::White, Square::E5);
+
+        assert_eq!(mask.count_bits(), 9);
+        assert!(mask.bit_occupied(Square::D8));
+        assert!(mask.bit_occupied(Square::F6));
+        assert!(!mask.bit_occupied(Square::E5));
+        assert!(!mask.bit_occupied(Square::G7));
+
+        let mask = passed_pawn_mask(Side::Black, Square::A7);
+
+        assert_eq!(mask.count_bits(), 12);
+        assert!(mask.bit_occupied(Square::B1));
+        assert!(!mask.bit_occupied(Square::C2));
+    }
+
+    #[test]
+    fn file_and_rank_masks() {
+        assert_eq!(file_mask(0).count_bits(), 8);
+        assert!(file_mask(4).bit_occupied(Square::E1));
+        assert!(file_mask(4).bit_occupied(Square::E8));
+        assert!(!file_mask(4).bit_occupied(Square::D4));
+        assert_eq!(adjacent_files_mask(0), file_mask(1));
+        assert_eq!(adjacent_files_mask(3), file_mask(2) | file_mask(4));
+        assert_eq!(adjacent_files_mask(7), file_mask(6));
+        assert!(rank_mask(1).bit_occupied(Square::A7));
+        assert!(rank_mask(1).bit_occupied(Square::H7));
+        assert_eq!(rank_mask(1).count_bits(), 8);
+    }
+
+    #[test]
+    fn black_masks_mirror_white() {
+        for square in Square::iter() {
+            let white_mask = passed_pawn_mask(Side::White, square.horizontal_mirror());
+            let black_mask = passed_pawn_mask(Side::Black, square);
+
+            assert_eq!(white_mask.count_bits(), black_mask.count_bits());
+
+            for mask_square in white_mask.squares() {
+                assert!(black_mask.bit_occupied(mask_square.horizontal_mirror()));
+            }
+        }
+    }
+}
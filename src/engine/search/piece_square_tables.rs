@@ -0,0 +1,235 @@
+use super::{evaluation::Value, tapered_value::TaperedValue};
+use crate::engine::game::{Piece, Side, Square};
+
+type PieceSquareTable = [Value; 64];
+
+#[rustfmt::skip]
+const PAWN_MIDDLEGAME_TABLE: PieceSquareTable = [
+     0,  0,  0,   0,   0,  0,  0,  0,
+    30, 30, 30,  40,  40, 30, 30, 30,
+    20, 20, 20,  30,  30, 30, 20, 20,
+    10, 10, 10,  20,  20, 10, 10, 10,
+     5,  5, 10,  20,  20,  5,  5,  5,
+     0,  0,  0,   5,   5,  0,  0,  0,
+     0,  0,  0, -10, -10,  0,  0,  0,
+     0,  0,  0,   0,   0,  0,  0,  0,
+];
+// Pawns are worth more the closer they are to promoting once there are few pieces left to stop them
+#[rustfmt::skip]
+const PAWN_ENDGAME_TABLE: PieceSquareTable = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    80, 80, 80, 80, 80, 80, 80, 80,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    30, 30, 30, 30, 30, 30, 30, 30,
+    15, 15, 15, 15, 15, 15, 15, 15,
+     5,  5,  5,  5,  5,  5,  5,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const KNIGHT_MIDDLEGAME_TABLE: PieceSquareTable = [
+    -5,   0,  0,  0,  0,  0,   0, -5,
+    -5,   0,  0, 10, 10,  0,   0, -5,
+    -5,   5, 20, 20, 20, 20,   5, -5,
+    -5,  10, 20, 30, 30, 20,  10, -5,
+    -5,  10, 20, 30, 30, 20,  10, -5,
+    -5,   5, 20, 10, 10, 20,   5, -5,
+    -5,   0,  0,  0,  0,  0,   0, -5,
+    -5, -10,  0,  0,  0,  0, -10, -5,
+];
+// Knights on the rim cover few squares, which matters more as the pawns come off and the board
+// opens up
+#[rustfmt::skip]
+const KNIGHT_ENDGAME_TABLE: PieceSquareTable = [
+    -30, -20, -10, -10, -10, -10, -20, -30,
+    -20, -10,   0,   5,   5,   0, -10, -20,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -10,   5,  15,  20,  20,  15,   5, -10,
+    -10,   5,  15,  20,  20,  15,   5, -10,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -20, -10,   0,   5,   5,   0, -10, -20,
+    -30, -20, -10, -10, -10, -10, -20, -30,
+];
+#[rustfmt::skip]
+const BISHOP_MIDDLEGAME_TABLE: PieceSquareTable = [
+    0,  0,   0,  0,  0,   0,  0, 0,
+    0,  0,   0,  0,  0,   0,  0, 0,
+    0,  0,   0, 10, 10,   0,  0, 0,
+    0,  0,  10, 20, 20,  10,  0, 0,
+    0,  0,  10, 20, 20,  10,  0, 0,
+    0, 10,   0,  0,  0,   0, 10, 0,
+    0, 30,   0,  0,  0,   0, 30, 0,
+    0,  0, -10,  0,  0, -10,  0, 0,
+];
+#[rustfmt::skip]
+const BISHOP_ENDGAME_TABLE: PieceSquareTable = [
+    -10, -5, -5, -5, -5, -5, -5, -10,
+     -5,  0,  0,  0,  0,  0,  0,  -5,
+     -5,  0,  5,  5,  5,  5,  0,  -5,
+     -5,  0,  5, 10, 10,  5,  0,  -5,
+     -5,  0,  5, 10, 10,  5,  0,  -5,
+     -5,  0,  5,  5,  5,  5,  0,  -5,
+     -5,  0,  0,  0,  0,  0,  0,  -5,
+    -10, -5, -5, -5, -5, -5, -5, -10,
+];
+#[rustfmt::skip]
+const ROOK_MIDDLEGAME_TABLE: PieceSquareTable = [
+    50, 50, 50, 50, 50, 50, 50, 50,
+    50, 50, 50, 50, 50, 50, 50, 50,
+     0,  0, 10, 20, 20, 10,  0,  0,
+     0,  0, 10, 20, 20, 10,  0,  0,
+     0,  0, 10, 20, 20, 10,  0,  0,
+     0,  0, 10, 20, 20, 10,  0,  0,
+     0,  0, 10, 20, 20, 10,  0,  0,
+     0,  0,  0, 20, 20,  0,  0,  0,
+];
+// A rook on the seventh rank still cuts off the king, but there are fewer pawns left there to win
+#[rustfmt::skip]
+const ROOK_ENDGAME_TABLE: PieceSquareTable = [
+     5,  5,  5,  5,  5,  5,  5,  5,
+    20, 20, 20, 20, 20, 20, 20, 20,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const QUEEN_MIDDLEGAME_TABLE: PieceSquareTable = [
+    -10, -5, -5, -5, -5, -5, -5, -10,
+     -5,  0,  0,  0,  0,  0,  0,  -5,
+     -5,  0,  5,  5,  5,  5,  0,  -5,
+     -5,  0,  5,  5,  5,  5,  0,  -5,
+     -5,  0,  5,  5,  5,  5,  0,  -5,
+     -5,  0,  5,  5,  5,  5,  0,  -5,
+     -5,  0,  0,  0,  0,  0,  0,  -5,
+    -10, -5, -5,  0,  0, -5, -5, -10,
+];
+#[rustfmt::skip]
+const QUEEN_ENDGAME_TABLE: PieceSquareTable = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+// The king shelters behind its pawns on either wing while there are pieces left to attack it
+#[rustfmt::skip]
+const KING_MIDDLEGAME_TABLE: PieceSquareTable = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     10,  10,   0,   0,   0,   0,  10,  10,
+     10,  20,  15,   0,   0,   5,  25,  10,
+];
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: PieceSquareTable = [
+    0, 0,  0,  0,   0,  0,  0, 0,
+    0, 0,  5,  5,   5,  5,  0, 0,
+    0, 5,  5, 10,  10,  5,  5, 0,
+    0, 5, 10, 20,  20, 10,  5, 0,
+    0, 5, 10, 20,  20, 10,  5, 0,
+    0, 0,  5, 10,  10,  5,  0, 0,
+    0, 5,  5, -5,  -5,  0,  5, 0,
+    0, 0,  5,  0, -15,  0, 10, 0,
+];
+
+// Middlegame and endgame tables from white's point of view, indexed by the Piece enum - these are
+// the values to tune, with the packed tables below generated from them
+const WHITE_TABLES: [(PieceSquareTable, PieceSquareTable); 6] = [
+    (PAWN_MIDDLEGAME_TABLE, PAWN_ENDGAME_TABLE),
+    (KNIGHT_MIDDLEGAME_TABLE, KNIGHT_ENDGAME_TABLE),
+    (BISHOP_MIDDLEGAME_TABLE, BISHOP_ENDGAME_TABLE),
+    (ROOK_MIDDLEGAME_TABLE, ROOK_ENDGAME_TABLE),
+    (QUEEN_MIDDLEGAME_TABLE, QUEEN_ENDGAME_TABLE),
+    (KING_MIDDLEGAME_TABLE, KING_ENDGAME_TABLE),
+];
+// Indexed by the Piece enum, then by square from white's point of view
+const PIECE_SQUARE_TABLES: [[TaperedValue; 64]; 6] = generate_tables();
+
+// Black's tables are white's mirrored vertically, so black's squares are mirrored onto white's
+pub fn value(piece: Piece, side: Side, square: Square) -> TaperedValue {
+    let square = match side {
+        Side::White => square,
+        Side::Black => square.horizontal_mirror(),
+    };
+
+    PIECE_SQUARE_TABLES[piece as usize][square as usize]
+}
+
+const fn generate_tables() -> [[TaperedValue; 64]; 6] {
+    let mut tables = [[TaperedValue::new(0, 0); 64]; 6];
+    let mut piece = 0;
+
+    while piece < WHITE_TABLES.len() {
+        let (middlegame_table, endgame_table) = WHITE_TABLES[piece];
+        let mut square = 0;
+
+        while square < 64 {
+            tables[piece][square] =
+                TaperedValue::new(middlegame_table[square], endgame_table[square]);
+            square += 1;
+        }
+
+        piece += 1;
+    }
+
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    fn middlegame_value(piece: Piece, side: Side, square: Square) -> Value {
+        value(piece, side, square).middlegame()
+    }
+
+    fn endgame_value(piece: Piece, side: Side, square: Square) -> Value {
+        value(piece, side, square).endgame()
+    }
+
+    #[test]
+    fn known_square_values() {
+        assert_eq!(middlegame_value(Piece::Pawn, Side::White, Square::E4), 20);
+        assert_eq!(middlegame_value(Piece::Pawn, Side::White, Square::D2), -10);
+        assert_eq!(middlegame_value(Piece::Pawn, Side::Black, Square::D7), -10);
+        assert_eq!(endgame_value(Piece::Pawn, Side::White, Square::A7), 80);
+        assert_eq!(endgame_value(Piece::Pawn, Side::Black, Square::A2), 80);
+        assert_eq!(middlegame_value(Piece::Knight, Side::White, Square::D4), 30);
+        assert_eq!(
+            middlegame_value(Piece::Knight, Side::Black, Square::B8),
+            -10
+        );
+        assert_eq!(endgame_value(Piece::Knight, Side::Black, Square::B8), -20);
+        assert_eq!(middlegame_value(Piece::Bishop, Side::White, Square::B2), 30);
+        assert_eq!(middlegame_value(Piece::Rook, Side::Black, Square::H2), 50);
+        assert_eq!(endgame_value(Piece::Rook, Side::Black, Square::H2), 20);
+        assert_eq!(middlegame_value(Piece::Queen, Side::White, Square::D1), 0);
+        assert_eq!(endgame_value(Piece::Queen, Side::Black, Square::E5), 15);
+        assert_eq!(middlegame_value(Piece::King, Side::White, Square::G1), 25);
+        assert_eq!(middlegame_value(Piece::King, Side::Black, Square::E4), -50);
+        assert_eq!(endgame_value(Piece::King, Side::White, Square::G1), 10);
+        assert_eq!(endgame_value(Piece::King, Side::Black, Square::E8), -15);
+    }
+
+    #[test]
+    fn black_tables_mirror_white() {
+        for piece in Piece::iter() {
+            for square in Square::iter() {
+                assert_eq!(
+                    value(piece, Side::Black, square),
+                    value(piece, Side::White, square.horizontal_mirror())
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt::Display;
+
+// Iterations normally take a few times the nodes of the previous one, so a much larger ratio
+// means reductions and their re-searches have started feeding each other
+const EXPLOSION_BRANCHING_FACTOR: u64 = 20;
+// Early iterations are too small for their ratio to mean anything
+const EXPLOSION_NODES_MIN: u64 = 10_000;
+
+// Searches repeated because a reduced depth or narrowed window search was too optimistic, or
+// because the score fell outside the aspiration window, and null move cutoffs which had to be
+// verified, counted over a single iteration
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReSearches {
+    pub late_move_reductions: u64,
+    pub null_windows: u64,
+    pub null_move_verifications: u64,
+    pub aspiration_fail_lows: u64,
+    pub aspiration_fail_highs: u64,
+}
+
+impl ReSearches {
+    pub fn branching_factor(previous_nodes: u64, nodes: u64) -> u64 {
+        nodes / previous_nodes.max(1)
+    }
+
+    pub fn is_explosion(previous_nodes: u64, nodes: u64) -> bool {
+        previous_nodes >= EXPLOSION_NODES_MIN
+            && Self::branching_factor(previous_nodes, nodes) >= EXPLOSION_BRANCHING_FACTOR
+    }
+}
+
+impl Display for ReSearches {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "re-searches lmr {} pvs {} null move verifications {} aspiration low {} high {}",
+            self.late_move_reductions,
+            self.null_windows,
+            self.null_move_verifications,
+            self.aspiration_fail_lows,
+            self.aspiration_fail_highs
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_explosion() {
+        assert_eq!(ReSearches::branching_factor(50_000, 250_000), 5);
+        assert_eq!(ReSearches::branching_factor(0, 30), 30);
+        assert!(!ReSearches::is_explosion(50_000, 250_000));
+        assert!(ReSearches::is_explosion(50_000, 1_000_000));
+        assert!(!ReSearches::is_explosion(100, 10_000));
+    }
+}
@@ -0,0 +1,198 @@
+use super::{
+    evaluation::Value,
+    time_manager::{TimeControl, TimeManager},
+    tune::TuneParameters,
+};
+use crate::engine::game::Side;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// All limits of a search, of which whichever is reached first ends the search
+pub struct SearchLimits {
+    node_limit: Option<u64>,
+    time_manager: Option<TimeManager>,
+    ponder_flag: Option<Arc<AtomicBool>>,
+}
+
+impl SearchLimits {
+    pub fn initialise(
+        node_limit: Option<u64>,
+        time_control: &TimeControl,
+        side: Side,
+        ponder_flag: &Arc<AtomicBool>,
+        tune: &TuneParameters,
+    ) -> Self {
+        let pondering = ponder_flag.load(Ordering::Relaxed);
+
+        Self {
+            node_limit,
+            time_manager: TimeManager::initialise(time_control, side, tune),
+            ponder_flag: pondering.then(|| Arc::clone(ponder_flag)),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self {
+            node_limit: None,
+            time_manager: None,
+            ponder_flag: None,
+        }
+    }
+
+    pub fn time_manager(&self) -> Option<&TimeManager> {
+        self.time_manager.as_ref()
+    }
+
+    pub fn is_time_limited(&mut self) -> bool {
+        self.time_manager.is_some() && !self.pondering()
+    }
+
+    // None of the limits apply while pondering, and the clock only starts once the opponent has
+    // played the expected move
+    pub fn pondering(&mut self) -> bool {
+        let ponder_flag = match &self.ponder_flag {
+            Some(ponder_flag) => ponder_flag,
+            None => return false,
+        };
+
+        if ponder_flag.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        if let Some(time_manager) = &mut self.time_manager {
+            time_manager.restart();
+        }
+
+        self.ponder_flag = None;
+
+        false
+    }
+
+    pub fn update_best_move_stability(&mut self, best_move_changed: bool) {
+        if let Some(time_manager) = &mut self.time_manager {
+            time_manager.update_best_move_stability(best_move_changed);
+        }
+    }
+
+    pub fn update_score_trend(&mut self, score_change: Value) {
+        if let Some(time_manager) = &mut self.time_manager {
+            time_manager.update_score_trend(score_change);
+        }
+    }
+
+    pub fn set_easy_move(&mut self) {
+        if let Some(time_manager) = &mut self.time_manager {
+            time_manager.set_easy_move();
+        }
+    }
+
+    pub fn iteration_allowed(&mut self, nodes_searched: u64) -> bool {
+        if self.pondering() {
+            return true;
+        }
+
+        let time_allowed = match &self.time_manager {
+            Some(time_manager) => time_manager.iteration_allowed(),
+            None => true,
+        };
+
+        time_allowed && !self.node_limit_reached(nodes_searched)
+    }
+
+    pub fn limit_reached(&mut self, nodes_searched: u64) -> bool {
+        if self.pondering() {
+            return false;
+        }
+
+        let hard_time_limit_exceeded = match &self.time_manager {
+            Some(time_manager) => time_manager.hard_limit_exceeded(),
+            None => false,
+        };
+
+        hard_time_limit_exceeded || self.node_limit_reached(nodes_searched)
+    }
+
+    fn node_limit_reached(&self, nodes_searched: u64) -> bool {
+        match self.node_limit {
+            Some(node_limit) => nodes_searched >= node_limit,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn node_limit() {
+        let ponder_flag = Arc::new(AtomicBool::new(false));
+        let mut search_limits = SearchLimits::initialise(
+            Some(1000),
+            &TimeControl::initialise(),
+            Side::White,
+            &ponder_flag,
+            &TuneParameters::default(),
+        );
+
+        assert!(!search_limits.is_time_limited());
+        assert!(search_limits.iteration_allowed(999));
+        assert!(!search_limits.limit_reached(999));
+        assert!(!search_limits.iteration_allowed(1000));
+        assert!(search_limits.limit_reached(1000));
+    }
+
+    #[test]
+    fn combined_limits() {
+        let mut time_control = TimeControl::initialise();
+        time_control.move_time = Some(Duration::ZERO);
+        let ponder_flag = Arc::new(AtomicBool::new(false));
+        let mut search_limits = SearchLimits::initialise(
+            Some(1000),
+            &time_control,
+            Side::White,
+            &ponder_flag,
+            &TuneParameters::default(),
+        );
+
+        assert!(search_limits.is_time_limited());
+        assert!(search_limits.limit_reached(0));
+
+        let mut unlimited = SearchLimits::unlimited();
+
+        assert!(unlimited.iteration_allowed(u64::MAX));
+        assert!(!unlimited.limit_reached(u64::MAX));
+    }
+
+    #[test]
+    fn ponder_limits() {
+        let mut time_control = TimeControl::initialise();
+        time_control.move_time = Some(Duration::from_millis(100));
+        let ponder_flag = Arc::new(AtomicBool::new(true));
+        let mut search_limits = SearchLimits::initialise(
+            Some(1000),
+            &time_control,
+            Side::White,
+            &ponder_flag,
+            &TuneParameters::default(),
+        );
+        thread::sleep(Duration::from_millis(100));
+
+        assert!(!search_limits.is_time_limited());
+        assert!(search_limits.iteration_allowed(1000));
+        assert!(!search_limits.limit_reached(1000));
+
+        ponder_flag.store(false, Ordering::Relaxed);
+
+        assert!(search_limits.is_time_limited());
+        assert!(search_limits.iteration_allowed(0));
+        assert!(search_limits.limit_reached(1000));
+
+        ponder_flag.store(true, Ordering::Relaxed);
+
+        assert!(!search_limits.pondering());
+    }
+}
@@ -0,0 +1,70 @@
+use super::evaluation::Evaluation;
+use crate::engine::{moves::Move, Engine};
+
+// A score from the point of view of the side to move, as reported in info lines
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    // Moves until checkmate, negative if the side to move is getting checkmated
+    Mate(i32),
+}
+
+impl From<Evaluation> for Score {
+    fn from(evaluation: Evaluation) -> Self {
+        match evaluation.mate_moves() {
+            Some(mate_moves) => Self::Mate(mate_moves),
+            None => Self::Centipawns(evaluation.value()),
+        }
+    }
+}
+
+// What a search reports in its last info line and bestmove reply, for programs embedding the
+// engine instead of reading its output
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Move,
+    // None if the search was stopped before its first iteration completed
+    pub score: Option<Score>,
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+    pub depth: u8,
+}
+
+// Sent after every completed iteration, with the best line found so far
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchProgress {
+    pub depth: u8,
+    pub score: Score,
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+}
+
+impl Engine {
+    pub fn search_result(&self, best_move: Move) -> SearchResult {
+        SearchResult {
+            best_move,
+            score: self
+                .search_parameters
+                .last_search_evaluation
+                .map(Score::from),
+            pv: self
+                .search_parameters
+                .last_search_principal_variation
+                .clone(),
+            nodes: self.search_parameters.last_search_nodes,
+            depth: self.search_parameters.last_search_depth,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::evaluation::CHECKMATE_EVALUATION, *};
+
+    #[test]
+    fn convert_scores() {
+        assert_eq!(Score::from(Evaluation::new(-35)), Score::Centipawns(-35));
+        assert_eq!(Score::from(CHECKMATE_EVALUATION - 5), Score::Mate(3));
+        assert_eq!(Score::from(-CHECKMATE_EVALUATION + 2), Score::Mate(-1));
+    }
+}
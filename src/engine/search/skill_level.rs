@@ -0,0 +1,97 @@
+use super::multi_pv::RootLine;
+use crate::random::Random;
+
+pub const SKILL_LEVEL_MAX: i64 = 20;
+// Below the top level the search keeps at least this many lines to choose the move from
+pub const SKILL_LEVEL_LINES: usize = 4;
+
+// Lines are weighted down exponentially by how far they are behind the best line, by a centipawn
+// scale growing this much with each level below the top
+const TEMPERATURE_PER_LEVEL: f64 = 10.0;
+
+// Chooses one of the lines at random, weighted by how close each is to the best line, so lower
+// levels are more likely to play a worse move but rarely a much worse one
+pub fn choose_line<'a>(
+    lines: &'a [RootLine],
+    skill_level: i64,
+    random: &mut Random,
+) -> Option<&'a RootLine> {
+    let best_value = lines.iter().map(|line| line.evaluation.value()).max()?;
+    let temperature =
+        (SKILL_LEVEL_MAX - skill_level.min(SKILL_LEVEL_MAX)) as f64 * TEMPERATURE_PER_LEVEL;
+
+    if temperature == 0.0 {
+        return lines.first();
+    }
+
+    let weights: Vec<f64> = lines
+        .iter()
+        .map(|line| (-(best_value - line.evaluation.value()) as f64 / temperature).exp())
+        .collect();
+    let mut choice = random.generate_u32() as f64 / u32::MAX as f64 * weights.iter().sum::<f64>();
+
+    for (line, weight) in lines.iter().zip(&weights) {
+        if choice < *weight {
+            return Some(line);
+        }
+
+        choice -= weight;
+    }
+
+    lines.last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{evaluation::Evaluation, PrincipalVariation},
+        *,
+    };
+
+    fn line_choice_counts(values: &[i32], skill_level: i64) -> Vec<usize> {
+        let lines: Vec<RootLine> = values
+            .iter()
+            .map(|value| RootLine {
+                evaluation: Evaluation::new(*value),
+                principal_variation: PrincipalVariation::initialise(),
+            })
+            .collect();
+        let mut random = Random::new(1).unwrap();
+        let mut counts = vec![0; lines.len()];
+
+        for _ in 0..1000 {
+            let line = choose_line(&lines, skill_level, &mut random).unwrap();
+            let index = lines
+                .iter()
+                .position(|other_line| std::ptr::eq(other_line, line))
+                .unwrap();
+            counts[index] += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn value_weighted_choice() {
+        assert_eq!(
+            line_choice_counts(&[50, 40, 0], SKILL_LEVEL_MAX),
+            [1000, 0, 0]
+        );
+
+        let counts = line_choice_counts(&[50, 0, -100, -1000], 0);
+
+        assert!(
+            counts[0] > counts[1] && counts[1] > counts[2],
+            "{:?}",
+            counts
+        );
+        assert!(counts[2] > 0, "{:?}", counts);
+        assert!(counts[3] < 20, "{:?}", counts);
+
+        let counts = line_choice_counts(&[50, 0, -100, -1000], 15);
+
+        assert!(counts[0] > 2 * counts[1], "{:?}", counts);
+        assert_eq!(counts[3], 0, "{:?}", counts);
+        assert!(choose_line(&[], 0, &mut Random::new(1).unwrap()).is_none());
+    }
+}
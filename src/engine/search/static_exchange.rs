@@ -0,0 +1,224 @@
+use super::Value;
+use crate::engine::{
+    game::{Bitboard, Game, Piece, Side, Square},
+    moves::{Move, MoveType},
+};
+use num_traits::FromPrimitive;
+use strum::IntoEnumIterator;
+
+// The king is worth more than all other material combined, so capturing with it is only good if
+// the square is no longer defended
+const SEE_PIECE_VALUE: [Value; 6] = [100, 300, 350, 500, 900, 20000];
+// One capture per piece on the board is the longest possible exchange
+const MAX_EXCHANGE_LENGTH: usize = 32;
+
+impl Move {
+    // Capturing a piece worth at least as much as the attacker can never lose material, so the
+    // full exchange only needs working out for the rest
+    pub fn is_losing_capture(&self, game: &Game) -> bool {
+        let victim_value = match game.piece_at_square(self.target_square()) {
+            Some((victim, _)) => SEE_PIECE_VALUE[victim as usize],
+            None => SEE_PIECE_VALUE[Piece::Pawn as usize],
+        };
+
+        victim_value < SEE_PIECE_VALUE[self.piece() as usize]
+            && self.static_exchange_evaluation(game) < 0
+    }
+
+    // Material won by the move itself, before any recapture - an en passant capture takes a pawn
+    // from a square other than the target, and a promotion gains the promoted piece for a pawn
+    pub fn material_gain(&self, game: &Game) -> Value {
+        let victim_value = match (self.move_type(), game.piece_at_square(self.target_square())) {
+            (MoveType::EnPassant, _) => SEE_PIECE_VALUE[Piece::Pawn as usize],
+            (_, Some((victim, _))) => SEE_PIECE_VALUE[victim as usize],
+            (_, None) => 0,
+        };
+        let promotion_value = self.promoted_piece().map_or(0, |promoted_piece| {
+            SEE_PIECE_VALUE[promoted_piece as usize] - SEE_PIECE_VALUE[Piece::Pawn as usize]
+        });
+
+        victim_value + promotion_value
+    }
+
+    // Material balance after both sides keep recapturing on the target square with their least
+    // valuable piece, with either side free to stop the exchange once it stops paying off
+    pub fn static_exchange_evaluation(&self, game: &Game) -> Value {
+        let target_square = self.target_square();
+        let mut occupancy = game.board(None);
+        let mut gains = [0; MAX_EXCHANGE_LENGTH];
+
+        gains[0] = match self.move_type() {
+            MoveType::EnPassant => {
+                if let Some(en_passant_capture_square) = en_passant_capture_square(self) {
+                    occupancy.pop_bit(en_passant_capture_square);
+                }
+
+                SEE_PIECE_VALUE[Piece::Pawn as usize]
+            }
+            _ => match game.piece_at_square(target_square) {
+                Some((victim, _)) => SEE_PIECE_VALUE[victim as usize],
+                None => 0,
+            },
+        };
+
+        let mut attacker = self.piece();
+        let mut attacker_square = self.source_square();
+        let mut side = game.side_to_move().opponent_side();
+        let mut depth = 0;
+
+        loop {
+            depth += 1;
+            // Stored speculatively, in case the attacker is recaptured
+            gains[depth] = SEE_PIECE_VALUE[attacker as usize] - gains[depth - 1];
+
+            if (-gains[depth - 1]).max(gains[depth]) < 0 || depth == MAX_EXCHANGE_LENGTH - 1 {
+                break;
+            }
+
+            // Removing the attacker reveals any slider behind it in the attack tables
+            occupancy.pop_bit(attacker_square);
+
+            match least_valuable_attacker(game, occupancy, side, target_square) {
+                Some((piece, square)) => {
+                    attacker = piece;
+                    attacker_square = square;
+                }
+                None => break,
+            }
+
+            side = side.opponent_side();
+        }
+
+        while depth > 1 {
+            depth -= 1;
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+        }
+
+        gains[0]
+    }
+}
+
+fn least_valuable_attacker(
+    game: &Game,
+    occupancy: Bitboard,
+    side: Side,
+    square: Square,
+) -> Option<(Piece, Square)> {
+    let attackers = game.attackers_to(square, occupancy) & game.board(Some(side));
+
+    if attackers == 0u64 {
+        return None;
+    }
+
+    Piece::iter().find_map(|piece| {
+        (attackers & game.piece_bitboard(piece, side))
+            .get_lsb_square()
+            .map(|attacker_square| (piece, attacker_square))
+    })
+}
+
+fn en_passant_capture_square(mv: &Move) -> Option<Square> {
+    let target_square_index = mv.target_square() as usize;
+
+    match mv.target_square().rank() {
+        2 => Square::from_usize(target_square_index + 8),
+        _ => Square::from_usize(target_square_index - 8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::moves::{MoveFlag, MoveList};
+
+    fn static_exchange_evaluation(fen: &str, move_string: &str) -> Value {
+        let mut game = Game::initialise();
+        let fen: Vec<&str> = fen.split_whitespace().collect();
+        game.load_fen(&fen).unwrap();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let mv = move_list.find_move_from_string(move_string).unwrap();
+
+        mv.static_exchange_evaluation(&game)
+    }
+
+    #[test]
+    fn undefended_capture() {
+        let fen = "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1";
+
+        assert_eq!(static_exchange_evaluation(fen, "e1e5"), 100);
+    }
+
+    #[test]
+    fn defended_capture() {
+        let fen = "4k3/8/3p4/4p3/8/5N2/8/4K3 w - - 0 1";
+
+        assert_eq!(static_exchange_evaluation(fen, "f3e5"), -200);
+
+        let fen = "4k3/8/3p4/4p3/3P4/8/8/4K3 w - - 0 1";
+
+        assert_eq!(static_exchange_evaluation(fen, "d4e5"), 0);
+    }
+
+    #[test]
+    fn losing_capture() {
+        let mut game = Game::initialise();
+        let fen = vec!["4k3/8/3p4/2n1r3/8/5N2/8/4K3", "w", "-", "-", "0", "1"];
+        game.load_fen(&fen).unwrap();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let knight_takes_rook = move_list.find_move_from_string("f3e5").unwrap();
+
+        assert!(!knight_takes_rook.is_losing_capture(&game));
+
+        let fen = vec!["4k3/8/3p4/4p3/8/5N2/8/4K3", "w", "-", "-", "0", "1"];
+        game.load_fen(&fen).unwrap();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let knight_takes_pawn = move_list.find_move_from_string("f3e5").unwrap();
+
+        assert!(knight_takes_pawn.is_losing_capture(&game));
+    }
+
+    #[test]
+    fn capture_material_gain() {
+        let mut game = Game::initialise();
+        let fen = vec!["r3k3/1P6/8/3pP3/2n5/1N6/8/4K3", "w", "-", "d6", "0", "1"];
+        game.load_fen(&fen).unwrap();
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let material_gain = |mv: &str| {
+            move_list
+                .find_move_from_string(mv)
+                .unwrap()
+                .material_gain(&game)
+        };
+
+        assert_eq!(material_gain("b3c5"), 0);
+        assert_eq!(material_gain("b3d4"), 0);
+        assert_eq!(material_gain("e5d6"), 100);
+        assert_eq!(material_gain("b7b8q"), 800);
+        assert_eq!(material_gain("b7a8n"), 700);
+    }
+
+    #[test]
+    fn x_ray_recapture() {
+        let fen = "4r1k1/8/8/4n3/8/8/4R3/4R1K1 w - - 0 1";
+
+        assert_eq!(static_exchange_evaluation(fen, "e2e5"), 300);
+    }
+
+    #[test]
+    fn king_cannot_recapture_defended_square() {
+        let fen = "8/8/8/3k4/4p3/8/6B1/4K3 w - - 0 1";
+
+        assert_eq!(static_exchange_evaluation(fen, "g2e4"), -250);
+
+        let fen = "8/8/8/3k4/4p3/3P4/6B1/4K3 w - - 0 1";
+
+        assert_eq!(static_exchange_evaluation(fen, "g2e4"), 100);
+    }
+
+    #[test]
+    fn en_passant_capture() {
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+
+        assert_eq!(static_exchange_evaluation(fen, "e5d6"), 100);
+    }
+}
@@ -0,0 +1,118 @@
+use super::Value;
+use crate::engine::zobrist_hashes::ZobristKey;
+
+pub const ELO_MIN: i64 = 1000;
+pub const ELO_MAX: i64 = 2500;
+
+// The strength model is tuned through the limits at either end of the Elo range, with every
+// target in between interpolated - the node limit on a logarithmic scale, as each doubling of the
+// nodes searched is worth roughly the same amount of strength
+const DEPTH_RANGE: (u8, u8) = (1, 12);
+const NODE_LIMIT_RANGE: (u64, u64) = (100, 1_000_000);
+const EVALUATION_NOISE_RANGE: (Value, Value) = (300, 0);
+
+// Spreads the bits of the position key so neighbouring keys get unrelated noise
+const NOISE_HASH_MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// How a search is weakened to play at a target Elo - the evaluation noise is a fixed function of
+// the position and seed, so the same game played with the same seed gets the same moves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StrengthLimit {
+    depth: u8,
+    node_limit: u64,
+    evaluation_noise: Value,
+    seed: u64,
+}
+
+impl StrengthLimit {
+    pub fn from_elo(elo: i64, seed: u32) -> Self {
+        let strength = (elo.clamp(ELO_MIN, ELO_MAX) - ELO_MIN) as f64 / (ELO_MAX - ELO_MIN) as f64;
+        let interpolate = |low: f64, high: f64| low + (high - low) * strength;
+
+        Self {
+            depth: interpolate(DEPTH_RANGE.0 as f64, DEPTH_RANGE.1 as f64).round() as u8,
+            node_limit: interpolate(
+                (NODE_LIMIT_RANGE.0 as f64).ln(),
+                (NODE_LIMIT_RANGE.1 as f64).ln(),
+            )
+            .exp()
+            .round() as u64,
+            evaluation_noise: interpolate(
+                EVALUATION_NOISE_RANGE.0 as f64,
+                EVALUATION_NOISE_RANGE.1 as f64,
+            )
+            .round() as Value,
+            seed: (seed as u64) << 32 | seed as u64,
+        }
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn node_limit(&self) -> u64 {
+        self.node_limit
+    }
+
+    // Spread evenly from minus to plus the noise amplitude
+    pub fn evaluation_noise(&self, zobrist_key: ZobristKey) -> Value {
+        if self.evaluation_noise == 0 {
+            return 0;
+        }
+
+        let hash = (zobrist_key ^ self.seed).wrapping_mul(NOISE_HASH_MULTIPLIER) >> 32;
+
+        (hash % (2 * self.evaluation_noise as u64 + 1)) as Value - self.evaluation_noise
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_from_elo() {
+        let weakest = StrengthLimit::from_elo(ELO_MIN, 1);
+        let strongest = StrengthLimit::from_elo(ELO_MAX, 1);
+
+        assert_eq!(weakest.depth(), DEPTH_RANGE.0);
+        assert_eq!(weakest.node_limit(), NODE_LIMIT_RANGE.0);
+        assert_eq!(weakest.evaluation_noise, EVALUATION_NOISE_RANGE.0);
+        assert_eq!(strongest.depth(), DEPTH_RANGE.1);
+        assert_eq!(strongest.node_limit(), NODE_LIMIT_RANGE.1);
+        assert_eq!(strongest.evaluation_noise(0x1234), 0);
+        assert_eq!(StrengthLimit::from_elo(0, 1), weakest);
+
+        let middle = StrengthLimit::from_elo((ELO_MIN + ELO_MAX) / 2, 1);
+
+        assert_eq!(middle.node_limit(), 10_000);
+        assert_eq!(middle.evaluation_noise, 150);
+    }
+
+    #[test]
+    fn evaluation_noise_per_seed() {
+        let strength_limit = StrengthLimit::from_elo(ELO_MIN, 1);
+        let same_seed_limit = StrengthLimit::from_elo(ELO_MIN, 1);
+        let other_seed_limit = StrengthLimit::from_elo(ELO_MIN, 2);
+        let keys = [0, 1, 0xDEAD_BEEF, u64::MAX, 0x0123_4567_89AB_CDEF];
+
+        let noise: Vec<Value> = keys
+            .iter()
+            .map(|key| strength_limit.evaluation_noise(*key))
+            .collect();
+        let same_seed_noise: Vec<Value> = keys
+            .iter()
+            .map(|key| same_seed_limit.evaluation_noise(*key))
+            .collect();
+        let other_seed_noise: Vec<Value> = keys
+            .iter()
+            .map(|key| other_seed_limit.evaluation_noise(*key))
+            .collect();
+
+        assert_eq!(noise, same_seed_noise);
+        assert_ne!(noise, other_seed_noise);
+        assert!(noise
+            .iter()
+            .all(|noise| noise.abs() <= EVALUATION_NOISE_RANGE.0));
+    }
+}
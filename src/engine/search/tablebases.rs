@@ -0,0 +1,429 @@
+use super::evaluation::{Evaluation, Value, TABLEBASE_WIN_EVALUATION};
+use crate::{
+    engine::{
+        attack_tables::ATTACK_TABLES,
+        game::{Bitboard, Game, Piece, Side, Square},
+        moves::{Move, MoveFlag, MoveList},
+    },
+    uci::{InputError, SyzygyPathError},
+};
+use num_traits::FromPrimitive;
+use pyrrhic_rs::{Color, DtzProbeValue, EngineAdapter, TBError, TableBases, WdlProbeResult};
+use std::{fs, path::Path};
+
+// The prober generates its own moves with the engine's attack tables - it numbers squares from A1
+// rather than A8, so ranks are flipped on the way in and out
+#[derive(Clone)]
+struct AttackTablesAdapter;
+
+impl EngineAdapter for AttackTablesAdapter {
+    fn pawn_attacks(color: Color, square: u64) -> u64 {
+        let side = match color {
+            Color::White => Side::White,
+            Color::Black => Side::Black,
+        };
+
+        attacks(Piece::Pawn, side, square, 0)
+    }
+
+    fn knight_attacks(square: u64) -> u64 {
+        attacks(Piece::Knight, Side::White, square, 0)
+    }
+
+    fn bishop_attacks(square: u64, occupied: u64) -> u64 {
+        attacks(Piece::Bishop, Side::White, square, occupied)
+    }
+
+    fn rook_attacks(square: u64, occupied: u64) -> u64 {
+        attacks(Piece::Rook, Side::White, square, occupied)
+    }
+
+    fn queen_attacks(square: u64, occupied: u64) -> u64 {
+        attacks(Piece::Queen, Side::White, square, occupied)
+    }
+
+    fn king_attacks(square: u64) -> u64 {
+        attacks(Piece::King, Side::White, square, 0)
+    }
+}
+
+fn attacks(piece: Piece, side: Side, square: u64, occupied: u64) -> u64 {
+    let square = Square::from_u64(flip_square(square)).unwrap();
+    let board = Bitboard::new(occupied.swap_bytes());
+
+    ATTACK_TABLES
+        .attack_table(board, piece, side, square)
+        .value()
+        .swap_bytes()
+}
+
+fn flip_square(square: u64) -> u64 {
+    square ^ 56
+}
+
+// The tables are opened once per process by the prober, so the same tables are shared with the
+// helper threads rather than opened by each of them
+pub struct Tablebases {
+    tables: TableBases<AttackTablesAdapter>,
+    max_pieces: u32,
+}
+
+impl Tablebases {
+    // Several directories can be given, separated by colons - they are looked through first, as
+    // the prober counts every table it knows of as found, and cannot be opened again after being
+    // opened on a path without any tables
+    pub fn load(syzygy_path: &str) -> Result<Self, InputError> {
+        let max_pieces = syzygy_path
+            .split(':')
+            .flat_map(|directory| fs::read_dir(directory).into_iter().flatten().flatten())
+            .filter_map(|entry| table_pieces(&entry.path()))
+            .max()
+            .ok_or(InputError::SyzygyPath(SyzygyPathError::NoTables))?;
+
+        let tables = TableBases::new(syzygy_path).map_err(|error| {
+            InputError::SyzygyPath(match error {
+                TBError::BadPath => SyzygyPathError::NoTables,
+                TBError::AlreadyInitialized => SyzygyPathError::InUse,
+                _ => SyzygyPathError::InitialisationFailed,
+            })
+        })?;
+
+        Ok(Self { tables, max_pieces })
+    }
+
+    pub fn max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    // Only positions straight after a capture or pawn move are probed, as the tables ignore the
+    // fifty move counter - a win could otherwise be scored which the counter turns into a draw
+    pub fn probe_wdl(
+        &self,
+        game: &Game,
+        ply: Value,
+        draw_evaluation: Evaluation,
+    ) -> Option<Evaluation> {
+        if !self.is_probeable(game) || game.halfmove_clock() != 0 {
+            return None;
+        }
+
+        let position = ProbePosition::from_game(game);
+        let wdl = self
+            .tables
+            .probe_wdl(
+                position.white,
+                position.black,
+                position.kings,
+                position.queens,
+                position.rooks,
+                position.bishops,
+                position.knights,
+                position.pawns,
+                position.en_passant,
+                position.white_to_move,
+            )
+            .ok()?;
+
+        Some(wdl_evaluation(wdl, ply, draw_evaluation))
+    }
+
+    // The move which keeps the best result with the fewest moves to the next capture or pawn move,
+    // which also takes the fifty move counter into account, with the evaluation of that result
+    pub fn probe_root(
+        &self,
+        game: &Game,
+        draw_evaluation: Evaluation,
+    ) -> Option<(Move, Evaluation)> {
+        if !self.is_probeable(game) {
+            return None;
+        }
+
+        let position = ProbePosition::from_game(game);
+        let probe_result = self
+            .tables
+            .probe_root(
+                position.white,
+                position.black,
+                position.kings,
+                position.queens,
+                position.rooks,
+                position.bishops,
+                position.knights,
+                position.pawns,
+                game.halfmove_clock() as u32,
+                position.en_passant,
+                position.white_to_move,
+            )
+            .ok()?;
+
+        let dtz_result = match probe_result.root {
+            DtzProbeValue::DtzResult(dtz_result) => dtz_result,
+            _ => return None,
+        };
+        let source_square = Square::from_u8(flip_square(dtz_result.from_square as u64) as u8)?;
+        let target_square = Square::from_u8(flip_square(dtz_result.to_square as u64) as u8)?;
+        let promoted_piece = match dtz_result.promotion {
+            pyrrhic_rs::Piece::Queen => Some(Piece::Queen),
+            pyrrhic_rs::Piece::Rook => Some(Piece::Rook),
+            pyrrhic_rs::Piece::Bishop => Some(Piece::Bishop),
+            pyrrhic_rs::Piece::Knight => Some(Piece::Knight),
+            _ => None,
+        };
+        let move_list = MoveList::generate_moves(game, MoveFlag::All);
+        let mv = *move_list.vec().iter().find(|mv| {
+            mv.source_square() == source_square
+                && mv.target_square() == target_square
+                && mv.promoted_piece() == promoted_piece
+        })?;
+
+        Some((mv, wdl_evaluation(dtz_result.wdl, 0, draw_evaluation)))
+    }
+
+    // The tables hold no positions where either side can still castle
+    fn is_probeable(&self, game: &Game) -> bool {
+        game.board(None).count_bits() <= self.max_pieces() && game.castling_rights_value() == 0
+    }
+}
+
+// The pieces of a table are in the name of its file, such as KRPvKR for five pieces
+fn table_pieces(path: &Path) -> Option<u32> {
+    if path.extension()? != "rtbw" {
+        return None;
+    }
+
+    let table_name = path.file_stem()?.to_str()?;
+
+    Some(
+        table_name
+            .chars()
+            .filter(|character| *character != 'v')
+            .count() as u32,
+    )
+}
+
+// Wins and losses the fifty move rule turns into draws are scored just either side of a draw
+fn wdl_evaluation(wdl: WdlProbeResult, ply: Value, draw_evaluation: Evaluation) -> Evaluation {
+    match wdl {
+        WdlProbeResult::Win => TABLEBASE_WIN_EVALUATION - ply,
+        WdlProbeResult::CursedWin => draw_evaluation + 1,
+        WdlProbeResult::Draw => draw_evaluation,
+        WdlProbeResult::BlessedLoss => draw_evaluation - 1,
+        WdlProbeResult::Loss => -TABLEBASE_WIN_EVALUATION + ply,
+    }
+}
+
+// A position as the prober takes it, with the bitboards of each side and piece type
+struct ProbePosition {
+    white: u64,
+    black: u64,
+    kings: u64,
+    queens: u64,
+    rooks: u64,
+    bishops: u64,
+    knights: u64,
+    pawns: u64,
+    // Zero when no en passant capture is possible, as no pawn can ever move to A1
+    en_passant: u32,
+    white_to_move: bool,
+}
+
+impl ProbePosition {
+    fn from_game(game: &Game) -> Self {
+        let pieces = |piece| {
+            (game.piece_bitboard(piece, Side::White) | game.piece_bitboard(piece, Side::Black))
+                .value()
+                .swap_bytes()
+        };
+
+        Self {
+            white: game.board(Some(Side::White)).value().swap_bytes(),
+            black: game.board(Some(Side::Black)).value().swap_bytes(),
+            kings: pieces(Piece::King),
+            queens: pieces(Piece::Queen),
+            rooks: pieces(Piece::Rook),
+            bishops: pieces(Piece::Bishop),
+            knights: pieces(Piece::Knight),
+            pawns: pieces(Piece::Pawn),
+            en_passant: match game.en_passant_square() {
+                Some(square) => flip_square(square as u64) as u32,
+                None => 0,
+            },
+            white_to_move: game.side_to_move() == Side::White,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::Engine, *};
+    use crate::{engine::options, uci};
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    const SYZYGY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy");
+
+    // Only one set of tables can be open at a time, so tests which open them take turns
+    static TABLEBASES_LOCK: Mutex<()> = Mutex::new(());
+
+    fn tablebases_lock() -> MutexGuard<'static, ()> {
+        TABLEBASES_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn game(fen: &str) -> Game {
+        Game::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn adapter_attacks() {
+        // B1 in the prober's numbering
+        assert_eq!(
+            AttackTablesAdapter::knight_attacks(1),
+            0x0000_0000_0005_0800
+        );
+        // A rook on A1 blocked by a piece on A3
+        assert_eq!(
+            AttackTablesAdapter::rook_attacks(0, 0x0000_0000_0001_0000),
+            0x0000_0000_0001_01FE
+        );
+        assert_eq!(
+            AttackTablesAdapter::pawn_attacks(Color::White, 12),
+            0x0000_0000_0028_0000
+        );
+        assert_eq!(
+            AttackTablesAdapter::pawn_attacks(Color::Black, 12),
+            0x0000_0000_0000_0028
+        );
+    }
+
+    #[test]
+    fn probe_win_draw_loss() {
+        let _lock = tablebases_lock();
+        let tablebases = Tablebases::load(SYZYGY_PATH).unwrap();
+        let draw = Evaluation::new(0);
+
+        assert_eq!(tablebases.max_pieces(), 4);
+        assert_eq!(
+            tablebases.probe_wdl(&game("6k1/8/8/3P4/4K3/8/8/8 w - - 0 1"), 3, draw),
+            Some(TABLEBASE_WIN_EVALUATION - 3)
+        );
+        assert_eq!(
+            tablebases.probe_wdl(&game("6k1/8/8/3P4/4K3/8/8/8 b - - 0 1"), 3, draw),
+            Some(draw)
+        );
+        assert_eq!(
+            tablebases.probe_wdl(&game("8/8/8/8/8/2k5/8/K6q w - - 0 1"), 5, draw),
+            Some(-TABLEBASE_WIN_EVALUATION + 5)
+        );
+        assert_eq!(
+            tablebases.probe_wdl(&game("8/8/8/8/4k3/8/8/R3K3 b - - 0 1"), 1, draw),
+            Some(-TABLEBASE_WIN_EVALUATION + 1)
+        );
+
+        // Castling rights, more pieces than the tables hold, or a position which the fifty move
+        // counter may already have turned into a draw
+        assert_eq!(
+            tablebases.probe_wdl(&game("8/8/8/8/4k3/8/8/R3K3 b Q - 0 1"), 1, draw),
+            None
+        );
+        assert_eq!(
+            tablebases.probe_wdl(&game("6k1/8/8/3P4/4K3/8/4P3/R7 w - - 0 1"), 1, draw),
+            None
+        );
+        assert_eq!(
+            tablebases.probe_wdl(&game("6k1/8/8/3P4/4K3/8/8/8 w - - 12 30"), 1, draw),
+            None
+        );
+    }
+
+    #[test]
+    fn probe_root_move() {
+        let _lock = tablebases_lock();
+        let tablebases = Tablebases::load(SYZYGY_PATH).unwrap();
+        let draw = Evaluation::new(0);
+
+        let (mv, evaluation) = tablebases
+            .probe_root(&game("6k1/8/8/3P4/4K3/8/8/8 w - - 0 1"), draw)
+            .unwrap();
+
+        assert_eq!(mv.as_string(), "d5d6");
+        assert_eq!(evaluation, TABLEBASE_WIN_EVALUATION);
+
+        // Checkmate ends the game sooner than any capture or pawn move could
+        let (mv, evaluation) = tablebases
+            .probe_root(&game("8/8/8/8/8/5k2/r7/5K2 b - - 40 80"), draw)
+            .unwrap();
+
+        assert_eq!(mv.as_string(), "a2a1");
+        assert_eq!(evaluation, TABLEBASE_WIN_EVALUATION);
+
+        let (mv, _) = tablebases
+            .probe_root(&game("8/1P6/8/8/8/8/5k2/7K w - - 0 1"), draw)
+            .unwrap();
+
+        assert_eq!(mv.as_string(), "b7b8q");
+    }
+
+    #[test]
+    fn load_errors() {
+        let _lock = tablebases_lock();
+
+        assert!(matches!(
+            Tablebases::load(env!("CARGO_MANIFEST_DIR")),
+            Err(InputError::SyzygyPath(SyzygyPathError::NoTables))
+        ));
+
+        let tablebases = Tablebases::load(SYZYGY_PATH).unwrap();
+
+        assert!(matches!(
+            Tablebases::load(SYZYGY_PATH),
+            Err(InputError::SyzygyPath(SyzygyPathError::InUse))
+        ));
+
+        drop(tablebases);
+
+        assert!(Tablebases::load(SYZYGY_PATH).is_ok());
+    }
+
+    #[test]
+    fn search_with_tablebases() {
+        let _lock = tablebases_lock();
+        let (output, buffer) = uci::captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        engine
+            .set_option(options::SYZYGY_PATH, SYZYGY_PATH)
+            .unwrap();
+
+        // Taking a rook leaves an ending which is probed instead of searched
+        let fen = vec!["4k3/8/8/8/8/8/R5K1/r6R", "b", "-", "-", "0", "1"];
+        engine.load_fen(&fen).unwrap();
+        engine.search_best_move(3).unwrap();
+        let last_line = buffer.lines().pop().unwrap();
+        let tablebase_hits = last_line
+            .split_whitespace()
+            .skip_while(|token| *token != "tbhits")
+            .nth(1)
+            .unwrap();
+
+        assert_ne!(tablebase_hits, "0", "{}", last_line);
+
+        let fen = vec!["8/8/8/8/8/5k2/r7/5K2", "b", "-", "-", "40", "80"];
+        engine.load_fen(&fen).unwrap();
+        let best_move = engine.search_best_move(10).unwrap();
+
+        assert_eq!(best_move.as_string(), "a2a1");
+        assert!(buffer.lines().iter().any(|line| line.starts_with(&format!(
+            "info depth 1 score cp {} nodes 0 ",
+            TABLEBASE_WIN_EVALUATION.value()
+        )) && line.contains(" tbhits 1 ")
+            && line.ends_with(" pv a2a1")));
+
+        // Without the tablebases the same position is searched as usual
+        engine.set_option(options::SYZYGY_PATH, "<empty>").unwrap();
+        let best_move = engine.search_best_move(4).unwrap();
+
+        assert_eq!(best_move.as_string(), "a2a1");
+        assert!(buffer.lines().last().unwrap().contains(" tbhits 0 "));
+    }
+}
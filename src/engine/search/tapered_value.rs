@@ -0,0 +1,111 @@
+use super::evaluation::Value;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+// Phase of a game with all the pieces still on the board, falling to zero once only kings and
+// pawns are left
+pub const PHASE_MAX: Value = 24;
+
+// A middlegame and an endgame value packed into one integer, with the endgame value in the upper
+// half, so both are added up together - they are only blended by the game phase once the whole
+// evaluation is summed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TaperedValue(i32);
+
+impl TaperedValue {
+    pub const fn new(middlegame: Value, endgame: Value) -> Self {
+        Self((endgame << 16) + middlegame)
+    }
+
+    pub fn middlegame(self) -> Value {
+        self.0 as i16 as Value
+    }
+
+    // Rounds up to undo the borrow taken from the upper half by a negative middlegame value
+    pub fn endgame(self) -> Value {
+        ((self.0 + 0x8000) >> 16) as i16 as Value
+    }
+
+    pub fn taper(self, phase: Value) -> Value {
+        let phase = phase.clamp(0, PHASE_MAX);
+
+        (self.middlegame() * phase + self.endgame() * (PHASE_MAX - phase)) / PHASE_MAX
+    }
+}
+
+impl Add for TaperedValue {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for TaperedValue {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Mul<Value> for TaperedValue {
+    type Output = Self;
+
+    fn mul(self, rhs: Value) -> Self::Output {
+        Self(self.0 * rhs)
+    }
+}
+
+impl Neg for TaperedValue {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl Sub for TaperedValue {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for TaperedValue {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_arithmetic() {
+        let value = TaperedValue::new(-30, 45);
+        let other_value = TaperedValue::new(120, -200);
+
+        assert_eq!((value.middlegame(), value.endgame()), (-30, 45));
+        assert_eq!(value + other_value, TaperedValue::new(90, -155));
+        assert_eq!(value - other_value, TaperedValue::new(-150, 245));
+        assert_eq!(-other_value, TaperedValue::new(-120, 200));
+        assert_eq!(value * 3, TaperedValue::new(-90, 135));
+
+        let mut total = value;
+        total += other_value;
+        total -= value;
+
+        assert_eq!(total, other_value);
+        assert_eq!(TaperedValue::default(), TaperedValue::new(0, 0));
+    }
+
+    #[test]
+    fn phase_blending() {
+        let value = TaperedValue::new(100, 300);
+
+        assert_eq!(value.taper(PHASE_MAX), 100);
+        assert_eq!(value.taper(0), 300);
+        assert_eq!(value.taper(PHASE_MAX / 2), 200);
+        assert_eq!(value.taper(PHASE_MAX + 8), 100);
+    }
+}
@@ -0,0 +1,159 @@
+use super::TimeControl;
+use crate::{
+    engine::{
+        epd::EpdRecord,
+        moves::{Move, MoveFlag, MoveList},
+        Engine, MAX_PLY,
+    },
+    uci::InputError,
+};
+use std::{fs, time::Duration};
+
+pub const TEST_SUITE_MOVE_TIME_DEFAULT: Duration = Duration::from_millis(1000);
+
+impl Engine {
+    // Searches every position of an EPD test suite for a fixed time with an empty hash table,
+    // counting it as solved when the best move is one of its bm moves and none of its am moves -
+    // the current engine is left untouched, as with the bench
+    pub fn test_suite(&self, file_path: &str, move_time: Duration) -> Result<usize, InputError> {
+        let contents = fs::read_to_string(file_path)
+            .map_err(|error| InputError::TestSuiteFile(error.kind()))?;
+
+        let mut engine = Engine::initialise();
+        engine.set_output(self.output.clone());
+        engine.search_parameters.silent = true;
+
+        let time_control = TimeControl {
+            move_time: Some(move_time),
+            ..TimeControl::initialise()
+        };
+        let mut solved = 0;
+        let mut total = 0;
+
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // A suite with a broken line is still worth running, so the line is only reported
+            let record: EpdRecord = match line.parse() {
+                Ok(record) => record,
+                Err(error) => {
+                    engine.output.line(format!(
+                        "info string testsuite line {} skipped: {}",
+                        index + 1,
+                        error
+                    ));
+
+                    continue;
+                }
+            };
+
+            let fen: Vec<&str> = record.fen().split_whitespace().collect();
+            engine.load_fen(&fen)?;
+
+            let move_list = MoveList::generate_moves(&engine.game, MoveFlag::All);
+            let find_moves = |sans: &[String]| -> Vec<Move> {
+                sans.iter()
+                    .filter_map(|san| move_list.find_move_from_san(san).ok())
+                    .collect()
+            };
+            let best_moves = find_moves(record.best_moves());
+            let avoid_moves = find_moves(record.avoid_moves());
+
+            if best_moves.is_empty() && avoid_moves.is_empty() {
+                continue;
+            }
+
+            engine.clear_transposition_table();
+            engine.set_search_limits(None, &time_control);
+            let best_move = engine.search_best_move(MAX_PLY as u8).ok();
+            let position_solved = best_move.is_some_and(|mv| {
+                (best_moves.is_empty() || best_moves.contains(&mv)) && !avoid_moves.contains(&mv)
+            });
+
+            total += 1;
+
+            if position_solved {
+                solved += 1;
+            }
+
+            // The suite's own evaluation is shown next to the engine's for comparison, but plays no
+            // part in whether the position is solved
+            let evaluations = match record.centipawn_evaluation() {
+                Some(centipawn_evaluation) => format!(
+                    " score {} ce {}",
+                    engine
+                        .search_parameters
+                        .last_search_evaluation
+                        .map_or("(none)".to_string(), |evaluation| evaluation.uci_score()),
+                    centipawn_evaluation
+                ),
+                None => String::new(),
+            };
+
+            engine.output.line(format!(
+                "info string testsuite {} bestmove {} {}{}",
+                record.id().unwrap_or(&(index + 1).to_string()),
+                best_move.map_or("(none)".to_string(), |mv| mv.to_san(&engine.game)),
+                match position_solved {
+                    true => "solved",
+                    false => "failed",
+                },
+                evaluations
+            ));
+        }
+
+        engine.output.line("===========================");
+        engine
+            .output
+            .line(format!("Solved          : {}/{}", solved, total));
+
+        Ok(solved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci;
+    use std::env;
+
+    #[test]
+    fn solve_test_suite() {
+        let file_path = env::temp_dir().join("pineapple_solve_test_suite.epd");
+        fs::write(
+            &file_path,
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; ce 32766; id \"back rank\";\n\
+            \n\
+            6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - am Kf1; id \"no bm\";\n\
+            not an epd line\n\
+            6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Kf1; id \"wrong bm\";\n",
+        )
+        .unwrap();
+
+        let (output, buffer) = uci::captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        let solved = engine
+            .test_suite(file_path.to_str().unwrap(), Duration::from_millis(200))
+            .unwrap();
+        fs::remove_file(&file_path).unwrap();
+
+        let lines = buffer.lines();
+
+        assert_eq!(solved, 2);
+        assert!(lines.contains(
+            &"info string testsuite back rank bestmove Ra8# solved score mate 1 ce 32766"
+                .to_string()
+        ));
+        assert!(lines.contains(&"info string testsuite wrong bm bestmove Ra8# failed".to_string()));
+        assert!(matches!(
+            engine.test_suite(
+                "pineapple_missing_test_suite.epd",
+                Duration::from_millis(50)
+            ),
+            Err(InputError::TestSuiteFile(_))
+        ));
+    }
+}
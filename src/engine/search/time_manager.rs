@@ -0,0 +1,333 @@
+use super::{evaluation::Value, tune::TuneParameters};
+use crate::engine::game::Side;
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+// Time kept in reserve to account for communication delays with the GUI
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+const HARD_LIMIT_TIME_LEFT_PERCENTAGE: u32 = 80;
+// A search still running at this point has overrun its hard limit, and would lose on time
+const EMERGENCY_TIME_LEFT_PERCENTAGE: u32 = 90;
+// Soft limit scaling obtained by indexing into array using the number of iterations the best move
+// has been stable for, so the search thinks longer on unstable best moves
+const BEST_MOVE_STABILITY_PERCENTAGES: [u32; 5] = [180, 120, 100, 80, 60];
+// Further soft limit scaling while the score falls between iterations, as the best move may be
+// about to be refuted - pairs of centipawn drop and percentage, from the largest drop down
+const SCORE_DROP_PERCENTAGES: [(Value, u32); 2] = [(100, 160), (30, 130)];
+// Soft limit scaling while the best move is an easy move, far ahead of every other move
+const EASY_MOVE_PERCENTAGE: u32 = 30;
+
+pub struct TimeControl {
+    pub white_time_left: Option<Duration>,
+    pub black_time_left: Option<Duration>,
+    pub white_increment: Duration,
+    pub black_increment: Duration,
+    pub moves_to_go: Option<u32>,
+    pub move_time: Option<Duration>,
+}
+
+impl TimeControl {
+    pub fn initialise() -> Self {
+        Self {
+            white_time_left: None,
+            black_time_left: None,
+            white_increment: Duration::ZERO,
+            black_increment: Duration::ZERO,
+            moves_to_go: None,
+            move_time: None,
+        }
+    }
+}
+
+pub struct TimeManager {
+    start_time: Instant,
+    soft_limit: Duration,
+    hard_limit: Duration,
+    best_move_stable_iterations: usize,
+    score_drop: Value,
+    easy_move: bool,
+}
+
+impl TimeManager {
+    // The moves to go when the GUI sends none, the share of the increment used and the hard limit
+    // relative to the soft limit all come from the tune parameters
+    pub fn initialise(
+        time_control: &TimeControl,
+        side: Side,
+        tune: &TuneParameters,
+    ) -> Option<Self> {
+        let start_time = Instant::now();
+
+        if let Some(move_time) = time_control.move_time {
+            let move_time = move_time.saturating_sub(MOVE_OVERHEAD);
+
+            return Some(Self {
+                start_time,
+                soft_limit: move_time,
+                hard_limit: move_time,
+                best_move_stable_iterations: 0,
+                score_drop: 0,
+                easy_move: false,
+            });
+        }
+
+        let (time_left, increment) = match side {
+            Side::White => (time_control.white_time_left?, time_control.white_increment),
+            Side::Black => (time_control.black_time_left?, time_control.black_increment),
+        };
+        let moves_to_go = time_control
+            .moves_to_go
+            .unwrap_or(tune.default_moves_to_go as u32)
+            .max(1);
+
+        let available_time = time_left.saturating_sub(MOVE_OVERHEAD);
+        let soft_limit =
+            available_time / moves_to_go + increment * tune.increment_usage_percentage as u32 / 100;
+        let hard_limit = (soft_limit * tune.hard_limit_soft_limit_percentage as u32 / 100)
+            .min(available_time * HARD_LIMIT_TIME_LEFT_PERCENTAGE / 100);
+
+        Some(Self {
+            start_time,
+            soft_limit: soft_limit.min(hard_limit),
+            hard_limit,
+            best_move_stable_iterations: 0,
+            score_drop: 0,
+            easy_move: false,
+        })
+    }
+
+    pub fn emergency_time(time_control: &TimeControl, side: Side) -> Option<Duration> {
+        if let Some(move_time) = time_control.move_time {
+            return Some(move_time.saturating_sub(MOVE_OVERHEAD / 2));
+        }
+
+        let time_left = match side {
+            Side::White => time_control.white_time_left?,
+            Side::Black => time_control.black_time_left?,
+        };
+
+        Some(time_left.saturating_sub(MOVE_OVERHEAD) * EMERGENCY_TIME_LEFT_PERCENTAGE / 100)
+    }
+
+    pub fn restart(&mut self) {
+        self.start_time = Instant::now();
+    }
+
+    // A new best move is no longer the easy move
+    pub fn update_best_move_stability(&mut self, best_move_changed: bool) {
+        if best_move_changed {
+            self.best_move_stable_iterations = 0;
+            self.easy_move = false;
+        } else {
+            self.best_move_stable_iterations += 1;
+        }
+    }
+
+    pub fn update_score_trend(&mut self, score_change: Value) {
+        self.score_drop = (-score_change).max(0);
+    }
+
+    pub fn set_easy_move(&mut self) {
+        self.easy_move = true;
+    }
+
+    pub fn iteration_allowed(&self) -> bool {
+        self.start_time.elapsed() < self.optimum_time()
+    }
+
+    pub fn hard_limit_exceeded(&self) -> bool {
+        self.start_time.elapsed() > self.hard_limit
+    }
+
+    fn optimum_time(&self) -> Duration {
+        let stability_index = self
+            .best_move_stable_iterations
+            .min(BEST_MOVE_STABILITY_PERCENTAGES.len() - 1);
+        let score_drop_percentage = SCORE_DROP_PERCENTAGES
+            .iter()
+            .find(|(score_drop, _)| self.score_drop >= *score_drop)
+            .map_or(100, |(_, percentage)| *percentage);
+        let easy_move_percentage = match self.easy_move {
+            true => EASY_MOVE_PERCENTAGE,
+            false => 100,
+        };
+        let optimum_time = self.soft_limit * BEST_MOVE_STABILITY_PERCENTAGES[stability_index] / 100
+            * score_drop_percentage
+            / 100
+            * easy_move_percentage
+            / 100;
+
+        optimum_time.min(self.hard_limit)
+    }
+}
+
+impl Display for TimeManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "time elapsed {}ms optimum {}ms hard limit {}ms",
+            self.start_time.elapsed().as_millis(),
+            self.optimum_time().as_millis(),
+            self.hard_limit.as_millis()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_time_allocation() {
+        let mut time_control = TimeControl::initialise();
+        time_control.move_time = Some(Duration::from_millis(1000));
+        let time_manager =
+            TimeManager::initialise(&time_control, Side::White, &TuneParameters::default())
+                .unwrap();
+
+        assert_eq!(time_manager.soft_limit, Duration::from_millis(950));
+        assert_eq!(time_manager.hard_limit, Duration::from_millis(950));
+    }
+
+    #[test]
+    fn clock_allocation_uses_side_to_move() {
+        let mut time_control = TimeControl::initialise();
+        time_control.white_time_left = Some(Duration::from_millis(60_050));
+        time_control.black_time_left = Some(Duration::from_millis(3_050));
+        time_control.white_increment = Duration::from_millis(1000);
+        let white_time_manager =
+            TimeManager::initialise(&time_control, Side::White, &TuneParameters::default())
+                .unwrap();
+        let black_time_manager =
+            TimeManager::initialise(&time_control, Side::Black, &TuneParameters::default())
+                .unwrap();
+
+        assert_eq!(white_time_manager.soft_limit, Duration::from_millis(2750));
+        assert_eq!(white_time_manager.hard_limit, Duration::from_millis(8250));
+        assert_eq!(black_time_manager.soft_limit, Duration::from_millis(100));
+        assert_eq!(black_time_manager.hard_limit, Duration::from_millis(300));
+    }
+
+    #[test]
+    fn hard_limit_never_exceeds_time_left() {
+        let mut time_control = TimeControl::initialise();
+        time_control.white_time_left = Some(Duration::from_millis(1050));
+        time_control.white_increment = Duration::from_millis(5000);
+        time_control.moves_to_go = Some(1);
+        let time_manager =
+            TimeManager::initialise(&time_control, Side::White, &TuneParameters::default())
+                .unwrap();
+
+        assert_eq!(time_manager.hard_limit, Duration::from_millis(800));
+        assert!(time_manager.soft_limit <= time_manager.hard_limit);
+
+        time_control.white_time_left = Some(Duration::from_millis(10));
+        let time_manager =
+            TimeManager::initialise(&time_control, Side::White, &TuneParameters::default())
+                .unwrap();
+
+        assert_eq!(time_manager.hard_limit, Duration::ZERO);
+    }
+
+    #[test]
+    fn emergency_time_after_hard_limit() {
+        let mut time_control = TimeControl::initialise();
+        time_control.black_time_left = Some(Duration::from_millis(1050));
+        let time_manager =
+            TimeManager::initialise(&time_control, Side::Black, &TuneParameters::default())
+                .unwrap();
+        let emergency_time = TimeManager::emergency_time(&time_control, Side::Black).unwrap();
+
+        assert_eq!(emergency_time, Duration::from_millis(900));
+        assert!(emergency_time > time_manager.hard_limit);
+        assert!(TimeManager::emergency_time(&time_control, Side::White).is_none());
+
+        time_control.move_time = Some(Duration::from_millis(1000));
+
+        assert_eq!(
+            TimeManager::emergency_time(&time_control, Side::White),
+            Some(Duration::from_millis(975))
+        );
+    }
+
+    #[test]
+    fn no_time_limit() {
+        let mut time_control = TimeControl::initialise();
+        time_control.white_time_left = Some(Duration::from_millis(1000));
+
+        assert!(
+            TimeManager::initialise(&time_control, Side::Black, &TuneParameters::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn best_move_stability_scaling() {
+        let mut time_control = TimeControl::initialise();
+        time_control.white_time_left = Some(Duration::from_millis(30_050));
+        let mut time_manager =
+            TimeManager::initialise(&time_control, Side::White, &TuneParameters::default())
+                .unwrap();
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(1800));
+
+        for _ in 0..10 {
+            time_manager.update_best_move_stability(false);
+        }
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(600));
+
+        time_manager.update_best_move_stability(true);
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(1800));
+    }
+
+    #[test]
+    fn easy_move_scaling() {
+        let mut time_control = TimeControl::initialise();
+        time_control.white_time_left = Some(Duration::from_millis(30_050));
+        let mut time_manager =
+            TimeManager::initialise(&time_control, Side::White, &TuneParameters::default())
+                .unwrap();
+        time_manager.update_best_move_stability(false);
+        time_manager.update_best_move_stability(false);
+        time_manager.set_easy_move();
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(300));
+
+        time_manager.update_best_move_stability(false);
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(240));
+
+        time_manager.update_best_move_stability(true);
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(1800));
+    }
+
+    #[test]
+    fn score_drop_scaling() {
+        let mut time_control = TimeControl::initialise();
+        time_control.white_time_left = Some(Duration::from_millis(30_050));
+        let mut time_manager =
+            TimeManager::initialise(&time_control, Side::White, &TuneParameters::default())
+                .unwrap();
+        time_manager.update_best_move_stability(false);
+        time_manager.update_best_move_stability(false);
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(1000));
+
+        time_manager.update_score_trend(-40);
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(1300));
+
+        time_manager.update_score_trend(-250);
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(1600));
+
+        time_manager.update_score_trend(250);
+
+        assert_eq!(time_manager.optimum_time(), Duration::from_millis(1000));
+    }
+}
@@ -0,0 +1,702 @@
+use super::{
+    evaluation::{Evaluation, Value},
+    EvaluationLimits,
+};
+use crate::{
+    engine::{moves::Move, zobrist_hashes::ZobristKey},
+    uci::{HashFileError, InputError},
+};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    mem,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+
+const HASH_FILE_SIGNATURE: &[u8; 8] = b"PNPLHASH";
+// Version 2 stores mate scores counted from the position rather than the root, and version 3
+// indexes entries by bucket
+const HASH_FILE_VERSION: u32 = 3;
+// Index, zobrist key, depth, bound, score and encoded best move of an occupied entry
+const HASH_FILE_ENTRY_BYTES: usize = 8 + 8 + 1 + 1 + 4 + 4;
+// Entries are spread evenly over the table, so the first are a fair sample of the whole
+const HASHFULL_SAMPLE_ENTRIES: usize = 1000;
+// Four entries of 16 bytes fill a 64 byte cache line, so probing a bucket is one memory access
+const BUCKET_ENTRIES: usize = 4;
+// When choosing the entry to replace, each search since an entry was stored counts as much as
+// this much depth, so stale entries make way even when they were searched deeply
+const AGE_DEPTH_EQUIVALENT: i32 = 8;
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x100_0000_01B3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower, // beta
+    Upper, // alpha
+}
+
+impl Bound {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Exact),
+            1 => Some(Self::Lower),
+            2 => Some(Self::Upper),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct TranspositionTableEntry {
+    zobrist_key: ZobristKey,
+    depth: u8,
+    bound: Bound,
+    evaluation: Evaluation,
+    best_move: Option<Move>,
+    generation: u8,
+}
+
+impl TranspositionTableEntry {
+    // Encoded move in bits 0-20, depth in bits 21-28, bound in bits 29-30, generation in bits
+    // 31-38 and evaluation in the upper 25 bits - the bound is offset by one so an occupied entry
+    // is never zero
+    fn data(&self) -> u64 {
+        self.best_move.as_ref().map_or(0, Move::encode) as u64
+            | (self.depth as u64) << 21
+            | (self.bound as u64 + 1) << 29
+            | (self.generation as u64) << 31
+            | (self.evaluation.value() as i64 as u64) << 39
+    }
+
+    fn from_data(zobrist_key: ZobristKey, data: u64) -> Option<Self> {
+        let bound = Bound::from_u8((((data >> 29) & 0b11) as u8).checked_sub(1)?)?;
+        let best_move = match (data & 0x1F_FFFF) as u32 {
+            0 => None,
+            encoded_move => Some(Move::decode(encoded_move)?),
+        };
+
+        Some(Self {
+            zobrist_key,
+            depth: (data >> 21) as u8,
+            bound,
+            evaluation: Evaluation::new((data as i64 >> 39) as Value),
+            best_move,
+            generation: (data >> 31) as u8,
+        })
+    }
+
+    // Searches since the entry was stored, with the generation counter wrapping around
+    fn age(&self, generation: u8) -> u8 {
+        generation.wrapping_sub(self.generation)
+    }
+}
+
+// Entries are written by every search thread without locking, so the key is stored xored with
+// the data - a read interleaved with a write from another thread then fails the key check
+// instead of returning a mix of two entries
+struct AtomicEntry {
+    checked_key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl AtomicEntry {
+    fn empty() -> Self {
+        Self {
+            checked_key: AtomicU64::new(0),
+            data: AtomicU64::new(0),
+        }
+    }
+
+    fn load(&self) -> Option<TranspositionTableEntry> {
+        let data = self.data.load(Ordering::Relaxed);
+        let zobrist_key = self.checked_key.load(Ordering::Relaxed) ^ data;
+
+        match data {
+            0 => None,
+            _ => TranspositionTableEntry::from_data(zobrist_key, data),
+        }
+    }
+
+    fn store(&self, entry: &TranspositionTableEntry) {
+        let data = entry.data();
+        self.checked_key
+            .store(entry.zobrist_key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.checked_key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+type Bucket = [AtomicEntry; BUCKET_ENTRIES];
+
+// Each key maps to a bucket of entries rather than a single one, so a position only pushes out
+// another when the whole bucket is full, and then the shallowest or most stale entry
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    pub fn initialise(size_mb: usize) -> Self {
+        let bucket_count = (size_mb * 1024 * 1024 / mem::size_of::<Bucket>()).max(1);
+
+        Self::with_bucket_count(bucket_count)
+    }
+
+    fn with_bucket_count(bucket_count: usize) -> Self {
+        Self {
+            buckets: (0..bucket_count)
+                .map(|_| std::array::from_fn(|_| AtomicEntry::empty()))
+                .collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries().for_each(AtomicEntry::clear);
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    // Called once per search, so entries left from earlier searches can be told apart
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Per mille of entries stored during the current search, as reported to the GUI - entries
+    // left from earlier searches are free to be replaced
+    pub fn hashfull(&self) -> u64 {
+        let generation = self.generation();
+        let sample: Vec<&AtomicEntry> = self.entries().take(HASHFULL_SAMPLE_ENTRIES).collect();
+        let occupied = sample
+            .iter()
+            .filter_map(|entry| entry.load())
+            .filter(|entry| entry.generation == generation)
+            .count();
+
+        (occupied * 1000 / sample.len()) as u64
+    }
+
+    pub fn probe(
+        &self,
+        zobrist_key: ZobristKey,
+        evaluation_limits: EvaluationLimits,
+        depth: u8,
+        ply: Value,
+    ) -> Option<Evaluation> {
+        let entry = self.entry(zobrist_key)?;
+
+        if entry.depth < depth {
+            return None;
+        }
+
+        let evaluation = entry.evaluation.relative_to_root(ply);
+
+        match entry.bound {
+            Bound::Exact => Some(evaluation),
+            Bound::Lower if evaluation >= evaluation_limits.max => Some(evaluation_limits.max),
+            Bound::Upper if evaluation <= evaluation_limits.min => Some(evaluation_limits.min),
+            _ => None,
+        }
+    }
+
+    pub fn best_move(&self, zobrist_key: ZobristKey) -> Option<Move> {
+        self.entry(zobrist_key)?.best_move
+    }
+
+    pub fn store(
+        &self,
+        zobrist_key: ZobristKey,
+        depth: u8,
+        ply: Value,
+        bound: Bound,
+        evaluation: Evaluation,
+        best_move: Option<&Move>,
+    ) {
+        let generation = self.generation();
+        let bucket = self.bucket(zobrist_key);
+        // A position already stored is always replaced, as the newer result is the more useful,
+        // and otherwise the entry worth the least depth once aged is
+        let replaced_entry = bucket
+            .iter()
+            .find(|entry| entry.load().map(|entry| entry.zobrist_key) == Some(zobrist_key))
+            .or_else(|| {
+                bucket.iter().min_by_key(|entry| match entry.load() {
+                    Some(entry) => {
+                        entry.depth as i32 - AGE_DEPTH_EQUIVALENT * entry.age(generation) as i32
+                    }
+                    None => i32::MIN,
+                })
+            })
+            .unwrap();
+
+        replaced_entry.store(&TranspositionTableEntry {
+            zobrist_key,
+            depth,
+            bound,
+            evaluation: evaluation.relative_to_position(ply),
+            best_move: best_move.cloned(),
+            generation,
+        });
+    }
+
+    // The file stores the table size so it can only be loaded into a table of the same size, as
+    // entry indices depend on it
+    pub fn save(&self, file_path: &str) -> Result<(), InputError> {
+        let file = File::create(file_path).map_err(HashFileError::from_io_error)?;
+        let mut writer = BufWriter::new(file);
+        let mut entries = Vec::new();
+
+        for (index, entry) in self.entries().enumerate() {
+            if let Some(entry) = entry.load() {
+                entries.extend_from_slice(&(index as u64).to_le_bytes());
+                entries.extend_from_slice(&entry.zobrist_key.to_le_bytes());
+                entries.push(entry.depth);
+                entries.push(entry.bound as u8);
+                entries.extend_from_slice(&entry.evaluation.value().to_le_bytes());
+                entries.extend_from_slice(
+                    &entry
+                        .best_move
+                        .as_ref()
+                        .map_or(0, Move::encode)
+                        .to_le_bytes(),
+                );
+            }
+        }
+
+        let entry_count = (entries.len() / HASH_FILE_ENTRY_BYTES) as u64;
+        writer
+            .write_all(HASH_FILE_SIGNATURE)
+            .and_then(|_| writer.write_all(&HASH_FILE_VERSION.to_le_bytes()))
+            .and_then(|_| writer.write_all(&(self.entry_count() as u64).to_le_bytes()))
+            .and_then(|_| writer.write_all(&entry_count.to_le_bytes()))
+            .and_then(|_| writer.write_all(&entries))
+            .and_then(|_| writer.write_all(&Self::checksum(&entries).to_le_bytes()))
+            .and_then(|_| writer.flush())
+            .map_err(HashFileError::from_io_error)?;
+
+        Ok(())
+    }
+
+    pub fn load(&self, file_path: &str) -> Result<(), InputError> {
+        let file = File::open(file_path).map_err(HashFileError::from_io_error)?;
+        let mut reader = BufReader::new(file);
+        let mut contents = Vec::new();
+        reader
+            .read_to_end(&mut contents)
+            .map_err(HashFileError::from_io_error)?;
+
+        let mut hash_file_reader = HashFileReader(&contents);
+
+        if hash_file_reader.read_bytes(HASH_FILE_SIGNATURE.len())? != HASH_FILE_SIGNATURE {
+            return Err(InputError::HashFile(HashFileError::InvalidSignature));
+        }
+
+        if hash_file_reader.read_u32()? != HASH_FILE_VERSION {
+            return Err(InputError::HashFile(HashFileError::UnsupportedVersion));
+        }
+
+        if hash_file_reader.read_u64()? != self.entry_count() as u64 {
+            return Err(InputError::HashFile(HashFileError::HashSizeMismatch));
+        }
+
+        let entry_count = hash_file_reader.read_u64()? as usize;
+        let entries_length = entry_count
+            .checked_mul(HASH_FILE_ENTRY_BYTES)
+            .ok_or(InputError::HashFile(HashFileError::Truncated))?;
+        let entries = hash_file_reader.read_bytes(entries_length)?;
+
+        if hash_file_reader.read_u64()? != Self::checksum(entries) {
+            return Err(InputError::HashFile(HashFileError::ChecksumMismatch));
+        }
+
+        let mut loaded_entries = Vec::with_capacity(entry_count);
+        let mut entries_reader = HashFileReader(entries);
+
+        for _ in 0..entry_count {
+            let index = entries_reader.read_u64()? as usize;
+            let zobrist_key = entries_reader.read_u64()?;
+            let depth = entries_reader.read_bytes(1)?[0];
+            let bound = Bound::from_u8(entries_reader.read_bytes(1)?[0]);
+            let evaluation = entries_reader.read_u32()? as Value;
+            let best_move = match entries_reader.read_u32()? {
+                0 => Some(None),
+                encoded_move => Move::decode(encoded_move).map(Some),
+            };
+
+            match (index < self.entry_count(), bound, best_move) {
+                (true, Some(bound), Some(best_move)) => loaded_entries.push((
+                    index,
+                    TranspositionTableEntry {
+                        zobrist_key,
+                        depth,
+                        bound,
+                        evaluation: Evaluation::new(evaluation),
+                        best_move,
+                        generation: self.generation(),
+                    },
+                )),
+                _ => return Err(InputError::HashFile(HashFileError::InvalidEntry)),
+            }
+        }
+
+        // The table is only modified once the whole file is known to be valid
+        self.clear();
+
+        for (index, entry) in loaded_entries {
+            self.buckets[index / BUCKET_ENTRIES][index % BUCKET_ENTRIES].store(&entry);
+        }
+
+        Ok(())
+    }
+
+    fn entry(&self, zobrist_key: ZobristKey) -> Option<TranspositionTableEntry> {
+        self.bucket(zobrist_key)
+            .iter()
+            .filter_map(AtomicEntry::load)
+            .find(|entry| entry.zobrist_key == zobrist_key)
+    }
+
+    fn bucket(&self, zobrist_key: ZobristKey) -> &Bucket {
+        &self.buckets[(zobrist_key % self.buckets.len() as u64) as usize]
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &AtomicEntry> {
+        self.buckets.iter().flatten()
+    }
+
+    fn entry_count(&self) -> usize {
+        self.buckets.len() * BUCKET_ENTRIES
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    // FNV-1a
+    fn checksum(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+}
+
+struct HashFileReader<'a>(&'a [u8]);
+
+impl<'a> HashFileReader<'a> {
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], InputError> {
+        if self.0.len() < length {
+            return Err(InputError::HashFile(HashFileError::Truncated));
+        }
+
+        let (bytes, remaining_bytes) = self.0.split_at(length);
+        self.0 = remaining_bytes;
+
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, InputError> {
+        let bytes = self.read_bytes(4)?;
+
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, InputError> {
+        let bytes = self.read_bytes(8)?;
+
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::evaluation::CHECKMATE_EVALUATION, *};
+    use crate::engine::{
+        game::Game,
+        moves::{MoveFlag, MoveList},
+    };
+    use std::{env, fs, sync::Arc, thread};
+
+    impl TranspositionTable {
+        fn loaded_entries(&self) -> Vec<Option<TranspositionTableEntry>> {
+            self.entries().map(AtomicEntry::load).collect()
+        }
+    }
+
+    fn filled_table() -> (TranspositionTable, ZobristKey, Move) {
+        let mut game = Game::initialise();
+        let fen = vec!["startpos"];
+        game.load_fen(&fen).unwrap();
+
+        let move_list = MoveList::generate_moves(&game, MoveFlag::All);
+        let mv = move_list.find_move_from_string("e2e4").unwrap();
+
+        let transposition_table = TranspositionTable::initialise(1);
+        transposition_table.store(
+            game.zobrist_key(),
+            5,
+            0,
+            Bound::Exact,
+            Evaluation::new(35),
+            Some(&mv),
+        );
+        transposition_table.store(1, 3, 0, Bound::Lower, Evaluation::new(-120), None);
+
+        (transposition_table, game.zobrist_key(), mv)
+    }
+
+    #[test]
+    fn probe_bounds() {
+        let transposition_table = TranspositionTable::initialise(1);
+        let evaluation_limits = EvaluationLimits {
+            min: Evaluation::new(-50),
+            max: Evaluation::new(50),
+        };
+        transposition_table.store(1, 4, 0, Bound::Lower, Evaluation::new(80), None);
+        transposition_table.store(2, 4, 0, Bound::Upper, Evaluation::new(10), None);
+        transposition_table.store(3, 4, 0, Bound::Exact, Evaluation::new(10), None);
+
+        assert_eq!(
+            transposition_table.probe(1, evaluation_limits, 4, 0),
+            Some(Evaluation::new(50))
+        );
+        assert_eq!(transposition_table.probe(1, evaluation_limits, 5, 0), None);
+        assert_eq!(transposition_table.probe(2, evaluation_limits, 3, 0), None);
+        assert_eq!(
+            transposition_table.probe(3, evaluation_limits, 2, 0),
+            Some(Evaluation::new(10))
+        );
+        assert_eq!(transposition_table.probe(4, evaluation_limits, 0, 0), None);
+    }
+
+    #[test]
+    fn mate_scores_relative_to_position() {
+        let transposition_table = TranspositionTable::initialise(1);
+        let evaluation_limits = EvaluationLimits::initialise();
+        let checkmate = CHECKMATE_EVALUATION.value();
+        transposition_table.store(1, 4, 5, Bound::Exact, Evaluation::new(checkmate - 7), None);
+        transposition_table.store(2, 4, 5, Bound::Exact, Evaluation::new(-checkmate + 6), None);
+        transposition_table.store(3, 4, 5, Bound::Exact, Evaluation::new(120), None);
+
+        // Mate two plies after the position stored is mate two plies after it when probed too
+        assert_eq!(
+            transposition_table.probe(1, evaluation_limits, 4, 1),
+            Some(Evaluation::new(checkmate - 3))
+        );
+        assert_eq!(
+            transposition_table.probe(2, evaluation_limits, 4, 9),
+            Some(Evaluation::new(-checkmate + 10))
+        );
+        assert_eq!(
+            transposition_table.probe(3, evaluation_limits, 4, 1),
+            Some(Evaluation::new(120))
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let (transposition_table, zobrist_key, mv) = filled_table();
+        let file_path = env::temp_dir().join("pineapple_save_and_load.hash");
+        let file_path = file_path.to_str().unwrap();
+        transposition_table.save(file_path).unwrap();
+
+        let loaded_transposition_table = TranspositionTable::initialise(1);
+        loaded_transposition_table.load(file_path).unwrap();
+        fs::remove_file(file_path).unwrap();
+
+        assert!(
+            loaded_transposition_table.loaded_entries() == transposition_table.loaded_entries()
+        );
+        assert_eq!(loaded_transposition_table.best_move(zobrist_key), Some(mv));
+    }
+
+    #[test]
+    fn load_rejects_invalid_files() {
+        let (transposition_table, _, _) = filled_table();
+        let file_path = env::temp_dir().join("pineapple_load_rejects_invalid_files.hash");
+        let file_path = file_path.to_str().unwrap();
+        transposition_table.save(file_path).unwrap();
+
+        let different_size_transposition_table = TranspositionTable::initialise(2);
+        let size_mismatch = different_size_transposition_table.load(file_path);
+
+        let mut contents = fs::read(file_path).unwrap();
+        let last_entry_byte = contents.len() - 9;
+        contents[last_entry_byte] ^= 0xFF;
+        fs::write(file_path, &contents).unwrap();
+
+        let loaded_transposition_table = TranspositionTable::initialise(1);
+        let checksum_mismatch = loaded_transposition_table.load(file_path);
+
+        fs::write(file_path, &contents[..20]).unwrap();
+        let truncated = loaded_transposition_table.load(file_path);
+
+        fs::write(file_path, b"not a hash file").unwrap();
+        let invalid_signature = loaded_transposition_table.load(file_path);
+        fs::remove_file(file_path).unwrap();
+
+        assert!(matches!(
+            size_mismatch,
+            Err(InputError::HashFile(HashFileError::HashSizeMismatch))
+        ));
+        assert!(matches!(
+            checksum_mismatch,
+            Err(InputError::HashFile(HashFileError::ChecksumMismatch))
+        ));
+        assert!(matches!(
+            truncated,
+            Err(InputError::HashFile(HashFileError::Truncated))
+        ));
+        assert!(matches!(
+            invalid_signature,
+            Err(InputError::HashFile(HashFileError::InvalidSignature))
+        ));
+        assert!(loaded_transposition_table
+            .loaded_entries()
+            .iter()
+            .all(Option::is_none));
+    }
+
+    #[test]
+    fn clear() {
+        let (transposition_table, zobrist_key, _) = filled_table();
+        let entry_count = transposition_table.entry_count();
+        transposition_table.clear();
+
+        assert_eq!(transposition_table.best_move(zobrist_key), None);
+        assert_eq!(transposition_table.entry_count(), entry_count);
+    }
+
+    #[test]
+    fn hashfull() {
+        let transposition_table = TranspositionTable::initialise(1);
+
+        assert_eq!(transposition_table.hashfull(), 0);
+
+        for zobrist_key in 0..HASHFULL_SAMPLE_ENTRIES as u64 / 4 {
+            transposition_table.store(zobrist_key, 1, 0, Bound::Exact, Evaluation::new(0), None);
+        }
+
+        assert_eq!(transposition_table.hashfull(), 250);
+
+        let single_bucket_table = TranspositionTable::with_bucket_count(1);
+
+        for zobrist_key in 0..BUCKET_ENTRIES as u64 {
+            single_bucket_table.store(zobrist_key, 1, 0, Bound::Exact, Evaluation::new(0), None);
+        }
+
+        assert_eq!(single_bucket_table.hashfull(), 1000);
+
+        single_bucket_table.new_search();
+
+        assert_eq!(single_bucket_table.hashfull(), 0);
+    }
+
+    #[test]
+    fn bucket_replacement() {
+        let transposition_table = TranspositionTable::with_bucket_count(1);
+        let store = |zobrist_key, depth| {
+            transposition_table.store(
+                zobrist_key,
+                depth,
+                0,
+                Bound::Exact,
+                Evaluation::new(0),
+                None,
+            )
+        };
+        let stored_keys = || {
+            let mut keys: Vec<ZobristKey> = transposition_table
+                .loaded_entries()
+                .iter()
+                .flatten()
+                .map(|entry| entry.zobrist_key)
+                .collect();
+            keys.sort();
+            keys
+        };
+
+        for (zobrist_key, depth) in [(1, 10), (2, 1), (3, 4), (4, 6)] {
+            store(zobrist_key, depth);
+        }
+
+        store(5, 2);
+
+        assert_eq!(stored_keys(), [1, 3, 4, 5]);
+
+        // Storing a position again replaces its own entry, even with a shallower search
+        store(1, 3);
+
+        assert_eq!(stored_keys(), [1, 3, 4, 5]);
+
+        store(1, 10);
+        transposition_table.new_search();
+        store(6, 1);
+        store(7, 1);
+        store(8, 1);
+
+        assert_eq!(stored_keys(), [1, 6, 7, 8]);
+
+        // A deep entry from one search ago is kept over the current search's shallow ones, but
+        // not from two searches ago
+        store(9, 1);
+
+        assert_eq!(stored_keys(), [1, 7, 8, 9]);
+
+        transposition_table.new_search();
+        store(10, 1);
+        store(11, 1);
+        store(12, 1);
+
+        assert_eq!(stored_keys(), [1, 10, 11, 12]);
+
+        store(13, 1);
+
+        assert_eq!(stored_keys(), [10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let transposition_table = Arc::new(TranspositionTable::initialise(1));
+        let threads: Vec<_> = (0..4)
+            .map(|thread_index| {
+                let transposition_table = Arc::clone(&transposition_table);
+
+                thread::spawn(move || {
+                    for zobrist_key in 0..10_000 {
+                        let evaluation = Evaluation::new(zobrist_key as Value + thread_index);
+                        transposition_table.store(
+                            zobrist_key,
+                            1,
+                            0,
+                            Bound::Exact,
+                            evaluation,
+                            None,
+                        );
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let evaluation_limits = EvaluationLimits::initialise();
+
+        // Writes to the same entry interleaving between threads are discarded on probing, so
+        // only entries which are found have to be intact
+        for zobrist_key in 0..10_000 {
+            let entry = transposition_table.probe(zobrist_key, evaluation_limits, 1, 0);
+
+            if let Some(evaluation) = entry {
+                let thread_index = evaluation.value() - zobrist_key as Value;
+
+                assert!((0..4).contains(&thread_index));
+            }
+        }
+    }
+}
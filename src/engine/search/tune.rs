@@ -0,0 +1,246 @@
+use super::evaluation::Value;
+
+// Parameters of the search and time management which external tuning can change through hidden
+// UCI options - each has a range to tune over and a step, the perturbation SPSA ends with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TuneParameters {
+    pub aspiration_window: Value,
+    pub aspiration_window_max: Value,
+    pub null_move_depth_reduction: Value,
+    pub lmp_moves_searched_base: Value,
+    pub lmr_moves_searched_min: Value,
+    pub lmr_depth_min: Value,
+    pub lmr_depth_reduction: Value,
+    pub easy_move_margin: Value,
+    pub quiescence_delta_margin: Value,
+    pub default_moves_to_go: Value,
+    pub increment_usage_percentage: Value,
+    pub hard_limit_soft_limit_percentage: Value,
+}
+
+pub struct Tunable {
+    pub name: &'static str,
+    pub default: Value,
+    pub min: Value,
+    pub max: Value,
+    pub step: Value,
+    field: fn(&mut TuneParameters) -> &mut Value,
+}
+
+// Mirrors the OpenBench default for how far the learning rate falls over a tuning run
+const LEARNING_RATE_END: f64 = 0.002;
+
+pub const TUNABLES: [Tunable; 12] = [
+    // The window is dropped entirely once it grows past the maximum, as the score is most likely
+    // decisive or a mate
+    Tunable {
+        name: "AspirationWindow",
+        default: 50,
+        min: 10,
+        max: 200,
+        step: 10,
+        field: |parameters| &mut parameters.aspiration_window,
+    },
+    Tunable {
+        name: "AspirationWindowMax",
+        default: 400,
+        min: 100,
+        max: 1000,
+        step: 50,
+        field: |parameters| &mut parameters.aspiration_window_max,
+    },
+    Tunable {
+        name: "NullMoveDepthReduction",
+        default: 3,
+        min: 1,
+        max: 5,
+        step: 1,
+        field: |parameters| &mut parameters.null_move_depth_reduction,
+    },
+    // LMP = late move pruning, which searches this many moves plus the depth squared
+    Tunable {
+        name: "LmpMovesSearchedBase",
+        default: 3,
+        min: 1,
+        max: 10,
+        step: 1,
+        field: |parameters| &mut parameters.lmp_moves_searched_base,
+    },
+    // LMR = late move reduction
+    Tunable {
+        name: "LmrMovesSearchedMin",
+        default: 4,
+        min: 2,
+        max: 10,
+        step: 1,
+        field: |parameters| &mut parameters.lmr_moves_searched_min,
+    },
+    Tunable {
+        name: "LmrDepthMin",
+        default: 3,
+        min: 2,
+        max: 6,
+        step: 1,
+        field: |parameters| &mut parameters.lmr_depth_min,
+    },
+    Tunable {
+        name: "LmrDepthReduction",
+        default: 2,
+        min: 1,
+        max: 4,
+        step: 1,
+        field: |parameters| &mut parameters.lmr_depth_reduction,
+    },
+    Tunable {
+        name: "EasyMoveMargin",
+        default: 150,
+        min: 50,
+        max: 400,
+        step: 20,
+        field: |parameters| &mut parameters.easy_move_margin,
+    },
+    // The futility margin of captures in the quiescence search
+    Tunable {
+        name: "QuiescenceDeltaMargin",
+        default: 200,
+        min: 50,
+        max: 500,
+        step: 20,
+        field: |parameters| &mut parameters.quiescence_delta_margin,
+    },
+    Tunable {
+        name: "DefaultMovesToGo",
+        default: 30,
+        min: 10,
+        max: 60,
+        step: 3,
+        field: |parameters| &mut parameters.default_moves_to_go,
+    },
+    Tunable {
+        name: "IncrementUsagePercentage",
+        default: 75,
+        min: 25,
+        max: 100,
+        step: 5,
+        field: |parameters| &mut parameters.increment_usage_percentage,
+    },
+    Tunable {
+        name: "HardLimitSoftLimitPercentage",
+        default: 300,
+        min: 100,
+        max: 600,
+        step: 25,
+        field: |parameters| &mut parameters.hard_limit_soft_limit_percentage,
+    },
+];
+
+impl TuneParameters {
+    pub fn set(&mut self, name: &str, value: Value) {
+        if let Some(tunable) = TUNABLES.iter().find(|tunable| tunable.name == name) {
+            *(tunable.field)(self) = value.clamp(tunable.min, tunable.max);
+        }
+    }
+
+    pub fn is_tunable(name: &str) -> bool {
+        TUNABLES.iter().any(|tunable| tunable.name == name)
+    }
+}
+
+impl Default for TuneParameters {
+    fn default() -> Self {
+        let mut parameters = Self {
+            aspiration_window: 0,
+            aspiration_window_max: 0,
+            null_move_depth_reduction: 0,
+            lmp_moves_searched_base: 0,
+            lmr_moves_searched_min: 0,
+            lmr_depth_min: 0,
+            lmr_depth_reduction: 0,
+            easy_move_margin: 0,
+            quiescence_delta_margin: 0,
+            default_moves_to_go: 0,
+            increment_usage_percentage: 0,
+            hard_limit_soft_limit_percentage: 0,
+        };
+
+        for tunable in &TUNABLES {
+            *(tunable.field)(&mut parameters) = tunable.default;
+        }
+
+        parameters
+    }
+}
+
+impl Tunable {
+    // One line of the OpenBench SPSA input: name, type, default, min, max, step and learning rate
+    pub fn spsa_spec(&self) -> String {
+        format!(
+            "{}, int, {}, {}, {}, {}, {}",
+            self.name, self.default, self.min, self.max, self.step, LEARNING_RATE_END
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tunables_cover_every_parameter() {
+        let mut parameters = TuneParameters::default();
+
+        for (index, tunable) in TUNABLES.iter().enumerate() {
+            assert!(tunable.min <= tunable.default && tunable.default <= tunable.max);
+            assert!(tunable.step > 0);
+            assert_ne!(tunable.default, -1 - index as Value);
+
+            *(tunable.field)(&mut parameters) = -1 - index as Value;
+        }
+
+        // Every field was set to a distinct value, so none is shared between two tunables
+        let mut values = [
+            parameters.aspiration_window,
+            parameters.aspiration_window_max,
+            parameters.null_move_depth_reduction,
+            parameters.lmp_moves_searched_base,
+            parameters.lmr_moves_searched_min,
+            parameters.lmr_depth_min,
+            parameters.lmr_depth_reduction,
+            parameters.easy_move_margin,
+            parameters.quiescence_delta_margin,
+            parameters.default_moves_to_go,
+            parameters.increment_usage_percentage,
+            parameters.hard_limit_soft_limit_percentage,
+        ];
+        values.sort();
+
+        assert_eq!(values, core::array::from_fn(|index| index as Value - 12));
+    }
+
+    #[test]
+    fn set_clamps_to_range() {
+        let mut parameters = TuneParameters::default();
+        parameters.set("LmrDepthReduction", 3);
+
+        assert_eq!(parameters.lmr_depth_reduction, 3);
+
+        parameters.set("AspirationWindow", 5000);
+
+        assert_eq!(parameters.aspiration_window, 200);
+
+        parameters.set("Hash", 64);
+
+        assert_eq!(
+            TuneParameters {
+                lmr_depth_reduction: 2,
+                aspiration_window: 50,
+                ..parameters
+            },
+            TuneParameters::default()
+        );
+        assert_eq!(
+            TUNABLES[0].spsa_spec(),
+            "AspirationWindow, int, 50, 10, 200, 10, 0.002"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use super::evaluation::Evaluation;
+use crate::engine::game::Game;
+use std::fmt::Display;
+
+// Material is counted in pawns, with the king not counted, and clamped to the range over which
+// the model is fitted
+const MATERIAL_VALUE: [u32; 6] = [1, 3, 3, 5, 9, 0];
+const MATERIAL_MIN: u32 = 17;
+const MATERIAL_MAX: u32 = 78;
+
+// The chance of winning rises along a logistic curve with the score, reaching even odds at the
+// offset and growing quicker the smaller the scale - the same score is more decisive with less
+// material left, as there is less play left to turn it around. Both are interpolated between their
+// values with the least and the most material
+const WIN_OFFSET_RANGE: (f64, f64) = (150.0, 300.0);
+const WIN_SCALE_RANGE: (f64, f64) = (60.0, 90.0);
+
+// Per mille chances of winning, drawing and losing for the side to move
+#[derive(Debug, PartialEq)]
+pub struct WinDrawLoss {
+    win: u32,
+    draw: u32,
+    loss: u32,
+}
+
+impl WinDrawLoss {
+    pub fn from_evaluation(evaluation: Evaluation, game: &Game) -> Self {
+        match evaluation.mate_moves() {
+            Some(mate_moves) if mate_moves > 0 => return Self::decided(true),
+            Some(_) => return Self::decided(false),
+            None => {}
+        }
+
+        let material = game
+            .piece_bitboards()
+            .iter()
+            .map(|(bitboard, piece, _)| bitboard.count_bits() * MATERIAL_VALUE[*piece as usize])
+            .sum::<u32>()
+            .clamp(MATERIAL_MIN, MATERIAL_MAX);
+        let phase = (material - MATERIAL_MIN) as f64 / (MATERIAL_MAX - MATERIAL_MIN) as f64;
+        let interpolate = |(low, high): (f64, f64)| low + (high - low) * phase;
+        let offset = interpolate(WIN_OFFSET_RANGE);
+        let scale = interpolate(WIN_SCALE_RANGE);
+        let score = evaluation.value() as f64;
+        let win_rate = |score: f64| (1000.0 / (1.0 + ((offset - score) / scale).exp())).round();
+        let win = win_rate(score) as u32;
+        let loss = win_rate(-score) as u32;
+
+        Self {
+            win,
+            draw: 1000 - win - loss,
+            loss,
+        }
+    }
+
+    fn decided(won: bool) -> Self {
+        match won {
+            true => Self {
+                win: 1000,
+                draw: 0,
+                loss: 0,
+            },
+            false => Self {
+                win: 0,
+                draw: 0,
+                loss: 1000,
+            },
+        }
+    }
+}
+
+impl Display for WinDrawLoss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "wdl {} {} {}", self.win, self.draw, self.loss)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::evaluation::CHECKMATE_EVALUATION, *};
+
+    fn win_draw_loss(fen: &str, value: i32) -> WinDrawLoss {
+        let mut game = Game::initialise();
+        let fen: Vec<&str> = fen.split_whitespace().collect();
+        game.load_fen(&fen).unwrap();
+
+        WinDrawLoss::from_evaluation(Evaluation::new(value), &game)
+    }
+
+    #[test]
+    fn score_to_win_draw_loss() {
+        let equal = win_draw_loss("startpos", 0);
+
+        assert_eq!(equal.win, equal.loss);
+        assert!(equal.draw > 900);
+        assert_eq!(
+            equal.to_string(),
+            format!("wdl {} {} {}", equal.win, equal.draw, equal.loss)
+        );
+
+        let ahead = win_draw_loss("startpos", 300);
+        let behind = win_draw_loss("startpos", -300);
+
+        assert_eq!(ahead.win, 500);
+        assert_eq!((ahead.win, ahead.loss), (behind.loss, behind.win));
+
+        // A pawn up counts for more once most of the material is gone
+        let endgame_ahead = win_draw_loss("4k3/4p3/8/8/8/8/3PP3/4K3 w - - 0 1", 100);
+
+        assert!(endgame_ahead.win > win_draw_loss("startpos", 100).win);
+
+        assert_eq!(
+            win_draw_loss("startpos", CHECKMATE_EVALUATION.value() - 3).to_string(),
+            "wdl 1000 0 0"
+        );
+        assert_eq!(
+            win_draw_loss("startpos", -CHECKMATE_EVALUATION.value() + 2).to_string(),
+            "wdl 0 0 1000"
+        );
+    }
+}
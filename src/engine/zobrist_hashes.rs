@@ -1,5 +1,5 @@
 use super::game::{Game, Piece, Side, Square};
-use crate::random;
+use crate::{engine, random::Random};
 use strum::IntoEnumIterator;
 
 pub type ZobristHash = u64;
@@ -894,6 +894,7 @@ pub const ZOBRIST_HASHES: ZobristHashes = ZobristHashes {
 
 const CASTLING_RIGHTS_PERMUTATIONS: usize = 16;
 
+#[derive(Debug, PartialEq)]
 pub struct ZobristHashes {
     piece_square_hashes: [[[ZobristHash; 64]; 6]; 2],
     en_passant_square_hashes: [ZobristHash; 64],
@@ -902,14 +903,14 @@ pub struct ZobristHashes {
 }
 
 impl ZobristHashes {
-    fn _initialise(random_state: &mut u32) -> Self {
+    pub fn generate(random: &mut Random) -> Self {
         let mut piece_square_hashes = [[[0; 64]; 6]; 2];
 
         for side in Side::iter() {
             for piece in Piece::iter() {
                 for square in Square::iter() {
                     piece_square_hashes[side as usize][piece as usize][square as usize] =
-                        random::_generate_random_u64(random_state);
+                        random.generate_u64();
                 }
             }
         }
@@ -917,16 +918,16 @@ impl ZobristHashes {
         let mut en_passant_square_hashes = [0; 64];
 
         for square in Square::iter() {
-            en_passant_square_hashes[square as usize] = random::_generate_random_u64(random_state);
+            en_passant_square_hashes[square as usize] = random.generate_u64();
         }
 
         let mut castling_hashes = [0; CASTLING_RIGHTS_PERMUTATIONS];
 
         for castling_key in &mut castling_hashes {
-            *castling_key = random::_generate_random_u64(random_state);
+            *castling_key = random.generate_u64();
         }
 
-        let side_hash = random::_generate_random_u64(random_state);
+        let side_hash = random.generate_u64();
 
         Self {
             piece_square_hashes,
@@ -936,14 +937,47 @@ impl ZobristHashes {
         }
     }
 
+    // Formatted as the `ZOBRIST_HASHES` constant above, so regenerated hashes can be pasted
+    // straight into the source
+    pub fn source(&self, seed: u32) -> String {
+        let mut source = format!(
+            "// Zobrist hashes generated with random_state = {}\n",
+            engine::number_literal(seed as u64)
+        );
+        source += "pub const ZOBRIST_HASHES: ZobristHashes = ZobristHashes {\n";
+        source += "    piece_square_hashes: [\n";
+
+        for side_hashes in &self.piece_square_hashes {
+            source += "        [\n";
+
+            for piece_hashes in side_hashes {
+                source += "            [\n";
+
+                for hash in piece_hashes {
+                    source += &format!("                {},\n", engine::hex_literal(*hash));
+                }
+
+                source += "            ],\n";
+            }
+
+            source += "        ],\n";
+        }
+
+        source += "    ],\n";
+        source += &engine::array_source("en_passant_square_hashes", &self.en_passant_square_hashes);
+        source += &engine::array_source("castling_hashes", &self.castling_hashes);
+        source += &format!("    side_hash: {},\n", engine::hex_literal(self.side_hash));
+        source += "};\n";
+
+        source
+    }
+
     pub fn generate_key(&self, game: &Game) -> ZobristKey {
         let mut key = 0;
 
-        for (mut bitboard, piece, side) in game.piece_bitboards() {
-            while let Some(square) = bitboard.get_lsb_square() {
+        for (bitboard, piece, side) in game.piece_bitboards() {
+            for square in bitboard.squares() {
                 key ^= self.piece_square_hashes[side as usize][piece as usize][square as usize];
-
-                bitboard.pop_bit(square);
             }
         }
 
@@ -973,3 +1007,19 @@ impl ZobristHashes {
         self.side_hash
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random;
+
+    #[test]
+    fn generate_zobrist_hashes() {
+        let mut random = Random::new(random::DEFAULT_SEED).unwrap();
+        let zobrist_hashes = ZobristHashes::generate(&mut random);
+
+        assert_eq!(zobrist_hashes, ZOBRIST_HASHES);
+        assert!(include_str!("zobrist_hashes.rs")
+            .contains(&ZOBRIST_HASHES.source(random::DEFAULT_SEED)));
+    }
+}
@@ -0,0 +1,23 @@
+// The engine as a library, for programs which embed it instead of talking to it over UCI, and the
+// board model it searches with, for programs which only need legal moves - the binary only hands
+// its arguments over to the command line handling
+mod cli;
+mod crash_report;
+mod engine;
+mod library;
+mod random;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+mod uci;
+
+pub use self::{
+    engine::{
+        Bitboard, Game, Move, MoveType, Piece, Score, SearchProgress, SearchResult, Side, Square,
+    },
+    library::{execute, Engine, SearchLimits},
+    uci::{Command, FenError, GoParams, InputError, PositionParams},
+};
+
+// Not part of the library, but has to be public for the binary to reach it
+#[doc(hidden)]
+pub use self::cli::run;
@@ -0,0 +1,120 @@
+use crate::{
+    engine::{self, SearchProgress, SearchResult, TimeControl, MAX_PLY},
+    uci::{self, Command, InputError, Output},
+};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::Duration,
+};
+
+// The limits of the go command - a search without any limits runs until it is stopped
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Duration,
+    pub black_increment: Duration,
+    pub moves_to_go: Option<u32>,
+}
+
+impl SearchLimits {
+    fn time_control(&self) -> TimeControl {
+        TimeControl {
+            white_time_left: self.white_time,
+            black_time_left: self.black_time,
+            white_increment: self.white_increment,
+            black_increment: self.black_increment,
+            moves_to_go: self.moves_to_go,
+            move_time: self.move_time,
+        }
+    }
+}
+
+// The engine for programs which embed it, searching on the calling thread without printing
+// anything - it is kept behind a lock so it can be shared with another thread which stops the
+// search, and every call other than stop waits for a running search to finish
+pub struct Engine {
+    engine: Mutex<engine::Engine>,
+    stop_search_flag: Arc<AtomicBool>,
+}
+
+impl Engine {
+    // Starts from the standard starting position
+    pub fn new() -> Self {
+        let mut engine = engine::Engine::initialise();
+        engine.set_output(Output::writer(io::sink()));
+        engine
+            .load_fen(&["startpos"])
+            .expect("the starting position is valid");
+        let stop_search_flag = engine.stop_search_flag();
+
+        Self {
+            engine: Mutex::new(engine),
+            stop_search_flag,
+        }
+    }
+
+    // The FEN may also be `startpos`, and the moves are in UCI notation - as with the position
+    // command, a move which cannot be played leaves the position after the moves before it
+    pub fn set_position(&self, fen: &str, moves: &[&str]) -> Result<(), InputError> {
+        let mut engine = self.engine();
+        engine.load_fen(&fen.split_whitespace().collect::<Vec<&str>>())?;
+
+        for move_string in moves {
+            engine.make_move(move_string)?;
+        }
+
+        Ok(())
+    }
+
+    // Fails if the position has no legal moves to search
+    pub fn go(&self, search_limits: SearchLimits) -> Result<SearchResult, InputError> {
+        let mut engine = self.engine();
+        engine.set_search_limits(search_limits.nodes, &search_limits.time_control());
+        let best_move = engine.search_best_move(search_limits.depth.unwrap_or(MAX_PLY as u8));
+        // Cleared once the search is over rather than as it starts, so a stop sent while this call
+        // was still waiting for the lock ends the search instead of being lost
+        self.stop_search_flag.store(false, Ordering::Relaxed);
+
+        Ok(engine.search_result(best_move?))
+    }
+
+    // Every later search sends its progress to the receiver, until another one is asked for
+    pub fn search_progress(&self) -> Receiver<SearchProgress> {
+        let (sender, receiver) = mpsc::channel();
+        self.engine().set_search_progress(sender);
+
+        receiver
+    }
+
+    // The running search returns with the best move of its last completed iteration, and a search
+    // about to start returns as soon as it does
+    pub fn stop(&self) {
+        self.stop_search_flag.store(true, Ordering::Relaxed);
+    }
+
+    // A search which panicked leaves the engine usable, as searches start from a clean state
+    fn engine(&self) -> MutexGuard<'_, engine::Engine> {
+        self.engine.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Runs a parsed line of UCI input on the engine, waiting for a running search to finish first -
+// anything the command prints is dropped, as with the rest of the library
+pub fn execute(engine: &Engine, command: Command) -> Result<(), InputError> {
+    uci::execute(&mut engine.engine(), command)
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,7 +1,7 @@
-mod engine;
-mod random;
-mod uci;
+use std::{env, process::ExitCode};
 
-fn main() {
-    uci::engine();
+fn main() -> ExitCode {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+
+    pineapple_chess_engine::run(&arguments)
 }
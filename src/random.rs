@@ -1,19 +1,54 @@
-pub fn _generate_random_u64(random_state: &mut u32) -> u64 {
-    // `& 0xFFFF` operation cuts off first 16 most significant bits from 32 bit integer
-    _xor_shift_mutate(random_state);
-    let random_u64_1 = (*random_state & 0xFFFF) as u64;
-    _xor_shift_mutate(random_state);
-    let random_u64_2 = (*random_state & 0xFFFF) as u64;
-    _xor_shift_mutate(random_state);
-    let random_u64_3 = (*random_state & 0xFFFF) as u64;
-    _xor_shift_mutate(random_state);
-    let random_u64_4 = (*random_state & 0xFFFF) as u64;
-
-    random_u64_1 | (random_u64_2 << 16) | (random_u64_3 << 32) | (random_u64_4 << 48)
+// Seed the magic numbers and zobrist hashes in the source were generated with
+pub const DEFAULT_SEED: u32 = 1_804_289_383;
+
+// 32 bit xorshift generator - the state must never be zero, as it would stay zero forever
+pub struct Random(u32);
+
+impl Random {
+    pub fn new(seed: u32) -> Option<Self> {
+        match seed {
+            0 => None,
+            _ => Some(Self(seed)),
+        }
+    }
+
+    pub fn generate_u64(&mut self) -> u64 {
+        // `& 0xFFFF` operation cuts off first 16 most significant bits from 32 bit integer
+        let random_u64_1 = (self.generate_u32() & 0xFFFF) as u64;
+        let random_u64_2 = (self.generate_u32() & 0xFFFF) as u64;
+        let random_u64_3 = (self.generate_u32() & 0xFFFF) as u64;
+        let random_u64_4 = (self.generate_u32() & 0xFFFF) as u64;
+
+        random_u64_1 | (random_u64_2 << 16) | (random_u64_3 << 32) | (random_u64_4 << 48)
+    }
+
+    pub fn generate_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+
+        self.0
+    }
 }
 
-fn _xor_shift_mutate(random_state: &mut u32) {
-    *random_state ^= *random_state << 13;
-    *random_state ^= *random_state >> 17;
-    *random_state ^= *random_state << 5;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_sequence() {
+        let mut random = Random::new(DEFAULT_SEED).unwrap();
+        let mut same_seed_random = Random::new(DEFAULT_SEED).unwrap();
+        let mut other_seed_random = Random::new(1).unwrap();
+
+        let sequence: Vec<u64> = (0..8).map(|_| random.generate_u64()).collect();
+        let same_seed_sequence: Vec<u64> =
+            (0..8).map(|_| same_seed_random.generate_u64()).collect();
+        let other_seed_sequence: Vec<u64> =
+            (0..8).map(|_| other_seed_random.generate_u64()).collect();
+
+        assert_eq!(sequence, same_seed_sequence);
+        assert_ne!(sequence, other_seed_sequence);
+        assert!(Random::new(0).is_none());
+    }
 }
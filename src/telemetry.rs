@@ -0,0 +1,207 @@
+use crate::uci::Output;
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+const ADDRESS_VARIABLE: &str = "PINEAPPLE_TELEMETRY_ADDRESS";
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8091";
+
+// The latest search information, taken from the `info` lines the engine sends to the GUI so the
+// search itself does not need to know anything is listening
+#[derive(Default)]
+struct SearchInfo {
+    depth: Option<u64>,
+    score: Option<String>,
+    nodes: Option<u64>,
+    nps: Option<u64>,
+    hashfull: Option<u64>,
+    principal_variation: Option<String>,
+}
+
+impl SearchInfo {
+    fn update(&mut self, line: &str) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        // Lines reporting the current move or a refutation do not describe a whole iteration
+        if tokens.first() != Some(&"info") || !tokens.contains(&"pv") {
+            return;
+        }
+
+        let value_after = |name: &str| {
+            let index = tokens.iter().position(|token| *token == name)?;
+            tokens.get(index + 1)?.parse().ok()
+        };
+
+        self.depth = value_after("depth").or(self.depth);
+        self.nodes = value_after("nodes").or(self.nodes);
+        self.nps = value_after("nps").or(self.nps);
+        self.hashfull = value_after("hashfull").or(self.hashfull);
+
+        if let Some(index) = tokens.iter().position(|token| *token == "score") {
+            self.score = tokens
+                .get(index + 1..index + 3)
+                .map(|score| score.join(" "));
+        }
+
+        if let Some(index) = tokens.iter().position(|token| *token == "pv") {
+            self.principal_variation = Some(tokens[index + 1..].join(" "));
+        }
+    }
+
+    fn json(&self) -> String {
+        let number = |value: Option<u64>| value.map_or("null".to_string(), |v| v.to_string());
+        let string = |value: &Option<String>| {
+            value
+                .as_ref()
+                .map_or("null".to_string(), |v| format!("\"{}\"", v))
+        };
+
+        format!(
+            "{{\"depth\":{},\"score\":{},\"nodes\":{},\"nps\":{},\"hashfull\":{},\"pv\":{}}}",
+            number(self.depth),
+            string(&self.score),
+            number(self.nodes),
+            number(self.nps),
+            number(self.hashfull),
+            string(&self.principal_variation)
+        )
+    }
+}
+
+// Passes everything through to the GUI unchanged, picking out search information on the way
+struct TelemetryWriter<W: Write> {
+    writer: W,
+    line: Vec<u8>,
+    search_info: Arc<Mutex<SearchInfo>>,
+}
+
+impl<W: Write> Write for TelemetryWriter<W> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buffer)?;
+
+        for byte in &buffer[..written] {
+            match byte {
+                b'\n' => {
+                    if let Ok(mut search_info) = self.search_info.lock() {
+                        search_info.update(&String::from_utf8_lossy(&self.line));
+                    }
+
+                    self.line.clear();
+                }
+                _ => self.line.push(*byte),
+            }
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// Serves the search information as JSON on every request, on a separate thread so a slow client
+// can never hold up the engine - standard output is used as normal if the address is unavailable
+pub fn output() -> Output {
+    let address = env::var(ADDRESS_VARIABLE).unwrap_or(DEFAULT_ADDRESS.to_string());
+    let search_info = Arc::new(Mutex::new(SearchInfo::default()));
+
+    match TcpListener::bind(&address) {
+        Ok(listener) => {
+            let server_search_info = Arc::clone(&search_info);
+            thread::spawn(move || serve(listener, server_search_info));
+        }
+        Err(error) => eprintln!("Failed to start telemetry on {}: {}", address, error),
+    }
+
+    Output::writer(TelemetryWriter {
+        writer: io::stdout(),
+        line: Vec::new(),
+        search_info,
+    })
+}
+
+fn serve(listener: TcpListener, search_info: Arc<Mutex<SearchInfo>>) {
+    for stream in listener.incoming().flatten() {
+        let json = match search_info.lock() {
+            Ok(search_info) => search_info.json(),
+            Err(_) => continue,
+        };
+
+        _ = respond(stream, &json);
+    }
+}
+
+fn respond(mut stream: TcpStream, json: &str) -> io::Result<()> {
+    // The request itself does not matter, but has to be read before replying
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+        Access-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        json.len(),
+        json
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn search_info_from_output() {
+        let search_info = Arc::new(Mutex::new(SearchInfo::default()));
+        let mut writer = TelemetryWriter {
+            writer: Vec::new(),
+            line: Vec::new(),
+            search_info: Arc::clone(&search_info),
+        };
+
+        assert_eq!(
+            search_info.lock().unwrap().json(),
+            "{\"depth\":null,\"score\":null,\"nodes\":null,\"nps\":null,\"hashfull\":null,\"pv\":null}"
+        );
+
+        writeln!(
+            writer,
+            "info depth 5 score cp 30 nodes 4000 nps 200000 hashfull 12 time 20 pv e2e4 e7e5"
+        )
+        .unwrap();
+        writeln!(writer, "info string depth 5 score trend +0").unwrap();
+        write!(writer, "info depth 6 score mate 3 nodes 9000 ").unwrap();
+        writeln!(writer, "nps 300000 time 30 pv d2d4").unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&writer.writer).lines().count(), 3);
+        assert_eq!(
+            search_info.lock().unwrap().json(),
+            "{\"depth\":6,\"score\":\"mate 3\",\"nodes\":9000,\"nps\":300000,\"hashfull\":12,\"pv\":\"d2d4\"}"
+        );
+    }
+
+    #[test]
+    fn serve_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let search_info = Arc::new(Mutex::new(SearchInfo::default()));
+        search_info
+            .lock()
+            .unwrap()
+            .update("info depth 2 score cp 10 nodes 50 nps 1000 time 1 pv g1f3");
+        thread::spawn(move || serve(listener, search_info));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\"pv\":\"g1f3\"}"));
+    }
+}
@@ -1,205 +1,774 @@
-use crate::engine::Engine;
-use std::{fmt::Display, io, str::FromStr, sync::mpsc, thread, time::Duration};
+use crate::{
+    crash_report,
+    engine::{self, epd::EpdRecord, BestMoveReply, Engine, TimeControl},
+};
+use std::{
+    fmt::Display,
+    io::{self, ErrorKind, Write},
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
 
 const STARTPOS_MOVES_STARTING_INDEX: usize = 1;
 const FEN_MOVES_STARTING_INDEX: usize = 7;
 
 const DEFAULT_DEPTH: u8 = 64;
-const DEFAULT_MOVES_TO_GO: u64 = 30;
+const NULL_MOVE_STRING: &str = "0000";
+const GO_ARGUMENTS: [&str; 13] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+    "perft",
+];
 
-struct Input<'a> {
-    command: &'a str,
-    arguments: Vec<&'a str>,
+// Parsed form of a line of UCI input, so the engine can be driven without going through stdin
+pub enum Command {
+    Uci,
+    Debug(bool),
+    IsReady,
+    UciNewGame,
+    Position(PositionParams),
+    Go(GoParams),
+    Stop,
+    PonderHit,
+    SetOption {
+        name: String,
+        value: String,
+    },
+    SaveHash(String),
+    LoadHash(String),
+    Verify,
+    History,
+    Display,
+    Eval,
+    Tune,
+    Bench(u8),
+    TestSuite {
+        file_path: String,
+        move_time: Duration,
+    },
+    Perft(u8),
+    Quit,
 }
 
-impl<'a> Input<'a> {
-    fn new(input: &'a str) -> Self {
+impl FromStr for Command {
+    type Err = InputError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
         let input: Vec<&str> = input.split_whitespace().collect();
-        let command = match input.first() {
-            Some(command) => command,
-            None => "",
+        let command = input.first().copied().unwrap_or_default();
+        let arguments = input.get(1..).unwrap_or_default();
+
+        match command {
+            "uci" => Ok(Self::Uci),
+            "debug" => match arguments.first() {
+                Some(&"on") => Ok(Self::Debug(true)),
+                Some(&"off") => Ok(Self::Debug(false)),
+                _ => Err(InputError::InvalidDebugArguments),
+            },
+            "isready" => Ok(Self::IsReady),
+            "ucinewgame" => Ok(Self::UciNewGame),
+            "position" => Ok(Self::Position(PositionParams::parse(arguments)?)),
+            "go" => Ok(Self::Go(GoParams::parse(arguments)?)),
+            "stop" => Ok(Self::Stop),
+            "ponderhit" => Ok(Self::PonderHit),
+            "setoption" => Self::parse_set_option(arguments),
+            "savehash" => Ok(Self::SaveHash(Self::parse_hash_file(arguments)?)),
+            "loadhash" => Ok(Self::LoadHash(Self::parse_hash_file(arguments)?)),
+            "verify" => Ok(Self::Verify),
+            "history" => Ok(Self::History),
+            "d" | "display" => Ok(Self::Display),
+            "eval" => Ok(Self::Eval),
+            "tune" => Ok(Self::Tune),
+            "bench" => match arguments.first() {
+                Some(depth) => match depth.parse() {
+                    Ok(depth) if depth > 0 => Ok(Self::Bench(depth)),
+                    _ => Err(InputError::InvalidBenchArguments),
+                },
+                None => Ok(Self::Bench(engine::BENCH_DEPTH_DEFAULT)),
+            },
+            "testsuite" => Self::parse_test_suite(arguments),
+            "perft" => match arguments.first().map(|depth| depth.parse()) {
+                Some(Ok(depth)) if depth > 0 => Ok(Self::Perft(depth)),
+                _ => Err(InputError::InvalidPerftArguments),
+            },
+            "quit" => Ok(Self::Quit),
+            _ => Err(InputError::UnknownCommand(command.to_string())),
+        }
+    }
+}
+
+impl Command {
+    fn parse_set_option(arguments: &[&str]) -> Result<Self, InputError> {
+        if arguments.first() != Some(&"name") {
+            return Err(InputError::InvalidSetOptionArguments);
+        }
+
+        let value_index = arguments.iter().position(|argument| *argument == "value");
+        let name = match value_index {
+            Some(value_index) => arguments[1..value_index].join(" "),
+            None => arguments[1..].join(" "),
+        };
+        let value = match value_index {
+            Some(value_index) => arguments[value_index + 1..].join(" "),
+            None => String::new(),
+        };
+
+        Ok(Self::SetOption { name, value })
+    }
+
+    // The move time is in milliseconds, as with go movetime
+    fn parse_test_suite(arguments: &[&str]) -> Result<Self, InputError> {
+        let move_time = match arguments.get(1).map(|move_time| move_time.parse()) {
+            Some(Ok(move_time)) if move_time > 0 => Duration::from_millis(move_time),
+            Some(_) => return Err(InputError::InvalidTestSuiteArguments),
+            None => engine::TEST_SUITE_MOVE_TIME_DEFAULT,
+        };
+
+        match arguments {
+            [file_path, ..] if arguments.len() <= 2 => Ok(Self::TestSuite {
+                file_path: file_path.to_string(),
+                move_time,
+            }),
+            _ => Err(InputError::InvalidTestSuiteArguments),
+        }
+    }
+
+    fn parse_hash_file(arguments: &[&str]) -> Result<String, InputError> {
+        if arguments.is_empty() {
+            return Err(InputError::InvalidHashFileArguments);
+        }
+
+        Ok(arguments.join(" "))
+    }
+}
+
+pub struct PositionParams {
+    // No FEN means the standard starting position
+    pub fen: Option<String>,
+    pub moves: Vec<String>,
+}
+
+impl PositionParams {
+    fn parse(arguments: &[&str]) -> Result<Self, InputError> {
+        let (fen, moves_starting_index) = match arguments.first() {
+            Some(&"startpos") => (None, STARTPOS_MOVES_STARTING_INDEX),
+            Some(&"fen") => {
+                let fen = arguments
+                    .get(1..FEN_MOVES_STARTING_INDEX)
+                    .ok_or(InputError::InvalidPositionArguments)?;
+
+                (Some(fen.join(" ")), FEN_MOVES_STARTING_INDEX)
+            }
+            // Operations may contain anything, so an EPD record takes up the rest of the line and
+            // cannot be followed by moves
+            Some(&"epd") => {
+                let record: EpdRecord = arguments[1..].join(" ").parse()?;
+
+                (Some(record.fen().to_string()), arguments.len())
+            }
+            _ => return Err(InputError::InvalidPositionArguments),
         };
-        let arguments = match input.get(1) {
-            Some(_) => input[1..].to_vec(),
+        let moves = match arguments.get(moves_starting_index) {
+            Some(&"moves") => arguments[moves_starting_index + 1..]
+                .iter()
+                .map(|move_string| move_string.to_string())
+                .collect(),
+            Some(_) => return Err(InputError::InvalidPositionArguments),
             None => Vec::new(),
         };
 
-        Self { command, arguments }
+        Ok(Self { fen, moves })
+    }
+}
+
+pub struct GoParams {
+    pub depth: u8,
+    pub time_control: TimeControl,
+    pub mate_moves: Option<u8>,
+    pub node_limit: Option<u64>,
+    pub search_moves: Vec<String>,
+    pub ponder: bool,
+    pub infinite: bool,
+    // Counts the moves from the position instead of searching, as a divide
+    pub perft: Option<u8>,
+}
+
+impl GoParams {
+    fn parse(arguments: &[&str]) -> Result<Self, InputError> {
+        let mut go = Self {
+            depth: DEFAULT_DEPTH,
+            time_control: TimeControl::initialise(),
+            mate_moves: None,
+            node_limit: None,
+            search_moves: Vec::new(),
+            ponder: false,
+            infinite: false,
+            perft: None,
+        };
+
+        for (index, argument) in arguments.iter().enumerate() {
+            match *argument {
+                "depth" => {
+                    go.depth = get_argument_value(
+                        arguments,
+                        index,
+                        InputError::InvalidGoArguments(GoArgumentError::Depth),
+                    )?;
+
+                    if go.depth == 0 {
+                        return Err(InputError::InvalidGoArguments(GoArgumentError::Depth));
+                    }
+                }
+                "winc" | "binc" => {
+                    let increment_ms = get_argument_value(
+                        arguments,
+                        index,
+                        InputError::InvalidGoArguments(GoArgumentError::Increment(
+                            argument.to_string(),
+                        )),
+                    )?;
+                    let increment = Duration::from_millis(increment_ms);
+
+                    match *argument {
+                        "winc" => go.time_control.white_increment = increment,
+                        _ => go.time_control.black_increment = increment,
+                    }
+                }
+                "movetime" => {
+                    let move_time_ms = get_argument_value(
+                        arguments,
+                        index,
+                        InputError::InvalidGoArguments(GoArgumentError::MoveTime),
+                    )?;
+                    go.time_control.move_time = Some(Duration::from_millis(move_time_ms));
+                }
+                "wtime" | "btime" => {
+                    // Some GUIs send negative times once the clock has run out
+                    let time_left_ms: i64 = get_argument_value(
+                        arguments,
+                        index,
+                        InputError::InvalidGoArguments(GoArgumentError::TimeLeft(
+                            argument.to_string(),
+                        )),
+                    )?;
+                    let time_left = Some(Duration::from_millis(time_left_ms.max(0) as u64));
+
+                    match *argument {
+                        "wtime" => go.time_control.white_time_left = time_left,
+                        _ => go.time_control.black_time_left = time_left,
+                    }
+                }
+                "movestogo" => {
+                    let moves_to_go = get_argument_value(
+                        arguments,
+                        index,
+                        InputError::InvalidGoArguments(GoArgumentError::MovesToGo),
+                    )?;
+                    go.time_control.moves_to_go = Some(moves_to_go);
+                }
+                "mate" => {
+                    let moves = get_argument_value(
+                        arguments,
+                        index,
+                        InputError::InvalidGoArguments(GoArgumentError::Mate),
+                    )?;
+
+                    if moves == 0 {
+                        return Err(InputError::InvalidGoArguments(GoArgumentError::Mate));
+                    }
+
+                    go.mate_moves = Some(moves);
+                }
+                "nodes" => {
+                    // Node counts are sometimes sent in scientific notation, such as 1e7
+                    let nodes: f64 = get_argument_value(
+                        arguments,
+                        index,
+                        InputError::InvalidGoArguments(GoArgumentError::Nodes),
+                    )?;
+
+                    if !nodes.is_finite() || nodes < 1.0 || nodes.fract() != 0.0 {
+                        return Err(InputError::InvalidGoArguments(GoArgumentError::Nodes));
+                    }
+
+                    go.node_limit = Some(nodes as u64);
+                }
+                "searchmoves" => {
+                    go.search_moves = arguments[index + 1..]
+                        .iter()
+                        .take_while(|argument| !GO_ARGUMENTS.contains(argument))
+                        .map(|move_string| move_string.to_string())
+                        .collect();
+                }
+                "perft" => {
+                    let depth = get_argument_value(
+                        arguments,
+                        index,
+                        InputError::InvalidGoArguments(GoArgumentError::Perft),
+                    )?;
+
+                    if depth == 0 {
+                        return Err(InputError::InvalidGoArguments(GoArgumentError::Perft));
+                    }
+
+                    go.perft = Some(depth);
+                }
+                "ponder" => go.ponder = true,
+                "infinite" => go.infinite = true,
+                _ => continue,
+            }
+        }
+
+        Ok(go)
+    }
+}
+
+// Destination for everything sent to the GUI, shared by the input loop and the search thread -
+// standard output normally, but any writer can be used so responses can be captured
+#[derive(Clone)]
+pub struct Output(Arc<Mutex<dyn Write + Send>>);
+
+impl Output {
+    pub fn stdout() -> Self {
+        Self::writer(io::stdout())
+    }
+
+    pub fn writer<W: Write + Send + 'static>(writer: W) -> Self {
+        Self(Arc::new(Mutex::new(writer)))
+    }
+
+    // Lines are flushed straight away, as the GUI waits on them - write errors are ignored, as
+    // there is nowhere left to report them
+    pub fn line(&self, line: impl Display) {
+        let mut writer = match self.0.lock() {
+            Ok(writer) => writer,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        _ = writeln!(writer, "{}", line);
+        _ = writer.flush();
+    }
+
+    pub fn flush(&self) {
+        let mut writer = match self.0.lock() {
+            Ok(writer) => writer,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        _ = writer.flush();
+    }
+}
+
+// Keeps everything written to it, for tests which check what was sent to the GUI
+#[cfg(test)]
+#[derive(Clone)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
-pub fn engine() {
-    let mut engine = Engine::initialise();
-    let (stop_search_sender, stop_search_receiver) = mpsc::channel();
+#[cfg(test)]
+impl SharedBuffer {
+    pub fn lines(&self) -> Vec<String> {
+        let buffer = self.0.lock().unwrap();
+
+        String::from_utf8_lossy(&buffer)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub fn captured_output() -> (Output, SharedBuffer) {
+    let buffer = SharedBuffer(Arc::new(Mutex::new(Vec::new())));
+
+    (Output::writer(buffer.clone()), buffer)
+}
+
+// Searches run on a worker thread which takes ownership of the engine and hands it back when the
+// search finishes, so commands such as `stop` and `isready` can be handled while thinking
+struct EngineThread {
+    engine: Option<Engine>,
+    search_thread: Option<JoinHandle<Engine>>,
+    stop_search_flag: Arc<AtomicBool>,
+    ponder_flag: Arc<AtomicBool>,
+    debug_flag: Arc<AtomicBool>,
+    output: Output,
+}
+
+impl EngineThread {
+    fn initialise(output: Output) -> Self {
+        let mut engine = Engine::initialise();
+        engine.set_output(output.clone());
+        let stop_search_flag = engine.stop_search_flag();
+        let ponder_flag = engine.ponder_flag();
+        let debug_flag = engine.debug_flag();
+
+        Self {
+            engine: Some(engine),
+            search_thread: None,
+            stop_search_flag,
+            ponder_flag,
+            debug_flag,
+            output,
+        }
+    }
+
+    // Any running search is stopped, as the GUI should only send commands which modify the
+    // engine when it is not thinking
+    fn engine(&mut self) -> &mut Engine {
+        let engine = self.take_engine();
+
+        self.engine.insert(engine)
+    }
+
+    fn idle_engine(&mut self) -> Option<&mut Engine> {
+        match &self.search_thread {
+            Some(search_thread) if !search_thread.is_finished() => None,
+            _ => Some(self.engine()),
+        }
+    }
+
+    fn go(&mut self, go_params: GoParams) {
+        let mut engine = self.take_engine();
+        self.stop_search_flag.store(false, Ordering::Relaxed);
+        // Set before the search starts, so a `ponderhit` sent straight after is not missed
+        self.ponder_flag.store(go_params.ponder, Ordering::Relaxed);
+
+        let best_move_reply = engine.new_best_move_reply();
+        // Ponder and infinite searches only reply when told to, so cannot run out of time
+        let emergency_time = match go_params.ponder || go_params.infinite {
+            true => None,
+            false => engine.emergency_time(&go_params.time_control),
+        };
+
+        if let Some(emergency_time) = emergency_time {
+            spawn_watchdog(self.output.clone(), best_move_reply, emergency_time);
+        }
+
+        self.search_thread = Some(thread::spawn(move || {
+            let search = panic::catch_unwind(AssertUnwindSafe(|| {
+                handle_command(go, &mut engine, go_params);
+            }));
+
+            // The GUI is still waiting on a reply, and the search state may be left half updated
+            if search.is_err() {
+                engine.reset_search();
+
+                if engine.best_move_reply().send() {
+                    engine
+                        .output()
+                        .line(format!("bestmove {}", NULL_MOVE_STRING));
+                }
+            }
+
+            engine
+        }));
+    }
+
+    // A fresh engine is used if the previous one was lost to a panic, so the loop can carry on
+    fn take_engine(&mut self) -> Engine {
+        let engine = match self.search_thread.take() {
+            Some(search_thread) => {
+                self.stop();
+
+                search_thread.join().ok()
+            }
+            None => self.engine.take(),
+        };
+
+        match engine {
+            Some(engine) => engine,
+            None => self.replacement_engine(),
+        }
+    }
+
+    // The replacement has its own flags, which have to be picked up for `stop` to reach it, and
+    // keeps the debug mode the lost engine was in
+    fn replacement_engine(&mut self) -> Engine {
+        let mut engine = Engine::initialise();
+        engine.set_output(self.output.clone());
+        engine.set_debug(self.debug_flag.load(Ordering::Relaxed));
+        self.stop_search_flag = engine.stop_search_flag();
+        self.ponder_flag = engine.ponder_flag();
+        self.debug_flag = engine.debug_flag();
+
+        engine
+    }
+
+    // Commands are handled in the order they arrive and initialisation finishes before the first
+    // is read, so everything sent earlier is complete by now - apart from a search, which carries
+    // on in the search thread while this is answered straight away
+    fn is_ready(&mut self) {
+        if let Some(engine) = self.idle_engine() {
+            engine.print_initialisation_times();
+        }
+
+        self.output.line("readyok");
+    }
+
+    fn stop(&self) {
+        self.stop_search_flag.store(true, Ordering::Relaxed);
+    }
+
+    // The opponent played the expected move, so the ponder search carries on as a normal search
+    fn ponder_hit(&self) {
+        self.ponder_flag.store(false, Ordering::Relaxed);
+    }
+
+    // Taken up by a running search from its next iteration, without interrupting it
+    fn debug(&self, debug: bool) {
+        self.debug_flag.store(debug, Ordering::Relaxed);
+    }
+}
+
+pub fn engine(output: Output) {
+    let mut engine_thread = EngineThread::initialise(output);
     let (input_sender, input_receiver) = mpsc::channel();
-    engine.set_stop_search_receiver(stop_search_receiver);
 
     thread::spawn(move || loop {
         let mut input = String::new();
 
+        // A line which is not valid UTF-8 is skipped, but the end of the input or any other
+        // error drops the sender, which the main loop sees as the input closing
         match io::stdin().read_line(&mut input) {
-            Ok(_) => match input.trim() {
-                "stop" => _ = stop_search_sender.send(true),
-                _ => _ = input_sender.send(Some(input)),
-            },
-            Err(_) => _ = input_sender.send(None),
+            Ok(bytes_read) if bytes_read > 0 => _ = input_sender.send(input),
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {}
+            _ => break,
         }
     });
 
     loop {
         let input = match input_receiver.recv() {
-            Ok(input) => match input {
-                Some(input) => input,
-                None => continue,
-            },
-            Err(_) => continue,
+            Ok(input) => input,
+            // The GUI closed the engine's input without sending `quit`, so nothing more can come
+            Err(_) => {
+                handle_input(&mut engine_thread, Command::Quit);
+                break;
+            }
+        };
+
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        crash_report::record_command(&input);
+
+        let command = match input.parse() {
+            Ok(command) => command,
+            Err(error) => {
+                engine_thread.output.line(error);
+                continue;
+            }
         };
-        let input = Input::new(&input);
+        let running = panic::catch_unwind(AssertUnwindSafe(|| {
+            handle_input(&mut engine_thread, command)
+        }));
 
-        match input.command {
-            "uci" => uci(),
-            "isready" => println!("readyok"),
-            "ucinewgame" => engine.reset_game(),
-            "position" => handle_command(position, &mut engine, input.arguments),
-            "go" => handle_command(go, &mut engine, input.arguments),
-            "quit" => break,
-            "" => {}
-            _ => println!("Unknown command"),
+        match running {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(_) => engine_thread.output.line("Failed to process command"),
         }
     }
-}
 
-fn uci() {
-    println!("id name Pineapple");
-    println!("id author Sebastian S.");
-    println!("uciok");
+    engine_thread.output.flush();
 }
 
-fn position(engine: &mut Engine, arguments: Vec<&str>) -> Result<(), InputError> {
-    if arguments.is_empty() {
-        return Err(InputError::InvalidPositionArguments);
+// Returns whether the engine should keep reading input
+fn handle_input(engine_thread: &mut EngineThread, command: Command) -> bool {
+    match command {
+        Command::IsReady => engine_thread.is_ready(),
+        Command::Go(go_params) => engine_thread.go(go_params),
+        Command::Stop => engine_thread.stop(),
+        Command::PonderHit => engine_thread.ponder_hit(),
+        Command::Debug(debug) => engine_thread.debug(debug),
+        Command::Quit => {
+            engine_thread.engine();
+            return false;
+        }
+        command => handle_command(execute, engine_thread.engine(), command),
     }
 
-    let moves_starting_index = match arguments[0] {
-        "startpos" => {
-            engine.load_fen(&arguments)?;
+    true
+}
 
-            STARTPOS_MOVES_STARTING_INDEX
+// Runs a command on the calling thread, so `go` blocks until the search has finished - `stop`
+// and `ponderhit` have nothing to act on, and an infinite search never returns
+pub fn execute(engine: &mut Engine, command: Command) -> Result<(), InputError> {
+    match command {
+        Command::Uci => uci(engine),
+        Command::Debug(debug) => engine.set_debug(debug),
+        Command::IsReady => {
+            engine.print_initialisation_times();
+            engine.output().line("readyok");
         }
-        "fen" => {
-            if arguments.get(FEN_MOVES_STARTING_INDEX - 1).is_none() {
-                return Err(InputError::InvalidPositionArguments);
-            }
+        Command::UciNewGame => engine.reset_game(),
+        Command::Position(position_params) => position(engine, position_params)?,
+        Command::Go(go_params) => {
+            engine.new_best_move_reply();
+            go(engine, go_params)?
+        }
+        Command::Stop | Command::PonderHit | Command::Quit => {}
+        Command::SetOption { name, value } => engine.set_option(&name, &value)?,
+        Command::SaveHash(file_path) => engine.save_transposition_table(&file_path)?,
+        Command::LoadHash(file_path) => engine.load_transposition_table(&file_path)?,
+        Command::Verify => engine.verify(),
+        Command::History => history(engine),
+        Command::Display => engine.display(),
+        Command::Eval => engine.print_evaluation(),
+        Command::Tune => engine.print_tune_parameters(),
+        Command::Bench(depth) => _ = engine.bench(depth),
+        Command::TestSuite {
+            file_path,
+            move_time,
+        } => _ = engine.test_suite(&file_path, move_time)?,
+        Command::Perft(depth) => _ = engine.print_perft(depth, false),
+    }
 
-            let fen: Vec<&str> = arguments
-                .clone()
-                .drain(1..FEN_MOVES_STARTING_INDEX)
-                .collect();
-            engine.load_fen(&fen)?;
+    Ok(())
+}
 
-            FEN_MOVES_STARTING_INDEX
-        }
-        _ => return Err(InputError::InvalidPositionArguments),
-    };
+fn uci(engine: &Engine) {
+    engine.output().line("id name Pineapple");
+    engine.output().line("id author Sebastian S.");
+    engine.print_options();
+    engine.output().line("uciok");
+}
 
-    match arguments.get(moves_starting_index) {
-        Some(argument) => {
-            if *argument != "moves" {
-                return Err(InputError::InvalidPositionArguments);
-            }
-        }
-        None => return Ok(()),
+fn history(engine: &Engine) {
+    match engine.history() {
+        Some(history) => engine.output().line(format!("info string {}", history)),
+        None => engine.output().line("info string no position set"),
+    }
+}
+
+fn position(engine: &mut Engine, position_params: PositionParams) -> Result<(), InputError> {
+    match &position_params.fen {
+        Some(fen) => engine.load_fen(&fen.split_whitespace().collect::<Vec<&str>>())?,
+        None => engine.load_fen(&["startpos"])?,
     }
 
-    for move_string in arguments[moves_starting_index + 1..].iter() {
-        make_move_from_string(engine, move_string)?;
+    for move_string in &position_params.moves {
+        engine.make_move(move_string)?;
     }
 
     Ok(())
 }
 
-fn go(engine: &mut Engine, arguments: Vec<&str>) -> Result<(), InputError> {
-    let mut depth = DEFAULT_DEPTH;
-    let mut increment = None;
-    let mut move_time = None;
-    let mut time_left = None;
-    let mut moves_to_go = DEFAULT_MOVES_TO_GO;
-
-    for (index, argument) in arguments.iter().enumerate() {
-        match *argument {
-            "depth" => {
-                depth = get_argument_value(
-                    &arguments,
-                    index,
-                    InputError::InvalidGoArguments(GoArgumentError::Depth),
-                )?;
-
-                if depth == 0 {
-                    return Err(InputError::InvalidGoArguments(GoArgumentError::Depth));
-                }
-            }
-            "winc" | "binc" => {
-                let increment_ms = get_argument_value(
-                    &arguments,
-                    index,
-                    InputError::InvalidGoArguments(GoArgumentError::Increment(
-                        argument.to_string(),
-                    )),
-                )?;
-                increment = Some(Duration::from_millis(increment_ms));
-            }
-            "movetime" => {
-                let move_time_ms = get_argument_value(
-                    &arguments,
-                    index,
-                    InputError::InvalidGoArguments(GoArgumentError::MoveTime),
-                )?;
-                move_time = Some(Duration::from_millis(move_time_ms));
-            }
-            "wtime" | "btime" => {
-                let time_left_ms = get_argument_value(
-                    &arguments,
-                    index,
-                    InputError::InvalidGoArguments(GoArgumentError::TimeLeft(argument.to_string())),
-                )?;
-                time_left = Some(Duration::from_millis(time_left_ms));
-            }
+fn go(engine: &mut Engine, go_params: GoParams) -> Result<(), InputError> {
+    if let Some(depth) = go_params.perft {
+        engine.print_perft(depth, true);
 
-            "movestogo" => {
-                moves_to_go = get_argument_value(
-                    &arguments,
-                    index,
-                    InputError::InvalidGoArguments(GoArgumentError::MovesToGo),
-                )?;
-            }
-            "infinite" => {}
-            _ => continue,
-        }
+        return Ok(());
     }
 
-    engine.set_search_timing(increment, move_time, time_left, moves_to_go);
+    let search_moves: Vec<&str> = go_params.search_moves.iter().map(String::as_str).collect();
+    engine.set_search_moves(&search_moves)?;
+    engine.set_search_limits(go_params.node_limit, &go_params.time_control);
+    engine.set_mate_search(go_params.mate_moves);
+
+    let search_result = match engine.instant_recapture_reply() {
+        Some(reply) => Ok(reply),
+        None => engine.search_best_move(go_params.depth),
+    };
+    let best_move = match search_result {
+        Ok(best_move) => Some(best_move),
+        Err(InputError::InvalidPosition | InputError::MateNotFound) => None,
+        Err(error) => return Err(error),
+    };
+
+    engine.wait_for_ponder_end();
+
+    if go_params.infinite {
+        engine.wait_for_stop_signal();
+    }
 
-    let best_move = engine.search_best_move(depth)?.as_string();
-    println!("bestmove {}", best_move);
+    // The watchdog has already replied if the search overran the clock
+    if !engine.best_move_reply().send() {
+        return Ok(());
+    }
+
+    match best_move {
+        Some(best_move) => {
+            match engine.ponder_move().filter(|_| engine.ponder_enabled()) {
+                Some(ponder_move) => engine.output().line(format!(
+                    "bestmove {} ponder {}",
+                    best_move.as_string(),
+                    ponder_move.as_string()
+                )),
+                None => engine
+                    .output()
+                    .line(format!("bestmove {}", best_move.as_string())),
+            }
+
+            engine.prepare_recapture_reply();
+            engine.analyse_while_idle(&best_move);
+        }
+        // There is no move to report if the game is over or a mate search found no forced mate
+        None => engine
+            .output()
+            .line(format!("bestmove {}", NULL_MOVE_STRING)),
+    }
 
     Ok(())
 }
 
-fn make_move_from_string(engine: &mut Engine, move_string: &str) -> Result<(), InputError> {
-    engine.make_move(move_string)?;
+// Replies with the best move found so far if the search has not replied by the emergency time,
+// so a search which is stuck costs the quality of a move instead of the game
+fn spawn_watchdog(output: Output, best_move_reply: Arc<BestMoveReply>, emergency_time: Duration) {
+    thread::spawn(move || {
+        if best_move_reply.wait_for_send(emergency_time) || !best_move_reply.send() {
+            return;
+        }
 
-    Ok(())
+        let best_move = match best_move_reply.best_move() {
+            Some(best_move) => best_move.as_string(),
+            None => NULL_MOVE_STRING.to_string(),
+        };
+
+        output.line(format!(
+            "info string search overran its time, sending emergency move {}",
+            best_move
+        ));
+        output.line(format!("bestmove {}", best_move));
+    });
 }
 
-fn handle_command<F: Fn(&mut Engine, Vec<&str>) -> Result<(), InputError>>(
+fn handle_command<T, F: Fn(&mut Engine, T) -> Result<(), InputError>>(
     command_fn: F,
     engine: &mut Engine,
-    arguments: Vec<&str>,
+    arguments: T,
 ) {
     let result = command_fn(engine, arguments);
 
     if let Err(error) = result {
-        println!("{}", error);
+        engine.output().line(error);
     }
 }
 
@@ -219,28 +788,66 @@ fn get_argument_value<T: FromStr>(
 
 #[derive(Debug)]
 pub enum InputError {
+    BookFile(ErrorKind),
+    #[cfg(feature = "nnue")]
+    EvalFile(EvalFileError),
+    HashFile(HashFileError),
     IllegalMove,
+    InvalidBenchArguments,
+    InvalidDebugArguments,
+    InvalidEpd(EpdError),
     InvalidFen(FenError),
     InvalidGoArguments(GoArgumentError),
+    InvalidHashFileArguments,
     InvalidMoveString,
+    InvalidOptionValue(String),
+    InvalidPerftArguments,
+    InvalidPgn(PgnError),
     InvalidPosition,
     InvalidPositionArguments,
+    InvalidSetOptionArguments,
+    InvalidTestSuiteArguments,
+    MateNotFound,
+    #[cfg(feature = "syzygy")]
+    SyzygyPath(SyzygyPathError),
+    TestSuiteFile(ErrorKind),
+    UnknownCommand(String),
+    UnknownOption(String),
 }
 
 impl Display for InputError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::BookFile(error) => write!(f, "Failed to read or write book file: {}", error),
+            #[cfg(feature = "nnue")]
+            Self::EvalFile(error) => write!(f, "Failed to load network file: {}", error),
+            Self::HashFile(error) => write!(f, "Failed to process hash file: {}", error),
             Self::IllegalMove => write!(f, "Attempted to play an illegal move"),
+            Self::InvalidBenchArguments => write!(f, "Invalid bench depth"),
+            Self::InvalidDebugArguments => write!(f, "Invalid debug command arguments"),
+            Self::InvalidEpd(error) => write!(f, "Failed to parse EPD: {}", error),
             Self::InvalidFen(error) => write!(f, "Failed to parse FEN: {}", error),
             Self::InvalidGoArguments(error) => write!(f, "Invalid go command argument: {}", error),
+            Self::InvalidHashFileArguments => write!(f, "No hash file path provided"),
             Self::InvalidMoveString => write!(f, "Failed to parse move string"),
+            Self::InvalidOptionValue(name) => write!(f, "Invalid value for option {}", name),
+            Self::InvalidPerftArguments => write!(f, "Invalid perft depth"),
+            Self::InvalidPgn(error) => write!(f, "Failed to parse PGN: {}", error),
             Self::InvalidPosition => write!(f, "Invalid board position"),
             Self::InvalidPositionArguments => write!(f, "Invalid position command arguments"),
+            Self::InvalidSetOptionArguments => write!(f, "Invalid setoption command arguments"),
+            Self::InvalidTestSuiteArguments => write!(f, "Invalid testsuite command arguments"),
+            Self::MateNotFound => write!(f, "No forced mate found"),
+            #[cfg(feature = "syzygy")]
+            Self::SyzygyPath(error) => write!(f, "Failed to load Syzygy tablebases: {}", error),
+            Self::TestSuiteFile(error) => write!(f, "Failed to read test suite: {}", error),
+            Self::UnknownCommand(command) => write!(f, "Unknown command {}", command),
+            Self::UnknownOption(name) => write!(f, "Unknown option {}", name),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum FenError {
     BoardPosition,
     SideToMove,
@@ -248,6 +855,12 @@ pub enum FenError {
     EnPassantSquare,
     ParseHalfmoveClock,
     InvalidHalfmoveClock,
+    FullmoveNumber,
+    RankLength,
+    PawnOnBackRank,
+    KingCount,
+    OpponentInCheck,
+    ImpossibleCastlingRights,
 }
 
 impl Display for FenError {
@@ -259,6 +872,121 @@ impl Display for FenError {
             Self::EnPassantSquare => write!(f, "unable to parse en passant square"),
             Self::ParseHalfmoveClock => write!(f, "unable to parse ply"),
             Self::InvalidHalfmoveClock => write!(f, "invalid halfmove value provided"),
+            Self::FullmoveNumber => write!(f, "unable to parse fullmove number"),
+            Self::RankLength => write!(f, "rank does not cover exactly eight squares"),
+            Self::PawnOnBackRank => write!(f, "pawn on the first or eighth rank"),
+            Self::KingCount => write!(f, "each side must have exactly one king"),
+            Self::OpponentInCheck => write!(f, "side not to move is in check"),
+            Self::ImpossibleCastlingRights => {
+                write!(f, "castling rights without a king and rook to castle with")
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EpdError {
+    UnterminatedString,
+    InvalidOperand(String),
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnterminatedString => write!(f, "unterminated string operand"),
+            Self::InvalidOperand(opcode) => write!(f, "invalid operands for {}", opcode),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PgnError {
+    Tag,
+    UnterminatedComment,
+    Move(String),
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tag => write!(f, "malformed tag pair"),
+            Self::UnterminatedComment => write!(f, "unterminated comment or variation"),
+            Self::Move(san) => write!(f, "unable to play move {}", san),
+        }
+    }
+}
+
+#[cfg(feature = "nnue")]
+#[derive(Debug)]
+pub enum EvalFileError {
+    Io(ErrorKind),
+    SizeMismatch,
+}
+
+#[cfg(feature = "nnue")]
+impl EvalFileError {
+    pub fn from_io_error(error: io::Error) -> InputError {
+        InputError::EvalFile(Self::Io(error.kind()))
+    }
+}
+
+#[cfg(feature = "nnue")]
+impl Display for EvalFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error_kind) => write!(f, "{}", error_kind),
+            Self::SizeMismatch => write!(f, "network file has the wrong size for the network"),
+        }
+    }
+}
+
+#[cfg(feature = "syzygy")]
+#[derive(Debug)]
+pub enum SyzygyPathError {
+    NoTables,
+    InitialisationFailed,
+    // The tables are only opened once per process, so another engine already has them open
+    InUse,
+}
+
+#[cfg(feature = "syzygy")]
+impl Display for SyzygyPathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoTables => write!(f, "no tablebase files found"),
+            Self::InitialisationFailed => write!(f, "tablebase files could not be opened"),
+            Self::InUse => write!(f, "tablebases already in use by another engine"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HashFileError {
+    Io(ErrorKind),
+    InvalidSignature,
+    UnsupportedVersion,
+    HashSizeMismatch,
+    Truncated,
+    ChecksumMismatch,
+    InvalidEntry,
+}
+
+impl HashFileError {
+    pub fn from_io_error(error: io::Error) -> InputError {
+        InputError::HashFile(Self::Io(error.kind()))
+    }
+}
+
+impl Display for HashFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error_kind) => write!(f, "{}", error_kind),
+            Self::InvalidSignature => write!(f, "not a hash file"),
+            Self::UnsupportedVersion => write!(f, "unsupported hash file version"),
+            Self::HashSizeMismatch => write!(f, "hash file was saved with a different hash size"),
+            Self::Truncated => write!(f, "hash file is truncated"),
+            Self::ChecksumMismatch => write!(f, "hash file checksum does not match"),
+            Self::InvalidEntry => write!(f, "hash file contains an invalid entry"),
         }
     }
 }
@@ -267,7 +995,10 @@ impl Display for FenError {
 pub enum GoArgumentError {
     Depth,
     Increment(String),
+    Mate,
     MoveTime,
+    Nodes,
+    Perft,
     TimeLeft(String),
     MovesToGo,
 }
@@ -277,7 +1008,10 @@ impl Display for GoArgumentError {
         match self {
             GoArgumentError::Depth => write!(f, "depth"),
             GoArgumentError::Increment(argument) => write!(f, "{}", argument),
+            GoArgumentError::Mate => write!(f, "mate"),
             GoArgumentError::MoveTime => write!(f, "movetime"),
+            GoArgumentError::Nodes => write!(f, "nodes"),
+            GoArgumentError::Perft => write!(f, "perft"),
             GoArgumentError::TimeLeft(argument) => {
                 write!(f, "{}", argument)
             }
@@ -288,13 +1022,20 @@ impl Display for GoArgumentError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Instant;
+
+    fn execute_input(engine: &mut Engine, input: &str) -> Result<(), InputError> {
+        execute(engine, input.parse()?)
+    }
+
+    fn handle_test_input(engine_thread: &mut EngineThread, input: &str) {
+        handle_input(engine_thread, input.parse().unwrap());
+    }
 
     #[test]
     fn start_position() {
         let mut engine = Engine::initialise();
-        let input = "position startpos moves e2e4 e7e5 g1f3";
-        let input = Input::new(input);
-        position(&mut engine, input.arguments).unwrap();
+        execute_input(&mut engine, "position startpos moves e2e4 e7e5 g1f3").unwrap();
     }
 
     #[test]
@@ -303,7 +1044,657 @@ mod tests {
         let input =
             "position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 \
             moves d5e6 a6e2 c3e2";
-        let input = Input::new(input);
-        position(&mut engine, input.arguments).unwrap();
+        execute_input(&mut engine, input).unwrap();
+    }
+
+    #[test]
+    fn parse_commands() {
+        let command = "position fen 8/8/8/8/8/8/8/K6k w - - 0 1 moves a1a2".parse();
+
+        assert!(matches!(
+            command,
+            Ok(Command::Position(PositionParams { fen: Some(fen), moves }))
+                if fen == "8/8/8/8/8/8/8/K6k w - - 0 1" && moves == ["a1a2"]
+        ));
+
+        let command = "position epd 8/8/8/8/8/8/8/K6k b - - bm Kg2; id \"moves\"; hmvc 3".parse();
+
+        assert!(matches!(
+            command,
+            Ok(Command::Position(PositionParams { fen: Some(fen), moves }))
+                if fen == "8/8/8/8/8/8/8/K6k b - - 3 1" && moves.is_empty()
+        ));
+
+        let command = "go ponder wtime 1000 nodes 1e4 searchmoves e2e4 d2d4 depth 5".parse();
+
+        assert!(matches!(
+            command,
+            Ok(Command::Go(GoParams {
+                depth: 5,
+                node_limit: Some(10_000),
+                ponder: true,
+                infinite: false,
+                ref search_moves,
+                ref time_control,
+                ..
+            })) if search_moves == &["e2e4", "d2d4"]
+                && time_control.white_time_left == Some(Duration::from_millis(1000))
+        ));
+
+        let command = "setoption name Clear Hash".parse();
+
+        assert!(matches!(
+            command,
+            Ok(Command::SetOption { name, value }) if name == "Clear Hash" && value.is_empty()
+        ));
+        assert!(matches!("debug on".parse(), Ok(Command::Debug(true))));
+        assert!(matches!(
+            "go depth 0".parse::<Command>(),
+            Err(InputError::InvalidGoArguments(GoArgumentError::Depth))
+        ));
+        assert!(matches!(
+            "bench".parse(),
+            Ok(Command::Bench(engine::BENCH_DEPTH_DEFAULT))
+        ));
+        assert!(matches!(
+            "bench 0".parse::<Command>(),
+            Err(InputError::InvalidBenchArguments)
+        ));
+        assert!(matches!("tune".parse(), Ok(Command::Tune)));
+        assert!(matches!(
+            "testsuite wac.epd 250".parse(),
+            Ok(Command::TestSuite { file_path, move_time })
+                if file_path == "wac.epd" && move_time == Duration::from_millis(250)
+        ));
+        assert!(matches!(
+            "testsuite wac.epd".parse(),
+            Ok(Command::TestSuite { move_time, .. })
+                if move_time == engine::TEST_SUITE_MOVE_TIME_DEFAULT
+        ));
+
+        for malformed_test_suite in ["testsuite", "testsuite wac.epd 0", "testsuite wac.epd 1 2"] {
+            assert!(
+                matches!(
+                    malformed_test_suite.parse::<Command>(),
+                    Err(InputError::InvalidTestSuiteArguments)
+                ),
+                "{}",
+                malformed_test_suite
+            );
+        }
+
+        assert!(matches!("perft 3".parse(), Ok(Command::Perft(3))));
+        assert!(matches!(
+            "perft".parse::<Command>(),
+            Err(InputError::InvalidPerftArguments)
+        ));
+        assert!(matches!(
+            "go perft 2".parse(),
+            Ok(Command::Go(GoParams { perft: Some(2), .. }))
+        ));
+        assert!(matches!(
+            "castle kingside".parse::<Command>(),
+            Err(InputError::UnknownCommand(command)) if command == "castle"
+        ));
+    }
+
+    #[test]
+    fn capture_responses() {
+        let (output, buffer) = captured_output();
+        let mut engine_thread = EngineThread::initialise(output);
+        handle_test_input(&mut engine_thread, "uci");
+        handle_test_input(&mut engine_thread, "position startpos moves e2e4");
+        handle_test_input(&mut engine_thread, "go depth 3");
+
+        while engine_thread.idle_engine().is_none() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        handle_test_input(&mut engine_thread, "isready");
+        handle_test_input(&mut engine_thread, "history");
+        handle_test_input(&mut engine_thread, "position startpos moves e2e5");
+
+        let lines = buffer.lines();
+
+        assert_eq!(lines[0], "id name Pineapple");
+        assert!(lines.contains(&"uciok".to_string()));
+        assert!(lines.iter().any(|line| line.starts_with("info depth 3 ")));
+        assert!(lines.iter().any(|line| line.starts_with("bestmove ")));
+        assert!(lines.contains(&"readyok".to_string()));
+        assert!(lines.contains(&"info string position startpos moves e2e4".to_string()));
+        assert_eq!(
+            lines.last().map(String::as_str),
+            Some("Attempted to play an illegal move")
+        );
+    }
+
+    #[test]
+    fn perft_commands() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        execute_input(&mut engine, "position startpos").unwrap();
+        execute_input(&mut engine, "perft 3").unwrap();
+
+        assert_eq!(buffer.lines()[0], "Nodes searched: 8902");
+
+        execute_input(&mut engine, "go perft 2").unwrap();
+        let lines = buffer.lines();
+
+        assert!(lines.contains(&"e2e4: 20".to_string()));
+        assert!(lines.contains(&"Nodes searched: 400".to_string()));
+        assert!(!lines.iter().any(|line| line.starts_with("bestmove")));
+    }
+
+    #[test]
+    fn display_command() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        execute_input(&mut engine, "position startpos moves e2e4 d7d5 f1b5").unwrap();
+        execute_input(&mut engine, "d").unwrap();
+        let lines = buffer.lines();
+
+        assert_eq!(lines[0], "8   r n b q k b n r ");
+        assert_eq!(lines[4], "4   . . . . P . . . ");
+        assert!(lines.contains(&"Side to move: Black".to_string()));
+        assert!(lines.contains(&"Checkers: b5".to_string()));
+        assert_eq!(
+            lines.last().map(String::as_str),
+            Some("position startpos moves e2e4 d7d5 f1b5")
+        );
+        assert!(matches!("display".parse(), Ok(Command::Display)));
+    }
+
+    #[test]
+    fn show_refutations_option() {
+        let mut engine = Engine::initialise();
+        execute_input(&mut engine, "setoption name UCI_ShowRefutations value true").unwrap();
+
+        assert!(execute_input(
+            &mut engine,
+            "setoption name UCI_ShowRefutations value maybe"
+        )
+        .is_err());
+        assert!(
+            execute_input(&mut engine, "setoption name Nonexistent Option value true").is_err()
+        );
+    }
+
+    #[test]
+    fn ponder_option() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        execute_input(&mut engine, "position startpos moves e2e4").unwrap();
+        execute_input(&mut engine, "go depth 3").unwrap();
+        let bestmove = buffer.lines().last().unwrap().clone();
+
+        assert_eq!(bestmove.split_whitespace().count(), 2, "{}", bestmove);
+
+        execute_input(&mut engine, "setoption name Ponder value true").unwrap();
+        execute_input(&mut engine, "go depth 3").unwrap();
+        let bestmove = buffer.lines().last().unwrap().clone();
+
+        assert_eq!(
+            bestmove.split_whitespace().nth(2),
+            Some("ponder"),
+            "{}",
+            bestmove
+        );
+    }
+
+    #[test]
+    fn aspiration_window_bounds() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        execute_input(
+            &mut engine,
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+        )
+        .unwrap();
+        execute_input(&mut engine, "go depth 3").unwrap();
+        let lines = buffer.lines();
+        let bound_lines: Vec<&String> = lines
+            .iter()
+            .filter(|line| line.contains(" lowerbound "))
+            .collect();
+
+        assert!(!bound_lines.is_empty(), "{:?}", lines);
+        assert!(bound_lines.iter().all(|line| !line.contains(" pv ")));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("info depth 2 score mate 1 ")));
+    }
+
+    #[test]
+    fn chess960_option() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        let position = "position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+        assert!(execute_input(&mut engine, &format!("{} moves e1h1", position)).is_err());
+
+        execute_input(&mut engine, "setoption name UCI_Chess960 value true").unwrap();
+        execute_input(&mut engine, &format!("{} moves e1h1 e8a8", position)).unwrap();
+        execute_input(&mut engine, position).unwrap();
+        execute_input(&mut engine, "go depth 2 searchmoves e1h1").unwrap();
+
+        assert_eq!(buffer.lines().last().unwrap(), "bestmove e1h1");
+    }
+
+    #[test]
+    fn show_wdl_option() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        execute_input(&mut engine, "position startpos").unwrap();
+        execute_input(&mut engine, "go depth 2").unwrap();
+
+        assert!(buffer.lines().iter().all(|line| !line.contains(" wdl ")));
+
+        execute_input(&mut engine, "setoption name UCI_ShowWDL value true").unwrap();
+        execute_input(&mut engine, "go depth 2").unwrap();
+        let lines = buffer.lines();
+        let info_lines: Vec<&String> = lines
+            .iter()
+            .filter(|line| line.starts_with("info depth 2 "))
+            .collect();
+
+        assert!(
+            info_lines.iter().any(|line| line.contains(" wdl ")),
+            "{:?}",
+            lines
+        );
+    }
+
+    #[test]
+    fn clamped_option_values() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        execute_input(&mut engine, "setoption name Threads value 0").unwrap();
+        execute_input(&mut engine, "setoption name MultiPV value 25").unwrap();
+        execute_input(&mut engine, "position startpos").unwrap();
+        execute_input(&mut engine, "go depth 1").unwrap();
+        let lines = buffer.lines();
+
+        assert_eq!(
+            lines[0],
+            "info string Threads 0 is outside the range 1 to 256, using 1"
+        );
+        assert_eq!(
+            lines[1],
+            "info string MultiPV 25 is more than the 20 moves to search, showing 20 lines"
+        );
+    }
+
+    #[test]
+    fn debug_command() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        execute_input(&mut engine, "isready").unwrap();
+
+        assert_eq!(buffer.lines(), ["readyok"]);
+
+        assert!(execute_input(&mut engine, "debug on").is_ok());
+
+        execute_input(&mut engine, "isready").unwrap();
+        execute_input(&mut engine, "position startpos").unwrap();
+        execute_input(&mut engine, "go wtime 10000 btime 10000 depth 3").unwrap();
+        let lines = buffer.lines();
+        let has_line = |start: &str, contains: &str| {
+            lines
+                .iter()
+                .any(|line| line.starts_with(start) && line.contains(contains))
+        };
+
+        assert!(has_line("info string initialised search parameters", ""));
+        assert!(has_line(
+            "info string depth 3 re-searches",
+            " aspiration low "
+        ));
+        assert!(has_line("info string depth 3 hash probes", " hit rate "));
+        assert!(has_line("info string depth 3 time elapsed", " hard limit "));
+
+        let line_count = lines.len();
+        assert!(execute_input(&mut engine, "debug off").is_ok());
+        execute_input(&mut engine, "go depth 2").unwrap();
+
+        assert!(!buffer.lines()[line_count..]
+            .iter()
+            .any(|line| line.starts_with("info string")));
+        assert!(matches!(
+            execute_input(&mut engine, "debug maybe"),
+            Err(InputError::InvalidDebugArguments)
+        ));
+        assert!(matches!(
+            execute_input(&mut engine, "debug"),
+            Err(InputError::InvalidDebugArguments)
+        ));
+    }
+
+    #[test]
+    fn stop_infinite_search() {
+        let mut engine_thread = EngineThread::initialise(Output::stdout());
+        handle_test_input(&mut engine_thread, "position startpos");
+        handle_test_input(&mut engine_thread, "go infinite");
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(engine_thread.idle_engine().is_none());
+
+        handle_test_input(&mut engine_thread, "stop");
+        engine_thread.engine();
+
+        assert!(engine_thread.search_thread.is_none());
+    }
+
+    #[test]
+    fn ponder_search() {
+        let mut engine_thread = EngineThread::initialise(Output::stdout());
+        handle_test_input(&mut engine_thread, "position startpos moves e2e4 e7e5");
+
+        // The ponder search keeps going past its time limit until the expected move is played
+        handle_test_input(&mut engine_thread, "go ponder movetime 100");
+        thread::sleep(Duration::from_millis(300));
+
+        assert!(engine_thread.idle_engine().is_none());
+
+        let start_time = Instant::now();
+        handle_test_input(&mut engine_thread, "ponderhit");
+
+        while engine_thread.idle_engine().is_none() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(start_time.elapsed() < Duration::from_millis(500));
+
+        let engine = engine_thread.engine();
+        assert!(engine.ponder_move().is_some());
+
+        handle_test_input(&mut engine_thread, "go ponder depth 1");
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(engine_thread.idle_engine().is_none());
+
+        handle_test_input(&mut engine_thread, "stop");
+        engine_thread.engine();
+
+        assert!(engine_thread.search_thread.is_none());
+    }
+
+    #[test]
+    fn respond_while_searching() {
+        let (output, buffer) = captured_output();
+        let mut engine_thread = EngineThread::initialise(output);
+        handle_test_input(&mut engine_thread, "position startpos");
+        handle_test_input(&mut engine_thread, "go infinite");
+        handle_test_input(&mut engine_thread, "isready");
+
+        // Answered straight away, while the search carries on
+        assert!(buffer.lines().contains(&"readyok".to_string()));
+        assert!(engine_thread.idle_engine().is_none());
+
+        handle_test_input(&mut engine_thread, "stop");
+        engine_thread.engine();
+
+        assert!(engine_thread.search_thread.is_none());
+        assert!(buffer
+            .lines()
+            .iter()
+            .any(|line| line.starts_with("bestmove")));
+    }
+
+    #[test]
+    fn debug_while_searching() {
+        let (output, buffer) = captured_output();
+        let mut engine_thread = EngineThread::initialise(output);
+        handle_test_input(&mut engine_thread, "position startpos");
+        handle_test_input(&mut engine_thread, "go infinite");
+        thread::sleep(Duration::from_millis(50));
+        handle_test_input(&mut engine_thread, "debug on");
+        let start_time = Instant::now();
+
+        // The search carries on, reporting debug information from its next iteration
+        while !buffer
+            .lines()
+            .iter()
+            .any(|line| line.starts_with("info string depth"))
+        {
+            assert!(start_time.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(engine_thread.idle_engine().is_none());
+        assert!(!buffer
+            .lines()
+            .iter()
+            .any(|line| line.starts_with("bestmove")));
+
+        handle_test_input(&mut engine_thread, "stop");
+        engine_thread.engine();
+
+        assert!(buffer
+            .lines()
+            .iter()
+            .any(|line| line.starts_with("bestmove")));
+    }
+
+    // Only run on request, as the limits depend on the machine and build
+    #[test]
+    #[ignore]
+    fn search_response_latency() {
+        let mut engine_thread = EngineThread::initialise(Output::stdout());
+        handle_test_input(&mut engine_thread, "position startpos");
+        handle_test_input(&mut engine_thread, "go infinite");
+        thread::sleep(Duration::from_millis(50));
+
+        let start_time = Instant::now();
+        handle_test_input(&mut engine_thread, "isready");
+
+        assert!(start_time.elapsed() < Duration::from_millis(20));
+
+        let start_time = Instant::now();
+        handle_test_input(&mut engine_thread, "stop");
+        engine_thread.engine();
+
+        assert!(start_time.elapsed() < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn analyse_while_idle_stops_on_next_command() {
+        let mut engine_thread = EngineThread::initialise(Output::stdout());
+        handle_test_input(
+            &mut engine_thread,
+            "setoption name AnalyseWhileIdle value true",
+        );
+        handle_test_input(&mut engine_thread, "position startpos");
+        handle_test_input(&mut engine_thread, "go depth 1");
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(engine_thread.idle_engine().is_none());
+
+        handle_test_input(&mut engine_thread, "position startpos moves e2e4");
+
+        assert!(engine_thread.search_thread.is_none());
+    }
+
+    #[test]
+    fn instant_recapture_reply() {
+        let (output, buffer) = captured_output();
+        let mut engine_thread = EngineThread::initialise(output);
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/3qP3/5N2/PPP2PPP/RNBQKB1R w KQkq - 0 4";
+        handle_test_input(
+            &mut engine_thread,
+            "setoption name InstantRecaptures value true",
+        );
+        handle_test_input(&mut engine_thread, "setoption name Ponder value true");
+        handle_test_input(&mut engine_thread, &format!("position fen {}", fen));
+        handle_test_input(&mut engine_thread, "go depth 8");
+
+        while !buffer
+            .lines()
+            .iter()
+            .any(|line| line.starts_with("bestmove "))
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(
+            buffer.lines().last().map(String::as_str),
+            Some("bestmove f3d4 ponder e5d4")
+        );
+
+        handle_test_input(
+            &mut engine_thread,
+            &format!("position fen {} moves f3d4 e5d4", fen),
+        );
+        let reply_start = buffer.lines().len();
+        handle_test_input(&mut engine_thread, "go wtime 60000 btime 60000");
+
+        while engine_thread.idle_engine().is_none() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let lines = buffer.lines();
+
+        assert_eq!(lines.len() - reply_start, 2);
+        assert!(lines[reply_start].starts_with("info depth "));
+        assert!(lines[reply_start + 1].starts_with("bestmove "));
+    }
+
+    #[test]
+    fn emergency_move_watchdog() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        execute_input(&mut engine, "position startpos").unwrap();
+        let best_move_reply = engine.new_best_move_reply();
+        // A search which finds a move but never replies with it
+        let best_move = engine.search_best_move(2).unwrap();
+        spawn_watchdog(output, best_move_reply, Duration::from_millis(10));
+
+        while !buffer
+            .lines()
+            .iter()
+            .any(|line| line.starts_with("bestmove"))
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(
+            buffer.lines().last(),
+            Some(&format!("bestmove {}", best_move.as_string()))
+        );
+        assert!(!engine.best_move_reply().send());
+
+        let (output, buffer) = captured_output();
+        let best_move_reply = engine.new_best_move_reply();
+        spawn_watchdog(
+            output,
+            Arc::clone(&best_move_reply),
+            Duration::from_millis(50),
+        );
+
+        assert!(best_move_reply.send());
+
+        thread::sleep(Duration::from_millis(100));
+
+        assert!(buffer.lines().is_empty());
+    }
+
+    #[test]
+    fn quit_command() {
+        let mut engine_thread = EngineThread::initialise(Output::stdout());
+        handle_test_input(&mut engine_thread, "go infinite");
+
+        assert!(!handle_input(&mut engine_thread, Command::Quit));
+        assert!(engine_thread.search_thread.is_none());
+    }
+
+    #[test]
+    fn combined_go_limits() {
+        let (output, buffer) = captured_output();
+        let mut engine = Engine::initialise();
+        engine.set_output(output);
+        execute_input(&mut engine, "position startpos").unwrap();
+        let start_time = Instant::now();
+        execute_input(&mut engine, "go depth 20 movetime 5000 nodes 1e4").unwrap();
+        let elapsed = start_time.elapsed();
+        let lines = buffer.lines();
+        let info_value = |line: &String, name: &str| -> u64 {
+            let mut fields = line.split_whitespace().skip_while(|field| *field != name);
+            fields.nth(1).unwrap().parse().unwrap()
+        };
+        let info_lines: Vec<&String> = lines
+            .iter()
+            .filter(|line| line.starts_with("info depth "))
+            .collect();
+        let best_move_string = lines.last().unwrap().split_whitespace().nth(1).unwrap();
+        let best_move = engine::Game::from_fen("startpos")
+            .unwrap()
+            .legal_moves()
+            .into_iter()
+            .find(|mv| mv.as_string() == best_move_string)
+            .unwrap();
+        let search_result = engine.search_result(best_move);
+
+        // The node limit is the first bound hit, and is checked every 1024 nodes
+        assert!(
+            (10_000..=11_024).contains(&search_result.nodes),
+            "{}",
+            search_result.nodes
+        );
+        assert!(search_result.depth < 20);
+        assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+        assert!(!info_lines.is_empty(), "{:?}", lines);
+        assert!(info_lines.iter().all(|line| info_value(line, "depth") < 20
+            && info_value(line, "nodes") <= 10_000
+            && info_value(line, "time") < 1000));
+
+        assert!(execute_input(&mut engine, "go nodes 1.5").is_err());
+    }
+
+    #[test]
+    fn malformed_input() {
+        let mut engine = Engine::initialise();
+        let malformed_inputs = [
+            "",
+            "position",
+            "position fen",
+            "position fen 8/8/8/8/8/8/8/8",
+            "position fen 99999999/8/8/8/8/8/8/8 w - - 0 1",
+            "position fen rnbqkbnP/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1",
+            "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            "position startpos moves e2",
+            "position startpos moves e2e4e5e6",
+            "position startpos moves é2é4",
+            "position startpos moves e2e9",
+            "position startpos movez e2e4",
+            "savehash",
+        ];
+
+        for malformed_input in malformed_inputs {
+            assert!(
+                execute_input(&mut engine, malformed_input).is_err(),
+                "{}",
+                malformed_input
+            );
+        }
+
+        execute_input(&mut engine, "position startpos").unwrap();
+
+        for malformed_go in ["go depth", "go wtime -", "go mate 0", "go searchmoves e2"] {
+            assert!(
+                execute_input(&mut engine, malformed_go).is_err(),
+                "{}",
+                malformed_go
+            );
+        }
+
+        execute_input(&mut engine, "go nonsense depth 1 tokens").unwrap();
     }
 }
@@ -0,0 +1,239 @@
+use pineapple_chess_engine::{
+    execute, Bitboard, Command, Engine, FenError, Game, GoParams, InputError, MoveType, Piece,
+    PositionParams, Score, SearchLimits, SearchProgress, Side, Square,
+};
+use std::{sync::Arc, thread, time::Duration};
+
+// Drives the engine through the library interface, as a program embedding it would
+
+#[test]
+fn search_position() {
+    let engine = Engine::new();
+    engine
+        .set_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &["g1f1", "g8h8"])
+        .unwrap();
+
+    let search_result = engine
+        .go(SearchLimits {
+            depth: Some(4),
+            ..SearchLimits::default()
+        })
+        .unwrap();
+
+    assert_eq!(search_result.best_move.as_string(), "a1a8");
+    assert_eq!(search_result.score, Some(Score::Mate(1)));
+    assert_eq!(search_result.pv.first(), Some(&search_result.best_move));
+    assert_eq!(search_result.depth, 4);
+    assert!(search_result.nodes > 0);
+
+    engine.set_position("startpos", &["e2e4"]).unwrap();
+    let search_result = engine
+        .go(SearchLimits {
+            nodes: Some(10_000),
+            ..SearchLimits::default()
+        })
+        .unwrap();
+
+    assert!(matches!(search_result.score, Some(Score::Centipawns(_))));
+}
+
+#[test]
+fn invalid_positions() {
+    let engine = Engine::default();
+
+    assert!(matches!(
+        engine.set_position("8/8/8 w - - 0 1", &[]),
+        Err(InputError::InvalidFen(FenError::BoardPosition))
+    ));
+    assert!(matches!(
+        engine.set_position("startpos", &["e2e5"]),
+        Err(InputError::IllegalMove)
+    ));
+
+    // Stalemate, so there is nothing to search
+    engine
+        .set_position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", &[])
+        .unwrap();
+
+    assert!(engine.go(SearchLimits::default()).is_err());
+}
+
+#[test]
+fn stop_from_another_thread() {
+    let engine = Arc::new(Engine::new());
+    let search_progress = engine.search_progress();
+    let search_engine = Arc::clone(&engine);
+    let search = thread::spawn(move || search_engine.go(SearchLimits::default()));
+
+    // Stopped once an iteration has completed, so there is a score to return
+    let first_iteration = search_progress
+        .recv_timeout(Duration::from_secs(10))
+        .unwrap();
+    engine.stop();
+
+    let search_result = search.join().unwrap().unwrap();
+
+    assert!(search_result.depth >= first_iteration.depth);
+    assert!(search_result.score.is_some());
+}
+
+// The stop may arrive before the search has even taken the engine, which still has to end it
+#[test]
+fn stop_before_search_starts() {
+    let engine = Arc::new(Engine::new());
+    let search_engine = Arc::clone(&engine);
+    let search = thread::spawn(move || search_engine.go(SearchLimits::default()));
+    engine.stop();
+
+    let search_result = search.join().unwrap().unwrap();
+
+    assert!(Game::from_fen("startpos")
+        .unwrap()
+        .legal_moves()
+        .contains(&search_result.best_move));
+
+    // The stop only ends the one search
+    let search_result = engine
+        .go(SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        })
+        .unwrap();
+
+    assert_eq!(search_result.depth, 3);
+}
+
+#[test]
+fn board_model() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let mut game = Game::from_fen(fen).unwrap();
+    let legal_moves = game.legal_moves();
+
+    assert_eq!(legal_moves.len(), 48);
+    assert_eq!(game.to_fen(), fen);
+    assert_eq!(game.side_to_move(), Side::White);
+    assert_eq!(
+        game.piece_at_square(Square::E1),
+        Some((Piece::King, Side::White))
+    );
+    assert_eq!(
+        game.piece_bitboard(Piece::Pawn, Side::White).count_bits(),
+        8
+    );
+
+    let castling_move = legal_moves
+        .iter()
+        .find(|mv| mv.move_type() == MoveType::Castling && mv.as_string() == "e1g1")
+        .unwrap();
+    game.make_move(castling_move).unwrap();
+
+    assert_eq!(game.side_to_move(), Side::Black);
+    assert_eq!(
+        game.piece_at_square(Square::F1),
+        Some((Piece::Rook, Side::White))
+    );
+
+    game.undo_move();
+
+    assert_eq!(game.to_fen(), fen);
+    assert_eq!(
+        Game::from_fen("startpos").unwrap().to_fen(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+    assert!(Game::from_fen("8/8/8 w - - 0 1").is_err());
+    assert_eq!(
+        Bitboard::new(0b1010).squares().collect::<Vec<Square>>(),
+        [Square::B8, Square::D8]
+    );
+}
+
+#[test]
+fn search_progress() {
+    let engine = Engine::new();
+    let search_progress = engine.search_progress();
+    let search_result = engine
+        .go(SearchLimits {
+            depth: Some(5),
+            ..SearchLimits::default()
+        })
+        .unwrap();
+    let updates: Vec<SearchProgress> = search_progress.try_iter().collect();
+
+    assert_eq!(
+        updates
+            .iter()
+            .map(|update| update.depth)
+            .collect::<Vec<u8>>(),
+        [1, 2, 3, 4, 5]
+    );
+    assert!(updates
+        .windows(2)
+        .all(|updates| updates[0].nodes <= updates[1].nodes));
+
+    let last_update = updates.last().unwrap();
+
+    assert_eq!(Some(last_update.score), search_result.score);
+    assert_eq!(last_update.pv, search_result.pv);
+
+    // Replaced receivers are no longer sent anything, and a dropped one does not stop the search
+    let replacement = engine.search_progress();
+    drop(replacement);
+    engine
+        .go(SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        })
+        .unwrap();
+
+    assert_eq!(search_progress.try_iter().count(), 0);
+}
+
+#[test]
+fn execute_commands() {
+    let engine = Engine::new();
+    let command: Command = "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1 moves g1f1 g8h8"
+        .parse()
+        .unwrap();
+
+    match &command {
+        Command::Position(PositionParams { fen, moves }) => {
+            assert_eq!(fen.as_deref(), Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"));
+            assert_eq!(moves, &["g1f1", "g8h8"]);
+        }
+        _ => panic!("expected a position command"),
+    }
+
+    execute(&engine, command).unwrap();
+
+    let command: Command = "go depth 2 nodes 5000".parse().unwrap();
+
+    match &command {
+        Command::Go(GoParams {
+            depth, node_limit, ..
+        }) => {
+            assert_eq!(*depth, 2);
+            assert_eq!(*node_limit, Some(5000));
+        }
+        _ => panic!("expected a go command"),
+    }
+
+    execute(&engine, command).unwrap();
+
+    // The position set by the command is the one the library searches
+    let search_result = engine
+        .go(SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        })
+        .unwrap();
+
+    assert_eq!(search_result.best_move.as_string(), "a1a8");
+    assert!(matches!(
+        "go depth 0".parse::<Command>(),
+        Err(InputError::InvalidGoArguments(_))
+    ));
+    assert!(matches!(
+        execute(&engine, "position startpos moves e2e5".parse().unwrap()),
+        Err(InputError::IllegalMove)
+    ));
+}
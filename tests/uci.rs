@@ -0,0 +1,321 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, ExitStatus, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Drives the compiled engine through its standard input and output, as a GUI would
+struct EngineProcess {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: Receiver<String>,
+}
+
+impl EngineProcess {
+    fn spawn() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_pineapple-chess-engine"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => _ = sender.send(line),
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Self {
+            child,
+            stdin,
+            lines,
+        }
+    }
+
+    fn send(&mut self, command: &str) {
+        writeln!(self.stdin.as_mut().unwrap(), "{}", command).unwrap();
+    }
+
+    // As a GUI closing without sending `quit` would
+    fn close_input(&mut self) {
+        self.stdin = None;
+    }
+
+    fn wait_for_exit(&mut self) -> ExitStatus {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+
+        loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return status;
+            }
+
+            assert!(Instant::now() < deadline, "engine did not exit");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    // Every line received up to and including the first one starting with the prefix
+    fn read_until(&self, prefix: &str) -> Vec<String> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        let mut lines = Vec::new();
+
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let line = self
+                .lines
+                .recv_timeout(timeout)
+                .unwrap_or_else(|_| panic!("no line starting with {:?} in {:?}", prefix, lines));
+            let found = line.starts_with(prefix);
+            lines.push(line);
+
+            if found {
+                return lines;
+            }
+        }
+    }
+
+    // Search information may still be sent, but not a best move
+    fn assert_no_bestmove(&self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+
+        while let Ok(line) = self
+            .lines
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            assert!(!line.starts_with("bestmove"), "{}", line);
+        }
+    }
+
+    fn bestmove(&self) -> String {
+        let lines = self.read_until("bestmove");
+
+        lines.last().unwrap().to_string()
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        _ = self.child.kill();
+    }
+}
+
+fn is_move(move_string: &str) -> bool {
+    let characters: Vec<char> = move_string.chars().collect();
+
+    matches!(characters.len(), 4 | 5)
+        && ('a'..='h').contains(&characters[0])
+        && ('1'..='8').contains(&characters[1])
+        && ('a'..='h').contains(&characters[2])
+        && ('1'..='8').contains(&characters[3])
+}
+
+fn assert_bestmove(line: &str) {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+
+    assert_eq!(tokens[0], "bestmove", "{}", line);
+    assert!(is_move(tokens[1]), "{}", line);
+
+    match tokens.get(2) {
+        Some(&"ponder") => assert!(is_move(tokens[3]), "{}", line),
+        Some(_) => panic!("unexpected bestmove arguments in {}", line),
+        None => {}
+    }
+}
+
+#[test]
+fn handshake() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("uci");
+    let lines = engine.read_until("uciok");
+
+    assert!(lines[0].starts_with("id name "));
+    assert!(lines[1].starts_with("id author "));
+    assert!(lines[2..lines.len() - 1]
+        .iter()
+        .all(|line| line.starts_with("option name ")));
+
+    engine.send("isready");
+
+    assert_eq!(engine.read_until("readyok").last().unwrap(), "readyok");
+
+    engine.send("ucinewgame");
+    engine.send("isready");
+
+    assert_eq!(engine.read_until("readyok"), ["readyok"]);
+}
+
+#[test]
+fn search_limits() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos moves e2e4 e7e5");
+    engine.send("go depth 4");
+    let lines = engine.read_until("bestmove");
+    let depths: Vec<&str> = lines
+        .iter()
+        .filter(|line| line.starts_with("info depth ") && line.contains(" pv "))
+        .map(|line| line.split_whitespace().nth(2).unwrap())
+        .collect();
+
+    assert_eq!(depths, ["1", "2", "3", "4"]);
+    assert_bestmove(lines.last().unwrap());
+
+    for go in [
+        "go nodes 5000",
+        "go movetime 200",
+        "go wtime 2000 btime 2000 winc 100 binc 100 movestogo 20",
+    ] {
+        let start_time = Instant::now();
+        engine.send(go);
+        let bestmove = engine.bestmove();
+
+        assert_bestmove(&bestmove);
+        assert!(start_time.elapsed() < Duration::from_secs(2), "{}", go);
+    }
+
+    engine.send("go wtime 1000 btime 1000 searchmoves g1f3 b1c3");
+    let bestmove = engine.bestmove();
+
+    assert!(
+        bestmove.starts_with("bestmove g1f3") || bestmove.starts_with("bestmove b1c3"),
+        "{}",
+        bestmove
+    );
+
+    engine.send("position fen 4k3/8/5K2/8/1Q6/8/8/8 w - - 0 1");
+    engine.send("go mate 1");
+
+    assert_eq!(engine.bestmove(), "bestmove b4e7");
+
+    engine.send("position fen 4k3/4Q3/5K2/8/8/8/8/8 b - - 0 1");
+    engine.send("go depth 3");
+
+    assert_eq!(engine.bestmove(), "bestmove 0000");
+}
+
+// Endings in the tablebases are played from them, without searching
+#[cfg(feature = "syzygy")]
+#[test]
+fn syzygy_tablebases() {
+    let mut engine = EngineProcess::spawn();
+    engine.send(concat!(
+        "setoption name SyzygyPath value ",
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/syzygy"
+    ));
+    engine.send("isready");
+
+    assert_eq!(
+        engine.read_until("readyok"),
+        [
+            "info string loaded Syzygy tablebases of up to 4 pieces",
+            "readyok"
+        ]
+    );
+
+    engine.send("position fen 6k1/8/8/3P4/4K3/8/8/8 w - - 0 1");
+    engine.send("go wtime 1000 btime 1000");
+    let lines = engine.read_until("bestmove");
+
+    assert!(lines[0].contains(" tbhits 1 "), "{:?}", lines);
+    assert_eq!(lines.last().unwrap(), "bestmove d5d6");
+}
+
+#[test]
+fn stop_and_ponderhit() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos");
+    engine.send("go infinite");
+    engine.assert_no_bestmove(Duration::from_millis(300));
+    engine.send("stop");
+
+    assert_bestmove(&engine.bestmove());
+
+    engine.send("position startpos moves e2e4");
+    engine.send("go ponder wtime 1000 btime 1000");
+    engine.assert_no_bestmove(Duration::from_millis(300));
+    let start_time = Instant::now();
+    engine.send("ponderhit");
+
+    assert_bestmove(&engine.bestmove());
+    assert!(start_time.elapsed() < Duration::from_secs(2));
+
+    engine.send("go ponder wtime 1000 btime 1000");
+    engine.assert_no_bestmove(Duration::from_millis(100));
+    engine.send("stop");
+
+    assert_bestmove(&engine.bestmove());
+}
+
+#[test]
+fn isready_synchronisation() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("setoption name Hash value 256");
+    engine.send("position startpos moves e2e4 e7e5 g1f3");
+    engine.send("isready");
+
+    assert_eq!(engine.read_until("readyok"), ["readyok"]);
+
+    engine.send("d");
+    let lines = engine.read_until("position");
+
+    assert_eq!(
+        lines.last().unwrap(),
+        "position startpos moves e2e4 e7e5 g1f3"
+    );
+
+    engine.send("go infinite");
+    engine.assert_no_bestmove(Duration::from_millis(100));
+    let start_time = Instant::now();
+    engine.send("isready");
+    let lines = engine.read_until("readyok");
+
+    assert!(lines.iter().all(|line| !line.starts_with("bestmove")));
+    assert!(start_time.elapsed() < Duration::from_secs(1));
+
+    engine.send("stop");
+
+    assert_bestmove(&engine.bestmove());
+}
+
+#[test]
+fn invalid_input_and_quit() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("castle kingside");
+
+    assert_eq!(engine.read_until("Unknown"), ["Unknown command castle"]);
+
+    engine.send("position startpos moves e2e5");
+
+    assert_eq!(
+        engine.read_until("Attempted"),
+        ["Attempted to play an illegal move"]
+    );
+
+    engine.send("go infinite");
+    engine.send("quit");
+
+    assert_bestmove(&engine.bestmove());
+    assert!(engine.wait_for_exit().success());
+}
+
+#[test]
+fn end_of_input() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos");
+    engine.send("go infinite");
+    engine.assert_no_bestmove(Duration::from_millis(100));
+    engine.close_input();
+
+    assert_bestmove(&engine.bestmove());
+    assert!(engine.wait_for_exit().success());
+}